@@ -40,7 +40,7 @@ fn create_mixed_document() -> Document {
     doc.set("bool", Value::Bool(true));
     doc.set("int32", Value::I32(42));
     doc.set("int64", Value::I64(i64::MAX));
-    doc.set("double", Value::F64(3.14159));
+    doc.set("double", Value::F64(std::f64::consts::PI));
     doc.set("string", Value::String("Hello, BSON!".to_string()));
     
     // Add array with mixed types
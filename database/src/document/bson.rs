@@ -1,5 +1,6 @@
 use crate::document::object_id::ObjectId;
 use crate::document::{Document, Value};
+use crate::document::id::DocumentId;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::collections::BTreeMap;
 use std::io::{Cursor, Read, Seek, SeekFrom, Write};
@@ -20,6 +21,17 @@ pub const TYPE_ARRAY: u8 = 0x04;
 pub const TYPE_OBJECT: u8 = 0x03;
 pub const TYPE_DATETIME: u8 = 0x09;
 pub const TYPE_BINARY: u8 = 0x05;
+/// Binary subtype marking a reference to another stored document. Subtypes from 0x80
+/// are user-defined, so references stay valid BSON that other readers see as binary.
+pub const SUBTYPE_DOCUMENT_REF: u8 = 0x80;
+
+/// Size of a document reference's binary payload: page id (8), slot id (2), a flag byte
+/// saying whether the target's `_id` is known, and the 12-byte `_id`, zeroed when it is
+/// not. Every reference has the same size.
+pub const DOCUMENT_REF_SIZE: usize = 8 + 2 + 1 + 12;
+
+/// Largest serialized document accepted, matching the BSON spec's 16MB limit
+pub const MAX_DOCUMENT_SIZE: usize = 16 * 1024 * 1024;
 
 /// Simple BSON serialization error
 #[derive(Debug, thiserror::Error)]
@@ -28,7 +40,7 @@ pub enum BsonError {
     Io(#[from] std::io::Error),
     #[error("Invalid BSON type: {0}")]
     InvalidType(u8),
-    #[error("Unsupported value type for basic encoding")]
+    #[error("Unsupported value type for basic encoding: 0x{0:02x}")]
     UnsupportedType(i32),
     #[error("Invalid string encoding")]
     InvalidString,
@@ -157,7 +169,7 @@ impl<W: Write + Seek> BsonEncoder<W> {
 
         // Stream only requested fields
         for field_name in fields {
-            if let Some(value) = doc.get(*field_name) {
+            if let Some(value) = doc.get(field_name) {
                 self.encode_field(field_name, value, 0)?;
             } else {
                 return Err(BsonError::FieldNotFound(field_name.to_string()));
@@ -263,6 +275,14 @@ impl<W: Write + Seek> BsonEncoder<W> {
                 self.bytes_written += 4 + 1 + bin.len();
                 Ok(())
             }
+            Value::DocumentRef(id) => {
+                self.writer
+                    .write_i32::<LittleEndian>(DOCUMENT_REF_SIZE as i32)?;
+                self.writer.write_u8(SUBTYPE_DOCUMENT_REF)?;
+                self.writer.write_all(&id.to_bytes())?;
+                self.bytes_written += 4 + 1 + DOCUMENT_REF_SIZE;
+                Ok(())
+            }
         }
     }
 
@@ -349,7 +369,7 @@ impl<W: Write + Seek> BsonEncoder<W> {
         let mut size = 4; // Length prefix
 
         for field_name in fields {
-            if let Some(value) = doc.get(*field_name) {
+            if let Some(value) = doc.get(field_name) {
                 size += 1; // Type byte
                 size += field_name.len() + 1; // Field name + null terminator
                 size += self.estimate_value_size(value, 0)?;
@@ -396,6 +416,7 @@ impl<W: Write + Seek> BsonEncoder<W> {
             }
             Value::DateTime(_) => Ok(8),
             Value::Binary(bin) => Ok(4 + 1 + bin.len()), // Length + subtype + data
+            Value::DocumentRef(_) => Ok(4 + 1 + DOCUMENT_REF_SIZE),
         }
     }
 
@@ -407,6 +428,24 @@ impl<W: Write + Seek> BsonEncoder<W> {
     }
 }
 
+/// A byte range skipped by `BsonDecoder::decode_documents_lossy`
+#[derive(Debug)]
+pub struct SkippedRegion {
+    /// Offset of the first skipped byte, counted from the start of the stream
+    pub offset: usize,
+    /// Number of bytes skipped
+    pub length: usize,
+    /// Why decoding failed at `offset`
+    pub error: BsonError,
+}
+
+/// Result of a lossy multi-document decode
+#[derive(Debug, Default)]
+pub struct LossyDecode {
+    pub documents: Vec<Document>,
+    pub skipped: Vec<SkippedRegion>,
+}
+
 /// Streaming BSON decoder with memory limits and progress tracking
 pub struct BsonDecoder<R> {
     reader: R,
@@ -520,6 +559,165 @@ impl<R: Read> BsonDecoder<R> {
         })
     }
 
+    /// Stream decode the documents for which `predicate` holds, dropping the rest.
+    ///
+    /// Every document is fully parsed before the predicate sees it, so this saves
+    /// memory but not decoding work; see `decode_matching_field` for a cheaper filter
+    /// on a single field. Errors are yielded as in `decode_documents`.
+    pub fn decode_matching<'a, F>(
+        &'a mut self,
+        predicate: F,
+    ) -> impl Iterator<Item = Result<Document, BsonError>> + 'a
+    where
+        F: Fn(&Document) -> bool + 'a,
+    {
+        self.decode_documents().filter(move |result| match result {
+            Ok(doc) => predicate(doc),
+            Err(_) => true,
+        })
+    }
+
+    /// Stream decode the documents whose top-level `field` satisfies `predicate`.
+    ///
+    /// Skipped documents are still read from the stream, but only partially parsed:
+    /// their fields are walked without decoding any value except `field`'s. A document
+    /// is fully parsed only once it matches. Documents without `field` never match, and
+    /// `field` names a top-level field only (no dotted paths).
+    pub fn decode_matching_field<'a, F>(
+        &'a mut self,
+        field: &'a str,
+        predicate: F,
+    ) -> impl Iterator<Item = Result<Document, BsonError>> + 'a
+    where
+        F: Fn(&Value) -> bool + 'a,
+    {
+        std::iter::from_fn(move || {
+            loop {
+                let document_bytes = match self.read_document_bytes() {
+                    Ok(bytes) => bytes,
+                    Err(BsonError::UnexpectedEndOfData { .. }) => return None, // End of stream
+                    Err(e) => return Some(Err(e)),
+                };
+                match peek_field(&document_bytes, field) {
+                    Ok(Some(value)) if predicate(&value) => {
+                        return Some(deserialize_document(&document_bytes));
+                    }
+                    Ok(_) => continue,
+                    Err(e) => return Some(Err(e)),
+                }
+            }
+        })
+    }
+
+    /// Read the next length-prefixed document, returning its bytes including the prefix
+    fn read_document_bytes(&mut self) -> Result<Vec<u8>, BsonError> {
+        let mut length_bytes = [0u8; 4];
+        self.read_exact(&mut length_bytes)?;
+        let document_length = u32::from_le_bytes(length_bytes) as usize;
+
+        if document_length > self.memory_limit {
+            return Err(BsonError::DocumentTooLarge(document_length));
+        }
+        if document_length < 5 {
+            return Err(BsonError::InvalidEmbeddedDocument);
+        }
+
+        let mut document_data = vec![0u8; document_length];
+        document_data[..4].copy_from_slice(&length_bytes);
+        self.read_exact(&mut document_data[4..])?;
+        self.update_progress(document_length, document_length);
+        Ok(document_data)
+    }
+
+    /// Decode every remaining document, skipping over malformed ones instead of stopping.
+    ///
+    /// When the document at the current offset fails to decode, the decoder tries to
+    /// resynchronize: first at the offset its length prefix points to, then byte by byte
+    /// after the bad offset, accepting the first position holding a fully valid document
+    /// (or the end of the stream). Everything in between is reported as a `SkippedRegion`.
+    ///
+    /// Limits of the heuristic:
+    /// - The rest of the stream is buffered in memory, since resynchronizing needs to look back.
+    /// - A corrupt length prefix falls back to the byte scan, which can lock onto an embedded
+    ///   subdocument of the bad document if one happens to be valid on its own.
+    /// - The byte scan is quadratic in the size of a bad region in the worst case.
+    pub fn decode_documents_lossy(&mut self) -> Result<LossyDecode, BsonError> {
+        let base = self.bytes_read;
+        let mut data = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let n = self.read(&mut chunk)?;
+            if n == 0 {
+                break;
+            }
+            data.extend_from_slice(&chunk[..n]);
+        }
+        self.update_progress(data.len(), data.len());
+
+        let mut result = LossyDecode::default();
+        let mut offset = 0;
+        while offset < data.len() {
+            match Self::decode_at(&data, offset) {
+                Ok((doc, length)) => {
+                    result.documents.push(doc);
+                    offset += length;
+                }
+                Err(error) => {
+                    let resume = Self::resync(&data, offset);
+                    result.skipped.push(SkippedRegion {
+                        offset: base + offset,
+                        length: resume - offset,
+                        error,
+                    });
+                    offset = resume;
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Decode the length-prefixed document starting at `offset`, returning it and its length
+    fn decode_at(data: &[u8], offset: usize) -> Result<(Document, usize), BsonError> {
+        let rest = &data[offset..];
+        if rest.len() < 4 {
+            return Err(BsonError::UnexpectedEndOfData {
+                expected: 4,
+                actual: rest.len(),
+            });
+        }
+        let length = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+        if length < 5 {
+            return Err(BsonError::InvalidEmbeddedDocument);
+        }
+        if length > rest.len() {
+            return Err(BsonError::UnexpectedEndOfData {
+                expected: length,
+                actual: rest.len(),
+            });
+        }
+        deserialize_document(&rest[..length]).map(|doc| (doc, length))
+    }
+
+    /// Find where decoding should resume after a failure at `offset`
+    fn resync(data: &[u8], offset: usize) -> usize {
+        let rest = &data[offset..];
+        if rest.len() >= 4 {
+            let declared = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let next = offset.saturating_add(declared);
+            if declared >= 5
+                && next <= data.len()
+                && (next == data.len() || Self::decode_at(data, next).is_ok())
+            {
+                return next;
+            }
+        }
+
+        (offset + 1..data.len())
+            .find(|&candidate| Self::decode_at(data, candidate).is_ok())
+            .unwrap_or(data.len())
+    }
+
     /// Lazy decode: read only specific fields from a document
     /// This is more memory-efficient for large documents when you only need certain fields
     pub fn decode_partial_document(&mut self, fields: &[&str]) -> Result<Document, BsonError> {
@@ -574,12 +772,7 @@ impl<R: Read> BsonDecoder<R> {
         let mut data_map = BTreeMap::new();
         let mut found_fields = std::collections::HashSet::new();
 
-        loop {
-            let field_type = match cursor.read_u8() {
-                Ok(ft) => ft,
-                Err(_) => break, // End of data
-            };
-
+        while let Ok(field_type) = cursor.read_u8() {
             if field_type == 0x00 {
                 break;
             } // Null terminator
@@ -596,7 +789,7 @@ impl<R: Read> BsonDecoder<R> {
                 found_fields.insert(field_name);
             } else {
                 // Skip this field's value
-                self.skip_value(&mut cursor, field_type)?;
+                skip_value(&mut cursor, field_type)?;
             }
         }
 
@@ -613,83 +806,6 @@ impl<R: Read> BsonDecoder<R> {
         })
     }
 
-    /// Skip a BSON value without decoding it (for lazy decoding)
-    fn skip_value(&mut self, cursor: &mut Cursor<&[u8]>, bson_type: u8) -> Result<(), BsonError> {
-        match bson_type {
-            TYPE_NULL => Ok(()),
-            TYPE_BOOL => {
-                read_u8_checked(cursor)?;
-                Ok(())
-            }
-            TYPE_INT32 => {
-                read_i32_checked(cursor)?;
-                Ok(())
-            }
-            TYPE_INT64 => {
-                read_i64_checked(cursor)?;
-                Ok(())
-            }
-            TYPE_DOUBLE => {
-                read_f64_checked(cursor)?;
-                Ok(())
-            }
-            TYPE_STRING => {
-                let length = read_i32_checked(cursor)?;
-                if length <= 0 {
-                    return Err(BsonError::InvalidStringLength(length));
-                }
-                let available = cursor.get_ref().len() - cursor.position() as usize;
-                if available < length as usize {
-                    return Err(BsonError::UnexpectedEndOfData {
-                        expected: length as usize,
-                        actual: available,
-                    });
-                }
-                cursor.seek(SeekFrom::Current(length as i64))?;
-                Ok(())
-            }
-            TYPE_OBJECTID => {
-                cursor.seek(SeekFrom::Current(12))?;
-                Ok(())
-            }
-            TYPE_ARRAY | TYPE_OBJECT => {
-                let length = read_i32_checked(cursor)? as u32;
-                if length < 4 {
-                    return Err(BsonError::InvalidEmbeddedDocument);
-                }
-                let available = cursor.get_ref().len() - cursor.position() as usize;
-                if available < (length as usize - 4) {
-                    return Err(BsonError::UnexpectedEndOfData {
-                        expected: length as usize - 4,
-                        actual: available,
-                    });
-                }
-                cursor.seek(SeekFrom::Current((length as i64) - 4))?;
-                Ok(())
-            }
-            TYPE_DATETIME => {
-                read_i64_checked(cursor)?;
-                Ok(())
-            }
-            TYPE_BINARY => {
-                let length = read_i32_checked(cursor)?;
-                if length < 0 {
-                    return Err(BsonError::InvalidBinaryLength(length));
-                }
-                let available = cursor.get_ref().len() - cursor.position() as usize;
-                if available < (length as usize + 1) {
-                    return Err(BsonError::UnexpectedEndOfData {
-                        expected: length as usize + 1,
-                        actual: available,
-                    });
-                }
-                cursor.seek(SeekFrom::Current((length as i64) + 1))?; // +1 for subtype
-                Ok(())
-            }
-            _ => Err(BsonError::InvalidType(bson_type)),
-        }
-    }
-
     /// Lazy decode: get field names without decoding values
     /// Useful for discovering document structure without loading all data
     pub fn get_field_names(&mut self) -> Result<Vec<String>, BsonError> {
@@ -732,12 +848,7 @@ impl<R: Read> BsonDecoder<R> {
         let mut cursor = Cursor::new(document_data.as_slice());
         let mut field_names = Vec::new();
 
-        loop {
-            let field_type = match cursor.read_u8() {
-                Ok(ft) => ft,
-                Err(_) => break, // End of data
-            };
-
+        while let Ok(field_type) = cursor.read_u8() {
             if field_type == 0x00 {
                 break;
             } // Null terminator
@@ -750,7 +861,7 @@ impl<R: Read> BsonDecoder<R> {
             field_names.push(field_name);
 
             // Skip the value
-            self.skip_value(&mut cursor, field_type)?;
+            skip_value(&mut cursor, field_type)?;
         }
 
         Ok(field_names)
@@ -796,6 +907,116 @@ impl<R: Read> BsonDecoder<R> {
     }
 }
 
+/// Decode only the top-level `field` of a serialized document, skipping over the others
+/// without decoding them. Returns `None` if the document has no such field.
+pub fn peek_field(document_bytes: &[u8], field: &str) -> Result<Option<Value>, BsonError> {
+    if document_bytes.len() < 5 {
+        return Err(BsonError::InvalidEmbeddedDocument);
+    }
+    let mut cursor = Cursor::new(&document_bytes[4..]);
+
+    while let Ok(field_type) = cursor.read_u8() {
+        if field_type == 0x00 {
+            break;
+        } // Null terminator
+
+        let field_name = read_cstring(&mut cursor)?;
+        if field_name.is_empty() {
+            return Err(BsonError::MalformedFieldName);
+        }
+
+        if field_name == field {
+            return deserialize_value(&mut cursor, field_type).map(Some);
+        }
+        skip_value(&mut cursor, field_type)?;
+    }
+
+    Ok(None)
+}
+
+/// Skip a BSON value without decoding it (for lazy decoding)
+///
+/// Advances the cursor past exactly one value of `bson_type`: scalars by their fixed
+/// width, strings, binaries and embedded documents by their length prefix. Lengths are
+/// checked against the remaining bytes, so truncated data is an error rather than a seek
+/// past the end. Used by `peek_field`, `decode_partial_document` and `get_field_names`.
+fn skip_value(cursor: &mut Cursor<&[u8]>, bson_type: u8) -> Result<(), BsonError> {
+    match bson_type {
+        TYPE_NULL => Ok(()),
+        TYPE_BOOL => {
+            read_u8_checked(cursor)?;
+            Ok(())
+        }
+        TYPE_INT32 => {
+            read_i32_checked(cursor)?;
+            Ok(())
+        }
+        TYPE_INT64 => {
+            read_i64_checked(cursor)?;
+            Ok(())
+        }
+        TYPE_DOUBLE => {
+            read_f64_checked(cursor)?;
+            Ok(())
+        }
+        TYPE_STRING => {
+            let length = read_i32_checked(cursor)?;
+            if length <= 0 {
+                return Err(BsonError::InvalidStringLength(length));
+            }
+            let available = remaining(cursor);
+            if available < length as usize {
+                return Err(BsonError::UnexpectedEndOfData {
+                    expected: length as usize,
+                    actual: available,
+                });
+            }
+            cursor.seek(SeekFrom::Current(length as i64))?;
+            Ok(())
+        }
+        TYPE_OBJECTID => {
+            ensure_available(cursor, 12)?;
+            cursor.seek(SeekFrom::Current(12))?;
+            Ok(())
+        }
+        TYPE_ARRAY | TYPE_OBJECT => {
+            let length = read_i32_checked(cursor)? as u32;
+            if length < 4 {
+                return Err(BsonError::InvalidEmbeddedDocument);
+            }
+            let available = remaining(cursor);
+            if available < (length as usize - 4) {
+                return Err(BsonError::UnexpectedEndOfData {
+                    expected: length as usize - 4,
+                    actual: available,
+                });
+            }
+            cursor.seek(SeekFrom::Current((length as i64) - 4))?;
+            Ok(())
+        }
+        TYPE_DATETIME => {
+            read_i64_checked(cursor)?;
+            Ok(())
+        }
+        TYPE_BINARY => {
+            let length = read_i32_checked(cursor)?;
+            if length < 0 {
+                return Err(BsonError::InvalidBinaryLength(length));
+            }
+            let available = remaining(cursor);
+            if available < (length as usize + 1) {
+                return Err(BsonError::UnexpectedEndOfData {
+                    expected: length as usize + 1,
+                    actual: available,
+                });
+            }
+            cursor.seek(SeekFrom::Current((length as i64) + 1))?; // +1 for subtype
+            Ok(())
+        }
+        _ => Err(BsonError::InvalidType(bson_type)),
+    }
+}
+
 // BSON is Binary JSON
 /// Serialize document to BSON with 4-byte little-endian length prefix
 pub fn serialize_document(doc: &Document) -> Result<Vec<u8>, BsonError> {
@@ -815,8 +1036,13 @@ pub fn serialize_document(doc: &Document) -> Result<Vec<u8>, BsonError> {
     // Null terminator
     buffer.write_u8(0x00)?;
 
-    // Write actual length at beginning
-    let total_length = buffer.len() as u32;
+    // Write actual length at beginning, refusing lengths the prefix can't represent
+    // rather than truncating them into a corrupt blob
+    if buffer.len() > MAX_DOCUMENT_SIZE {
+        return Err(BsonError::DocumentTooLarge(buffer.len()));
+    }
+    let total_length =
+        u32::try_from(buffer.len()).map_err(|_| BsonError::DocumentTooLarge(buffer.len()))?;
     let mut cursor = Cursor::new(&mut buffer);
     cursor.set_position(0);
     cursor.write_u32::<LittleEndian>(total_length)?;
@@ -859,7 +1085,7 @@ pub fn deserialize_document(data: &[u8]) -> Result<Document, BsonError> {
         }
 
         // Check for maximum document size (16MB)
-        if document_length > 16 * 1024 * 1024 {
+        if document_length > MAX_DOCUMENT_SIZE {
             return Err(BsonError::DocumentTooLarge(document_length));
         }
 
@@ -914,6 +1140,7 @@ fn value_to_bson_type(value: &Value) -> u8 {
         Value::Object(_) => TYPE_OBJECT,
         Value::DateTime(_) => TYPE_DATETIME,
         Value::Binary(_) => TYPE_BINARY,
+        Value::DocumentRef(_) => TYPE_BINARY,
     }
 }
 
@@ -977,7 +1204,24 @@ fn serialize_value(buffer: &mut Vec<u8>, value: &Value) -> Result<(), BsonError>
             buffer.extend_from_slice(bin);
             Ok(())
         }
+        Value::DocumentRef(id) => {
+            buffer.write_i32::<LittleEndian>(DOCUMENT_REF_SIZE as i32)?;
+            buffer.write_u8(SUBTYPE_DOCUMENT_REF)?;
+            buffer.extend_from_slice(&id.to_bytes());
+            Ok(())
+        }
+    }
+}
+
+// A binary payload as a value: a document reference if it carries that subtype and
+// size, which must then hold a valid id, or plain binary otherwise
+fn binary_value(subtype: u8, data: Vec<u8>) -> Result<Value, BsonError> {
+    if subtype != SUBTYPE_DOCUMENT_REF || data.len() != DOCUMENT_REF_SIZE {
+        return Ok(Value::Binary(data));
     }
+    DocumentId::from_bytes(&data)
+        .map(Value::DocumentRef)
+        .map_err(|_| BsonError::InvalidEmbeddedDocument)
 }
 
 fn read_u8_checked(cursor: &mut Cursor<&[u8]>) -> Result<u8, BsonError> {
@@ -992,37 +1236,27 @@ fn read_u8_checked(cursor: &mut Cursor<&[u8]>) -> Result<u8, BsonError> {
     }
 }
 fn read_i32_checked(cursor: &mut Cursor<&[u8]>) -> Result<i32, BsonError> {
-    use std::io::ErrorKind;
-    match cursor.read_i32::<LittleEndian>() {
-        Ok(b) => Ok(b),
-        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Err(BsonError::UnexpectedEndOfData {
-            expected: 4,
-            actual: 0,
-        }),
-        Err(e) => Err(BsonError::Io(e)),
-    }
+    ensure_available(cursor, 4)?;
+    Ok(cursor.read_i32::<LittleEndian>()?)
 }
 fn read_i64_checked(cursor: &mut Cursor<&[u8]>) -> Result<i64, BsonError> {
-    use std::io::ErrorKind;
-    match cursor.read_i64::<LittleEndian>() {
-        Ok(b) => Ok(b),
-        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Err(BsonError::UnexpectedEndOfData {
-            expected: 8,
-            actual: 0,
-        }),
-        Err(e) => Err(BsonError::Io(e)),
-    }
+    ensure_available(cursor, 8)?;
+    Ok(cursor.read_i64::<LittleEndian>()?)
 }
 fn read_f64_checked(cursor: &mut Cursor<&[u8]>) -> Result<f64, BsonError> {
-    use std::io::ErrorKind;
-    match cursor.read_f64::<LittleEndian>() {
-        Ok(b) => Ok(b),
-        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Err(BsonError::UnexpectedEndOfData {
-            expected: 8,
-            actual: 0,
-        }),
-        Err(e) => Err(BsonError::Io(e)),
-    }
+    ensure_available(cursor, 8)?;
+    Ok(cursor.read_f64::<LittleEndian>()?)
+}
+/// Fail with the exact shortfall before reading a fixed-width little-endian value
+fn ensure_available(cursor: &Cursor<&[u8]>, width: usize) -> Result<(), BsonError> {
+    let available = remaining(cursor);
+    if available < width {
+        return Err(BsonError::UnexpectedEndOfData {
+            expected: width,
+            actual: available,
+        });
+    }
+    Ok(())
 }
 fn read_exact_checked(cursor: &mut Cursor<&[u8]>, buf: &mut [u8]) -> Result<(), BsonError> {
     use std::io::ErrorKind;
@@ -1036,7 +1270,26 @@ fn read_exact_checked(cursor: &mut Cursor<&[u8]>, buf: &mut [u8]) -> Result<(),
     }
 }
 
+/// Deepest nesting of arrays/objects the decoder follows before giving up.
+///
+/// Generous compared to the encoder and validator default of 100, so anything they accept
+/// reads back, while crafted input cannot recurse deep enough to overflow the stack.
+pub const MAX_DECODE_DEPTH: usize = 512;
+
+/// Bytes left after the cursor, zero if it has been positioned past the end
+fn remaining(cursor: &Cursor<&[u8]>) -> usize {
+    (cursor.get_ref().len() as u64).saturating_sub(cursor.position()) as usize
+}
+
 fn deserialize_value(cursor: &mut Cursor<&[u8]>, bson_type: u8) -> Result<Value, BsonError> {
+    deserialize_value_at_depth(cursor, bson_type, 0)
+}
+
+fn deserialize_value_at_depth(
+    cursor: &mut Cursor<&[u8]>,
+    bson_type: u8,
+    depth: usize,
+) -> Result<Value, BsonError> {
     match bson_type {
         TYPE_NULL => Ok(Value::Null),
         TYPE_BOOL => Ok(Value::Bool(read_u8_checked(cursor)? != 0)),
@@ -1048,7 +1301,7 @@ fn deserialize_value(cursor: &mut Cursor<&[u8]>, bson_type: u8) -> Result<Value,
             if length <= 0 {
                 return Err(BsonError::InvalidStringLength(length));
             }
-            let available = cursor.get_ref().len() - cursor.position() as usize;
+            let available = remaining(cursor);
             if available < length as usize {
                 return Err(BsonError::UnexpectedEndOfData {
                     expected: length as usize,
@@ -1067,11 +1320,14 @@ fn deserialize_value(cursor: &mut Cursor<&[u8]>, bson_type: u8) -> Result<Value,
             Ok(Value::ObjectId(ObjectId::from_bytes(bytes)))
         }
         TYPE_ARRAY | TYPE_OBJECT => {
+            if depth >= MAX_DECODE_DEPTH {
+                return Err(BsonError::NestedDocumentTooDeep);
+            }
             let length = read_i32_checked(cursor)? as u32;
             if length < 4 {
                 return Err(BsonError::InvalidEmbeddedDocument);
             }
-            let available = cursor.get_ref().len() - cursor.position() as usize;
+            let available = remaining(cursor);
             if available < (length as usize - 4) {
                 return Err(BsonError::UnexpectedEndOfData {
                     expected: length as usize - 4,
@@ -1095,14 +1351,20 @@ fn deserialize_value(cursor: &mut Cursor<&[u8]>, bson_type: u8) -> Result<Value,
                 if field_name.is_empty() {
                     return Err(BsonError::MalformedFieldName);
                 }
-                let field_value = deserialize_value(&mut embedded_cursor, field_type)?;
+                let field_value =
+                    deserialize_value_at_depth(&mut embedded_cursor, field_type, depth + 1)?;
                 obj.insert(field_name, field_value);
             }
             if bson_type == TYPE_ARRAY {
-                // Convert numeric keys to array
+                // Convert numeric keys to array. An index can't exceed the element count,
+                // otherwise a single crafted key like "4000000000" would allocate gigabytes.
+                let element_count = obj.len();
                 let mut arr = Vec::new();
                 for (key, value) in obj {
                     if let Ok(index) = key.parse::<usize>() {
+                        if index >= element_count {
+                            return Err(BsonError::InvalidEmbeddedDocument);
+                        }
                         while arr.len() <= index {
                             arr.push(Value::Null);
                         }
@@ -1125,17 +1387,17 @@ fn deserialize_value(cursor: &mut Cursor<&[u8]>, bson_type: u8) -> Result<Value,
             if length < 0 {
                 return Err(BsonError::InvalidBinaryLength(length));
             }
-            let available = cursor.get_ref().len() - cursor.position() as usize;
+            let available = remaining(cursor);
             if available < (length as usize + 1) {
                 return Err(BsonError::UnexpectedEndOfData {
                     expected: length as usize + 1,
                     actual: available,
                 });
             }
-            read_u8_checked(cursor)?; // Skip subtype
+            let subtype = read_u8_checked(cursor)?;
             let mut data = vec![0u8; length as usize];
             read_exact_checked(cursor, &mut data)?;
-            Ok(Value::Binary(data))
+            binary_value(subtype, data)
         }
         _ => Err(BsonError::InvalidType(bson_type)),
     }
@@ -1170,10 +1432,8 @@ fn read_cstring(cursor: &mut Cursor<&[u8]>) -> Result<String, BsonError> {
 }
 
 /// Encode a single Value into BSON binary format (basic types only)
-/// Returns value bytes
-pub fn encode_value(value: &Value) -> Result<Vec<u8>, BsonError> {
-    use crate::document::bson::*;
-    use byteorder::{LittleEndian, WriteBytesExt};
+/// Returns value bytes, or `UnsupportedType` for arrays and objects instead of panicking
+pub fn try_encode_value(value: &Value) -> Result<Vec<u8>, BsonError> {
     let mut buf = Vec::new();
     match value {
         Value::Null => {}
@@ -1201,11 +1461,24 @@ pub fn encode_value(value: &Value) -> Result<Vec<u8>, BsonError> {
         Value::DateTime(dt) => {
             buf.write_i64::<LittleEndian>(dt.timestamp_millis())?;
         }
-        _ => return Err(BsonError::UnsupportedType(0)),
+        Value::DocumentRef(id) => {
+            buf.write_i32::<LittleEndian>(DOCUMENT_REF_SIZE as i32)?;
+            buf.push(SUBTYPE_DOCUMENT_REF);
+            buf.extend_from_slice(&id.to_bytes());
+        }
+        Value::Array(_) | Value::Object(_) => {
+            return Err(BsonError::UnsupportedType(value_to_bson_type(value) as i32));
+        }
     }
     Ok(buf)
 }
 
+/// Encode a single Value into BSON binary format (basic types only)
+/// Kept for existing callers; see `try_encode_value`
+pub fn encode_value(value: &Value) -> Result<Vec<u8>, BsonError> {
+    try_encode_value(value)
+}
+
 /// Decode a single Value from BSON binary format (basic types only)
 /// Returns (Value, bytes_consumed)
 pub fn decode_value(data: &[u8], bson_type: u8) -> Result<(Value, usize), BsonError> {
@@ -1242,6 +1515,140 @@ mod tests {
         assert!(length > 0);
     }
 
+    fn person_stream() -> Vec<u8> {
+        let mut stream = Vec::new();
+        for (name, age) in [("ann", 31), ("bob", 17), ("cy", 45), ("dee", 12)] {
+            let mut doc = Document::new();
+            doc.set("name", Value::String(name.to_string()));
+            doc.set("age", Value::I32(age));
+            doc.set("bio", Value::String("x".repeat(200)));
+            stream.extend_from_slice(&serialize_document(&doc).unwrap());
+        }
+        // A document without the filtered field
+        let mut anonymous = Document::new();
+        anonymous.set("name", Value::String("eve".to_string()));
+        stream.extend_from_slice(&serialize_document(&anonymous).unwrap());
+        stream
+    }
+
+    fn names(docs: Vec<Result<Document, BsonError>>) -> Vec<String> {
+        docs.into_iter()
+            .map(|doc| match doc.unwrap().get("name") {
+                Some(Value::String(name)) => name.clone(),
+                other => panic!("expected a name, got {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_matching_yields_only_matches() {
+        let stream = person_stream();
+        let mut decoder = BsonDecoder::new(Cursor::new(&stream));
+        let adults: Vec<_> = decoder
+            .decode_matching(|doc| {
+                doc.get("age")
+                    .and_then(Value::as_i64)
+                    .is_some_and(|age| age >= 18)
+            })
+            .collect();
+
+        assert_eq!(names(adults), vec!["ann", "cy"]);
+        assert_eq!(decoder.bytes_read(), stream.len());
+    }
+
+    #[test]
+    fn test_decode_matching_field_skips_non_matching() {
+        let stream = person_stream();
+        let mut decoder = BsonDecoder::new(Cursor::new(&stream));
+        let minors: Vec<_> = decoder
+            .decode_matching_field("age", |age| age.as_i64().is_some_and(|age| age < 18))
+            .collect();
+
+        assert_eq!(names(minors), vec!["bob", "dee"]);
+        assert_eq!(decoder.bytes_read(), stream.len());
+
+        let mut decoder = BsonDecoder::new(Cursor::new(&stream));
+        let named: Vec<_> = decoder
+            .decode_matching_field("name", |name| *name == Value::String("eve".to_string()))
+            .collect();
+        assert_eq!(names(named), vec!["eve"]);
+
+        let mut decoder = BsonDecoder::new(Cursor::new(&stream));
+        assert_eq!(decoder.decode_matching_field("missing", |_| true).count(), 0);
+    }
+
+    #[test]
+    fn test_decode_matching_field_reports_malformed_document() {
+        let mut doc = Document::new();
+        doc.set("age", Value::I32(40));
+        let good = serialize_document(&doc).unwrap();
+        let mut bad = good.clone();
+        // Corrupt the type byte of the last field ("age" follows "_id")
+        let age_type = bad.len() - 1 - 4 - "age".len() - 1 - 1;
+        bad[age_type] = 0x7F;
+
+        let stream = [good.clone(), bad, good].concat();
+        let mut decoder = BsonDecoder::new(Cursor::new(&stream));
+        let results: Vec<_> = decoder.decode_matching_field("age", |_| true).collect();
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(BsonError::InvalidType(0x7F))));
+        assert!(results[2].is_ok());
+    }
+
+    #[test]
+    fn test_decode_documents_lossy_skips_corrupt_document() {
+        let mut first = Document::new();
+        first.set("name", Value::String("first".to_string()));
+        let mut bad = Document::new();
+        bad.set("name", Value::String("bad".to_string()));
+        let mut last = Document::new();
+        last.set("name", Value::String("last".to_string()));
+
+        let first_bytes = serialize_document(&first).unwrap();
+        let mut bad_bytes = serialize_document(&bad).unwrap();
+        // An unknown type byte for the first field after _id
+        let field_start = bad_bytes.len() - "bad".len() - 12;
+        bad_bytes[field_start] = 0x7F;
+        assert!(deserialize_document(&bad_bytes).is_err());
+
+        let mut stream = first_bytes.clone();
+        stream.extend_from_slice(&bad_bytes);
+        stream.extend_from_slice(&serialize_document(&last).unwrap());
+
+        let result = BsonDecoder::new(Cursor::new(&stream))
+            .decode_documents_lossy()
+            .unwrap();
+
+        assert_eq!(result.documents.len(), 2);
+        assert_eq!(result.documents[0].get("name"), first.get("name"));
+        assert_eq!(result.documents[1].get("name"), last.get("name"));
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].offset, first_bytes.len());
+        assert_eq!(result.skipped[0].length, bad_bytes.len());
+    }
+
+    #[test]
+    fn test_decode_documents_lossy_corrupt_length_prefix() {
+        let mut doc = Document::new();
+        doc.set("n", Value::I32(1));
+        let good = serialize_document(&doc).unwrap();
+
+        let mut stream = good.clone();
+        stream.extend_from_slice(&[0xFF, 0xFF, 0xFF, 0x7F, 0x01, 0x02]);
+        stream.extend_from_slice(&good);
+
+        let result = BsonDecoder::new(Cursor::new(&stream))
+            .decode_documents_lossy()
+            .unwrap();
+
+        assert_eq!(result.documents.len(), 2);
+        assert_eq!(result.skipped.len(), 1);
+        assert_eq!(result.skipped[0].offset, good.len());
+        assert_eq!(result.skipped[0].length, 6);
+    }
+
     /// Test basic roundtrip serialization/deserialization
     /// This is the fundamental test that our BSON implementation works end-to-end
     #[test]
@@ -1276,7 +1683,7 @@ mod tests {
             ("bool_false", Value::Bool(false), TYPE_BOOL),
             ("int32", Value::I32(42), TYPE_INT32),
             ("int64", Value::I64(1234567890123456789), TYPE_INT64),
-            ("double", Value::F64(3.14159), TYPE_DOUBLE),
+            ("double", Value::F64(std::f64::consts::PI), TYPE_DOUBLE),
             (
                 "string",
                 Value::String("Hello, World!".to_string()),
@@ -1289,6 +1696,11 @@ mod tests {
                 Value::Binary(vec![0x01, 0x02, 0x03, 0x04]),
                 TYPE_BINARY,
             ),
+            (
+                "document_ref",
+                Value::DocumentRef(DocumentId::new(3, 7)),
+                TYPE_BINARY,
+            ),
         ];
 
         for (name, value, _bson_type) in test_cases {
@@ -1329,7 +1741,7 @@ mod tests {
             Value::String("first".to_string()),
             Value::I32(42),
             Value::Bool(true),
-            Value::F64(3.14),
+            Value::F64(std::f64::consts::PI),
         ];
         doc.set("items", Value::Array(array));
 
@@ -1341,7 +1753,7 @@ mod tests {
             assert_eq!(deserialized_array[0], Value::String("first".to_string()));
             assert_eq!(deserialized_array[1], Value::I32(42));
             assert_eq!(deserialized_array[2], Value::Bool(true));
-            assert_eq!(deserialized_array[3], Value::F64(3.14));
+            assert_eq!(deserialized_array[3], Value::F64(std::f64::consts::PI));
         } else {
             panic!("Expected array value");
         }
@@ -1407,20 +1819,61 @@ mod tests {
         let deserialized = deserialize_document(&serialized).unwrap();
 
         // Navigate through the nested structure
-        if let Some(Value::Object(root_obj)) = deserialized.get("root_object") {
-            if let Some(Value::Array(level1_arr)) = root_obj.get("level1_array") {
-                if let Some(Value::Object(level2_obj)) = level1_arr.get(0) {
-                    if let Some(Value::Object(level3_obj)) = level2_obj.get("level2_object") {
-                        if let Some(Value::Array(level3_arr)) = level3_obj.get("level3_array") {
-                            if let Some(Value::Object(deepest_obj)) = level3_arr.get(0) {
-                                assert_eq!(
-                                    deepest_obj.get("deepest_field"),
-                                    Some(&Value::String("deepest".to_string()))
-                                );
-                            }
-                        }
-                    }
-                }
+        if let Some(Value::Object(root_obj)) = deserialized.get("root_object")
+            && let Some(Value::Array(level1_arr)) = root_obj.get("level1_array")
+            && let Some(Value::Object(level2_obj)) = level1_arr.first()
+            && let Some(Value::Object(level3_obj)) = level2_obj.get("level2_object")
+            && let Some(Value::Array(level3_arr)) = level3_obj.get("level3_array")
+            && let Some(Value::Object(deepest_obj)) = level3_arr.first()
+        {
+            assert_eq!(
+                deepest_obj.get("deepest_field"),
+                Some(&Value::String("deepest".to_string()))
+            );
+        }
+    }
+
+    /// Structural equality that also treats NaN as equal to itself, bit for bit
+    fn same_value(a: &Value, b: &Value) -> bool {
+        match (a, b) {
+            (Value::F64(x), Value::F64(y)) => x.to_bits() == y.to_bits(),
+            (Value::Array(x), Value::Array(y)) => {
+                x.len() == y.len() && x.iter().zip(y).all(|(x, y)| same_value(x, y))
+            }
+            (Value::Object(x), Value::Object(y)) => {
+                x.len() == y.len()
+                    && x.iter().zip(y).all(|((xk, xv), (yk, yv))| xk == yk && same_value(xv, yv))
+            }
+            _ => a == b,
+        }
+    }
+
+    proptest::proptest! {
+        /// Every value type, nested inside arrays and objects, survives a BSON roundtrip
+        #[test]
+        fn prop_document_roundtrip(
+            fields in proptest::collection::btree_map(
+                "[a-z][a-z0-9_]{0,7}",
+                proptest::arbitrary::any::<Value>(),
+                0..8,
+            )
+        ) {
+            let mut doc = Document::new();
+            for (key, value) in fields {
+                doc.set(key, value);
+            }
+
+            let serialized = serialize_document(&doc).unwrap();
+            let deserialized = deserialize_document(&serialized).unwrap();
+
+            proptest::prop_assert_eq!(deserialized.id(), doc.id());
+            proptest::prop_assert_eq!(deserialized.len(), doc.len());
+            for (key, value) in doc.iter() {
+                let decoded = deserialized.get(key);
+                proptest::prop_assert!(
+                    decoded.is_some_and(|decoded| same_value(decoded, value)),
+                    "field {} changed: {:?} -> {:?}", key, value, decoded
+                );
             }
         }
     }
@@ -1454,7 +1907,7 @@ mod tests {
             let field_name = format!("field_{}", i);
             let value = match i % 7 {
                 0 => Value::String(format!("string_{}", i)),
-                1 => Value::I32(i as i32),
+                1 => Value::I32(i),
                 2 => Value::I64(i as i64),
                 3 => Value::F64(i as f64),
                 4 => Value::Bool(i % 2 == 0),
@@ -1732,7 +2185,7 @@ mod tests {
     /// Test invalid UTF-8 sequences in strings
     #[test]
     fn test_error_handling_invalid_utf8_sequences() {
-        let invalid_utf8_cases = vec![
+        let invalid_utf8_cases = [
             vec![0xFF, 0xFE, 0x00],       // Invalid UTF-8 sequence
             vec![0xC0, 0xAF],             // Overlong encoding
             vec![0xE0, 0x80, 0x80],       // Overlong encoding
@@ -1850,8 +2303,8 @@ mod tests {
             (Value::I32(-42), TYPE_INT32, "int32_negative"),
             (Value::I64(123456789), TYPE_INT64, "int64"),
             (Value::I64(-123456789), TYPE_INT64, "int64_negative"),
-            (Value::F64(3.14159), TYPE_DOUBLE, "double"),
-            (Value::F64(-3.14159), TYPE_DOUBLE, "double_negative"),
+            (Value::F64(std::f64::consts::PI), TYPE_DOUBLE, "double"),
+            (Value::F64(-std::f64::consts::PI), TYPE_DOUBLE, "double_negative"),
             (
                 Value::String("Hello, World!".to_string()),
                 TYPE_STRING,
@@ -1902,6 +2355,96 @@ mod tests {
         }
     }
 
+    /// Test that try_encode_value rejects container types with an error instead of panicking
+    #[test]
+    fn test_error_handling_excessive_decode_depth() {
+        // Every level is an object field "a" holding the next level, built inside-out
+        let mut level = vec![5, 0, 0, 0, 0];
+        for _ in 0..(MAX_DECODE_DEPTH + 10) {
+            let mut next = vec![0u8; 4];
+            next.push(TYPE_OBJECT);
+            next.extend_from_slice(b"a\0");
+            next.extend_from_slice(&level);
+            next.push(0);
+            let length = next.len() as u32;
+            next[..4].copy_from_slice(&length.to_le_bytes());
+            level = next;
+        }
+
+        assert!(matches!(
+            deserialize_document(&level),
+            Err(BsonError::NestedDocumentTooDeep)
+        ));
+    }
+
+    #[test]
+    fn test_error_handling_sparse_array_index() {
+        // An array with a single element claiming index 4000000000
+        let mut array = vec![0u8; 4];
+        array.push(TYPE_INT32);
+        array.extend_from_slice(b"4000000000\0");
+        array.extend_from_slice(&1i32.to_le_bytes());
+        array.push(0);
+        let length = array.len() as u32;
+        array[..4].copy_from_slice(&length.to_le_bytes());
+
+        let mut doc = vec![0u8; 4];
+        doc.push(TYPE_ARRAY);
+        doc.extend_from_slice(b"arr\0");
+        doc.extend_from_slice(&array);
+        doc.push(0);
+        let length = doc.len() as u32;
+        doc[..4].copy_from_slice(&length.to_le_bytes());
+
+        assert!(matches!(
+            deserialize_document(&doc),
+            Err(BsonError::InvalidEmbeddedDocument)
+        ));
+    }
+
+    #[test]
+    fn test_truncated_fixed_width_reports_shortfall() {
+        let data = [0u8; 5];
+        match decode_value(&data, TYPE_INT64) {
+            Err(BsonError::UnexpectedEndOfData { expected, actual }) => {
+                assert_eq!((expected, actual), (8, 5));
+            }
+            other => panic!("expected UnexpectedEndOfData, got {:?}", other),
+        }
+        assert!(matches!(
+            decode_value(&data[..3], TYPE_DOUBLE),
+            Err(BsonError::UnexpectedEndOfData { expected: 8, actual: 3 })
+        ));
+    }
+
+    #[test]
+    fn test_serialize_document_rejects_oversized_document() {
+        let mut doc = Document::new();
+        doc.set("blob", Value::Binary(vec![0u8; MAX_DOCUMENT_SIZE]));
+
+        match serialize_document(&doc) {
+            Err(BsonError::DocumentTooLarge(size)) => assert!(size > MAX_DOCUMENT_SIZE),
+            other => panic!("expected DocumentTooLarge, got {:?}", other.map(|b| b.len())),
+        }
+    }
+
+    #[test]
+    fn test_try_encode_value_unsupported_types() {
+        let array = Value::Array(vec![Value::I32(1), Value::I32(2)]);
+        match try_encode_value(&array) {
+            Err(BsonError::UnsupportedType(t)) => assert_eq!(t, TYPE_ARRAY as i32),
+            other => panic!("Expected UnsupportedType for array, got {:?}", other),
+        }
+
+        let object = Value::Object(BTreeMap::new());
+        match encode_value(&object) {
+            Err(BsonError::UnsupportedType(t)) => assert_eq!(t, TYPE_OBJECT as i32),
+            other => panic!("Expected UnsupportedType for object, got {:?}", other),
+        }
+
+        assert!(try_encode_value(&Value::I32(7)).is_ok());
+    }
+
     /// Test error handling in decode_value for insufficient data
     #[test]
     fn test_decode_value_insufficient_data() {
@@ -2034,7 +2577,7 @@ mod tests {
             let field_name = format!("field_{}", i);
             let value = match i % 5 {
                 0 => Value::String(format!("string_{}", i)),
-                1 => Value::I32(i as i32),
+                1 => Value::I32(i),
                 2 => Value::F64(i as f64),
                 3 => Value::Bool(i % 2 == 0),
                 4 => Value::ObjectId(ObjectId::new()),
@@ -2084,7 +2627,7 @@ mod tests {
         doc.set("bool_false", Value::Bool(false));
         doc.set("int32_field", Value::I32(42));
         doc.set("int64_field", Value::I64(1234567890123456789));
-        doc.set("double_field", Value::F64(3.14159));
+        doc.set("double_field", Value::F64(std::f64::consts::PI));
         doc.set("string_field", Value::String("Hello, BSON!".to_string()));
         doc.set("objectid_field", Value::ObjectId(ObjectId::new()));
         doc.set("datetime_field", Value::DateTime(Utc::now()));
@@ -2103,7 +2646,7 @@ mod tests {
             Value::String("array_item_1".to_string()),
             Value::I32(456),
             Value::Bool(true),
-            Value::F64(2.718),
+            Value::F64(std::f64::consts::E),
         ];
         doc.set("array_field", Value::Array(array));
 
@@ -2120,7 +2663,7 @@ mod tests {
             deserialized.get("int64_field"),
             Some(&Value::I64(1234567890123456789))
         );
-        assert_eq!(deserialized.get("double_field"), Some(&Value::F64(3.14159)));
+        assert_eq!(deserialized.get("double_field"), Some(&Value::F64(std::f64::consts::PI)));
         assert_eq!(
             deserialized.get("string_field"),
             Some(&Value::String("Hello, BSON!".to_string()))
@@ -2155,7 +2698,7 @@ mod tests {
             assert_eq!(array_data[0], Value::String("array_item_1".to_string()));
             assert_eq!(array_data[1], Value::I32(456));
             assert_eq!(array_data[2], Value::Bool(true));
-            assert_eq!(array_data[3], Value::F64(2.718));
+            assert_eq!(array_data[3], Value::F64(std::f64::consts::E));
         } else {
             panic!("Expected array");
         }
@@ -2283,7 +2826,7 @@ mod tests {
             let field_name = format!("field_{}", i);
             let value = match i % 5 {
                 0 => Value::String(format!("string_{}", i)),
-                1 => Value::I32(i as i32),
+                1 => Value::I32(i),
                 2 => Value::F64(i as f64),
                 3 => Value::Bool(i % 2 == 0),
                 4 => Value::ObjectId(ObjectId::new()),
@@ -2382,6 +2925,87 @@ mod tests {
         assert_eq!(decoder.bytes_read(), serialized.len());
     }
 
+    #[test]
+    fn test_document_ref_keeps_target_object_id() {
+        let target_id = ObjectId::new();
+        let reference = DocumentId::with_object_id(u64::MAX, 42, Some(target_id));
+        let mut doc = Document::new();
+        doc.set("tagged", Value::DocumentRef(reference));
+        doc.set("untagged", Value::DocumentRef(DocumentId::new(1, 0)));
+
+        let serialized = serialize_document(&doc).unwrap();
+        let deserialized = deserialize_document(&serialized).unwrap();
+
+        match deserialized.get("tagged") {
+            Some(Value::DocumentRef(id)) => {
+                assert_eq!((id.page_id(), id.slot_id()), (u64::MAX, 42));
+                assert_eq!(id.object_id(), Some(target_id));
+            }
+            other => panic!("expected a document reference, got {:?}", other),
+        }
+        match deserialized.get("untagged") {
+            Some(Value::DocumentRef(id)) => assert_eq!(id.object_id(), None),
+            other => panic!("expected a document reference, got {:?}", other),
+        }
+
+        let skipped = peek_field(&serialized, "untagged").unwrap();
+        assert_eq!(skipped, Some(Value::DocumentRef(DocumentId::new(1, 0))));
+    }
+
+    #[test]
+    fn test_document_ref_rejects_bad_flag() {
+        let mut doc = Document::new();
+        doc.set("r", Value::DocumentRef(DocumentId::new(1, 2)));
+        let mut serialized = serialize_document(&doc).unwrap();
+
+        let flag = serialized.len() - 1 - 12 - 1;
+        serialized[flag] = 7;
+        assert!(deserialize_document(&serialized).is_err());
+    }
+
+    #[test]
+    fn test_document_ref_is_user_defined_binary() {
+        let mut doc = Document::new();
+        doc.set("r", Value::DocumentRef(DocumentId::new(1, 2)));
+        let serialized = serialize_document(&doc).unwrap();
+
+        // Type, "r\0", payload length, then the subtype ahead of the id. The element
+        // follows `_id`, so it ends just before the document's terminator.
+        let element = &serialized[serialized.len() - 1 - (3 + 4 + 1 + DOCUMENT_REF_SIZE)..];
+        assert_eq!(&element[..3], &[TYPE_BINARY, b'r', 0]);
+        assert_eq!(&element[3..7], &(DOCUMENT_REF_SIZE as i32).to_le_bytes());
+        assert_eq!(element[7], SUBTYPE_DOCUMENT_REF);
+
+        // Other binary under that subtype is left as binary
+        let mut doc = Document::new();
+        doc.set("r", Value::Binary(vec![1, 2, 3]));
+        let mut serialized = serialize_document(&doc).unwrap();
+        let subtype = serialized.len() - 1 - 3 - 1;
+        serialized[subtype] = SUBTYPE_DOCUMENT_REF;
+        assert_eq!(
+            deserialize_document(&serialized).unwrap().get("r"),
+            Some(&Value::Binary(vec![1, 2, 3]))
+        );
+    }
+
+    #[test]
+    fn test_dbpointer_is_not_read_as_document_ref() {
+        // A DBPointer from another tool: a namespace string and an ObjectId
+        let mut element = vec![0x0C];
+        element.extend_from_slice(b"p\0");
+        element.extend_from_slice(&5i32.to_le_bytes());
+        element.extend_from_slice(b"coll\0");
+        element.extend_from_slice(&ObjectId::new().to_bytes());
+        let mut data = ((4 + element.len() + 1) as i32).to_le_bytes().to_vec();
+        data.extend_from_slice(&element);
+        data.push(0x00);
+
+        assert!(matches!(
+            deserialize_document(&data),
+            Err(BsonError::InvalidType(0x0C))
+        ));
+    }
+
     /// Test BsonDecoder with all BSON types
     #[test]
     fn test_bson_decoder_all_types() {
@@ -2390,7 +3014,7 @@ mod tests {
         doc.set("bool", Value::Bool(true));
         doc.set("int32", Value::I32(42));
         doc.set("int64", Value::I64(123456789));
-        doc.set("double", Value::F64(3.14159));
+        doc.set("double", Value::F64(std::f64::consts::PI));
         doc.set("string", Value::String("Hello".to_string()));
         doc.set("objectid", Value::ObjectId(ObjectId::new()));
         doc.set("datetime", Value::DateTime(Utc::now()));
@@ -2405,7 +3029,7 @@ mod tests {
         assert_eq!(decoded.get("bool"), Some(&Value::Bool(true)));
         assert_eq!(decoded.get("int32"), Some(&Value::I32(42)));
         assert_eq!(decoded.get("int64"), Some(&Value::I64(123456789)));
-        assert_eq!(decoded.get("double"), Some(&Value::F64(3.14159)));
+        assert_eq!(decoded.get("double"), Some(&Value::F64(std::f64::consts::PI)));
         assert_eq!(
             decoded.get("string"),
             Some(&Value::String("Hello".to_string()))
@@ -2582,7 +3206,7 @@ mod tests {
         let mut doc = Document::new();
 
         // Create a large array
-        let large_array: Vec<Value> = (0..1000).map(|i| Value::I32(i)).collect();
+        let large_array: Vec<Value> = (0..1000).map(Value::I32).collect();
         doc.set("large_array", Value::Array(large_array));
 
         let mut buffer = Vec::new();
@@ -2610,7 +3234,7 @@ mod tests {
         let mut doc = Document::new();
 
         // Create an array that's too large (over 1M elements)
-        let too_large_array: Vec<Value> = (0..1_100_000).map(|i| Value::I32(i)).collect();
+        let too_large_array: Vec<Value> = (0..1_100_000).map(Value::I32).collect();
         doc.set("too_large_array", Value::Array(too_large_array));
 
         let mut buffer = Vec::new();
@@ -2727,7 +3351,7 @@ mod tests {
             nested_data.insert("value".to_string(), Value::I32(i));
             nested_data.insert(
                 "array".to_string(),
-                Value::Array((0..100).map(|j| Value::I32(j)).collect()),
+                Value::Array((0..100).map(Value::I32).collect()),
             );
             if i < 9 {
                 nested_data.insert("nested".to_string(), Value::Object(current_data));
@@ -2893,4 +3517,52 @@ mod tests {
         let result2 = encoder2.encode_document(&doc);
         assert!(result2.is_ok());
     }
+
+    #[test]
+    fn test_skip_value_advances_past_exactly_one_value() {
+        let mut nested = BTreeMap::new();
+        nested.insert("name".to_string(), Value::String("inner".to_string()));
+        nested.insert(
+            "tags".to_string(),
+            Value::Array(vec![Value::I32(1), Value::String("two".to_string())]),
+        );
+        let values = [
+            Value::Null,
+            Value::Bool(true),
+            Value::I32(-7),
+            Value::I64(1 << 40),
+            Value::F64(2.5),
+            Value::String("hello".to_string()),
+            Value::ObjectId(ObjectId::new()),
+            Value::Array(vec![Value::I32(1), Value::Null]),
+            Value::Object(nested),
+            Value::DateTime(Utc::now()),
+            Value::Binary(vec![1, 2, 3, 4, 5]),
+            Value::DocumentRef(DocumentId::new(3, 9)),
+        ];
+
+        for value in &values {
+            let mut bytes = Vec::new();
+            serialize_value(&mut bytes, value).unwrap();
+            let width = bytes.len();
+            // Trailing bytes must be left alone
+            bytes.extend_from_slice(&[0xAA; 8]);
+
+            let mut cursor = Cursor::new(bytes.as_slice());
+            skip_value(&mut cursor, value_to_bson_type(value)).unwrap();
+            assert_eq!(cursor.position() as usize, width, "skipping {:?}", value);
+
+            // The same width decoding would consume
+            let mut cursor = Cursor::new(bytes.as_slice());
+            deserialize_value(&mut cursor, value_to_bson_type(value)).unwrap();
+            assert_eq!(cursor.position() as usize, width);
+
+            // Truncated values are rejected, not skipped past the end
+            if width > 0 {
+                let truncated = &bytes[..width - 1];
+                let mut cursor = Cursor::new(truncated);
+                assert!(skip_value(&mut cursor, value_to_bson_type(value)).is_err());
+            }
+        }
+    }
 }
@@ -0,0 +1,221 @@
+// In-place access to a possibly missing nested field
+//
+// `Document::entry` resolves a dotted path once and hands back either the value found
+// there or a place to put one, like `BTreeMap::entry` does for a single key. Read, check
+// and write then happen in one expression, e.g. counting a view:
+//
+//     doc.entry("stats.views")
+//         .and_modify(|views| *views = Value::I64(views.as_i64().unwrap_or(0) + 1))
+//         .or_insert(Value::I64(1));
+//
+// Paths follow `Document::get_path_mut`: a segment names a field of an object or, if it is
+// an integer, an existing element of an array, and `[n]` after a field name indexes an
+// array. Filling a vacant entry creates the objects and arrays missing along the way.
+
+use crate::document::{Document, PathStep};
+use crate::document::types::Value;
+use std::collections::BTreeMap;
+
+/// A field of a document that may or may not be present; see `Document::entry`.
+#[derive(Debug)]
+pub enum Entry<'a> {
+    /// The path leads to a value, which may be `Value::Null`
+    Occupied(&'a mut Value),
+    /// Nothing is stored at the path yet
+    Vacant(VacantEntry<'a>),
+}
+
+/// A path with no value, to be filled with `insert`.
+#[derive(Debug)]
+pub struct VacantEntry<'a> {
+    document: &'a mut Document,
+    path: String,
+}
+
+impl<'a> Entry<'a> {
+    pub(crate) fn new(document: &'a mut Document, path: &str) -> Self {
+        if document.get_path_mut(path).is_none() {
+            return Entry::Vacant(VacantEntry {
+                document,
+                path: path.to_string(),
+            });
+        }
+        Entry::Occupied(document.get_path_mut(path).expect("a value was just found here"))
+    }
+
+    /// The value at the path, inserting `default` first if there is none.
+    pub fn or_insert(self, default: Value) -> &'a mut Value {
+        self.or_insert_with(|| default)
+    }
+
+    /// The value at the path, inserting `f()` first if there is none. `f` is only called
+    /// when the entry is vacant.
+    pub fn or_insert_with<F: FnOnce() -> Value>(self, f: F) -> &'a mut Value {
+        match self {
+            Entry::Occupied(value) => value,
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    /// Runs `f` on the value if there is one, leaving a vacant entry as it is.
+    pub fn and_modify<F: FnOnce(&mut Value)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(value) => {
+                f(value);
+                Entry::Occupied(value)
+            }
+            vacant => vacant,
+        }
+    }
+}
+
+impl<'a> VacantEntry<'a> {
+    /// The dotted path the entry was looked up with
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Stores `value` at the path and returns a reference to it.
+    ///
+    /// Missing fields along the path become empty objects. A value that blocks the path,
+    /// such as a string where an object was expected or an array without the element
+    /// named, is replaced by an object, so the value always lands at the path. A
+    /// bracketed index instead makes an array of the value before it, extending it with
+    /// `Value::Null` up to the index. A path starting with a bracket has no field to
+    /// index, so its first index is used as a field name.
+    pub fn insert(self, value: Value) -> &'a mut Value {
+        let steps = PathStep::parse(&self.path);
+        let (first, rest) = steps.split_first().expect("a path has a step");
+        let slot = self.document.data.entry(first.to_string()).or_insert(Value::Null);
+        fill(slot, rest, value)
+    }
+}
+
+// Walks `steps` down from `current`, making a place for each one, and stores `value` at
+// the end
+fn fill<'v>(current: &'v mut Value, steps: &[PathStep], value: Value) -> &'v mut Value {
+    let Some((step, rest)) = steps.split_first() else {
+        *current = value;
+        return current;
+    };
+
+    let child = match *step {
+        PathStep::Index(index) => {
+            if !matches!(current, Value::Array(_)) {
+                *current = Value::Array(Vec::new());
+            }
+            let Value::Array(items) = current else {
+                unreachable!("current was just made an array");
+            };
+            if items.len() <= index {
+                items.resize(index + 1, Value::Null);
+            }
+            &mut items[index]
+        }
+        PathStep::Key(key) => {
+            let index = match current {
+                Value::Array(items) => step.index().filter(|&i| i < items.len()),
+                _ => None,
+            };
+            match (current, index) {
+                (Value::Array(items), Some(index)) => &mut items[index],
+                (current, _) => {
+                    if !matches!(current, Value::Object(_)) {
+                        *current = Value::Object(BTreeMap::new());
+                    }
+                    let Value::Object(map) = current else {
+                        unreachable!("current was just made an object");
+                    };
+                    map.entry(key.to_string()).or_insert(Value::Null)
+                }
+            }
+        }
+    };
+    fill(child, rest, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn increment(value: &mut Value) {
+        *value = Value::I64(value.as_i64().unwrap_or(0) + 1);
+    }
+
+    #[test]
+    fn test_and_modify_updates_existing_nested_value() {
+        let mut doc = Document::new();
+        let mut stats = BTreeMap::new();
+        stats.insert("views".to_string(), Value::I64(41));
+        doc.set("stats", Value::Object(stats));
+
+        let views = doc.entry("stats.views").and_modify(increment).or_insert(Value::I64(1));
+        assert_eq!(*views, Value::I64(42));
+        assert_eq!(doc.get_path("stats.views"), Some(&Value::I64(42)));
+
+        // Array elements are reachable by index, as with get_path
+        doc.set("tags", Value::Array(vec![Value::String("a".to_string())]));
+        assert!(matches!(doc.entry("tags.0"), Entry::Occupied(Value::String(_))));
+    }
+
+    #[test]
+    fn test_or_insert_creates_missing_intermediate_objects() {
+        let mut doc = Document::new();
+
+        let views = doc.entry("stats.daily.views").and_modify(increment).or_insert(Value::I64(1));
+        assert_eq!(*views, Value::I64(1));
+        assert_eq!(doc.get_path("stats.daily.views"), Some(&Value::I64(1)));
+        assert!(matches!(doc.get("stats"), Some(Value::Object(_))));
+
+        // Now present, so the default is ignored and the closure is not called
+        let views = doc
+            .entry("stats.daily.views")
+            .and_modify(increment)
+            .or_insert_with(|| panic!("entry should be occupied"));
+        assert_eq!(*views, Value::I64(2));
+
+        // Filling in next to existing fields keeps them
+        doc.entry("stats.daily.clicks").or_insert(Value::I32(0));
+        assert_eq!(doc.get_path("stats.daily.views"), Some(&Value::I64(2)));
+        assert_eq!(doc.get_path("stats.daily.clicks"), Some(&Value::I32(0)));
+    }
+
+    #[test]
+    fn test_vacant_insert_replaces_blocking_values() {
+        let mut doc = Document::new();
+        doc.set("name", Value::String("Ada".to_string()));
+        doc.set("items", Value::Array(vec![Value::Object(BTreeMap::new())]));
+
+        match doc.entry("items.0.qty") {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.path(), "items.0.qty");
+                entry.insert(Value::I32(3));
+            }
+            Entry::Occupied(_) => panic!("items.0.qty should be vacant"),
+        }
+        assert_eq!(doc.get_path("items.0.qty"), Some(&Value::I32(3)));
+
+        doc.entry("name.first").or_insert(Value::String("Ada".to_string()));
+        assert_eq!(doc.get_path("name.first"), Some(&Value::String("Ada".to_string())));
+    }
+
+    #[test]
+    fn test_vacant_insert_with_bracket_index_makes_array() {
+        let mut doc = Document::new();
+        doc.set("tags", Value::Array(vec![Value::String("a".to_string())]));
+
+        doc.entry("tags[2]").or_insert(Value::String("c".to_string()));
+        assert_eq!(
+            doc.get("tags"),
+            Some(&Value::Array(vec![
+                Value::String("a".to_string()),
+                Value::Null,
+                Value::String("c".to_string()),
+            ]))
+        );
+
+        doc.entry("points[0].x").or_insert(Value::I32(1));
+        assert_eq!(doc.get_path("points[0].x"), Some(&Value::I32(1)));
+        assert!(matches!(doc.entry("tags[0]"), Entry::Occupied(Value::String(_))));
+    }
+}
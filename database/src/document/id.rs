@@ -0,0 +1,143 @@
+use crate::document::Document;
+use crate::document::bson::DOCUMENT_REF_SIZE;
+use crate::document::object_id::ObjectId;
+use crate::error::DatabaseError;
+use crate::result::Result;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// Location of a stored document.
+///
+/// Ids handed out by the engine also carry the document's `_id`, which `get_document`
+/// checks against what it reads so that an id whose slot has since been reused is
+/// reported instead of returning an unrelated document. Equality, ordering and hashing
+/// only look at the location.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct DocumentId {
+    page_id: u64,
+    slot_id: u16,
+    object_id: Option<ObjectId>,
+}
+
+impl PartialEq for DocumentId {
+    fn eq(&self, other: &Self) -> bool {
+        self.page_id == other.page_id && self.slot_id == other.slot_id
+    }
+}
+
+impl Eq for DocumentId {}
+
+impl PartialOrd for DocumentId {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DocumentId {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.page_id, self.slot_id).cmp(&(other.page_id, other.slot_id))
+    }
+}
+
+impl fmt::Display for DocumentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.page_id, self.slot_id)
+    }
+}
+
+impl Hash for DocumentId {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.page_id.hash(state);
+        self.slot_id.hash(state);
+    }
+}
+
+impl DocumentId {
+    /// Create a new DocumentId
+    pub fn new(page_id: u64, slot_id: u16) -> Self {
+        Self {
+            page_id,
+            slot_id,
+            object_id: None,
+        }
+    }
+
+    // Rebuilds an id read back from storage, e.g. a stored document reference
+    pub(crate) fn with_object_id(page_id: u64, slot_id: u16, object_id: Option<ObjectId>) -> Self {
+        Self {
+            page_id,
+            slot_id,
+            object_id,
+        }
+    }
+
+    /// The `_id` of the document this id was issued for, if known
+    pub fn object_id(&self) -> Option<ObjectId> {
+        self.object_id
+    }
+
+    // Records which document the slot held when this id was handed out
+    pub(crate) fn tagged(self, document: &Document) -> Self {
+        Self {
+            object_id: document.get_id().copied(),
+            ..self
+        }
+    }
+
+    /// Get the page ID where the document is stored
+    pub fn page_id(&self) -> u64 {
+        self.page_id
+    }
+
+    /// Get the slot ID within the page where the document is stored
+    pub fn slot_id(&self) -> u16 {
+        self.slot_id
+    }
+
+    /// The id in the form a `Value::DocumentRef` is stored in: the page id and slot id,
+    /// little-endian, then 1 and the `_id` if the id carries one, or zeros if not.
+    pub fn to_bytes(&self) -> [u8; DOCUMENT_REF_SIZE] {
+        let mut bytes = [0u8; DOCUMENT_REF_SIZE];
+        bytes[..8].copy_from_slice(&self.page_id.to_le_bytes());
+        bytes[8..10].copy_from_slice(&self.slot_id.to_le_bytes());
+        if let Some(object_id) = self.object_id {
+            bytes[10] = 1;
+            bytes[11..].copy_from_slice(&object_id.to_bytes());
+        }
+        bytes
+    }
+
+    /// Reads an id written by `to_bytes`. The `_id` comes back with it, so `get_document`
+    /// still reports a slot that has since been reused. Fails with
+    /// `DatabaseError::Validation` unless `bytes` is exactly what `to_bytes` produces.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let invalid =
+            |reason: &str| DatabaseError::Validation(format!("invalid document id: {}", reason));
+        let bytes: &[u8; DOCUMENT_REF_SIZE] = bytes.try_into().map_err(|_| {
+            invalid(&format!("expected {} bytes, got {}", DOCUMENT_REF_SIZE, bytes.len()))
+        })?;
+        let page_id = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+        let slot_id = u16::from_le_bytes(bytes[8..10].try_into().unwrap());
+        let object_id = match bytes[10] {
+            0 if bytes[11..].iter().all(|&b| b == 0) => None,
+            1 => Some(ObjectId::from_bytes(bytes[11..].try_into().unwrap())),
+            _ => return Err(invalid("malformed _id")),
+        };
+        Ok(Self::with_object_id(page_id, slot_id, object_id))
+    }
+
+    /// `to_bytes` as lowercase hex, a string to hand to clients or store in a document
+    pub fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+
+    /// Parses a string written by `to_hex`, failing with `DatabaseError::Validation` for
+    /// anything else.
+    pub fn from_hex(s: &str) -> Result<Self> {
+        let bytes = hex::decode(s)
+            .map_err(|e| DatabaseError::Validation(format!("invalid document id '{}': {}", s, e)))?;
+        Self::from_bytes(&bytes)
+    }
+}
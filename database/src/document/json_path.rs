@@ -0,0 +1,212 @@
+// JSONPath-like selectors
+//
+// A JsonPath picks values out of a document by walking its nested objects and arrays,
+// e.g. `$.teams[*].members[*].name` selects the name of every member of every team.
+// Supported steps are `.field`, `[index]` and the `[*]` wildcard, which matches every
+// element of an array or every value of an object.
+
+use crate::document::types::Value;
+use crate::error::DatabaseError;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// One step of a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    /// `.name`: the named field of an object
+    Field(String),
+    /// `[n]`: the nth element of an array
+    Index(usize),
+    /// `[*]`: every element of an array or every value of an object
+    Wildcard,
+}
+
+/// A parsed selector such as `$.teams[0].members[*].name`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    segments: Vec<PathSegment>,
+}
+
+impl JsonPath {
+    /// Parse a path. The leading `$` is optional, as is the `.` before the first field,
+    /// so `$.a.b`, `.a.b` and `a.b` are the same path.
+    pub fn parse(input: &str) -> Result<Self, DatabaseError> {
+        let error =
+            |reason: &str| DatabaseError::Query(format!("invalid path '{}': {}", input, reason));
+
+        let (rest, rooted) = match input.strip_prefix('$') {
+            Some(rest) => (rest, true),
+            None => (input, false),
+        };
+        let mut chars = rest.chars().peekable();
+        let mut segments = Vec::new();
+
+        // A path without `$` may start directly with a field name
+        let mut expect_field = !rooted && !rest.is_empty() && !rest.starts_with(['.', '[']);
+
+        loop {
+            if expect_field {
+                let mut name = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c == '.' || c == '[' {
+                        break;
+                    }
+                    name.push(c);
+                    chars.next();
+                }
+                if name.is_empty() {
+                    return Err(error("empty field name"));
+                }
+                segments.push(PathSegment::Field(name));
+                expect_field = false;
+                continue;
+            }
+
+            match chars.next() {
+                None => break,
+                Some('.') => expect_field = true,
+                Some('[') => {
+                    let mut inner = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(c) => inner.push(c),
+                            None => return Err(error("missing ']'")),
+                        }
+                    }
+                    let segment =
+                        match inner.trim() {
+                            "*" => PathSegment::Wildcard,
+                            index => PathSegment::Index(index.parse().map_err(|_| {
+                                error(&format!("'{}' is not an array index", index))
+                            })?),
+                        };
+                    segments.push(segment);
+                }
+                Some(c) => return Err(error(&format!("unexpected '{}'", c))),
+            }
+        }
+
+        if segments.is_empty() {
+            return Err(error("path selects nothing"));
+        }
+        Ok(Self { segments })
+    }
+
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.segments
+    }
+
+    /// Every value the path reaches from `root`, in document order
+    pub(crate) fn select_from<'a>(&self, root: &'a BTreeMap<String, Value>) -> Vec<&'a Value> {
+        let (first, rest) = self
+            .segments
+            .split_first()
+            .expect("a parsed path has at least one segment");
+
+        let mut current: Vec<&Value> = match first {
+            PathSegment::Field(name) => root.get(name).into_iter().collect(),
+            PathSegment::Wildcard => root.values().collect(),
+            PathSegment::Index(_) => Vec::new(),
+        };
+
+        for segment in rest {
+            let mut next = Vec::new();
+            for value in current {
+                Self::step(segment, value, &mut next);
+            }
+            current = next;
+        }
+        current
+    }
+
+    fn step<'a>(segment: &PathSegment, value: &'a Value, out: &mut Vec<&'a Value>) {
+        match (segment, value) {
+            (PathSegment::Field(name), Value::Object(map)) => out.extend(map.get(name)),
+            (PathSegment::Index(index), Value::Array(items)) => out.extend(items.get(*index)),
+            (PathSegment::Wildcard, Value::Array(items)) => out.extend(items.iter()),
+            (PathSegment::Wildcard, Value::Object(map)) => out.extend(map.values()),
+            _ => {}
+        }
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = DatabaseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for segment in &self.segments {
+            match segment {
+                PathSegment::Field(name) => write!(f, ".{}", name)?,
+                PathSegment::Index(index) => write!(f, "[{}]", index)?,
+                PathSegment::Wildcard => write!(f, "[*]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_segments() {
+        let path = JsonPath::parse("$.teams[*].members[1].name").unwrap();
+        assert_eq!(
+            path.segments(),
+            &[
+                PathSegment::Field("teams".to_string()),
+                PathSegment::Wildcard,
+                PathSegment::Field("members".to_string()),
+                PathSegment::Index(1),
+                PathSegment::Field("name".to_string()),
+            ]
+        );
+        assert_eq!(path.to_string(), "$.teams[*].members[1].name");
+    }
+
+    #[test]
+    fn test_parse_optional_root() {
+        let expected = JsonPath::parse("$.a.b").unwrap();
+        assert_eq!(JsonPath::parse(".a.b").unwrap(), expected);
+        assert_eq!("a.b".parse::<JsonPath>().unwrap(), expected);
+        assert_eq!(
+            JsonPath::parse("$[*]").unwrap().segments(),
+            &[PathSegment::Wildcard]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for input in ["", "$", "$.", "$.a..b", "$.a[", "$.a[x]", "$.a[-1]", "$a"] {
+            match JsonPath::parse(input) {
+                Err(DatabaseError::Query(msg)) => assert!(msg.contains("invalid path")),
+                other => panic!("expected a query error for {:?}, got {:?}", input, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_wildcard_over_object_values() {
+        let mut inner = BTreeMap::new();
+        inner.insert("x".to_string(), Value::I32(1));
+        inner.insert("y".to_string(), Value::I32(2));
+        let mut root = BTreeMap::new();
+        root.insert("point".to_string(), Value::Object(inner));
+
+        let path = JsonPath::parse("$.point[*]").unwrap();
+        assert_eq!(
+            path.select_from(&root),
+            vec![&Value::I32(1), &Value::I32(2)]
+        );
+    }
+}
@@ -1,10 +1,20 @@
+pub mod id;
+pub mod json_path;
 pub mod object_id;
+pub mod predicate;
 pub mod types;
 pub mod bson;
+pub mod schema;
 pub mod validator;
+pub mod entry;
 
+use crate::document::entry::Entry;
+use crate::document::json_path::JsonPath;
 use crate::document::object_id::ObjectId;
-use crate::document::types::Value;
+use crate::document::types::{NonFiniteFloatMode, Value};
+use crate::error::DatabaseError;
+use crate::result::Result;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
@@ -14,6 +24,10 @@ use std::collections::BTreeMap;
 const MAX_DOCUMENT_SIZE: usize = 16 * 1024 * 1024; // 16mb
 const MAX_NAME_LENGTH: usize = 100; // 100 chars
 
+// Fields maintained by the storage engine when timestamps are enabled
+pub const CREATED_FIELD: &str = "_created";
+pub const UPDATED_FIELD: &str = "_updated";
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Document {
     data: BTreeMap<String, Value>,
@@ -41,7 +55,7 @@ impl Document {
         }
     }
 
-    pub fn from_json(input: &str) -> Result<Self, serde_json::Error> {
+    pub fn from_json(input: &str) -> Result<Self> {
         let map: BTreeMap<String, serde_json::Value> = serde_json::from_str(input)?;
         let data = map
             .into_iter()
@@ -53,6 +67,21 @@ impl Document {
         })
     }
 
+    /// The document as a JSON object: its fields converted with `Value::to_json`, plus
+    /// the `_id` as a hex string unless the id is null.
+    pub fn to_json(&self, mode: NonFiniteFloatMode) -> Result<serde_json::Value> {
+        let mut object = serde_json::Map::new();
+        if !self.id.is_null() {
+            object.insert("_id".to_string(), self.id.to_json(mode)?);
+        }
+        for (key, value) in &self.data {
+            object.insert(key.clone(), value.to_json(mode)?);
+        }
+        Ok(serde_json::Value::Object(object))
+    }
+
+    /// The value of a top-level field. A field set to `Value::Null` is present and
+    /// returns `Some(&Value::Null)`; only a missing field returns `None`.
     pub fn get(&self, input: &str) -> Option<&Value> {
         self.data.get(input)
     }
@@ -65,27 +94,209 @@ impl Document {
         self.data.remove(input)
     }
 
+    /// Removes a top-level field and returns its value, moving it out rather than
+    /// cloning it. Prefer this over `get(..).cloned()` followed by `remove` when the
+    /// value is being moved elsewhere. The same as `remove`.
+    pub fn take_field(&mut self, key: &str) -> Option<Value> {
+        self.remove(key)
+    }
+
+    /// The value of the first of `keys` that is present and not `Value::Null`. Missing
+    /// fields are skipped just like null ones.
+    pub fn coalesce_fields(&self, keys: &[&str]) -> Option<&Value> {
+        keys.iter()
+            .filter_map(|key| self.get(key))
+            .find(|value| !value.is_null())
+    }
+
+    /// Get a mutable reference to a field, inserting `f()` first if it is absent.
+    ///
+    /// Like `HashMap::entry(key).or_insert_with(f)`: `f` is only called when the field
+    /// is missing.
+    pub fn get_or_insert_with<F: FnOnce() -> Value>(&mut self, key: &str, f: F) -> &mut Value {
+        self.data.entry(key.to_string()).or_insert_with(f)
+    }
+
+    /// The field at a dotted path, for checking and changing it in one expression, e.g.
+    /// `doc.entry("stats.views").and_modify(bump).or_insert(Value::I64(1))`.
+    ///
+    /// The path is followed as by `get_path`. Filling a vacant entry creates the objects
+    /// missing along the way; see `Entry`.
+    pub fn entry(&mut self, path: &str) -> Entry<'_> {
+        Entry::new(self, path)
+    }
+
+    /// Follow a dotted path such as `teams.0.members.1.name` or `teams[0].members[1].name`.
+    ///
+    /// A segment names a field of an object or, if it is a non-negative integer, an
+    /// element of an array. A field name may be followed by bracketed indexes, as in
+    /// `grid[1][2]`, which only apply to arrays. Returns `None` when a segment is missing,
+    /// an index is out of bounds, or a segment does not fit the value it is applied to.
+    /// As with `get`, a field set to `Value::Null` is `Some(&Value::Null)`.
     pub fn get_path(&self, input: &str) -> Option<&Value> {
-        let mut cur;
+        let mut steps = PathStep::parse(input).into_iter();
 
-        let mut iter = input.split('.');
+        let mut cur = match steps.next()? {
+            PathStep::Key(first) => self.data.get(first),
+            PathStep::Index(_) => return None,
+        };
 
-        if let Some(first) = iter.next() {
-            cur = self.data.get(first);
-        } else {
+        for step in steps {
+            cur = step.child(cur?);
+        }
+
+        cur
+    }
+
+    /// Mutable counterpart of `get_path`, for changing a nested value in place. A path
+    /// ending on an object returns the whole `Value::Object`, so the subtree can be
+    /// replaced. Returns `None` for an empty path and wherever `get_path` would: a
+    /// segment that is missing or does not fit the value it is applied to.
+    pub fn get_path_mut(&mut self, input: &str) -> Option<&mut Value> {
+        if input.is_empty() {
             return None;
         }
+        let mut steps = PathStep::parse(input).into_iter();
+        let first = match steps.next()? {
+            PathStep::Key(first) => self.data.get_mut(first),
+            PathStep::Index(_) => None,
+        };
+        steps.fold(first, |cur, step| step.child_mut(cur?))
+    }
 
-        for key in iter {
-            match cur {
-                Some(Value::Object(map)) => {
-                    cur = map.get(key);
+    /// Stores `value` at a dotted path, replacing any value already there. Fields missing
+    /// along the way are created as empty objects, so `set_path("address.geo.lat", ..)`
+    /// works on an empty document. Existing values are followed as by `get_path`.
+    ///
+    /// Fails, leaving the document unchanged, for an empty path or one that runs into a
+    /// value other than an object, or into an array without the indexed element. Arrays
+    /// are never created, so a bracketed index into a missing field fails too.
+    pub fn set_path(&mut self, path: &str, value: Value) -> Result<()> {
+        let steps = PathStep::parse(path);
+        // `reason` says what is wrong with the value at the first `depth + 1` steps
+        let blocked = |depth: usize, reason: String| {
+            DatabaseError::Document(format!(
+                "cannot set '{}': '{}' {}",
+                path,
+                PathStep::join(&steps[..=depth]),
+                reason
+            ))
+        };
+        let mismatch = |found: &Value, step: PathStep| match step {
+            PathStep::Key(_) => format!("holds {}, not an object", found.type_name()),
+            PathStep::Index(_) => format!("holds {}, not an array", found.type_name()),
+        };
+        // Whether anything after the first `depth + 1` steps needs an existing array
+        let indexed_after = |depth: usize| {
+            steps[depth + 1..]
+                .iter()
+                .any(|step| matches!(step, PathStep::Index(_)))
+        };
+        if path.is_empty() {
+            return Err(DatabaseError::Document("cannot set an empty path".to_string()));
+        }
+        let Some(&PathStep::Key(first)) = steps.first() else {
+            return Err(DatabaseError::Document(format!(
+                "cannot set '{}': a path starts with a field name",
+                path
+            )));
+        };
+
+        if steps.len() == 1 {
+            self.data.insert(first.to_string(), value);
+            return Ok(());
+        }
+        let (leaf, parents) = steps.split_last().expect("a path has a step");
+
+        // Only existing values can block the path, and a field is only created once no
+        // index follows it, so a failure never leaves objects behind
+        if !self.data.contains_key(first) && indexed_after(0) {
+            return Err(blocked(0, "is missing".to_string()));
+        }
+        let mut cur = self
+            .data
+            .entry(first.to_string())
+            .or_insert_with(|| Value::Object(BTreeMap::new()));
+        for (depth, step) in parents.iter().enumerate().skip(1) {
+            cur = match (cur, *step) {
+                (Value::Object(map), PathStep::Key(key)) => {
+                    if !map.contains_key(key) && indexed_after(depth) {
+                        return Err(blocked(depth, "is missing".to_string()));
+                    }
+                    map.entry(key.to_string())
+                        .or_insert_with(|| Value::Object(BTreeMap::new()))
                 }
-                _ => return None,
+                (Value::Array(items), step) => match step.index() {
+                    Some(index) if index < items.len() => &mut items[index],
+                    _ => return Err(blocked(depth - 1, format!("has no element {}", step))),
+                },
+                (found, step) => return Err(blocked(depth - 1, mismatch(found, step))),
+            };
+        }
+
+        let depth = steps.len() - 2;
+        match (cur, *leaf) {
+            (Value::Object(map), PathStep::Key(key)) => {
+                map.insert(key.to_string(), value);
             }
+            (Value::Array(items), step) => match step.index() {
+                Some(index) if index < items.len() => items[index] = value,
+                _ => return Err(blocked(depth, format!("has no element {}", step))),
+            },
+            (found, step) => return Err(blocked(depth, mismatch(found, step))),
         }
+        Ok(())
+    }
 
-        cur
+    /// Every value matched by `path`, e.g. `$.teams[*].members[*].name`, in document order.
+    ///
+    /// Returns an empty vector when nothing matches.
+    pub fn select(&self, path: &JsonPath) -> Vec<&Value> {
+        path.select_from(&self.data)
+    }
+
+    /// A copy holding only the named top-level fields, with the same id.
+    ///
+    /// A field set to `Value::Null` is kept as `Null`, while a field the document does not
+    /// have is left out rather than added as `Null`.
+    pub fn project(&self, fields: &[&str]) -> Document {
+        let data = fields
+            .iter()
+            .filter_map(|&name| Some((name.to_string(), self.data.get(name)?.clone())))
+            .collect();
+        Document {
+            data,
+            id: self.id.clone(),
+        }
+    }
+
+    /// A copy of the object stored in `key` as a document of its own, so `Document`
+    /// methods such as `get_path` apply to it. The copy's id is `Value::Null`; an `_id`
+    /// field inside the object stays an ordinary field. Returns `None` when the field is
+    /// missing or not an object.
+    pub fn subdocument(&self, key: &str) -> Option<Document> {
+        match self.data.get(key)? {
+            Value::Object(map) => Some(Document {
+                data: map.clone(),
+                id: Value::Null,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Stores the fields of `document` as an object in `key`, replacing any value there.
+    /// The id of `document` is not stored.
+    pub fn set_subdocument<S: Into<String>>(&mut self, key: S, document: Document) {
+        self.set(key, Value::Object(document.data));
+    }
+
+    /// Copies every top-level field of `other` into this document, replacing fields of
+    /// the same name and leaving the others alone. Values are replaced whole, objects
+    /// included. The id of `other` is ignored.
+    pub fn merge(&mut self, other: &Document) {
+        for (key, value) in &other.data {
+            self.data.insert(key.clone(), value.clone());
+        }
     }
 
     pub fn get_id(&self) -> Option<&ObjectId> {
@@ -100,6 +311,11 @@ impl Document {
         &self.id
     }
 
+    // Used by the storage engine so a replacement keeps the stored document's `_id`
+    pub(crate) fn replace_id(&mut self, id: Value) {
+        self.id = id;
+    }
+
     pub fn ensure_id(&mut self) -> &ObjectId {
         // Check if id is already an ObjectId
         if let Value::ObjectId(ref oid) = self.id {
@@ -117,6 +333,16 @@ impl Document {
         }
     }
 
+    /// When the document was first stored, if timestamps are maintained for it
+    pub fn created_at(&self) -> Option<DateTime<Utc>> {
+        self.get(CREATED_FIELD).and_then(Value::as_datetime)
+    }
+
+    /// When the document was last written, if timestamps are maintained for it
+    pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+        self.get(UPDATED_FIELD).and_then(Value::as_datetime)
+    }
+
     /// Get an iterator over all field names in the document
     pub fn keys(&self) -> impl Iterator<Item = &String> {
         self.data.keys()
@@ -141,132 +367,174 @@ impl Document {
     pub fn len(&self) -> usize {
         self.data.len()
     }
-}
 
-// Returns the size of the document in bytes
-fn document_size_validation(document: &str) -> bool {
-    document.len() <= MAX_DOCUMENT_SIZE
-}
-
-fn document_name_validation(name: &str) -> bool {
-    !name.is_empty() && name.chars().count() <= MAX_NAME_LENGTH
-}
-
-// Combine both functions for normal calls outside of this file.
-pub fn validate_document(document: &str, name: &str) -> bool {
-    document_size_validation(document) && document_name_validation(name)
-}
+    /// Canonicalize numeric values in place, including the id and nested values.
+    ///
+    /// Two documents holding the same logical values (e.g. `I64(42)` vs `I32(42)`)
+    /// serialize to identical bytes after normalizing, so call this before hashing or
+    /// comparing serialized documents. See `Value::normalize` for the rules.
+    pub fn normalize(&mut self) {
+        self.id.normalize();
+        self.data.values_mut().for_each(Value::normalize);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::document::object_id::ObjectId;
-    use crate::document::types::Value;
+    /// Renders the document like `Value::pretty`, with `_id` as its first field.
+    pub fn pretty(&self, indent: usize, max_depth: Option<usize>) -> String {
+        let mut out = String::new();
+        let fields = std::iter::once(("_id", &self.id))
+            .chain(self.data.iter().map(|(key, value)| (key.as_str(), value)));
+        Value::write_pretty_fields(&mut out, fields, indent, 0, max_depth);
+        out
+    }
 
-    #[test]
-    fn test_new_document() {
-        let doc = Document::new();
-        assert!(doc.data.is_empty());
-        match &doc.id {
-            Value::ObjectId(_) => (),
-            _ => panic!("id should be an ObjectId"),
+    /// Replace every field value with `f(name, value)`, e.g. to widen all `I32` fields
+    /// to `I64` during a migration. The document id is never passed to `f`.
+    ///
+    /// With `deep`, nested objects and arrays are transformed too: their contents are
+    /// mapped first, then the container itself. Nested values are passed the name of
+    /// their own field; array elements get the name of the array's field.
+    pub fn map_values<F: FnMut(&str, Value) -> Value>(&mut self, deep: bool, mut f: F) {
+        for (name, value) in self.data.iter_mut() {
+            Self::map_value(name, value, deep, &mut f);
         }
     }
 
-    #[test]
-    fn test_with_id() {
-        let oid = ObjectId::new();
-        let doc = Document::with_id(oid.clone());
-        assert!(doc.data.is_empty());
-        match &doc.id {
-            Value::ObjectId(id) => assert_eq!(id, &oid),
-            _ => panic!("id should be an ObjectId"),
+    fn map_value<F: FnMut(&str, Value) -> Value>(
+        name: &str,
+        value: &mut Value,
+        deep: bool,
+        f: &mut F,
+    ) {
+        if deep {
+            match value {
+                Value::Object(map) => {
+                    for (key, nested) in map.iter_mut() {
+                        Self::map_value(key, nested, deep, f);
+                    }
+                }
+                Value::Array(items) => {
+                    for item in items.iter_mut() {
+                        Self::map_value(name, item, deep, f);
+                    }
+                }
+                _ => {}
+            }
         }
+        let taken = std::mem::replace(value, Value::Null);
+        *value = f(name, taken);
     }
+}
 
-    #[test]
-    fn test_from_json() {
-        let json = r#"{"foo": 42, "bar": true}"#;
-        let doc = Document::from_json(json).unwrap();
-        assert_eq!(doc.get("foo"), Some(&Value::I32(42)));
-        assert_eq!(doc.get("bar"), Some(&Value::Bool(true)));
-    }
+/// One step of a document path. `items.0.name` is three keys, while `items[0].name` is a
+/// key, an index and a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PathStep<'a> {
+    /// A field of an object, or an element of an array if it is a non-negative integer
+    Key(&'a str),
+    /// A bracketed index, which only applies to arrays
+    Index(usize),
+}
 
-    #[test]
-    fn test_get_set_remove() {
-        let mut doc = Document::new();
-        doc.set("alpha", Value::I32(1));
-        assert_eq!(doc.get("alpha"), Some(&Value::I32(1)));
-        let removed = doc.remove("alpha");
-        assert_eq!(removed, Some(Value::I32(1)));
-        assert_eq!(doc.get("alpha"), None);
+impl<'a> PathStep<'a> {
+    /// Splits `path` on dots and each segment into its field name and bracketed indexes.
+    /// A segment with brackets that are not all `[<digits>]` at its end is a field name
+    /// as written, so `a[b]` still names the field `a[b]`.
+    pub(crate) fn parse(path: &'a str) -> Vec<PathStep<'a>> {
+        let mut steps = Vec::new();
+        for segment in path.split('.') {
+            match bracket_indexes(segment) {
+                Some((name, indexes)) => {
+                    if !name.is_empty() {
+                        steps.push(PathStep::Key(name));
+                    }
+                    steps.extend(indexes.into_iter().map(PathStep::Index));
+                }
+                None => steps.push(PathStep::Key(segment)),
+            }
+        }
+        steps
     }
 
-    #[test]
-    fn test_get_path_simple() {
-        let mut doc = Document::new();
-        let mut inner = std::collections::BTreeMap::new();
-        inner.insert("y".to_owned(), Value::I32(9));
-        doc.set("x", Value::Object(inner));
-        assert_eq!(doc.get_path("x.y"), Some(&Value::I32(9)));
+    /// The steps written back as a path, e.g. for error messages
+    pub(crate) fn join(steps: &[PathStep]) -> String {
+        let mut path = String::new();
+        for (i, step) in steps.iter().enumerate() {
+            match step {
+                PathStep::Key(key) if i == 0 => path.push_str(key),
+                PathStep::Key(key) => {
+                    path.push('.');
+                    path.push_str(key);
+                }
+                PathStep::Index(index) => path.push_str(&format!("[{}]", index)),
+            }
+        }
+        path
     }
 
-    #[test]
-    fn test_get_path_missing() {
-        let doc = Document::new();
-        assert_eq!(doc.get_path("no.such.path"), None);
+    /// The array index the step names, if any
+    pub(crate) fn index(self) -> Option<usize> {
+        match self {
+            PathStep::Key(key) => key.parse().ok(),
+            PathStep::Index(index) => Some(index),
+        }
     }
 
-    #[test]
-    fn test_get_id_and_ensure_id() {
-        let mut doc = Document::new();
-        // get_id should always return Some
-        let id1 = doc.get_id().unwrap().clone();
-        // ensure_id should return the same id
-        let id2 = doc.ensure_id();
-        assert_eq!(&id1, id2);
+    fn child(self, value: &Value) -> Option<&Value> {
+        match (value, self) {
+            (Value::Object(map), PathStep::Key(key)) => map.get(key),
+            (Value::Array(items), step) => items.get(step.index()?),
+            _ => None,
+        }
     }
 
-    #[test]
-    fn test_ensure_id_sets_id_if_missing() {
-        // Manually set id to a non-ObjectId value
-        let mut doc = Document::new();
-        doc.id = Value::I32(123);
-        let id = doc.ensure_id().clone();
-        match &doc.id {
-            Value::ObjectId(oid) => assert_eq!(oid, &id),
-            _ => panic!("id should be ObjectId"),
+    fn child_mut(self, value: &mut Value) -> Option<&mut Value> {
+        match (value, self) {
+            (Value::Object(map), PathStep::Key(key)) => map.get_mut(key),
+            (Value::Array(items), step) => items.get_mut(step.index()?),
+            _ => None,
         }
     }
+}
 
-    #[test]
-    fn test_document_size_validation() {
-        let valid = "a".repeat(MAX_DOCUMENT_SIZE);
-        let invalid = "a".repeat(MAX_DOCUMENT_SIZE + 1);
-        assert!(super::document_size_validation(&valid));
-        assert!(!super::document_size_validation(&invalid));
+impl std::fmt::Display for PathStep<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathStep::Key(key) => write!(f, "{}", key),
+            PathStep::Index(index) => write!(f, "{}", index),
+        }
     }
+}
 
-    #[test]
-    fn test_document_name_validation() {
-        assert!(super::document_name_validation("a_valid_name"));
-        assert!(!super::document_name_validation(""));
-        let long_name = "a".repeat(MAX_NAME_LENGTH + 1);
-        assert!(!super::document_name_validation(&long_name));
-    }
+// Splits `name[1][2]` into `name` and its indexes, or returns `None` if the segment does
+// not end in well-formed brackets
+fn bracket_indexes(segment: &str) -> Option<(&str, Vec<usize>)> {
+    let open = segment.find('[')?;
+    let (name, brackets) = segment.split_at(open);
+    let inner = brackets.strip_prefix('[')?.strip_suffix(']')?;
+    let indexes = inner
+        .split("][")
+        .map(|index| {
+            if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+                return None;
+            }
+            index.parse().ok()
+        })
+        .collect::<Option<Vec<usize>>>()?;
+    Some((name, indexes))
+}
 
-    #[test]
-    fn test_validate_document() {
-        let valid_doc = "a".repeat(MAX_DOCUMENT_SIZE);
-        let valid_name = "goodname";
-        assert!(super::validate_document(&valid_doc, &valid_name));
+// Returns the size of the document in bytes
+fn document_size_validation(document: &str) -> bool {
+    document.len() <= MAX_DOCUMENT_SIZE
+}
 
-        let invalid_doc = "a".repeat(MAX_DOCUMENT_SIZE + 1);
-        assert!(!super::validate_document(&invalid_doc, &valid_name));
+fn document_name_validation(name: &str) -> bool {
+    !name.is_empty() && name.chars().count() <= MAX_NAME_LENGTH
+}
 
-        let invalid_name = "";
-        assert!(!super::validate_document(&valid_doc, &invalid_name));
-    }
+// Combine both functions for normal calls outside of this file.
+pub fn validate_document(document: &str, name: &str) -> bool {
+    document_size_validation(document) && document_name_validation(name)
 }
 
 // Example 1: Nested user profile with hobbies array and address object
@@ -406,3 +674,581 @@ fn example_mixed_types() -> Document {
         id: Value::ObjectId(ObjectId::new()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::object_id::ObjectId;
+    use crate::document::types::Value;
+    use crate::document::bson::{deserialize_document, serialize_document};
+
+    #[test]
+    fn test_new_document() {
+        let doc = Document::new();
+        assert!(doc.data.is_empty());
+        match &doc.id {
+            Value::ObjectId(_) => (),
+            _ => panic!("id should be an ObjectId"),
+        }
+    }
+
+    #[test]
+    fn test_with_id() {
+        let oid = ObjectId::new();
+        let doc = Document::with_id(oid);
+        assert!(doc.data.is_empty());
+        match &doc.id {
+            Value::ObjectId(id) => assert_eq!(id, &oid),
+            _ => panic!("id should be an ObjectId"),
+        }
+    }
+
+    #[test]
+    fn test_from_json() {
+        let json = r#"{"foo": 42, "bar": true}"#;
+        let doc = Document::from_json(json).unwrap();
+        assert_eq!(doc.get("foo"), Some(&Value::I32(42)));
+        assert_eq!(doc.get("bar"), Some(&Value::Bool(true)));
+    }
+
+    #[test]
+    fn test_get_set_remove() {
+        let mut doc = Document::new();
+        doc.set("alpha", Value::I32(1));
+        assert_eq!(doc.get("alpha"), Some(&Value::I32(1)));
+        let removed = doc.remove("alpha");
+        assert_eq!(removed, Some(Value::I32(1)));
+        assert_eq!(doc.get("alpha"), None);
+    }
+
+    #[test]
+    fn test_get_path_simple() {
+        let mut doc = Document::new();
+        let mut inner = std::collections::BTreeMap::new();
+        inner.insert("y".to_owned(), Value::I32(9));
+        doc.set("x", Value::Object(inner));
+        assert_eq!(doc.get_path("x.y"), Some(&Value::I32(9)));
+    }
+
+    #[test]
+    fn test_get_path_array_index() {
+        let mut doc = example_organization_structure();
+        doc.set(
+            "tags",
+            Value::Array(vec![
+                Value::String("a".to_string()),
+                Value::String("b".to_string()),
+            ]),
+        );
+
+        assert_eq!(doc.get_path("tags.1"), Some(&Value::String("b".to_string())));
+        assert_eq!(
+            doc.get_path("teams.0.members.1.name"),
+            Some(&Value::String("Dana".to_string()))
+        );
+        assert_eq!(doc.get_path("tags.2"), None);
+        assert_eq!(doc.get_path("teams.0.members.5.name"), None);
+        assert_eq!(doc.get_path("tags.first"), None);
+        assert_eq!(doc.get_path("tags.-1"), None);
+        // Numeric segments still name fields of objects
+        doc.set(
+            "rounds",
+            Value::Object(BTreeMap::from([("0".to_string(), Value::I32(7))])),
+        );
+        assert_eq!(doc.get_path("rounds.0"), Some(&Value::I32(7)));
+    }
+
+    #[test]
+    fn test_null_field_is_present() {
+        let mut doc = Document::new();
+        doc.set("nickname", Value::Null);
+        doc.set(
+            "address",
+            Value::Object(BTreeMap::from([("zip".to_string(), Value::Null)])),
+        );
+
+        assert_eq!(doc.get("nickname"), Some(&Value::Null));
+        assert_eq!(doc.get("email"), None);
+        assert_eq!(doc.get_path("address.zip"), Some(&Value::Null));
+        assert_eq!(doc.get_path("address.city"), None);
+    }
+
+    #[test]
+    fn test_project_keeps_null_and_skips_missing() {
+        let mut doc = Document::new();
+        doc.set("name", Value::String("Alice".to_string()));
+        doc.set("nickname", Value::Null);
+        doc.set("age", Value::I32(30));
+
+        let projected = doc.project(&["name", "nickname", "email"]);
+        assert_eq!(projected.id(), doc.id());
+        assert_eq!(projected.len(), 2);
+        assert_eq!(projected.get("name"), Some(&Value::String("Alice".to_string())));
+        assert_eq!(projected.get("nickname"), Some(&Value::Null));
+        assert_eq!(projected.get("email"), None);
+        assert_eq!(projected.get("age"), None);
+    }
+
+    #[test]
+    fn test_get_path_missing() {
+        let doc = Document::new();
+        assert_eq!(doc.get_path("no.such.path"), None);
+    }
+
+    #[test]
+    fn test_get_id_and_ensure_id() {
+        let mut doc = Document::new();
+        // get_id should always return Some
+        let id1 = *doc.get_id().unwrap();
+        // ensure_id should return the same id
+        let id2 = doc.ensure_id();
+        assert_eq!(&id1, id2);
+    }
+
+    #[test]
+    fn test_ensure_id_sets_id_if_missing() {
+        // Manually set id to a non-ObjectId value
+        let mut doc = Document::new();
+        doc.id = Value::I32(123);
+        let id = *doc.ensure_id();
+        match &doc.id {
+            Value::ObjectId(oid) => assert_eq!(oid, &id),
+            _ => panic!("id should be ObjectId"),
+        }
+    }
+
+    #[test]
+    fn test_normalize_produces_identical_serializations() {
+        use crate::document::bson::serialize_document;
+
+        let oid = ObjectId::new();
+
+        let mut inner_a = BTreeMap::new();
+        inner_a.insert("count".to_string(), Value::I64(7));
+        let mut a = Document::with_id(oid);
+        a.set("n", Value::I64(42));
+        a.set("list", Value::Array(vec![Value::I64(1), Value::I32(2)]));
+        a.set("inner", Value::Object(inner_a));
+        a.set("ratio", Value::F64(-0.0));
+
+        let mut inner_b = BTreeMap::new();
+        inner_b.insert("count".to_string(), Value::I32(7));
+        let mut b = Document::with_id(oid);
+        b.set("n", Value::I32(42));
+        b.set("list", Value::Array(vec![Value::I32(1), Value::I64(2)]));
+        b.set("inner", Value::Object(inner_b));
+        b.set("ratio", Value::F64(0.0));
+
+        assert_ne!(serialize_document(&a).unwrap(), serialize_document(&b).unwrap());
+
+        a.normalize();
+        b.normalize();
+        assert_eq!(serialize_document(&a).unwrap(), serialize_document(&b).unwrap());
+    }
+
+    #[test]
+    fn test_document_size_validation() {
+        let valid = "a".repeat(MAX_DOCUMENT_SIZE);
+        let invalid = "a".repeat(MAX_DOCUMENT_SIZE + 1);
+        assert!(super::document_size_validation(&valid));
+        assert!(!super::document_size_validation(&invalid));
+    }
+
+    #[test]
+    fn test_document_name_validation() {
+        assert!(super::document_name_validation("a_valid_name"));
+        assert!(!super::document_name_validation(""));
+        let long_name = "a".repeat(MAX_NAME_LENGTH + 1);
+        assert!(!super::document_name_validation(&long_name));
+    }
+
+    #[test]
+    fn test_validate_document() {
+        let valid_doc = "a".repeat(MAX_DOCUMENT_SIZE);
+        let valid_name = "goodname";
+        assert!(super::validate_document(&valid_doc, valid_name));
+
+        let invalid_doc = "a".repeat(MAX_DOCUMENT_SIZE + 1);
+        assert!(!super::validate_document(&invalid_doc, valid_name));
+
+        let invalid_name = "";
+        assert!(!super::validate_document(&valid_doc, invalid_name));
+    }
+
+    #[test]
+    fn test_get_or_insert_with_absent_key() {
+        let mut doc = Document::new();
+        let value = doc.get_or_insert_with("status", || Value::String("new".to_string()));
+        assert_eq!(*value, Value::String("new".to_string()));
+
+        *value = Value::String("active".to_string());
+        assert_eq!(doc.get("status"), Some(&Value::String("active".to_string())));
+    }
+
+    #[test]
+    fn test_get_or_insert_with_present_key() {
+        let mut doc = Document::new();
+        doc.set("count", Value::I32(3));
+
+        let mut called = false;
+        let value = doc.get_or_insert_with("count", || {
+            called = true;
+            Value::I32(0)
+        });
+        assert_eq!(*value, Value::I32(3));
+        assert!(!called, "closure must not run for an existing field");
+        assert_eq!(doc.len(), 1);
+    }
+
+    #[test]
+    fn test_select_wildcard_member_names() {
+        let doc = example_organization_structure();
+        let path = JsonPath::parse("$.teams[*].members[*].name").unwrap();
+        assert_eq!(
+            doc.select(&path),
+            vec![
+                &Value::String("Charlie".to_string()),
+                &Value::String("Dana".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_select_specific_index() {
+        let doc = example_organization_structure();
+        let path = JsonPath::parse("$.teams[0].members[1].role").unwrap();
+        assert_eq!(doc.select(&path), vec![&Value::String("Designer".to_string())]);
+
+        let team = JsonPath::parse("teams[0].name").unwrap();
+        assert_eq!(doc.select(&team), vec![&Value::String("Frontend".to_string())]);
+    }
+
+    #[test]
+    fn test_select_non_matching_path() {
+        let doc = example_organization_structure();
+        for path in [
+            "$.teams[*].members[*].email",
+            "$.teams[5].name",
+            "$.org_name[*]",
+            "$.departments[*].name",
+        ] {
+            let path = JsonPath::parse(path).unwrap();
+            assert!(doc.select(&path).is_empty(), "{} should match nothing", path);
+        }
+    }
+
+    #[test]
+    fn test_map_values_uppercase_strings() {
+        let mut doc = Document::new();
+        let id = doc.id().clone();
+        doc.set("name", Value::String("alice".to_string()));
+        doc.set("city", Value::String("Paris".to_string()));
+        doc.set("age", Value::I32(30));
+
+        doc.map_values(false, |_, value| match value {
+            Value::String(s) => Value::String(s.to_uppercase()),
+            other => other,
+        });
+
+        assert_eq!(doc.get("name"), Some(&Value::String("ALICE".to_string())));
+        assert_eq!(doc.get("city"), Some(&Value::String("PARIS".to_string())));
+        assert_eq!(doc.get("age"), Some(&Value::I32(30)));
+        assert_eq!(doc.id(), &id);
+    }
+
+    #[test]
+    fn test_map_values_widens_i32() {
+        let mut doc = Document::new();
+        doc.set("count", Value::I32(7));
+        doc.set("total", Value::I64(100));
+        doc.set(
+            "nested",
+            Value::Object(BTreeMap::from([("n".to_string(), Value::I32(1))])),
+        );
+
+        let mut seen = Vec::new();
+        doc.map_values(false, |name, value| {
+            seen.push(name.to_string());
+            match value {
+                Value::I32(n) => Value::I64(n as i64),
+                other => other,
+            }
+        });
+
+        assert_eq!(seen, vec!["count", "nested", "total"]);
+        assert_eq!(doc.get("count"), Some(&Value::I64(7)));
+        assert_eq!(doc.get("total"), Some(&Value::I64(100)));
+        // Shallow mapping leaves nested values alone
+        assert_eq!(doc.get_path("nested.n"), Some(&Value::I32(1)));
+    }
+
+    #[test]
+    fn test_map_values_deep() {
+        let mut doc = example_organization_structure();
+        doc.map_values(true, |name, value| match value {
+            Value::String(s) if name == "name" => Value::String(s.to_uppercase()),
+            other => other,
+        });
+
+        let path = JsonPath::parse("$.teams[*].members[*].name").unwrap();
+        assert_eq!(
+            doc.select(&path),
+            vec![
+                &Value::String("CHARLIE".to_string()),
+                &Value::String("DANA".to_string()),
+            ]
+        );
+        let team = JsonPath::parse("teams[0].name").unwrap();
+        assert_eq!(doc.select(&team), vec![&Value::String("FRONTEND".to_string())]);
+        let role = JsonPath::parse("$.teams[0].members[1].role").unwrap();
+        assert_eq!(doc.select(&role), vec![&Value::String("Designer".to_string())]);
+    }
+
+    #[test]
+    fn test_pretty_lists_id_first() {
+        let mut doc = Document::new();
+        doc.set("name", Value::String("Ada".to_string()));
+        let pretty = doc.pretty(2, None);
+
+        let lines: Vec<&str> = pretty.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[1], format!("  _id: {},", doc.id()));
+        assert_eq!(lines[2], "  name: \"Ada\"");
+        assert_eq!(doc.pretty(2, Some(0)), "{...}");
+    }
+
+    #[test]
+    fn test_take_field_moves_value_out() {
+        let mut doc = Document::new();
+        let items = Value::Array((0..100).map(Value::I32).collect());
+        let items_ptr = items.as_array().unwrap().as_ptr();
+        doc.set("items", items);
+
+        let taken = doc.take_field("items").unwrap();
+        // The same allocation, so nothing was cloned
+        assert_eq!(taken.as_array().unwrap().as_ptr(), items_ptr);
+        assert_eq!(doc.get("items"), None);
+        assert_eq!(doc.take_field("items"), None);
+    }
+
+    #[test]
+    fn test_coalesce_fields_skips_null_and_missing() {
+        let mut doc = Document::new();
+        doc.set("nickname", Value::Null);
+        doc.set("name", Value::String("Ada".to_string()));
+        doc.set("email", Value::String("ada@example.com".to_string()));
+
+        assert_eq!(
+            doc.coalesce_fields(&["display_name", "nickname", "name", "email"]),
+            Some(&Value::String("Ada".to_string()))
+        );
+        assert_eq!(doc.coalesce_fields(&["display_name", "nickname"]), None);
+        assert_eq!(doc.coalesce_fields(&[]), None);
+    }
+
+    #[test]
+    fn test_subdocument_round_trip() {
+        let mut doc = Document::from_json(
+            r#"{"name": "Ada", "address": {"city": "London", "geo": {"lat": 51}}}"#,
+        )
+        .unwrap();
+
+        let mut address = doc.subdocument("address").unwrap();
+        assert_eq!(address.id(), &Value::Null);
+        assert_eq!(address.get("city"), Some(&Value::String("London".to_string())));
+        assert_eq!(address.get_path("geo.lat"), doc.get_path("address.geo.lat"));
+
+        address.set("city", Value::String("Cambridge".to_string()));
+        address.remove("geo");
+        doc.set_subdocument("address", address);
+        assert_eq!(
+            doc.get_path("address.city"),
+            Some(&Value::String("Cambridge".to_string()))
+        );
+        assert_eq!(doc.get_path("address.geo"), None);
+
+        // Only objects are subdocuments
+        assert_eq!(doc.subdocument("name"), None);
+        assert_eq!(doc.subdocument("missing"), None);
+    }
+
+    #[test]
+    fn test_from_json_keeps_large_integers() {
+        let json = r#"{"small": 2147483647, "large": 2147483648, "negative": -9000000000}"#;
+        let doc = Document::from_json(json).unwrap();
+        assert_eq!(doc.get("small"), Some(&Value::I32(i32::MAX)));
+        assert_eq!(doc.get("large"), Some(&Value::I64(2147483648)));
+        assert_eq!(doc.get("negative"), Some(&Value::I64(-9000000000)));
+
+        // The same values through the single JSON conversion, and after storing
+        let value = Value::from_json_value(serde_json::from_str(json).unwrap());
+        for key in ["small", "large", "negative"] {
+            assert_eq!(value.as_object().unwrap().get(key), doc.get(key));
+        }
+        let stored = deserialize_document(&serialize_document(&doc).unwrap()).unwrap();
+        assert_eq!(stored.get("large"), Some(&Value::I64(2147483648)));
+    }
+
+    #[test]
+    fn test_merge_overlays_fields_and_keeps_id() {
+        let mut doc = Document::new();
+        doc.set("name", Value::String("Ada".to_string()));
+        doc.set("age", Value::I32(36));
+        let id = doc.get_id().copied();
+
+        let mut changes = Document::new();
+        changes.set("age", Value::I32(37));
+        changes.set("city", Value::String("London".to_string()));
+        doc.merge(&changes);
+
+        assert_eq!(doc.get_id().copied(), id);
+        assert_eq!(doc.get("name"), Some(&Value::String("Ada".to_string())));
+        assert_eq!(doc.get("age"), Some(&Value::I32(37)));
+        assert_eq!(doc.get("city"), Some(&Value::String("London".to_string())));
+        assert_eq!(doc.len(), 3);
+    }
+
+    #[test]
+    fn test_to_json_includes_id_and_nested_fields() {
+        let mut doc = Document::from_json(r#"{"name": "Ada", "address": {"city": "London"}}"#)
+            .unwrap();
+        doc.set("ratio", Value::F64(f64::NAN));
+        let json = doc.to_json(NonFiniteFloatMode::ExtendedJson).unwrap();
+        assert_eq!(json["_id"], doc.get_id().unwrap().to_hex());
+        assert_eq!(json["name"], "Ada");
+        assert_eq!(json["address"]["city"], "London");
+        assert_eq!(json["ratio"]["$numberDouble"], "NaN");
+        assert!(doc.to_json(NonFiniteFloatMode::Error).is_err());
+
+        let untracked = doc.subdocument("address").unwrap();
+        let json = untracked.to_json(NonFiniteFloatMode::Error).unwrap();
+        assert_eq!(json, serde_json::json!({"city": "London"}));
+    }
+
+    #[test]
+    fn test_get_path_mut_changes_nested_values() {
+        let mut doc =
+            Document::from_json(r#"{"x": {"y": {"z": 1}}, "name": "Ada", "tags": ["a"]}"#)
+                .unwrap();
+
+        *doc.get_path_mut("x.y.z").unwrap() = Value::I32(2);
+        assert_eq!(doc.get_path("x.y.z"), Some(&Value::I32(2)));
+
+        // Ending on an object hands back the whole subtree
+        let subtree = doc.get_path_mut("x.y").unwrap();
+        assert!(matches!(subtree, Value::Object(_)));
+        *subtree = Value::String("flat".to_string());
+        assert_eq!(doc.get_path("x.y"), Some(&Value::String("flat".to_string())));
+        assert_eq!(doc.get_path("x.y.z"), None);
+
+        assert!(doc.get_path_mut("").is_none());
+        assert!(doc.get_path_mut("missing.z").is_none());
+        assert!(doc.get_path_mut("x.missing").is_none());
+        // Through a value that is not an object
+        assert!(doc.get_path_mut("name.first").is_none());
+        assert!(doc.get_path_mut("x.y.z").is_none());
+        // Array elements by index, as with get_path
+        assert_eq!(doc.get_path_mut("tags.0"), Some(&mut Value::String("a".to_string())));
+        assert!(doc.get_path_mut("tags.1").is_none());
+    }
+
+    #[test]
+    fn test_set_path_creates_intermediate_objects() {
+        let mut doc = Document::new();
+        doc.set_path("address.geo.lat", Value::F64(12.3)).unwrap();
+        doc.set_path("address.geo.lng", Value::F64(4.5)).unwrap();
+        doc.set_path("name", Value::String("Ada".to_string())).unwrap();
+
+        let json = doc.to_json(NonFiniteFloatMode::Error).unwrap();
+        assert_eq!(json["address"], serde_json::json!({"geo": {"lat": 12.3, "lng": 4.5}}));
+        assert_eq!(json["name"], "Ada");
+    }
+
+    #[test]
+    fn test_set_path_overwrites_existing_leaf() {
+        let mut doc =
+            Document::from_json(r#"{"address": {"city": "Paris", "zip": "75001"}, "tags": [1, 2]}"#)
+                .unwrap();
+        doc.set_path("address.city", Value::String("Lyon".to_string())).unwrap();
+        assert_eq!(doc.get_path("address.city"), Some(&Value::String("Lyon".to_string())));
+        assert_eq!(doc.get_path("address.zip"), Some(&Value::String("75001".to_string())));
+
+        // A whole subtree, and an array element by index
+        doc.set_path("address", Value::Null).unwrap();
+        assert_eq!(doc.get("address"), Some(&Value::Null));
+        doc.set_path("tags.1", Value::I32(3)).unwrap();
+        assert_eq!(doc.get_path("tags.1"), Some(&Value::I32(3)));
+    }
+
+    #[test]
+    fn test_set_path_rejects_non_object_intermediate() {
+        let mut doc = Document::from_json(r#"{"a": {"b": 1}, "tags": [1]}"#).unwrap();
+        let before = doc.clone();
+
+        match doc.set_path("a.b.c", Value::I32(2)) {
+            Err(DatabaseError::Document(msg)) => assert!(msg.contains("'a.b' holds"), "{}", msg),
+            other => panic!("expected a document error, got {:?}", other),
+        }
+        assert!(doc.set_path("tags.5.x", Value::I32(2)).is_err());
+        assert!(doc.set_path("tags.5", Value::I32(2)).is_err());
+        assert!(doc.set_path("", Value::I32(2)).is_err());
+        assert_eq!(doc, before);
+    }
+
+    #[test]
+    fn test_get_path_bracket_index() {
+        let mut doc = example_post_with_comments();
+        let bob = Value::String("bob".to_string());
+
+        assert_eq!(doc.get_path("comments[1].user"), Some(&bob));
+        assert_eq!(doc.get_path("comments.1.user"), Some(&bob));
+        assert_eq!(doc.get_path("comments[5].user"), None);
+        assert_eq!(doc.get_path("comments[1].missing"), None);
+        // Brackets only index arrays
+        assert_eq!(doc.get_path("likes[0]"), None);
+        assert_eq!(doc.get_path("comments[0][0]"), None);
+
+        // Nested arrays take one bracket per level
+        doc.set(
+            "grid",
+            Value::Array(vec![Value::Array(vec![Value::I32(1), Value::I32(2)])]),
+        );
+        assert_eq!(doc.get_path("grid[0][1]"), Some(&Value::I32(2)));
+
+        // Anything else in brackets is part of a field name
+        doc.set("odd[key]", Value::Bool(true));
+        assert_eq!(doc.get_path("odd[key]"), Some(&Value::Bool(true)));
+
+        *doc.get_path_mut("comments[1].user").unwrap() = Value::String("carol".to_string());
+        assert_eq!(
+            doc.get_path("comments.1.user"),
+            Some(&Value::String("carol".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_set_path_bracket_index() {
+        let mut doc = example_post_with_comments();
+
+        doc.set_path("comments[0].likes", Value::I32(3)).unwrap();
+        assert_eq!(doc.get_path("comments.0.likes"), Some(&Value::I32(3)));
+
+        // Arrays are never created, so nothing is left behind
+        let before = doc.clone();
+        match doc.set_path("meta.tags[0]", Value::I32(1)) {
+            Err(DatabaseError::Document(msg)) => {
+                assert!(msg.contains("'meta' is missing"), "{}", msg)
+            }
+            other => panic!("expected a document error, got {:?}", other),
+        }
+        match doc.set_path("comments[2].user", Value::Null) {
+            Err(DatabaseError::Document(msg)) => {
+                assert!(msg.contains("'comments' has no element 2"), "{}", msg)
+            }
+            other => panic!("expected a document error, got {:?}", other),
+        }
+        assert!(doc.set_path("title[0]", Value::Null).is_err());
+        assert!(doc.set_path("[0]", Value::Null).is_err());
+        assert_eq!(doc, before);
+    }
+}
@@ -6,10 +6,42 @@ use proptest::strategy::{BoxedStrategy, Strategy};
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::time::Instant;
 use std::time::SystemTime;
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+// ObjectId layout (same as MongoDB):
+// [0..4]  seconds since the Unix epoch, big-endian
+// [4..9]  random value chosen once per process
+// [9..12] per-process counter, big-endian, incremented for every id
+//
+// The counter makes two ids generated back-to-back in the same second distinct and
+// ordered, and the process-unique bytes keep ids from different processes apart.
+// It starts at a random value in the lower half of its 24-bit range so it will not
+// wrap until at least 2^23 ids have been generated.
+const COUNTER_MASK: u32 = 0x00FF_FFFF;
+
+static PROCESS_UNIQUE: OnceLock<[u8; 5]> = OnceLock::new();
+static COUNTER: OnceLock<AtomicU32> = OnceLock::new();
+
+fn process_unique() -> [u8; 5] {
+    *PROCESS_UNIQUE.get_or_init(|| rand::rng().random())
+}
+
+fn next_counter() -> u32 {
+    COUNTER
+        .get_or_init(|| AtomicU32::new(rand::rng().random_range(0..=COUNTER_MASK >> 1)))
+        .fetch_add(1, Ordering::SeqCst)
+        & COUNTER_MASK
+}
+
+/// A 12-byte document id.
+///
+/// Ids order by their raw bytes, so by creation second first. Ids from one process within
+/// the same second follow their counter, which keeps them in creation order until it wraps;
+/// ids from different processes within a second order by their process bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct ObjectId {
     bytes: [u8; 12],
 }
@@ -42,17 +74,20 @@ impl Default for ObjectId {
 
 impl ObjectId {
     pub fn new() -> Self {
-        let mut bytes = [0u8; 12];
         let now = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .expect("Time went backwards")
             .as_secs() as u32;
 
-        bytes[0..4].copy_from_slice(&now.to_be_bytes());
-
-        let mut rng = rand::rng();
-        rng.fill(&mut bytes[4..]);
+        ObjectId::from_parts(now, process_unique(), next_counter())
+    }
 
+    /// Build an ObjectId from its parts. Only the low 24 bits of `counter` are used.
+    pub fn from_parts(timestamp: u32, process_unique: [u8; 5], counter: u32) -> Self {
+        let mut bytes = [0u8; 12];
+        bytes[0..4].copy_from_slice(&timestamp.to_be_bytes());
+        bytes[4..9].copy_from_slice(&process_unique);
+        bytes[9..12].copy_from_slice(&(counter & COUNTER_MASK).to_be_bytes()[1..]);
         ObjectId { bytes }
     }
 
@@ -153,6 +188,44 @@ mod tests {
         assert_eq!(dt, Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).single().unwrap());
     }
 
+    #[test]
+    fn test_from_parts() {
+        let object = ObjectId::from_parts(1, [2, 3, 4, 5, 6], 0x0A0B0C);
+        assert_eq!(object.bytes, [0, 0, 0, 1, 2, 3, 4, 5, 6, 0x0A, 0x0B, 0x0C]);
+
+        // Counter is truncated to 24 bits
+        let object = ObjectId::from_parts(1, [0; 5], 0xFF01_0203);
+        assert_eq!(object.bytes[9..], [0x01, 0x02, 0x03]);
+    }
+
+    #[test]
+    fn test_new_ids_unique_and_ordered() {
+        let ids: Vec<ObjectId> = (0..100_000).map(|_| ObjectId::new()).collect();
+
+        let unique: std::collections::BTreeSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), ids.len());
+
+        for pair in ids.windows(2) {
+            assert!(pair[0] <= pair[1], "{} generated before {}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn test_ordering_follows_timestamp_then_counter() {
+        let process = [0xFF; 5];
+        let earlier = ObjectId::from_parts(1_700_000_000, process, COUNTER_MASK);
+        let later = ObjectId::from_parts(1_700_000_001, [0; 5], 0);
+        assert!(earlier < later);
+
+        let first = ObjectId::from_parts(1_700_000_000, process, 7);
+        let second = ObjectId::from_parts(1_700_000_000, process, 8);
+        assert!(first < second);
+
+        let mut ids = vec![later, second, earlier, first];
+        ids.sort();
+        assert_eq!(ids, [first, second, earlier, later]);
+    }
+
     // -- BENCHMARK TESTS ----
 
     #[test]
@@ -185,6 +258,13 @@ mod tests {
             prop_assert_eq!(obj.to_hex(), format!("{}", obj));
         }
 
+        #[test]
+        fn prop_ordering_matches_bytes(a in any::<ObjectId>(), b in any::<ObjectId>()) {
+            prop_assert_eq!(a.cmp(&b), a.to_bytes().cmp(&b.to_bytes()));
+            let (a2, b2) = (ObjectId::from_bytes(a.to_bytes()), ObjectId::from_bytes(b.to_bytes()));
+            prop_assert_eq!(a2.cmp(&b2), a.cmp(&b));
+        }
+
         #[test]
         fn prop_timestamp_conversion(ts in 0u32..=4102444800) { // up to year 2100
             let mut bytes = [0u8; 12];
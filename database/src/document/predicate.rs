@@ -0,0 +1,251 @@
+// Field predicates
+//
+// A Predicate tests one (possibly dotted) field of a document against an operator, e.g.
+// "name starts with 'al'". The text operators compare case- and accent-folded text, so
+// `EqIgnoreCase("jose")` matches "José", and only `Value::String` fields can match them.
+//
+// A field set to `Value::Null` is present, while a missing field is absent: `Exists` and
+// `Eq(Value::Null)` match the former, and no operator matches the latter.
+
+use crate::document::Document;
+use crate::document::types::Value;
+use crate::error::DatabaseError;
+use regex::Regex;
+
+/// How a field's value is compared
+#[derive(Debug, Clone)]
+pub enum Operator {
+    /// The field is present, whatever its value, including `Null`
+    Exists,
+    /// The value equals the operand, comparing numbers across types like indexes do
+    /// (`I32(30)` equals `I64(30)`). `Eq(Null)` matches only fields set to `Null`.
+    /// Floats must be exactly equal, so `0.1 + 0.2` does not equal `0.3`; use
+    /// `EqApprox` for computed floats.
+    Eq(Value),
+    /// A number (`I32`, `I64` or `F64`) within `epsilon` of `value`. Any other type,
+    /// including numeric strings, never matches.
+    EqApprox { value: f64, epsilon: f64 },
+    /// The whole string equals the operand, ignoring case and accents
+    EqIgnoreCase(String),
+    /// The string begins with the operand, ignoring case and accents
+    StartsWith(String),
+    /// The operand appears anywhere in the string, ignoring case and accents
+    Contains(String),
+    /// The pattern matches somewhere in the string, exactly as written; use `(?i)` or
+    /// anchors in the pattern for case-insensitive or whole-string matches
+    Regex(Regex),
+}
+
+/// An operator applied to one field
+#[derive(Debug, Clone)]
+pub struct Predicate {
+    field: String,
+    operator: Operator,
+}
+
+impl Predicate {
+    pub fn new(field: &str, operator: Operator) -> Self {
+        Self {
+            field: field.to_string(),
+            operator,
+        }
+    }
+
+    pub fn exists(field: &str) -> Self {
+        Self::new(field, Operator::Exists)
+    }
+
+    pub fn eq(field: &str, value: Value) -> Self {
+        Self::new(field, Operator::Eq(value))
+    }
+
+    pub fn eq_approx(field: &str, value: f64, epsilon: f64) -> Self {
+        Self::new(field, Operator::EqApprox { value, epsilon })
+    }
+
+    pub fn eq_ignore_case(field: &str, value: &str) -> Self {
+        Self::new(field, Operator::EqIgnoreCase(value.to_string()))
+    }
+
+    pub fn starts_with(field: &str, prefix: &str) -> Self {
+        Self::new(field, Operator::StartsWith(prefix.to_string()))
+    }
+
+    pub fn contains(field: &str, needle: &str) -> Self {
+        Self::new(field, Operator::Contains(needle.to_string()))
+    }
+
+    /// Compiles `pattern`, failing with `DatabaseError::Query` if it is not a valid regex
+    pub fn regex(field: &str, pattern: &str) -> Result<Self, DatabaseError> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| DatabaseError::Query(format!("invalid regex '{}': {}", pattern, e)))?;
+        Ok(Self::new(field, Operator::Regex(regex)))
+    }
+
+    /// The dotted field path this predicate reads
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn operator(&self) -> &Operator {
+        &self.operator
+    }
+
+    /// Whether `document` holds a value at the field that satisfies the operator.
+    /// A missing field never matches.
+    pub fn matches(&self, document: &Document) -> bool {
+        document
+            .get_path(&self.field)
+            .is_some_and(|value| self.matches_value(value))
+    }
+
+    /// Whether a present `value` satisfies the operator. Non-string values never match
+    /// the text operators.
+    pub fn matches_value(&self, value: &Value) -> bool {
+        match (&self.operator, value) {
+            (Operator::Exists, _) => true,
+            (Operator::Eq(operand), _) => value.total_cmp(operand).is_eq(),
+            (Operator::EqApprox { value: operand, epsilon }, _) if value.is_number() => value
+                .as_f64()
+                .is_some_and(|number| (number - operand).abs() <= *epsilon),
+            (Operator::EqIgnoreCase(operand), Value::String(text)) => fold(text) == fold(operand),
+            (Operator::StartsWith(operand), Value::String(text)) => {
+                fold(text).starts_with(&fold(operand))
+            }
+            (Operator::Contains(operand), Value::String(text)) => {
+                fold(text).contains(&fold(operand))
+            }
+            (Operator::Regex(regex), Value::String(text)) => regex.is_match(text),
+            _ => false,
+        }
+    }
+}
+
+// Lowercases and strips the accents of Latin-1 letters, so "Éclair" folds to "eclair".
+// Other scripts are only lowercased.
+fn fold(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .map(|c| match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+            'ç' => 'c',
+            'è' | 'é' | 'ê' | 'ë' => 'e',
+            'ì' | 'í' | 'î' | 'ï' => 'i',
+            'ñ' => 'n',
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+            'ù' | 'ú' | 'û' | 'ü' => 'u',
+            'ý' | 'ÿ' => 'y',
+            other => other,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person(name: &str, age: i32) -> Document {
+        let mut doc = Document::new();
+        doc.set("name", Value::String(name.to_string()));
+        doc.set("age", Value::I32(age));
+        doc
+    }
+
+    #[test]
+    fn test_string_operators() {
+        let alice = person("Alice", 30);
+
+        assert!(Predicate::eq_ignore_case("name", "alice").matches(&alice));
+        assert!(Predicate::starts_with("name", "Al").matches(&alice));
+        assert!(Predicate::contains("name", "ic").matches(&alice));
+        assert!(Predicate::regex("name", "^A.*e$").unwrap().matches(&alice));
+
+        assert!(!Predicate::eq_ignore_case("name", "alic").matches(&alice));
+        assert!(!Predicate::starts_with("name", "li").matches(&alice));
+        assert!(!Predicate::contains("name", "bob").matches(&alice));
+        assert!(!Predicate::regex("name", "^a").unwrap().matches(&alice));
+        assert!(Predicate::regex("name", "(?i)^a").unwrap().matches(&alice));
+    }
+
+    #[test]
+    fn test_accent_insensitive() {
+        let jose = person("José Núñez", 40);
+
+        assert!(Predicate::eq_ignore_case("name", "jose nunez").matches(&jose));
+        assert!(Predicate::starts_with("name", "JOSE").matches(&jose));
+        assert!(Predicate::contains("name", "nun").matches(&jose));
+    }
+
+    #[test]
+    fn test_non_strings_never_match() {
+        let alice = person("Alice", 30);
+
+        assert!(!Predicate::eq_ignore_case("age", "30").matches(&alice));
+        assert!(!Predicate::contains("age", "3").matches(&alice));
+        assert!(!Predicate::regex("age", ".*").unwrap().matches(&alice));
+        assert!(!Predicate::starts_with("missing", "").matches(&alice));
+    }
+
+    #[test]
+    fn test_nested_field() {
+        let mut address = std::collections::BTreeMap::new();
+        address.insert("city".to_string(), Value::String("Zürich".to_string()));
+        let mut doc = Document::new();
+        doc.set("address", Value::Object(address));
+
+        assert!(Predicate::eq_ignore_case("address.city", "zurich").matches(&doc));
+    }
+
+    #[test]
+    fn test_null_is_present_missing_is_absent() {
+        let mut doc = person("Alice", 30);
+        doc.set("nickname", Value::Null);
+
+        assert!(Predicate::exists("nickname").matches(&doc));
+        assert!(Predicate::exists("name").matches(&doc));
+        assert!(!Predicate::exists("email").matches(&doc));
+
+        assert!(Predicate::eq("nickname", Value::Null).matches(&doc));
+        assert!(!Predicate::eq("email", Value::Null).matches(&doc));
+        assert!(!Predicate::eq("name", Value::Null).matches(&doc));
+
+        // Null is not a string
+        assert!(!Predicate::contains("nickname", "").matches(&doc));
+    }
+
+    #[test]
+    fn test_eq_compares_numbers_across_types() {
+        let alice = person("Alice", 30);
+
+        assert!(Predicate::eq("age", Value::I64(30)).matches(&alice));
+        assert!(Predicate::eq("name", Value::String("Alice".to_string())).matches(&alice));
+        assert!(!Predicate::eq("name", Value::String("alice".to_string())).matches(&alice));
+    }
+
+    #[test]
+    fn test_eq_approx_tolerates_rounding() {
+        let mut doc = Document::new();
+        doc.set("price", Value::F64(0.1 + 0.2));
+        doc.set("count", Value::I32(3));
+        doc.set("label", Value::String("0.3".to_string()));
+        doc.set("flag", Value::Bool(true));
+
+        assert!(!Predicate::eq("price", Value::F64(0.3)).matches(&doc));
+        assert!(Predicate::eq_approx("price", 0.3, 1e-9).matches(&doc));
+        assert!(!Predicate::eq_approx("price", 0.31, 1e-9).matches(&doc));
+        assert!(Predicate::eq_approx("count", 3.0000001, 1e-6).matches(&doc));
+
+        // Only numbers compare
+        assert!(!Predicate::eq_approx("label", 0.3, 1e-9).matches(&doc));
+        assert!(!Predicate::eq_approx("flag", 1.0, 1e-9).matches(&doc));
+        assert!(!Predicate::eq_approx("missing", 0.0, f64::INFINITY).matches(&doc));
+    }
+
+    #[test]
+    fn test_invalid_regex() {
+        match Predicate::regex("name", "(unclosed") {
+            Err(DatabaseError::Query(msg)) => assert!(msg.contains("invalid regex")),
+            other => panic!("expected a query error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+// Schema inference
+//
+// Documents are schemaless, but imported data usually follows a de-facto schema.
+// InferredSchema records, for every dotted field path seen in a sample of documents,
+// how many documents contained it and which value types appeared there.
+
+use crate::document::Document;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Observations for a single field path
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldStats {
+    /// Number of sampled documents containing this path
+    pub present: usize,
+    /// Occurrences of each value type at this path, keyed by `Value::type_name`
+    pub types: BTreeMap<&'static str, usize>,
+}
+
+/// The field paths and value types observed across a sample of documents
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InferredSchema {
+    /// Number of documents the schema was inferred from
+    pub documents_sampled: usize,
+    /// Per-path statistics, keyed by dotted path (e.g. "address.city")
+    pub fields: BTreeMap<String, FieldStats>,
+}
+
+impl InferredSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a schema from a set of documents
+    pub fn from_documents<'a>(documents: impl IntoIterator<Item = &'a Document>) -> Self {
+        let mut schema = Self::new();
+        for document in documents {
+            schema.observe(document);
+        }
+        schema
+    }
+
+    /// Record the fields of one more document
+    pub fn observe(&mut self, document: &Document) {
+        self.documents_sampled += 1;
+
+        for (key, value) in document.iter() {
+            for (path, leaf) in value.leaves() {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", key, path)
+                };
+
+                let stats = self.fields.entry(path).or_default();
+                stats.present += 1;
+                *stats.types.entry(leaf.type_name()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    /// Fraction of sampled documents containing `path`, between 0.0 and 1.0
+    pub fn presence(&self, path: &str) -> Option<f64> {
+        if self.documents_sampled == 0 {
+            return None;
+        }
+        self.fields
+            .get(path)
+            .map(|stats| stats.present as f64 / self.documents_sampled as f64)
+    }
+
+    /// Type names observed at `path`, in alphabetical order
+    pub fn types(&self, path: &str) -> Option<Vec<&'static str>> {
+        self.fields
+            .get(path)
+            .map(|stats| stats.types.keys().copied().collect())
+    }
+}
+
+// One line per field, e.g. "`age`: i32 (100%)" or "`id`: i32 | string (40%)"
+impl fmt::Display for InferredSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, stats) in &self.fields {
+            let types: Vec<&str> = stats.types.keys().copied().collect();
+            let percent = stats.present as f64 / self.documents_sampled as f64 * 100.0;
+            writeln!(f, "`{}`: {} ({:.0}%)", path, types.join(" | "), percent)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Value;
+
+    fn person(age: Value, nickname: Option<&str>) -> Document {
+        let mut doc = Document::new();
+        doc.set("age", age);
+        if let Some(nickname) = nickname {
+            doc.set("nickname", Value::String(nickname.to_string()));
+        }
+        doc
+    }
+
+    #[test]
+    fn test_infer_types_and_presence() {
+        let mut address = BTreeMap::new();
+        address.insert("city".to_string(), Value::String("Oslo".to_string()));
+
+        let mut with_address = person(Value::I32(40), None);
+        with_address.set("address", Value::Object(address));
+
+        let documents = vec![
+            person(Value::I32(30), Some("Al")),
+            person(Value::I32(31), Some("Bo")),
+            person(Value::String("unknown".to_string()), None),
+            person(Value::I32(33), None),
+            with_address,
+        ];
+
+        let schema = InferredSchema::from_documents(&documents);
+
+        assert_eq!(schema.documents_sampled, 5);
+        assert_eq!(schema.presence("age"), Some(1.0));
+        assert_eq!(schema.types("age"), Some(vec!["i32", "string"]));
+        assert_eq!(schema.fields["age"].types["i32"], 4);
+        assert_eq!(schema.presence("nickname"), Some(0.4));
+        assert_eq!(schema.types("nickname"), Some(vec!["string"]));
+        assert_eq!(schema.presence("address.city"), Some(0.2));
+        assert_eq!(schema.presence("address"), None);
+        assert_eq!(schema.presence("missing"), None);
+    }
+
+    #[test]
+    fn test_display_report() {
+        let documents = vec![
+            person(Value::I32(30), Some("Al")),
+            person(Value::I32(31), None),
+        ];
+
+        let report = InferredSchema::from_documents(&documents).to_string();
+        assert_eq!(report, "`age`: i32 (100%)\n`nickname`: string (50%)\n");
+    }
+}
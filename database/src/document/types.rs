@@ -37,15 +37,37 @@
 // 8. All conversion methods return `Option<T>`:
 //    - This makes it explicit to the caller when a conversion may fail, and ensures no panics or silent failures occur.
 //
+// 9. Arithmetic never wraps:
+//    - `checked_add`/`checked_sub`/`checked_mul` promote I32 results to I64 when they leave the i32 range,
+//      return `None` when an I64 result overflows, and yield F64 as soon as either operand is F64.
+//    - Non-numeric operands (including Bool and numeric-looking Strings) return `None`.
+//
+// 10. JSON output never silently changes floats:
+//    - JSON numbers cannot hold NaN or +/-Infinity. `to_json` either emits the Extended JSON form
+//      `{"$numberDouble": "NaN"}` (and "Infinity"/"-Infinity"), or returns an error, depending on
+//      `NonFiniteFloatMode`. `from_json_value` reads the Extended JSON form back into an F64.
+//
+// 11. Conversions to and from Rust types:
+//    - `From<T> for Value` picks the obvious variant (`i32` -> I32, `&str` -> String, `Vec<u8>` -> Binary, ...).
+//    - `TryFrom<Value> for T` reuses the `as_*` conversions above, so it is exactly as lenient
+//      (`Value::String("42")` converts to `42i32`), and fails with `DatabaseError::Validation` where they return `None`.
+//
+// 12. One entry point for "any number":
+//    - `as_number` returns a `Number`, either `Int(i64)` or `Float(f64)`, for I32, I64, F64 and numeric
+//      Strings, keeping integers exact. Unlike the `as_*` conversions it does not treat Bool as 0/1.
+//
 // These choices make all value conversions safe, predictable, and easy to reason about, which is essential in a database
 // context where correctness is critical.
 
 use crate::document::object_id::ObjectId;
+use crate::error::DatabaseError;
+use crate::document::id::DocumentId;
 use chrono::{DateTime, Utc};
 use proptest::arbitrary::Arbitrary;
 use proptest::prelude::*;
 use proptest::strategy::{BoxedStrategy, Strategy};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::fmt;
@@ -63,8 +85,53 @@ pub enum Value {
     Object(BTreeMap<String, Value>),
     DateTime(DateTime<Utc>),
     Binary(Vec<u8>),
+    /// A reference to another stored document, followed by `StorageEngine::resolve_ref`
+    DocumentRef(DocumentId),
+}
+
+/// How `Value::to_json` represents F64 values that plain JSON numbers cannot hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFiniteFloatMode {
+    /// Emit `{"$numberDouble": "NaN" | "Infinity" | "-Infinity"}`
+    #[default]
+    ExtendedJson,
+    /// Fail the conversion
+    Error,
+}
+
+/// A numeric `Value`, as returned by `Value::as_number`: integers stay exact
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Number {
+    Int(i64),
+    Float(f64),
+}
+
+impl Number {
+    /// The number as a float, rounding integers beyond 2^53
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Number::Int(i) => *i as f64,
+            Number::Float(f) => *f,
+        }
+    }
+
+    /// The number as an integer, or `None` for a float with a fractional part or
+    /// outside the i64 range
+    pub fn as_i64_checked(&self) -> Option<i64> {
+        match self {
+            Number::Int(i) => Some(*i),
+            // 2^63 is exactly representable, and is the first float past i64::MAX
+            Number::Float(f) => (f.fract() == 0.0 && *f >= i64::MIN as f64 && *f < i64::MAX as f64)
+                .then_some(*f as i64),
+        }
+    }
 }
 
+const NUMBER_DOUBLE_KEY: &str = "$numberDouble";
+
+/// Arrays longer than this are summarized by `Value::pretty` instead of listed
+pub const PRETTY_ARRAY_LIMIT: usize = 20;
+
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -89,6 +156,7 @@ impl fmt::Display for Value {
                 let hex: String = bin.iter().map(|b| format!("{:02x}", b)).collect();
                 write!(f, "Binary({})", hex)
             }
+            Value::DocumentRef(id) => write!(f, "Ref({})", id),
         }
     }
 }
@@ -97,9 +165,17 @@ impl Arbitrary for Value {
     type Parameters = ();
     type Strategy = BoxedStrategy<Self>;
 
+    // Arrays and objects nest up to 3 levels deep with at most 6 children each, which keeps
+    // generated values small while still covering every variant inside every container.
     fn arbitrary_with(_args: Self::Parameters) -> Self::Strategy {
         use Value::*;
-        prop_oneof![
+        // BSON stores DateTime as milliseconds, so generate whole milliseconds
+        // between years 0 and 9999
+        let datetime = (-62_167_219_200_000i64..253_402_300_800_000i64)
+            .prop_map(|millis| {
+                chrono::DateTime::from_timestamp_millis(millis).unwrap_or_default()
+            });
+        let leaf = prop_oneof![
             Just(Null),
             any::<bool>().prop_map(Bool),
             any::<i32>().prop_map(I32),
@@ -107,7 +183,18 @@ impl Arbitrary for Value {
             any::<f64>().prop_map(F64),
             ".*".prop_map(String),
             any::<crate::document::object_id::ObjectId>().prop_map(ObjectId),
-        ]
+            datetime.prop_map(DateTime),
+            prop::collection::vec(any::<u8>(), 0..32).prop_map(Binary),
+            (any::<u64>(), any::<u16>())
+                .prop_map(|(page_id, slot_id)| DocumentRef(DocumentId::new(page_id, slot_id))),
+        ];
+        leaf.prop_recursive(3, 48, 6, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..6).prop_map(Array),
+                // Field names must be non-empty C strings to survive BSON encoding
+                prop::collection::btree_map("[a-z][a-z0-9_]{0,7}", inner, 0..6).prop_map(Object),
+            ]
+        })
         .boxed()
     }
 }
@@ -228,7 +315,8 @@ impl Value {
             Value::I32(x) => Some(*x as f64),
             Value::I64(x) => Some(*x as f64),
             Value::F64(x) => Some(*x),
-            Value::String(x) => x.parse::<f64>().ok(),
+            // "inf", "nan" and out-of-range literals parse, but could not be stored
+            Value::String(x) => x.parse::<f64>().ok().filter(|f| f.is_finite()),
             Value::Bool(x) => match x {
                 true => Some(1f64),
                 false => Some(0f64),
@@ -238,6 +326,30 @@ impl Value {
         }
     }
 
+    /// The value as a `Number`, for code that accepts any numeric type. Strings holding
+    /// an integer become `Int`, other finite numeric strings `Float`.
+    pub fn as_number(&self) -> Option<Number> {
+        match self {
+            Value::I32(x) => Some(Number::Int(*x as i64)),
+            Value::I64(x) => Some(Number::Int(*x)),
+            Value::F64(x) => Some(Number::Float(*x)),
+            Value::String(x) => match x.parse::<i64>() {
+                Ok(i) => Some(Number::Int(i)),
+                Err(_) => x.parse::<f64>().ok().filter(|f| f.is_finite()).map(Number::Float),
+            },
+            _ => None,
+        }
+    }
+
+    /// The text of a `Value::String`, borrowed rather than copied. Unlike `to_str`,
+    /// other variants are not converted and give `None`.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
     pub fn to_str(&self) -> Option<String> {
         match self {
             Value::Null => None,
@@ -256,7 +368,7 @@ impl Value {
 
     pub fn as_object_id(&self) -> Option<ObjectId> {
         match self {
-            Value::ObjectId(oid) => Some(oid.clone()),
+            Value::ObjectId(oid) => Some(*oid),
             _ => None,
         }
     }
@@ -296,6 +408,27 @@ impl Value {
         }
     }
 
+    /// Reads a number as milliseconds since the Unix epoch, for timestamps imported as
+    /// integers. Fractional milliseconds of an `F64` are truncated.
+    ///
+    /// Returns `None` for non-numeric values and for numbers outside the range
+    /// `DateTime` can represent.
+    pub fn as_datetime_from_epoch_millis(&self) -> Option<DateTime<Utc>> {
+        let millis = match self {
+            Value::I32(x) => *x as i64,
+            Value::I64(x) => *x,
+            // The cast saturates, so huge and infinite values stay out of range below
+            Value::F64(x) if !x.is_nan() => *x as i64,
+            _ => return None,
+        };
+        DateTime::from_timestamp_millis(millis)
+    }
+
+    /// A `DateTime` as milliseconds since the Unix epoch; `None` for any other type.
+    pub fn datetime_to_epoch_millis(&self) -> Option<i64> {
+        self.as_datetime().map(|dt| dt.timestamp_millis())
+    }
+
     pub fn as_binary(&self) -> Option<&Vec<u8>> {
         match self {
             Value::Binary(bin) => Some(bin),
@@ -304,11 +437,18 @@ impl Value {
     }
 
     pub fn from_json_value(v: serde_json::Value) -> Self {
+        if let serde_json::Value::Object(ref obj) = v
+            && let Some(f) = Self::parse_number_double(obj)
+        {
+            return Value::F64(f);
+        }
+
         match v {
             serde_json::Value::Bool(b) => Value::Bool(b),
             serde_json::Value::Number(n) => {
+                // Integers too large for I32 are kept whole as I64
                 if let Some(i) = n.as_i64() {
-                    Value::I32(i as i32)
+                    i32::try_from(i).map_or(Value::I64(i), Value::I32)
                 } else if let Some(f) = n.as_f64() {
                     Value::F64(f)
                 } else {
@@ -327,6 +467,487 @@ impl Value {
             serde_json::Value::Null => Value::Null, // if you have this variant
         }
     }
+
+    /// Convert to JSON. Arrays and objects are converted recursively; ObjectIds, DateTimes,
+    /// and Binary values become strings. NaN and infinite F64 values follow `mode`.
+    pub fn to_json(&self, mode: NonFiniteFloatMode) -> Result<serde_json::Value, DatabaseError> {
+        Ok(match self {
+            Value::Null => serde_json::Value::Null,
+            Value::Bool(b) => serde_json::Value::Bool(*b),
+            Value::I32(i) => serde_json::Value::from(*i),
+            Value::I64(i) => serde_json::Value::from(*i),
+            Value::F64(f) => match serde_json::Number::from_f64(*f) {
+                Some(n) => serde_json::Value::Number(n),
+                None => match mode {
+                    NonFiniteFloatMode::ExtendedJson => {
+                        let repr = if f.is_nan() {
+                            "NaN"
+                        } else if f.is_sign_positive() {
+                            "Infinity"
+                        } else {
+                            "-Infinity"
+                        };
+                        let mut obj = serde_json::Map::new();
+                        obj.insert(NUMBER_DOUBLE_KEY.to_string(), repr.into());
+                        serde_json::Value::Object(obj)
+                    }
+                    NonFiniteFloatMode::Error => {
+                        return Err(DatabaseError::Document(format!(
+                            "{} cannot be represented as a JSON number",
+                            f
+                        )));
+                    }
+                },
+            },
+            Value::String(s) => serde_json::Value::String(s.clone()),
+            Value::ObjectId(oid) => serde_json::Value::String(oid.to_hex()),
+            Value::Array(arr) => serde_json::Value::Array(
+                arr.iter()
+                    .map(|v| v.to_json(mode))
+                    .collect::<Result<_, _>>()?,
+            ),
+            Value::Object(obj) => serde_json::Value::Object(
+                obj.iter()
+                    .map(|(k, v)| Ok((k.clone(), v.to_json(mode)?)))
+                    .collect::<Result<_, DatabaseError>>()?,
+            ),
+            Value::DateTime(dt) => serde_json::Value::String(dt.to_rfc3339()),
+            Value::Binary(bin) => serde_json::Value::String(hex::encode(bin)),
+            Value::DocumentRef(id) => serde_json::Value::String(id.to_string()),
+        })
+    }
+
+    /// Read an Extended JSON `{"$numberDouble": "..."}` object, if that is what `obj` is.
+    pub fn parse_number_double(obj: &serde_json::Map<String, serde_json::Value>) -> Option<f64> {
+        if obj.len() != 1 {
+            return None;
+        }
+        match obj.get(NUMBER_DOUBLE_KEY)?.as_str()? {
+            "NaN" => Some(f64::NAN),
+            "Infinity" => Some(f64::INFINITY),
+            "-Infinity" => Some(f64::NEG_INFINITY),
+            other => other.parse::<f64>().ok(),
+        }
+    }
+
+    /// Short lowercase name of this value's type, e.g. "i32" or "string".
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Bool(_) => "bool",
+            Value::I32(_) => "i32",
+            Value::I64(_) => "i64",
+            Value::F64(_) => "f64",
+            Value::String(_) => "string",
+            Value::ObjectId(_) => "object_id",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+            Value::DateTime(_) => "datetime",
+            Value::Binary(_) => "binary",
+            Value::DocumentRef(_) => "document_ref",
+        }
+    }
+
+    /// A total ordering over all values, used for sorting and index keys.
+    ///
+    /// Values of different types sort by type in this order: Null, numbers, String, Object,
+    /// Array, Binary, ObjectId, Bool, DateTime, DocumentRef. Numbers compare by numeric value across
+    /// I32/I64/F64, so `I32(30)` and `I64(30)` are equal; integers are compared with floats
+    /// exactly, not by rounding them to f64. NaN sorts below every other number.
+    pub fn total_cmp(&self, other: &Value) -> Ordering {
+        match (self, other) {
+            (Value::I32(_) | Value::I64(_), Value::I32(_) | Value::I64(_)) => {
+                self.as_i64().cmp(&other.as_i64())
+            }
+            (Value::I32(x), Value::F64(y)) => cmp_int_float(*x as i64, *y),
+            (Value::I64(x), Value::F64(y)) => cmp_int_float(*x, *y),
+            (Value::F64(x), Value::I32(y)) => cmp_int_float(*y as i64, *x).reverse(),
+            (Value::F64(x), Value::I64(y)) => cmp_int_float(*y, *x).reverse(),
+            (Value::F64(x), Value::F64(y)) => match (x.is_nan(), y.is_nan()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Less,
+                (false, true) => Ordering::Greater,
+                // f64::total_cmp would order -0.0 below 0.0; they are equal here
+                (false, false) => x.partial_cmp(y).unwrap_or(Ordering::Equal),
+            },
+            (Value::String(x), Value::String(y)) => x.cmp(y),
+            (Value::Object(x), Value::Object(y)) => {
+                for ((xk, xv), (yk, yv)) in x.iter().zip(y.iter()) {
+                    let ordering = xk.cmp(yk).then_with(|| xv.total_cmp(yv));
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+                x.len().cmp(&y.len())
+            }
+            (Value::Array(x), Value::Array(y)) => {
+                for (xv, yv) in x.iter().zip(y.iter()) {
+                    let ordering = xv.total_cmp(yv);
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+                x.len().cmp(&y.len())
+            }
+            (Value::Binary(x), Value::Binary(y)) => x.cmp(y),
+            (Value::ObjectId(x), Value::ObjectId(y)) => x.cmp(y),
+            (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+            (Value::DateTime(x), Value::DateTime(y)) => x.cmp(y),
+            (Value::DocumentRef(x), Value::DocumentRef(y)) => x.cmp(y),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+
+    fn type_rank(&self) -> u8 {
+        match self {
+            Value::Null => 0,
+            Value::I32(_) | Value::I64(_) | Value::F64(_) => 1,
+            Value::String(_) => 2,
+            Value::Object(_) => 3,
+            Value::Array(_) => 4,
+            Value::Binary(_) => 5,
+            Value::ObjectId(_) => 6,
+            Value::Bool(_) => 7,
+            Value::DateTime(_) => 8,
+            Value::DocumentRef(_) => 9,
+        }
+    }
+
+    /// Sort an array in place by `total_cmp`. Errors if this is not an array.
+    pub fn sort_array(&mut self) -> Result<(), DatabaseError> {
+        self.sort_array_by(Value::total_cmp)
+    }
+
+    /// Sort an array in place with a custom comparator. Errors if this is not an array.
+    ///
+    /// The sort is stable, so elements comparing equal keep their order.
+    pub fn sort_array_by<F>(&mut self, compare: F) -> Result<(), DatabaseError>
+    where
+        F: FnMut(&Value, &Value) -> Ordering,
+    {
+        match self {
+            Value::Array(arr) => {
+                arr.sort_by(compare);
+                Ok(())
+            }
+            other => Err(DatabaseError::Validation(format!(
+                "cannot sort a {} value, expected an array",
+                other.type_name()
+            ))),
+        }
+    }
+
+    /// Sort an array of objects by the value at a dotted field path.
+    ///
+    /// Elements without the field (including non-objects) sort first, in their original order.
+    pub fn sort_array_by_key(&mut self, path: &str) -> Result<(), DatabaseError> {
+        self.sort_array_by(|a, b| match (a.field_at(path), b.field_at(path)) {
+            (Some(x), Some(y)) => x.total_cmp(y),
+            (x, y) => x.is_some().cmp(&y.is_some()),
+        })
+    }
+
+    // Follows a dotted path through nested objects
+    fn field_at(&self, path: &str) -> Option<&Value> {
+        path.split('.')
+            .try_fold(self, |current, key| current.as_object()?.get(key))
+    }
+
+    /// Every non-object value reachable from this one, paired with its dotted path.
+    ///
+    /// Objects are descended into; arrays and scalars are leaves. A non-object value
+    /// yields itself with an empty path, and an empty object yields nothing.
+    pub fn leaves(&self) -> Vec<(String, &Value)> {
+        match self {
+            Value::Object(obj) => obj
+                .iter()
+                .flat_map(|(key, val)| {
+                    val.leaves().into_iter().map(move |(path, leaf)| {
+                        if path.is_empty() {
+                            (key.clone(), leaf)
+                        } else {
+                            (format!("{}.{}", key, path), leaf)
+                        }
+                    })
+                })
+                .collect(),
+            _ => vec![(String::new(), self)],
+        }
+    }
+
+    /// Rewrites numbers into their canonical representation, recursing into arrays and objects.
+    ///
+    /// - `I64` values that fit in an `i32` become `I32`.
+    /// - `F64` values stay `F64`, even when whole-valued, so a float field never silently
+    ///   turns into an integer field. Negative zero becomes positive zero.
+    /// - All other variants are left untouched.
+    pub fn normalize(&mut self) {
+        match self {
+            Value::I64(x) => {
+                if let Ok(narrowed) = i32::try_from(*x) {
+                    *self = Value::I32(narrowed);
+                }
+            }
+            Value::F64(x) if *x == 0.0 => *x = 0.0,
+            Value::Array(arr) => arr.iter_mut().for_each(Value::normalize),
+            Value::Object(obj) => obj.values_mut().for_each(Value::normalize),
+            _ => {}
+        }
+    }
+
+    /// Moves the value out, leaving `Value::Null` in its place, like `std::mem::take`.
+    ///
+    /// Lets a transformation reuse a nested value, e.g. one element of an array, without
+    /// cloning it.
+    pub fn take(&mut self) -> Value {
+        std::mem::replace(self, Value::Null)
+    }
+
+    /// The first of `values` that is not `Value::Null`, like SQL's `COALESCE`.
+    pub fn coalesce<'a>(values: &[&'a Value]) -> Option<&'a Value> {
+        values.iter().copied().find(|value| !value.is_null())
+    }
+
+    /// Renders the value over multiple lines for human inspection, with nested fields
+    /// indented by `indent` spaces per level. This is not JSON: strings are quoted but
+    /// keys are not, and other scalars print as their `Display` form.
+    ///
+    /// Containers nested more than `max_depth` levels deep print as `{...}` or `[...]`,
+    /// so `Some(0)` elides even the outermost one. Arrays with more than
+    /// `PRETTY_ARRAY_LIMIT` elements print as `[... N items]`.
+    pub fn pretty(&self, indent: usize, max_depth: Option<usize>) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, indent, 0, max_depth);
+        out
+    }
+
+    fn write_pretty(
+        &self,
+        out: &mut String,
+        indent: usize,
+        depth: usize,
+        max_depth: Option<usize>,
+    ) {
+        let elided = max_depth.is_some_and(|max| depth >= max);
+        match self {
+            Value::String(s) => out.push_str(&format!("{:?}", s)),
+            Value::Array(arr) if arr.is_empty() => out.push_str("[]"),
+            Value::Array(_) if elided => out.push_str("[...]"),
+            Value::Array(arr) if arr.len() > PRETTY_ARRAY_LIMIT => {
+                out.push_str(&format!("[... {} items]", arr.len()));
+            }
+            Value::Array(arr) => {
+                out.push('[');
+                for (i, element) in arr.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    out.push_str(&" ".repeat(indent * (depth + 1)));
+                    element.write_pretty(out, indent, depth + 1, max_depth);
+                }
+                out.push('\n');
+                out.push_str(&" ".repeat(indent * depth));
+                out.push(']');
+            }
+            Value::Object(obj) => Self::write_pretty_fields(
+                out,
+                obj.iter().map(|(key, value)| (key.as_str(), value)),
+                indent,
+                depth,
+                max_depth,
+            ),
+            other => out.push_str(&other.to_string()),
+        }
+    }
+
+    // Writes `{ key: value, ... }` over multiple lines; shared with `Document::pretty`
+    pub(crate) fn write_pretty_fields<'a>(
+        out: &mut String,
+        fields: impl Iterator<Item = (&'a str, &'a Value)>,
+        indent: usize,
+        depth: usize,
+        max_depth: Option<usize>,
+    ) {
+        let mut fields = fields.peekable();
+        if fields.peek().is_none() {
+            out.push_str("{}");
+            return;
+        }
+        if max_depth.is_some_and(|max| depth >= max) {
+            out.push_str("{...}");
+            return;
+        }
+        out.push('{');
+        for (i, (key, value)) in fields.enumerate() {
+            out.push_str(if i == 0 { "\n" } else { ",\n" });
+            out.push_str(&" ".repeat(indent * (depth + 1)));
+            out.push_str(key);
+            out.push_str(": ");
+            value.write_pretty(out, indent, depth + 1, max_depth);
+        }
+        out.push('\n');
+        out.push_str(&" ".repeat(indent * depth));
+        out.push('}');
+    }
+
+    /// Adds two numeric values without wrapping. See `checked_arith` for promotion rules.
+    pub fn checked_add(&self, other: &Value) -> Option<Value> {
+        self.checked_arith(other, i64::checked_add, |a, b| a + b)
+    }
+
+    /// Subtracts `other` from `self` without wrapping.
+    pub fn checked_sub(&self, other: &Value) -> Option<Value> {
+        self.checked_arith(other, i64::checked_sub, |a, b| a - b)
+    }
+
+    /// Multiplies two numeric values without wrapping.
+    pub fn checked_mul(&self, other: &Value) -> Option<Value> {
+        self.checked_arith(other, i64::checked_mul, |a, b| a * b)
+    }
+
+    // I32 op I32 is computed in i64 (which cannot overflow for add/sub/mul) and narrowed
+    // back to I32 when it fits. Any I64 operand yields I64, and any F64 operand yields F64.
+    fn checked_arith(
+        &self,
+        other: &Value,
+        int_op: fn(i64, i64) -> Option<i64>,
+        float_op: fn(f64, f64) -> f64,
+    ) -> Option<Value> {
+        match (self, other) {
+            (Value::I32(a), Value::I32(b)) => {
+                let result = int_op(*a as i64, *b as i64)?;
+                Some(match i32::try_from(result) {
+                    Ok(narrowed) => Value::I32(narrowed),
+                    Err(_) => Value::I64(result),
+                })
+            }
+            (Value::I32(_) | Value::I64(_), Value::I32(_) | Value::I64(_)) => {
+                int_op(self.as_i64()?, other.as_i64()?).map(Value::I64)
+            }
+            (Value::F64(_), _) | (_, Value::F64(_)) if self.is_number() && other.is_number() => {
+                Some(Value::F64(float_op(self.as_f64()?, other.as_f64()?)))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Self {
+        Value::I32(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::I64(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::F64(value)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(value: Vec<u8>) -> Self {
+        Value::Binary(value)
+    }
+}
+
+impl From<DateTime<Utc>> for Value {
+    fn from(value: DateTime<Utc>) -> Self {
+        Value::DateTime(value)
+    }
+}
+
+// Compares an integer with a float exactly. Converting the integer to f64 instead would
+// round above 2^53 and make mixed comparisons intransitive.
+fn cmp_int_float(x: i64, y: f64) -> Ordering {
+    if y.is_nan() {
+        return Ordering::Greater;
+    }
+    // i64::MIN as f64 is exactly -2^63; every float in [-2^63, 2^63) truncates to an i64
+    if y >= -(i64::MIN as f64) {
+        return Ordering::Less;
+    }
+    if y < i64::MIN as f64 {
+        return Ordering::Greater;
+    }
+    let whole = y.trunc();
+    // The fractional part decides between an integer and a float with the same whole part
+    x.cmp(&(whole as i64))
+        .then_with(|| 0.0.partial_cmp(&(y - whole)).unwrap_or(Ordering::Equal))
+}
+
+fn conversion_error(value: &Value, target: &str) -> DatabaseError {
+    DatabaseError::Validation(format!(
+        "cannot convert {} value '{}' to {}",
+        value.type_name(),
+        value,
+        target
+    ))
+}
+
+macro_rules! impl_try_from_value {
+    ($target:ty, $conversion:ident) => {
+        impl TryFrom<Value> for $target {
+            type Error = DatabaseError;
+
+            fn try_from(value: Value) -> Result<Self, Self::Error> {
+                value
+                    .$conversion()
+                    .ok_or_else(|| conversion_error(&value, stringify!($target)))
+            }
+        }
+    };
+}
+
+impl_try_from_value!(i32, as_i32);
+impl_try_from_value!(i64, as_i64);
+impl_try_from_value!(f64, as_f64);
+impl_try_from_value!(bool, as_bool);
+impl_try_from_value!(DateTime<Utc>, as_datetime);
+
+impl TryFrom<Value> for String {
+    type Error = DatabaseError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(s) => Ok(s),
+            other => other
+                .to_str()
+                .ok_or_else(|| conversion_error(&other, "String")),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<u8> {
+    type Error = DatabaseError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Binary(bytes) => Ok(bytes),
+            other => Err(conversion_error(&other, "Vec<u8>")),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -365,7 +986,7 @@ mod tests {
         let value = Value::I32(10);
         assert!(value.is_number());
 
-        let value = Value::F64(3.14);
+        let value = Value::F64(std::f64::consts::PI);
         assert!(value.is_number());
 
         let value = Value::String("Not a number".to_string());
@@ -419,8 +1040,8 @@ mod tests {
 
     #[test]
     fn test_value_as_f64() {
-        let value = Value::String("3.14".to_string());
-        assert_eq!(value.as_f64(), Some(3.14));
+        let value = Value::String("2.5".to_string());
+        assert_eq!(value.as_f64(), Some(2.5));
 
         let value = Value::I32(42);
         assert_eq!(value.as_f64(), Some(42.0));
@@ -432,6 +1053,18 @@ mod tests {
         assert_eq!(value.as_f64(), Some(0.0));
     }
 
+    #[test]
+    fn test_string_as_f64_parse_forms() {
+        assert_eq!(Value::String("1e5".to_string()).as_f64(), Some(100_000.0));
+        assert_eq!(Value::String("-3.2".to_string()).as_f64(), Some(-3.2));
+
+        // Non-finite results would be rejected by the validator, so they do not convert
+        assert_eq!(Value::String("inf".to_string()).as_f64(), None);
+        assert_eq!(Value::String("-inf".to_string()).as_f64(), None);
+        assert_eq!(Value::String("nan".to_string()).as_f64(), None);
+        assert_eq!(Value::String("1e400".to_string()).as_f64(), None);
+    }
+
     #[test]
     fn test_value_to_str() {
         let value = Value::String("Hello".to_string());
@@ -446,14 +1079,159 @@ mod tests {
         let value = Value::Bool(true);
         assert_eq!(value.to_str(), Some("true".to_string()));
 
-        let value = Value::F64(3.14);
-        assert_eq!(value.to_str(), Some("3.14".to_string()));
+        let value = Value::F64(2.5);
+        assert_eq!(value.to_str(), Some("2.5".to_string()));
+    }
+
+    #[test]
+    fn test_value_checked_add_promotes_on_overflow() {
+        let value = Value::I32(40).checked_add(&Value::I32(2));
+        assert_eq!(value, Some(Value::I32(42)));
+
+        let value = Value::I32(i32::MAX).checked_add(&Value::I32(1));
+        assert_eq!(value, Some(Value::I64(i32::MAX as i64 + 1)));
+
+        let value = Value::I32(i32::MIN).checked_sub(&Value::I32(1));
+        assert_eq!(value, Some(Value::I64(i32::MIN as i64 - 1)));
+
+        let value = Value::I32(i32::MAX).checked_mul(&Value::I32(i32::MAX));
+        assert_eq!(value, Some(Value::I64(i32::MAX as i64 * i32::MAX as i64)));
+
+        let value = Value::I64(i64::MAX).checked_add(&Value::I32(1));
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn test_value_checked_arith_mixed_types() {
+        let value = Value::I32(1).checked_add(&Value::I64(2));
+        assert_eq!(value, Some(Value::I64(3)));
+
+        let value = Value::I32(1).checked_add(&Value::F64(0.5));
+        assert_eq!(value, Some(Value::F64(1.5)));
+
+        let value = Value::F64(2.5).checked_mul(&Value::I64(2));
+        assert_eq!(value, Some(Value::F64(5.0)));
+
+        let value = Value::I64(10).checked_sub(&Value::I32(4));
+        assert_eq!(value, Some(Value::I64(6)));
+    }
+
+    #[test]
+    fn test_value_checked_arith_non_numeric() {
+        let value = Value::I32(1).checked_add(&Value::String("1".to_string()));
+        assert_eq!(value, None);
+
+        let value = Value::Bool(true).checked_add(&Value::F64(1.0));
+        assert_eq!(value, None);
+
+        let value = Value::Null.checked_mul(&Value::I32(2));
+        assert_eq!(value, None);
+    }
+
+    #[test]
+    fn test_value_checked_arith_i64_overflow_is_none() {
+        let value = Value::I64(i64::MIN).checked_sub(&Value::I32(1));
+        assert_eq!(value, None);
+
+        let value = Value::I64(i64::MAX / 2 + 1).checked_mul(&Value::I64(2));
+        assert_eq!(value, None);
+
+        // At the edge of the range, but still in it
+        let value = Value::I64(i64::MAX - 1).checked_add(&Value::I32(1));
+        assert_eq!(value, Some(Value::I64(i64::MAX)));
+
+        // Floats do not overflow, they saturate to infinity
+        let value = Value::I64(i64::MAX).checked_add(&Value::F64(1.0));
+        assert_eq!(value, Some(Value::F64(i64::MAX as f64 + 1.0)));
+        let value = Value::F64(f64::MAX).checked_mul(&Value::I32(2));
+        assert_eq!(value, Some(Value::F64(f64::INFINITY)));
+    }
+
+    #[test]
+    fn test_to_json_non_finite_roundtrip() {
+        for f in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let json = Value::F64(f).to_json(NonFiniteFloatMode::ExtendedJson).unwrap();
+            assert!(json.is_object(), "{} should not become a plain number", f);
+
+            let text = serde_json::to_string(&json).unwrap();
+            let parsed = Value::from_json_value(serde_json::from_str(&text).unwrap());
+            match parsed {
+                Value::F64(back) if f.is_nan() => assert!(back.is_nan()),
+                Value::F64(back) => assert_eq!(back, f),
+                other => panic!("Expected F64 for {}, got {:?}", f, other),
+            }
+        }
+
+        let json = Value::F64(f64::NAN).to_json(NonFiniteFloatMode::ExtendedJson).unwrap();
+        assert_eq!(json, serde_json::json!({"$numberDouble": "NaN"}));
+    }
+
+    #[test]
+    fn test_to_json_non_finite_error_mode() {
+        assert!(Value::F64(f64::NAN).to_json(NonFiniteFloatMode::Error).is_err());
+        assert!(
+            Value::Array(vec![Value::F64(f64::INFINITY)])
+                .to_json(NonFiniteFloatMode::Error)
+                .is_err()
+        );
+        assert_eq!(
+            Value::F64(1.5).to_json(NonFiniteFloatMode::Error).unwrap(),
+            serde_json::json!(1.5)
+        );
+    }
+
+    #[test]
+    fn test_value_leaves() {
+        let mut inner = BTreeMap::new();
+        inner.insert("city".to_string(), Value::String("Paris".to_string()));
+        inner.insert("empty".to_string(), Value::Object(BTreeMap::new()));
+        let mut outer = BTreeMap::new();
+        outer.insert("address".to_string(), Value::Object(inner));
+        outer.insert("tags".to_string(), Value::Array(vec![Value::I32(1)]));
+
+        let value = Value::Object(outer);
+        let leaves: Vec<(String, &Value)> = value.leaves();
+        assert_eq!(
+            leaves,
+            vec![
+                ("address.city".to_string(), &Value::String("Paris".to_string())),
+                ("tags".to_string(), &Value::Array(vec![Value::I32(1)])),
+            ]
+        );
+
+        assert_eq!(Value::I32(5).leaves(), vec![(String::new(), &Value::I32(5))]);
+    }
+
+    #[test]
+    fn test_value_normalize() {
+        let mut value = Value::I64(42);
+        value.normalize();
+        assert_eq!(value, Value::I32(42));
+
+        let mut value = Value::I64(i64::MAX);
+        value.normalize();
+        assert_eq!(value, Value::I64(i64::MAX));
+
+        let mut value = Value::F64(42.0);
+        value.normalize();
+        assert_eq!(value, Value::F64(42.0));
+
+        let mut value = Value::F64(-0.0);
+        value.normalize();
+        assert!(matches!(value, Value::F64(x) if x.is_sign_positive()));
+
+        let mut value = Value::Array(vec![Value::I64(1), Value::Array(vec![Value::I64(2)])]);
+        value.normalize();
+        assert_eq!(
+            value,
+            Value::Array(vec![Value::I32(1), Value::Array(vec![Value::I32(2)])])
+        );
     }
 
     #[test]
     fn test_value_is_object_id() {
         let oid = ObjectId::new();
-        let value = Value::ObjectId(oid.clone());
+        let value = Value::ObjectId(oid);
         assert!(value.is_object_id());
         assert_eq!(value.as_object_id(), Some(oid));
     }
@@ -461,7 +1239,7 @@ mod tests {
     #[test]
     fn test_object_id_display_and_hex() {
         let oid = ObjectId::from_bytes([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);
-        let value = Value::ObjectId(oid.clone());
+        let value = Value::ObjectId(oid);
         assert_eq!(value.to_string(), oid.to_hex());
     }
 
@@ -545,7 +1323,7 @@ mod tests {
                 Value::Object(obj) => {
                     let elements: Vec<String> = obj
                         .iter()
-                        .map(|(k, v)| format!("{}: {}", k, v.to_string()))
+                        .map(|(k, v)| format!("{}: {}", k, v))
                         .collect();
                     assert_eq!(display, format!("{{{}}}", elements.join(", ")));
                 }
@@ -554,6 +1332,9 @@ mod tests {
                     let hex: String = bin.iter().map(|b| format!("{:02x}", b)).collect();
                     assert_eq!(display, format!("Binary({})", hex));
                 }
+                Value::DocumentRef(id) => {
+                    assert_eq!(display, format!("Ref({}:{})", id.page_id(), id.slot_id()));
+                }
             }
         }
 
@@ -689,11 +1470,7 @@ mod tests {
                 Value::I64(i) => assert_eq!(result, Some(i as f64)),
                 Value::F64(f) => assert_eq!(result, Some(f)),
                 Value::String(s) => {
-                    if s.chars().all(|c| c.is_ascii_digit() || c == '.') {
-                        assert_eq!(result, s.parse::<f64>().ok());
-                    } else {
-                        assert_eq!(result, None);
-                    }
+                    assert_eq!(result, s.parse::<f64>().ok().filter(|f| f.is_finite()));
                 }
                 Value::Bool(b) => assert_eq!(result, Some(if b { 1.0 } else { 0.0 })),
                 Value::ObjectId(_) => assert_eq!(result, None), // ObjectId cannot be converted to
@@ -701,6 +1478,17 @@ mod tests {
             }
         }
 
+        #[test]
+        fn prop_numeric_string_as_f64(
+            s in "[-+]?[0-9]{0,4}(\\.[0-9]{0,3})?([eE][-+]?[0-9]{1,3})?|[-+]?(inf|infinity|nan|NaN)"
+        ) {
+            let result = Value::String(s.clone()).as_f64();
+            match s.parse::<f64>() {
+                Ok(f) if f.is_finite() => assert_eq!(result, Some(f)),
+                _ => assert_eq!(result, None),
+            }
+        }
+
         #[test]
         fn prop_value_as_str(value in any::<Value>()) {
             let result = value.to_str();
@@ -716,15 +1504,26 @@ mod tests {
             }
         }
 
+        #[test]
+        fn prop_value_as_str_agrees_with_to_str(value in any::<Value>()) {
+            match &value {
+                Value::String(s) => {
+                    assert_eq!(value.as_str(), Some(s.as_str()));
+                    assert_eq!(value.as_str().map(str::to_string), value.to_str());
+                }
+                _ => assert_eq!(value.as_str(), None),
+            }
+        }
+
         #[test]
         fn prop_value_object_id_roundtrip(oid in any::<ObjectId>()) {
-            let value = Value::ObjectId(oid.clone());
+            let value = Value::ObjectId(oid);
             prop_assert_eq!(value.as_object_id(), Some(oid));
         }
 
         #[test]
         fn prop_value_object_id_display_and_hex(oid in any::<ObjectId>()) {
-            let value = Value::ObjectId(oid.clone());
+            let value = Value::ObjectId(oid);
             prop_assert_eq!(value.to_string(), oid.to_hex());
         }
 
@@ -738,4 +1537,268 @@ mod tests {
             prop_assert!(value.to_str().is_none());
         }
     }
+
+    #[test]
+    fn test_from_rust_types() {
+        let now = Utc::now();
+        assert_eq!(Value::from(7i32), Value::I32(7));
+        assert_eq!(Value::from(7i64), Value::I64(7));
+        assert_eq!(Value::from(2.5f64), Value::F64(2.5));
+        assert_eq!(Value::from(true), Value::Bool(true));
+        assert_eq!(Value::from("abc".to_string()), Value::String("abc".to_string()));
+        assert_eq!(Value::from("abc"), Value::String("abc".to_string()));
+        assert_eq!(Value::from(vec![1u8, 2]), Value::Binary(vec![1, 2]));
+        assert_eq!(Value::from(now), Value::DateTime(now));
+
+        let into: Value = 7i32.into();
+        assert_eq!(into, Value::I32(7));
+    }
+
+    #[test]
+    fn test_try_from_value() {
+        let now = Utc::now();
+        assert_eq!(i32::try_from(Value::I32(7)).unwrap(), 7);
+        assert_eq!(i32::try_from(Value::String("42".to_string())).unwrap(), 42);
+        assert_eq!(i64::try_from(Value::I32(7)).unwrap(), 7);
+        assert_eq!(f64::try_from(Value::I64(3)).unwrap(), 3.0);
+        assert!(bool::try_from(Value::Bool(true)).unwrap());
+        assert_eq!(String::try_from(Value::String("abc".to_string())).unwrap(), "abc");
+        assert_eq!(String::try_from(Value::I32(5)).unwrap(), "5");
+        assert_eq!(Vec::<u8>::try_from(Value::Binary(vec![1, 2])).unwrap(), vec![1, 2]);
+        assert_eq!(DateTime::<Utc>::try_from(Value::DateTime(now)).unwrap(), now);
+    }
+
+    #[test]
+    fn test_try_from_value_mismatch() {
+        let err = i32::try_from(Value::String("abc".to_string())).unwrap_err();
+        assert!(matches!(err, DatabaseError::Validation(_)));
+        assert_eq!(
+            err.to_string(),
+            "Validation error: cannot convert string value 'abc' to i32"
+        );
+
+        assert!(i32::try_from(Value::I64(i64::MAX)).is_err());
+        assert!(i64::try_from(Value::Null).is_err());
+        assert!(f64::try_from(Value::Array(vec![])).is_err());
+        assert!(bool::try_from(Value::I32(2)).is_err());
+        assert!(String::try_from(Value::Null).is_err());
+        assert!(Vec::<u8>::try_from(Value::String("bytes".to_string())).is_err());
+        assert!(DateTime::<Utc>::try_from(Value::I64(0)).is_err());
+    }
+
+    #[test]
+    fn test_sort_array_numbers() {
+        let mut value = Value::Array(vec![
+            Value::F64(2.5),
+            Value::I32(3),
+            Value::I64(-1),
+            Value::I32(1),
+        ]);
+        value.sort_array().unwrap();
+        assert_eq!(
+            value,
+            Value::Array(vec![
+                Value::I64(-1),
+                Value::I32(1),
+                Value::F64(2.5),
+                Value::I32(3),
+            ])
+        );
+
+        value.sort_array_by(|a, b| b.total_cmp(a)).unwrap();
+        assert_eq!(value.as_array().unwrap()[0], Value::I32(3));
+    }
+
+    #[test]
+    fn test_total_cmp_int_float_is_exact_above_2_pow_53() {
+        let two_53 = 1_i64 << 53;
+        let below = Value::I64(two_53);
+        let float = Value::F64(two_53 as f64);
+        let above = Value::I64(two_53 + 1);
+
+        // 2^53 + 1 rounds to 2^53 as an f64, but must still sort above it
+        assert_eq!(below.total_cmp(&float), Ordering::Equal);
+        assert_eq!(above.total_cmp(&float), Ordering::Greater);
+        assert_eq!(float.total_cmp(&above), Ordering::Less);
+        assert_eq!(below.total_cmp(&above), Ordering::Less);
+
+        let cmp = |x: Value, y: f64| x.total_cmp(&Value::F64(y));
+        assert_eq!(cmp(Value::I64(i64::MAX), 2f64.powi(63)), Ordering::Less);
+        assert_eq!(cmp(Value::I64(i64::MIN), -(2f64.powi(63))), Ordering::Equal);
+        assert_eq!(cmp(Value::I64(i64::MIN), f64::NEG_INFINITY), Ordering::Greater);
+        assert_eq!(cmp(Value::I64(2), 2.5), Ordering::Less);
+        assert_eq!(cmp(Value::I64(-2), -2.5), Ordering::Greater);
+        assert_eq!(cmp(Value::I32(0), -0.0), Ordering::Equal);
+        assert_eq!(cmp(Value::I64(i64::MIN), f64::NAN), Ordering::Greater);
+    }
+
+    #[test]
+    fn test_sort_array_by_nested_key() {
+        let person = |name: &str, age: Option<i32>| {
+            let mut profile = BTreeMap::new();
+            if let Some(age) = age {
+                profile.insert("age".to_string(), Value::I32(age));
+            }
+            let mut obj = BTreeMap::new();
+            obj.insert("name".to_string(), Value::String(name.to_string()));
+            obj.insert("profile".to_string(), Value::Object(profile));
+            Value::Object(obj)
+        };
+        let mut value = Value::Array(vec![
+            person("c", Some(40)),
+            person("a", Some(20)),
+            person("x", None),
+            person("b", Some(30)),
+        ]);
+
+        value.sort_array_by_key("profile.age").unwrap();
+        let names: Vec<_> = value
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|p| p.as_object().unwrap()["name"].to_string())
+            .collect();
+        assert_eq!(names, vec!["x", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_sort_array_non_array_errors() {
+        let mut value = Value::String("not an array".to_string());
+        assert!(matches!(value.sort_array(), Err(DatabaseError::Validation(_))));
+        assert!(value.sort_array_by_key("a").is_err());
+        assert_eq!(value, Value::String("not an array".to_string()));
+    }
+
+    #[test]
+    fn test_epoch_millis_round_trip() {
+        use chrono::TimeZone;
+
+        let expected = Utc.with_ymd_and_hms(2024, 3, 1, 12, 30, 0).unwrap();
+        let millis = 1_709_296_200_000i64;
+
+        let dt = Value::I64(millis).as_datetime_from_epoch_millis().unwrap();
+        assert_eq!(dt, expected);
+        assert_eq!(Value::DateTime(dt).datetime_to_epoch_millis(), Some(millis));
+        assert_eq!(
+            Value::F64(millis as f64 + 0.9).as_datetime_from_epoch_millis(),
+            Some(expected)
+        );
+
+        // Before 1970
+        let moon_landing = Utc.with_ymd_and_hms(1969, 7, 20, 20, 17, 40).unwrap();
+        let dt = Value::I64(-14_182_940_000).as_datetime_from_epoch_millis().unwrap();
+        assert_eq!(dt, moon_landing);
+        assert_eq!(
+            Value::DateTime(dt).datetime_to_epoch_millis(),
+            Some(-14_182_940_000)
+        );
+
+        assert_eq!(
+            Value::I32(1_000).as_datetime_from_epoch_millis(),
+            Some(Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 1).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_epoch_millis_rejects_invalid() {
+        for value in [
+            Value::I64(i64::MAX),
+            Value::I64(i64::MIN),
+            Value::F64(f64::NAN),
+            Value::F64(f64::INFINITY),
+            Value::F64(1e300),
+            Value::String("1709296200000".to_string()),
+            Value::Null,
+        ] {
+            assert_eq!(value.as_datetime_from_epoch_millis(), None, "{:?}", value);
+        }
+        assert_eq!(Value::I64(1_000).datetime_to_epoch_millis(), None);
+    }
+
+    #[test]
+    fn test_pretty_indents_nested_object() {
+        let value = Value::Object(BTreeMap::from([
+            ("name".to_string(), Value::String("Ada".to_string())),
+            (
+                "address".to_string(),
+                Value::Object(BTreeMap::from([(
+                    "city".to_string(),
+                    Value::String("Oslo".to_string()),
+                )])),
+            ),
+            ("tags".to_string(), Value::Array(vec![Value::I32(1), Value::Null])),
+            ("empty".to_string(), Value::Array(vec![])),
+        ]));
+        let expected = "{\n  address: {\n    city: \"Oslo\"\n  },\n  empty: [],\n  name: \"Ada\",\n  tags: [\n    1,\n    null\n  ]\n}";
+        assert_eq!(value.pretty(2, None), expected);
+        assert_eq!(Value::I32(5).pretty(2, None), "5");
+    }
+
+    #[test]
+    fn test_pretty_truncates_at_max_depth() {
+        let inner = Value::Object(BTreeMap::from([("deep".to_string(), Value::I32(1))]));
+        let value = Value::Object(BTreeMap::from([
+            ("nested".to_string(), inner),
+            ("list".to_string(), Value::Array(vec![Value::I32(1)])),
+            ("n".to_string(), Value::I32(2)),
+        ]));
+
+        assert_eq!(
+            value.pretty(2, Some(1)),
+            "{\n  list: [...],\n  n: 2,\n  nested: {...}\n}"
+        );
+        assert_eq!(value.pretty(2, Some(0)), "{...}");
+        assert!(value.pretty(2, Some(2)).contains("deep: 1"));
+    }
+
+    #[test]
+    fn test_pretty_summarizes_large_arrays() {
+        let large = Value::Array((0..1000).map(Value::I32).collect());
+        assert_eq!(large.pretty(4, None), "[... 1000 items]");
+
+        let at_limit = Value::Array((0..PRETTY_ARRAY_LIMIT as i32).map(Value::I32).collect());
+        assert_eq!(at_limit.pretty(0, None).lines().count(), PRETTY_ARRAY_LIMIT + 2);
+    }
+
+    #[test]
+    fn test_take_leaves_null() {
+        let mut value = Value::Array(vec![Value::String("a".to_string()), Value::I32(1)]);
+        let taken = value.as_array_mut().unwrap()[0].take();
+        assert_eq!(taken, Value::String("a".to_string()));
+        assert_eq!(value, Value::Array(vec![Value::Null, Value::I32(1)]));
+
+        let mut value = Value::I64(7);
+        assert_eq!(value.take(), Value::I64(7));
+        assert_eq!(value, Value::Null);
+    }
+
+    #[test]
+    fn test_coalesce_skips_nulls() {
+        let a = Value::I32(1);
+        let b = Value::String("b".to_string());
+        assert_eq!(Value::coalesce(&[&Value::Null, &Value::Null, &a, &b]), Some(&a));
+        assert_eq!(Value::coalesce(&[&b, &a]), Some(&b));
+        assert_eq!(Value::coalesce(&[&Value::Null, &Value::Null]), None);
+        assert_eq!(Value::coalesce(&[]), None);
+    }
+
+    #[test]
+    fn test_as_number() {
+        assert_eq!(Value::I32(-7).as_number(), Some(Number::Int(-7)));
+        assert_eq!(Value::I64(i64::MAX).as_number(), Some(Number::Int(i64::MAX)));
+        assert_eq!(Value::F64(2.5).as_number(), Some(Number::Float(2.5)));
+        assert_eq!(Value::String("42".into()).as_number(), Some(Number::Int(42)));
+        assert_eq!(Value::String("0.5".into()).as_number(), Some(Number::Float(0.5)));
+        assert_eq!(Value::String("nan".into()).as_number(), None);
+        assert_eq!(Value::String("abc".into()).as_number(), None);
+        assert_eq!(Value::Bool(true).as_number(), None);
+        assert_eq!(Value::Null.as_number(), None);
+
+        assert_eq!(Number::Int(3).as_f64(), 3.0);
+        assert_eq!(Number::Int(i64::MAX).as_i64_checked(), Some(i64::MAX));
+        assert_eq!(Number::Float(-4.0).as_i64_checked(), Some(-4));
+        assert_eq!(Number::Float(4.5).as_i64_checked(), None);
+        assert_eq!(Number::Float(9.3e18).as_i64_checked(), None);
+        assert_eq!(Number::Float(f64::NAN).as_i64_checked(), None);
+    }
 }
@@ -1,6 +1,7 @@
 // Document validator
 
-use crate::document::{Document, Value};
+use crate::document::bson::DOCUMENT_REF_SIZE;
+use crate::document::{CREATED_FIELD, Document, UPDATED_FIELD, Value};
 use std::collections::HashSet;
 
 #[cfg(test)]
@@ -25,6 +26,8 @@ pub enum ValidationError {
     NestingDepthExceeded(usize, usize),
     #[error("Field count limit exceeded: {0} fields (max: {1})")]
     FieldCountExceeded(usize, usize),
+    #[error("Total element limit exceeded: {0} values (max: {1})")]
+    TotalElementsExceeded(usize, usize),
     #[error("Numeric value out of range: {0}")]
     NumericRangeExceeded(String),
     #[error("Invalid string field: {0}")]
@@ -36,9 +39,16 @@ pub struct DocumentValidator {
     max_size: usize, // default to 16MB
     max_depth: usize, // default to 100 levels
     max_fields: usize, // default to 1000 fields
+    max_total_elements: usize, // default to 100,000 scalar values
     reserved_field_names: HashSet<String>,
 }
 
+impl Default for DocumentValidator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DocumentValidator {
 
     pub fn new() -> Self {
@@ -53,10 +63,25 @@ impl DocumentValidator {
             max_size: 16 * 1024 * 1024,
             max_depth: 100,
             max_fields: 1000,
+            max_total_elements: 100_000,
             reserved_field_names: reserved_names,
         }
     }
 
+    // Permit the `_created`/`_updated` fields the storage engine's timestamp mode maintains
+    pub fn with_timestamp_fields(mut self) -> Self {
+        self.reserved_field_names.remove(CREATED_FIELD);
+        self.reserved_field_names.remove(UPDATED_FIELD);
+        self
+    }
+
+    /// Caps the number of scalar values in the whole document, counting every element
+    /// of every array and every field of every nested object.
+    pub fn with_max_total_elements(mut self, limit: usize) -> Self {
+        self.max_total_elements = limit;
+        self
+    }
+
     // doc size validation
     pub fn validate_size(&self, doc: &Document) -> Result<(), ValidationError> {
         let size = doc.size();
@@ -66,6 +91,39 @@ impl DocumentValidator {
         Ok(())
     }
 
+    /// Checks only the field names that would break storage: a top-level name the engine
+    /// reserves, such as a data field called `_id` that would be confused with the
+    /// document's id, or a name anywhere in the document containing a null byte, which
+    /// BSON cannot encode. Much cheaper than `validate_document`.
+    pub fn validate_storable_names(&self, doc: &Document) -> Result<(), ValidationError> {
+        for (name, value) in &doc.data {
+            if self.reserved_field_names.contains(name) {
+                return Err(ValidationError::ReservedFieldName(name.clone()));
+            }
+            if name.contains('\0') {
+                return Err(ValidationError::FieldNameContainsNullBytes);
+            }
+            Self::check_nested_names(value)?;
+        }
+        Ok(())
+    }
+
+    fn check_nested_names(value: &Value) -> Result<(), ValidationError> {
+        match value {
+            Value::Object(obj) => {
+                for (name, value) in obj {
+                    if name.contains('\0') {
+                        return Err(ValidationError::FieldNameContainsNullBytes);
+                    }
+                    Self::check_nested_names(value)?;
+                }
+                Ok(())
+            }
+            Value::Array(arr) => arr.iter().try_for_each(Self::check_nested_names),
+            _ => Ok(()),
+        }
+    }
+
     // field name validation
     pub fn validate_field_name(&self, name: &str) -> Result<(), ValidationError> {
         if name.is_empty() {
@@ -98,7 +156,7 @@ impl DocumentValidator {
     // DFS to find maximum nesting depth
     fn find_max_depth(&self, doc: &Document) -> usize {
         let mut max_depth = 0;
-        for (_, value) in &doc.data {
+        for value in doc.data.values() {
             let depth = self.get_value_depth(value, 1);
             max_depth = max_depth.max(depth);
         }
@@ -110,7 +168,7 @@ impl DocumentValidator {
         match value {
             Value::Object(obj) => {
                 let mut max_depth = current_depth;
-                for (_, val) in obj {
+                for val in obj.values() {
                     let depth = self.get_value_depth(val, current_depth + 1);
                     max_depth = max_depth.max(depth);
                 }
@@ -137,6 +195,29 @@ impl DocumentValidator {
         Ok(())
     }
 
+    /// Checks the document holds at most `max_total_elements` scalar values. Unlike
+    /// `max_fields`, this bounds the document as a whole, however its values are spread
+    /// over arrays and nested objects. Arrays and objects themselves are not counted,
+    /// only what they contain, so `Value::leaves`, which stops at arrays, is not used.
+    pub fn validate_total_elements(&self, doc: &Document) -> Result<(), ValidationError> {
+        let mut count = 0;
+        for value in doc.data.values() {
+            count += Self::count_scalars(value);
+            if count > self.max_total_elements {
+                return Err(ValidationError::TotalElementsExceeded(count, self.max_total_elements));
+            }
+        }
+        Ok(())
+    }
+
+    fn count_scalars(value: &Value) -> usize {
+        match value {
+            Value::Object(obj) => obj.values().map(Self::count_scalars).sum(),
+            Value::Array(arr) => arr.iter().map(Self::count_scalars).sum(),
+            _ => 1,
+        }
+    }
+
     // UTF-8 validation for strings
     pub fn validate_string_field(&self, string: &str) -> Result<(), ValidationError> {
         if !string.is_ascii() {
@@ -177,6 +258,9 @@ impl DocumentValidator {
         
         // Validate nesting depth
         self.validate_nesting_depth(doc)?;
+
+        // Validate the number of values across the whole document
+        self.validate_total_elements(doc)?;
         
         // Validate all fields recursively with path tracking
         self.validate_fields_recursive(doc, "")?;
@@ -234,6 +318,17 @@ impl DocumentValidator {
 
 // Add size method to Document
 impl Document {
+    /// Checks the document against a default `DocumentValidator`, i.e.
+    /// `DocumentValidator::new().validate_document(self)`.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.validate_with(&DocumentValidator::new())
+    }
+
+    /// Checks the document against `validator`, for non-default rules.
+    pub fn validate_with(&self, validator: &DocumentValidator) -> Result<(), ValidationError> {
+        validator.validate_document(self)
+    }
+
     pub fn size(&self) -> usize {
         // Estimate document size by serializing to BSON
         // This is a simplified implementation - you might want to use the actual BSON serializer
@@ -248,6 +343,12 @@ impl Document {
         size += 1; // Document null terminator
         size
     }
+
+    /// The exact length of `serialize_document(self)`, computed without serializing:
+    /// `size` plus the `_id` field written ahead of the others.
+    pub fn serialized_size(&self) -> usize {
+        self.size() + 1 + "_id".len() + 1 + self.estimate_value_size(&self.id)
+    }
     
     fn estimate_value_size(&self, value: &Value) -> usize {
         match value {
@@ -258,6 +359,7 @@ impl Document {
             Value::F64(_) => 8,
             Value::String(s) => 4 + s.len() + 1, // Length prefix + string + null terminator
             Value::ObjectId(_) => 12,
+            Value::DocumentRef(_) => 4 + 1 + DOCUMENT_REF_SIZE, // Binary length + subtype + id
             Value::Array(arr) => {
                 let mut size = 4; // Array length prefix
                 for (i, val) in arr.iter().enumerate() {
@@ -370,6 +472,30 @@ mod tests {
         assert!(size < 1000); // Should be reasonable for this simple document
     }
 
+    #[test]
+    fn test_serialized_size_matches_serialization() {
+        let mut nested = BTreeMap::new();
+        nested.insert("when".to_string(), Value::DateTime(chrono::Utc::now()));
+        nested.insert("blob".to_string(), Value::Binary(vec![1, 2, 3]));
+
+        let mut doc = Document::new();
+        doc.set("string", Value::String("hello".to_string()));
+        doc.set("number", Value::I32(42));
+        doc.set("big", Value::I64(1 << 40));
+        doc.set("ratio", Value::F64(0.5));
+        doc.set("flag", Value::Bool(true));
+        doc.set("nothing", Value::Null);
+        doc.set("nested", Value::Object(nested));
+        doc.set("list", Value::Array((0..12).map(Value::I32).collect()));
+
+        let bytes = crate::document::bson::serialize_document(&doc).unwrap();
+        assert_eq!(doc.serialized_size(), bytes.len());
+
+        let empty = Document::new();
+        let bytes = crate::document::bson::serialize_document(&empty).unwrap();
+        assert_eq!(empty.serialized_size(), bytes.len());
+    }
+
     #[test]
     fn test_field_name_validation() {
         let validator = DocumentValidator::new();
@@ -403,6 +529,15 @@ mod tests {
         assert!(validator.validate_field_name("_updated").is_err());
     }
 
+    #[test]
+    fn test_timestamp_fields_allowed_when_enabled() {
+        let validator = DocumentValidator::new().with_timestamp_fields();
+        assert!(validator.validate_field_name("_created").is_ok());
+        assert!(validator.validate_field_name("_updated").is_ok());
+        assert!(validator.validate_field_name("_id").is_err());
+        assert!(validator.validate_field_name("_version").is_err());
+    }
+
     #[test]
     fn test_string_validation() {
         let validator = DocumentValidator::new();
@@ -550,4 +685,92 @@ mod tests {
         assert!(result.is_err());
         // The error should be about the invalid string in the array
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_document_validate_convenience() {
+        let mut doc = Document::new();
+        doc.set("name", Value::String("Ada".to_string()));
+        assert!(doc.validate().is_ok());
+
+        doc.set("bad-name", Value::I32(1));
+        assert!(matches!(
+            doc.validate(),
+            Err(ValidationError::InvalidFieldName(name)) if name == "bad-name"
+        ));
+
+        // A custom validator can allow what the default one rejects
+        let mut stamped = Document::new();
+        stamped.set(CREATED_FIELD, Value::I32(1));
+        assert!(matches!(stamped.validate(), Err(ValidationError::ReservedFieldName(_))));
+        let validator = DocumentValidator::new().with_timestamp_fields();
+        assert!(stamped.validate_with(&validator).is_ok());
+    }
+
+    #[test]
+    fn test_validate_storable_names() {
+        let validator = DocumentValidator::new();
+        let mut doc = Document::new();
+        doc.set("bad-name", Value::I32(1));
+        // Only names that break storage are checked
+        assert!(validator.validate_storable_names(&doc).is_ok());
+
+        doc.set("_id", Value::I32(2));
+        assert!(matches!(
+            validator.validate_storable_names(&doc),
+            Err(ValidationError::ReservedFieldName(name)) if name == "_id"
+        ));
+
+        let mut nested = BTreeMap::new();
+        nested.insert("_id".to_string(), Value::I32(1));
+        let mut doc = Document::new();
+        doc.set("items", Value::Array(vec![Value::Object(nested.clone())]));
+        assert!(validator.validate_storable_names(&doc).is_ok());
+
+        nested.insert("a\0b".to_string(), Value::I32(1));
+        doc.set("items", Value::Array(vec![Value::Object(nested)]));
+        assert!(matches!(
+            validator.validate_storable_names(&doc),
+            Err(ValidationError::FieldNameContainsNullBytes)
+        ));
+    }
+
+    #[test]
+    fn test_total_elements_limit() {
+        let validator = DocumentValidator::new().with_max_total_elements(100);
+        let small_array = || Value::Array(vec![Value::I32(1), Value::I32(2)]);
+
+        // 49 arrays of two plus one scalar: exactly at the limit
+        let mut doc = Document::new();
+        for i in 0..49 {
+            doc.set(format!("list_{}", i), small_array());
+        }
+        doc.set("name", Value::String("x".to_string()));
+        assert!(validator.validate_document(&doc).is_ok());
+
+        // Many small arrays push it over, though no single field is large
+        doc.set("list_49", small_array());
+        assert!(matches!(
+            validator.validate_document(&doc),
+            Err(ValidationError::TotalElementsExceeded(101, 100))
+        ));
+    }
+
+    #[test]
+    fn test_total_elements_counts_nested_objects() {
+        let validator = DocumentValidator::new().with_max_total_elements(3);
+        let mut inner = BTreeMap::new();
+        inner.insert("a".to_string(), Value::I32(1));
+        inner.insert("b".to_string(), Value::Array(vec![Value::I32(2), Value::I32(3)]));
+        let mut doc = Document::new();
+        doc.set("outer", Value::Object(inner.clone()));
+        assert!(validator.validate_total_elements(&doc).is_ok());
+
+        let deeper = BTreeMap::from([("d".to_string(), Value::Null)]);
+        inner.insert("c".to_string(), Value::Object(deeper));
+        doc.set("outer", Value::Object(inner));
+        assert!(matches!(
+            validator.validate_total_elements(&doc),
+            Err(ValidationError::TotalElementsExceeded(4, 3))
+        ));
+    }
+}
@@ -1,3 +1,7 @@
+use crate::document::bson::BsonError;
+use crate::document::types::Value;
+use crate::document::validator::ValidationError;
+use crate::storage::integrity::IntegrityReport;
 use bincode;
 use serde_json;
 use std::fmt;
@@ -11,7 +15,16 @@ pub enum DatabaseError {
     Index(String),
     Network(String),
     Validation(String),
+    DuplicateKey { field: String, value: Value },
+    BufferPoolExhausted { capacity: usize, pinned: usize },
+    WorkingSetTooLarge { needed: usize, capacity: usize },
+    TooManyResults { max_buffered: usize },
+    Cancelled,
+    Corruption(String),
+    IntegrityCheckFailed(IntegrityReport),
+    DanglingReference(String),
     InvalidChecksum,
+    Bson(BsonError),
     Io(io::Error),
     Json(serde_json::Error),
     Bincode(bincode::Error),
@@ -26,7 +39,34 @@ impl fmt::Display for DatabaseError {
             DatabaseError::Index(msg) => write!(f, "Index error: {}", msg),
             DatabaseError::Network(msg) => write!(f, "Network error: {}", msg),
             DatabaseError::Validation(msg) => write!(f, "Validation error: {}", msg),
+            DatabaseError::DuplicateKey { field, value } => {
+                write!(f, "Duplicate key: {} = {}", field, value)
+            }
+            DatabaseError::BufferPoolExhausted { capacity, pinned } => write!(
+                f,
+                "Buffer pool exhausted: {} of {} pages pinned",
+                pinned, capacity
+            ),
+            DatabaseError::WorkingSetTooLarge { needed, capacity } => write!(
+                f,
+                "Operation needs {} pages pinned at once but the buffer pool holds {}; \
+                 open the database with a buffer pool of at least {} pages",
+                needed, capacity, needed
+            ),
+            DatabaseError::TooManyResults { max_buffered } => write!(
+                f,
+                "Query matched more than {} documents, the most it may buffer; \
+                 add a limit or visit the documents with scan_with",
+                max_buffered
+            ),
+            DatabaseError::Cancelled => write!(f, "Operation cancelled"),
+            DatabaseError::Corruption(msg) => write!(f, "Corruption detected: {}", msg),
+            DatabaseError::IntegrityCheckFailed(report) => {
+                write!(f, "Integrity check failed: {}", report)
+            }
+            DatabaseError::DanglingReference(msg) => write!(f, "Dangling reference: {}", msg),
             DatabaseError::InvalidChecksum => write!(f, "Invalid page checksum"),
+            DatabaseError::Bson(err) => write!(f, "BSON error: {}", err),
             DatabaseError::Io(err) => write!(f, "IO error: {}", err),
             DatabaseError::Json(err) => write!(f, "JSON error: {}", err),
             DatabaseError::Bincode(err) => write!(f, "Bincode error: {}", err),
@@ -37,6 +77,7 @@ impl fmt::Display for DatabaseError {
 impl std::error::Error for DatabaseError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
+            DatabaseError::Bson(err) => Some(err),
             DatabaseError::Io(err) => Some(err),
             DatabaseError::Json(err) => Some(err),
             DatabaseError::Bincode(err) => Some(err),
@@ -51,13 +92,32 @@ impl From<io::Error> for DatabaseError {
     }
 }
 
+impl From<BsonError> for DatabaseError {
+    fn from(err: BsonError) -> DatabaseError {
+        DatabaseError::Bson(err)
+    }
+}
+
+// Validation errors carry no data worth keeping beyond their message
+impl From<ValidationError> for DatabaseError {
+    fn from(err: ValidationError) -> DatabaseError {
+        DatabaseError::Validation(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for DatabaseError {
+    fn from(err: serde_json::Error) -> DatabaseError {
+        DatabaseError::Json(err)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_error_conversion_and_display() {
-        let io_error = io::Error::new(io::ErrorKind::Other, "disk full");
+        let io_error = io::Error::other("disk full");
         let db_error: DatabaseError = io_error.into();
 
         match db_error {
@@ -129,4 +189,106 @@ mod tests {
             "Validation error: Invalid data format"
         );
     }
+
+    #[test]
+    fn test_duplicate_key_display() {
+        let duplicate_error = DatabaseError::DuplicateKey {
+            field: "email".to_string(),
+            value: Value::String("a@example.com".to_string()),
+        };
+        assert_eq!(
+            format!("{}", duplicate_error),
+            "Duplicate key: email = a@example.com"
+        );
+    }
+
+    #[test]
+    fn test_buffer_pool_exhausted_display() {
+        let exhausted = DatabaseError::BufferPoolExhausted {
+            capacity: 4,
+            pinned: 4,
+        };
+        assert_eq!(
+            format!("{}", exhausted),
+            "Buffer pool exhausted: 4 of 4 pages pinned"
+        );
+    }
+
+    #[test]
+    fn test_cancelled_display() {
+        assert_eq!(format!("{}", DatabaseError::Cancelled), "Operation cancelled");
+    }
+
+    #[test]
+    fn test_too_many_results_display() {
+        let error = DatabaseError::TooManyResults { max_buffered: 100 };
+        assert_eq!(
+            format!("{}", error),
+            "Query matched more than 100 documents, the most it may buffer; \
+             add a limit or visit the documents with scan_with"
+        );
+    }
+
+    #[test]
+    fn test_dangling_reference_display() {
+        let error = DatabaseError::DanglingReference("1:4 no longer holds a document".to_string());
+        assert_eq!(
+            format!("{}", error),
+            "Dangling reference: 1:4 no longer holds a document"
+        );
+    }
+
+    #[test]
+    fn test_corruption_display() {
+        let error = DatabaseError::Corruption("slot 3 of page 1 holds another document".to_string());
+        assert_eq!(
+            format!("{}", error),
+            "Corruption detected: slot 3 of page 1 holds another document"
+        );
+    }
+
+    #[test]
+    fn test_working_set_too_large_display() {
+        let error = DatabaseError::WorkingSetTooLarge {
+            needed: 3,
+            capacity: 2,
+        };
+        assert_eq!(
+            format!("{}", error),
+            "Operation needs 3 pages pinned at once but the buffer pool holds 2; \
+             open the database with a buffer pool of at least 3 pages"
+        );
+    }
+
+    #[test]
+    fn test_bson_error_propagates_with_question_mark() {
+        fn decode(data: &[u8]) -> crate::result::Result<crate::Document> {
+            Ok(crate::bson::deserialize_document(data)?)
+        }
+
+        let err = decode(&[5, 0, 0]).unwrap_err();
+        assert!(matches!(err, DatabaseError::Bson(_)));
+        assert!(format!("{}", err).starts_with("BSON error:"));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn test_validation_error_propagates_with_question_mark() {
+        fn check(name: &str) -> crate::result::Result<()> {
+            crate::document::validator::DocumentValidator::new().validate_field_name(name)?;
+            Ok(())
+        }
+
+        assert!(check("name").is_ok());
+        match check("") {
+            Err(DatabaseError::Validation(msg)) => assert_eq!(msg, "Empty field name"),
+            other => panic!("Expected Validation, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_json_error_propagates_with_question_mark() {
+        let err = crate::Document::from_json("{not json").unwrap_err();
+        assert!(matches!(err, DatabaseError::Json(_)));
+    }
 }
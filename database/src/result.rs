@@ -1,4 +1,4 @@
 use crate::error::DatabaseError;
 
-#[allow(dead_code)]
-type DbResult<T> = Result<T, DatabaseError>;
+/// Result of the public storage and document APIs
+pub type Result<T> = std::result::Result<T, DatabaseError>;
@@ -3,6 +3,20 @@ use crate::storage::file::DatabaseFile;
 use crate::storage::page::Page;
 use std::collections::HashMap;
 
+/// A fixed-capacity cache of pages with least-recently-used eviction.
+///
+/// When a page must be evicted, the victim is the unpinned page whose last use is
+/// oldest. A use is any `pin_page` or `get_page` of the page, or loading it. Every use
+/// moves the page to the front of one recency list, so no two pages are ever equally
+/// old and the victim is fully determined by the order of calls. In particular, pages
+/// loaded together by `pin_pages` or `prefetch` and not used since are evicted in the
+/// order they were loaded, oldest insertion first. `eviction_candidate` names the page
+/// the next eviction would pick.
+///
+/// Evicting a dirty page writes it back first, which makes that miss slow. With a dirty
+/// high-water mark (see `with_dirty_high_water_mark`), `flush_excess_dirty` writes back
+/// the least recently used dirty pages ahead of time instead, so evictions mostly find
+/// clean pages.
 pub struct BufferPool {
     // Maximum number of pages in buffer pool
     capacity: usize,
@@ -16,6 +30,21 @@ pub struct BufferPool {
     dirty_pages: std::collections::HashSet<u64>,
     // Pinned pages (cannot be evicted)
     pinned_pages: std::collections::HashSet<u64>,
+    // Pages unpinned dirty since the last `take_modified`
+    modified_pages: std::collections::HashSet<u64>,
+    // Extra pages allowed beyond capacity when every resident page is pinned
+    slack: usize,
+    // Fraction of capacity past which `flush_excess_dirty` writes dirty pages back
+    dirty_high_water_mark: Option<f64>,
+    // Whether pages in `modified_pages` must stay in memory until `take_modified`
+    no_steal: bool,
+    // Page requests served from memory and from disk, and pages loaded by `prefetch`
+    hits: u64,
+    misses: u64,
+    prefetched: u64,
+    // Dirty pages written back by `flush_excess_dirty`, and evictions that had to write back
+    early_flushes: u64,
+    dirty_evictions: u64,
 }
 
 type LruNodeId = usize;
@@ -45,9 +74,104 @@ impl BufferPool {
             page_to_node: HashMap::new(),
             dirty_pages: std::collections::HashSet::new(),
             pinned_pages: std::collections::HashSet::new(),
+            modified_pages: std::collections::HashSet::new(),
+            slack: 0,
+            dirty_high_water_mark: None,
+            no_steal: false,
+            hits: 0,
+            misses: 0,
+            prefetched: 0,
+            early_flushes: 0,
+            dirty_evictions: 0,
         }
     }
 
+    /// Allow up to `slack` pages beyond capacity while every resident page is pinned.
+    ///
+    /// With the default slack of zero the pool never holds more than `capacity` pages;
+    /// loading another page while all are pinned fails with `BufferPoolExhausted`.
+    /// Pages held over capacity are evicted again as soon as they are unpinned.
+    pub fn with_slack(mut self, slack: usize) -> Self {
+        self.slack = slack;
+        self
+    }
+
+    /// Lets `flush_excess_dirty` write back dirty pages once more than `fraction` (between
+    /// 0 and 1) of capacity is dirty. Off by default, leaving write-back to evictions and
+    /// flushes.
+    pub fn with_dirty_high_water_mark(mut self, fraction: f64) -> Self {
+        self.dirty_high_water_mark = Some(fraction);
+        self
+    }
+
+    /// Returns the dirty high-water mark, if one is set.
+    pub fn dirty_high_water_mark(&self) -> Option<f64> {
+        self.dirty_high_water_mark
+    }
+
+    /// Keeps pages unpinned dirty since the last `take_modified` out of the data file
+    /// until that call, so a write-ahead log can make them durable first.
+    ///
+    /// Evictions and `flush_excess_dirty` pass over such pages. When nothing else can be
+    /// evicted the pool grows past capacity instead of failing, and shrinks back at the
+    /// loads after `take_modified`. `flush_all` still writes every dirty page.
+    pub fn with_no_steal(mut self) -> Self {
+        self.no_steal = true;
+        self
+    }
+
+    // Whether `page_id` holds a change the caller has not yet taken with `take_modified`
+    // and may not be written back
+    fn is_unlogged(&self, page_id: u64) -> bool {
+        self.no_steal && self.modified_pages.contains(&page_id)
+    }
+
+    /// Once more pages are dirty than the high-water mark allows, writes back the least
+    /// recently used unpinned dirty pages until at most half that many remain dirty.
+    /// Those are the pages evictions would reach first. Returns the number of pages
+    /// written, which is zero without a high-water mark or below it.
+    ///
+    /// Call it between operations, with no pages pinned for writing: a page is written
+    /// as it is in the pool. Halving the dirty set means one call covers many writes.
+    pub fn flush_excess_dirty(
+        &mut self,
+        database_file: &mut DatabaseFile,
+    ) -> Result<usize, DatabaseError> {
+        let Some(fraction) = self.dirty_high_water_mark else {
+            return Ok(0);
+        };
+        let limit = (self.capacity as f64 * fraction) as usize;
+        if self.dirty_pages.len() <= limit {
+            return Ok(0);
+        }
+
+        let excess = self.dirty_pages.len() - limit / 2;
+        let mut page_ids = Vec::with_capacity(excess);
+        let mut current = self.lru_list.tail;
+        while let Some(node_id) = current {
+            if page_ids.len() == excess {
+                break;
+            }
+            let node = &self.lru_list.nodes[node_id];
+            if self.dirty_pages.contains(&node.page_id)
+                && !self.pinned_pages.contains(&node.page_id)
+                && !self.is_unlogged(node.page_id)
+            {
+                page_ids.push(node.page_id);
+            }
+            current = node.prev;
+        }
+
+        // Ascending, like `flush_all`
+        page_ids.sort_unstable();
+        for &page_id in &page_ids {
+            self.write_page_to_disk(page_id, database_file)?;
+            self.dirty_pages.remove(&page_id);
+            self.early_flushes += 1;
+        }
+        Ok(page_ids.len())
+    }
+
     /// Pin a page in memory (prevents eviction)
     pub fn pin_page(
         &mut self,
@@ -56,15 +180,15 @@ impl BufferPool {
     ) -> Result<&mut Page, DatabaseError> {
         // Check if page is already in buffer pool
         if let Some(_page) = self.pages.get(&page_id) {
+            self.hits += 1;
             self.pinned_pages.insert(page_id);
             self.move_to_front(page_id);
             return Ok(self.pages.get_mut(&page_id).unwrap());
         }
+        self.misses += 1;
 
         // If buffer pool is full, evict a page
-        if self.pages.len() >= self.capacity {
-            self.evict_page(database_file)?;
-        }
+        self.make_room(database_file)?;
 
         // Load page from disk (you'll need to implement this)
         let page = database_file.read_page(page_id)?;
@@ -77,14 +201,62 @@ impl BufferPool {
         Ok(self.pages.get_mut(&page_id).unwrap())
     }
 
+    /// Pin several pages at once for an operation that needs them all resident together.
+    ///
+    /// The demand is checked up front: if these pages plus the ones already pinned would
+    /// not fit in capacity (plus slack), nothing is loaded and the call fails with
+    /// `WorkingSetTooLarge`, telling the caller how big the pool needs to be. Otherwise
+    /// every page is pinned, and on any load failure the pages pinned by this call are
+    /// released again. Unpin each page with `unpin_page` when done.
+    pub fn pin_pages(
+        &mut self,
+        page_ids: &[u64],
+        database_file: &mut DatabaseFile,
+    ) -> Result<(), DatabaseError> {
+        let mut needed = self.pinned_pages.clone();
+        needed.extend(page_ids.iter().copied());
+        if needed.len() > self.capacity + self.slack {
+            return Err(DatabaseError::WorkingSetTooLarge {
+                needed: needed.len(),
+                capacity: self.capacity,
+            });
+        }
+
+        let mut newly_pinned = Vec::new();
+        for &page_id in page_ids {
+            let already_pinned = self.pinned_pages.contains(&page_id);
+            if let Err(e) = self.pin_page(page_id, database_file) {
+                for page_id in newly_pinned {
+                    self.unpin_page(page_id, false);
+                }
+                return Err(e);
+            }
+            if !already_pinned {
+                newly_pinned.push(page_id);
+            }
+        }
+        Ok(())
+    }
+
     /// Unpin a page (allows eviction)
     pub fn unpin_page(&mut self, page_id: u64, is_dirty: bool) {
         self.pinned_pages.remove(&page_id);
         if is_dirty {
             self.dirty_pages.insert(page_id);
+            self.modified_pages.insert(page_id);
         }
     }
 
+    /// Ids of the pages unpinned dirty since the last call, in ascending order.
+    ///
+    /// Unlike the dirty set, this is not cleared by write-backs, so it names every page
+    /// a write changed even if the page was evicted meanwhile.
+    pub fn take_modified(&mut self) -> Vec<u64> {
+        let mut page_ids: Vec<u64> = self.modified_pages.drain().collect();
+        page_ids.sort_unstable();
+        page_ids
+    }
+
     /// Get read-only access to a page
     pub fn get_page(
         &mut self,
@@ -92,14 +264,14 @@ impl BufferPool {
         database_file: &mut DatabaseFile,
     ) -> Result<&Page, DatabaseError> {
         if self.pages.contains_key(&page_id) {
+            self.hits += 1;
             self.move_to_front(page_id);
             return Ok(self.pages.get(&page_id).unwrap());
         }
+        self.misses += 1;
 
         // Load from disk if not in buffer pool
-        if self.pages.len() >= self.capacity {
-            self.evict_page(database_file)?;
-        }
+        self.make_room(database_file)?;
 
         let page = self.load_page_from_disk(page_id, database_file)?;
         self.pages.insert(page_id, page);
@@ -108,34 +280,91 @@ impl BufferPool {
         Ok(self.pages.get(&page_id).unwrap())
     }
 
-    /// Evict least recently used page
-    fn evict_page(&mut self, database_file: &mut DatabaseFile) -> Result<(), DatabaseError> {
-        // Find LRU page that's not pinned
+    /// Load pages ahead of use without pinning them, so later requests for them are hits.
+    ///
+    /// Pages already resident are skipped. At most as many pages are loaded as fit
+    /// beside the pinned ones without evicting each other, and loading stops quietly at
+    /// the first page that cannot be read; a later request for it reports the error.
+    /// Returns the number of pages loaded.
+    pub fn prefetch(
+        &mut self,
+        page_ids: impl IntoIterator<Item = u64>,
+        database_file: &mut DatabaseFile,
+    ) -> usize {
+        let room = self.capacity.saturating_sub(self.pinned_pages.len());
+        let mut loaded = 0;
+        for page_id in page_ids.into_iter().take(room) {
+            if self.pages.contains_key(&page_id) {
+                continue;
+            }
+            if self.make_room(database_file).is_err() {
+                break;
+            }
+            let Ok(page) = self.load_page_from_disk(page_id, database_file) else {
+                break;
+            };
+            self.pages.insert(page_id, page);
+            self.add_to_front(page_id);
+            loaded += 1;
+        }
+        self.prefetched += loaded as u64;
+        loaded
+    }
+
+    /// The page the next eviction would pick: the least recently used unpinned page,
+    /// or `None` when every resident page is pinned (or, with no-steal, unlogged)
+    pub fn eviction_candidate(&self) -> Option<u64> {
         let mut current = self.lru_list.tail;
         while let Some(node_id) = current {
             let node = &self.lru_list.nodes[node_id];
-            let page_id = node.page_id;
-
             // Can't evict pinned pages
-            if !self.pinned_pages.contains(&page_id) {
-                // Write back if dirty
-                if self.dirty_pages.contains(&page_id) {
-                    self.write_page_to_disk(page_id, database_file)?;
-                    self.dirty_pages.remove(&page_id);
-                }
-
-                // Remove from buffer pool
-                self.pages.remove(&page_id);
-                self.remove_from_lru(page_id);
-                return Ok(());
+            if !self.pinned_pages.contains(&node.page_id) && !self.is_unlogged(node.page_id) {
+                return Some(node.page_id);
             }
-
             current = node.prev;
         }
+        None
+    }
+
+    /// Evict least recently used page
+    fn evict_page(&mut self, database_file: &mut DatabaseFile) -> Result<(), DatabaseError> {
+        let Some(page_id) = self.eviction_candidate() else {
+            return Err(DatabaseError::BufferPoolExhausted {
+                capacity: self.capacity,
+                pinned: self.pinned_pages.len(),
+            });
+        };
+
+        // Write back if dirty
+        if self.dirty_pages.contains(&page_id) {
+            self.write_page_to_disk(page_id, database_file)?;
+            self.dirty_pages.remove(&page_id);
+            self.dirty_evictions += 1;
+        }
+
+        // Remove from buffer pool
+        self.pages.remove(&page_id);
+        self.remove_from_lru(page_id);
+        Ok(())
+    }
 
-        Err(DatabaseError::Storage(
-            "No pages available for eviction".to_string(),
-        ))
+    /// Evict until there is room for one more page, within capacity plus slack
+    fn make_room(&mut self, database_file: &mut DatabaseFile) -> Result<(), DatabaseError> {
+        while self.pages.len() >= self.capacity {
+            match self.evict_page(database_file) {
+                Ok(()) => {}
+                Err(DatabaseError::BufferPoolExhausted { .. })
+                    if self.pages.len() < self.capacity + self.slack
+                        || self.pages.keys().any(|&page_id| {
+                            self.is_unlogged(page_id) && !self.pinned_pages.contains(&page_id)
+                        }) =>
+                {
+                    return Ok(());
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
     }
 
     /// Move page to front of LRU list (most recently used)
@@ -201,6 +430,11 @@ impl BufferPool {
             pages_in_pool: self.pages.len(),
             dirty_pages: self.dirty_pages.len(),
             pinned_pages: self.pinned_pages.len(),
+            hits: self.hits,
+            misses: self.misses,
+            prefetched: self.prefetched,
+            early_flushes: self.early_flushes,
+            dirty_evictions: self.dirty_evictions,
         }
     }
 
@@ -234,9 +468,13 @@ impl BufferPool {
         Ok(())
     }
 
-    /// Force flush all dirty pages to disk
+    /// Force flush all dirty pages to disk.
+    ///
+    /// Pages are written in ascending id order, so a large flush moves through the file
+    /// mostly sequentially instead of seeking back and forth. `clear` flushes the same way.
     pub fn flush_all(&mut self, database_file: &mut DatabaseFile) -> Result<(), DatabaseError> {
-        let dirty_page_ids: Vec<u64> = self.dirty_pages.iter().cloned().collect();
+        let mut dirty_page_ids: Vec<u64> = self.dirty_pages.iter().cloned().collect();
+        dirty_page_ids.sort_unstable();
 
         for page_id in dirty_page_ids {
             self.write_page_to_disk(page_id, database_file)?;
@@ -274,6 +512,26 @@ impl BufferPool {
         Ok(())
     }
 
+    /// Drop all pages from buffer pool without writing dirty pages back
+    pub fn discard_all(&mut self) {
+        self.pages.clear();
+        self.dirty_pages.clear();
+        self.modified_pages.clear();
+        self.pinned_pages.clear();
+        self.page_to_node.clear();
+        self.lru_list = LruList::new();
+    }
+
+    /// Drop one page from the buffer pool without writing it back, e.g. once the page on
+    /// disk has been replaced and the cached copy is stale
+    pub fn discard_page(&mut self, page_id: u64) {
+        self.pages.remove(&page_id);
+        self.dirty_pages.remove(&page_id);
+        self.modified_pages.remove(&page_id);
+        self.pinned_pages.remove(&page_id);
+        self.remove_from_lru(page_id);
+    }
+
     /// Get detailed buffer pool statistics
     pub fn get_detailed_stats(&self) -> DetailedBufferPoolStats {
         let lru_chain = self.get_lru_chain();
@@ -362,6 +620,16 @@ impl BufferPool {
 
     /// Validate buffer pool internal consistency (for testing)
     pub fn validate_consistency(&self) -> Result<(), String> {
+        // The LRU list must be well formed, or the eviction order is undefined
+        self.lru_list.validate()?;
+        if self.page_to_node.len() != self.pages.len() {
+            return Err(format!(
+                "{} pages in buffer pool but {} LRU nodes mapped",
+                self.pages.len(),
+                self.page_to_node.len()
+            ));
+        }
+
         // Check that all pages in the buffer pool are in the LRU list
         let lru_pages: std::collections::HashSet<u64> = self.get_lru_chain().into_iter().collect();
         let buffer_pages: std::collections::HashSet<u64> = self.pages.keys().cloned().collect();
@@ -481,6 +749,49 @@ impl LruList {
         self.head = Some(node_id);
     }
 
+    // Checks the links run consistently from head to tail, without cycles, and that no
+    // freed node is still linked
+    fn validate(&self) -> Result<(), String> {
+        let mut seen = std::collections::HashSet::new();
+        let mut prev = None;
+        let mut current = self.head;
+        while let Some(node_id) = current {
+            if !seen.insert(node_id) {
+                return Err(format!("LRU list has a cycle through node {}", node_id));
+            }
+            let node = self
+                .nodes
+                .get(node_id)
+                .ok_or_else(|| format!("LRU list links to missing node {}", node_id))?;
+            if node.prev != prev {
+                return Err(format!(
+                    "Node {} links back to {:?} instead of {:?}",
+                    node_id, node.prev, prev
+                ));
+            }
+            prev = Some(node_id);
+            current = node.next;
+        }
+        if self.tail != prev {
+            return Err(format!(
+                "LRU tail is {:?} but the list ends at {:?}",
+                self.tail, prev
+            ));
+        }
+        if let Some(node_id) = self.free_nodes.iter().find(|id| seen.contains(id)) {
+            return Err(format!("Freed node {} is still in the LRU list", node_id));
+        }
+        if seen.len() + self.free_nodes.len() != self.nodes.len() {
+            return Err(format!(
+                "{} linked and {} free nodes, but {} allocated",
+                seen.len(),
+                self.free_nodes.len(),
+                self.nodes.len()
+            ));
+        }
+        Ok(())
+    }
+
     fn remove(&mut self, node_id: LruNodeId) {
         let node = &self.nodes[node_id];
         let prev = node.prev;
@@ -511,6 +822,28 @@ pub struct BufferPoolStats {
     pub pages_in_pool: usize,
     pub dirty_pages: usize,
     pub pinned_pages: usize,
+    /// Page requests served from memory since the pool was created
+    pub hits: u64,
+    /// Page requests that had to read the page from disk
+    pub misses: u64,
+    /// Pages loaded ahead of use by `prefetch`
+    pub prefetched: u64,
+    /// Dirty pages written back early by `flush_excess_dirty`
+    pub early_flushes: u64,
+    /// Evictions that had to write the evicted page back first
+    pub dirty_evictions: u64,
+}
+
+impl BufferPoolStats {
+    /// Share of page requests served from memory, or 0.0 before any request
+    pub fn hit_ratio(&self) -> f64 {
+        let requests = self.hits + self.misses;
+        if requests == 0 {
+            0.0
+        } else {
+            self.hits as f64 / requests as f64
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,57 @@
+// Cooperative cancellation
+//
+// Long-running operations (scans, range lookups, vacuum) take a CancellationToken and
+// check it between pages. Cancelling never interrupts a page mid-way, so an abandoned
+// request stops within one page of work and leaves the database consistent.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Shared flag asking an in-progress operation to stop early.
+///
+/// Clones share the same flag, so one clone can be handed to the worker and another
+/// kept by whoever may abandon the request.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation. Operations notice it at their next page boundary.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// `Err(DatabaseError::Cancelled)` once cancellation has been requested
+    pub fn check(&self) -> Result<(), crate::error::DatabaseError> {
+        if self.is_cancelled() {
+            Err(crate::error::DatabaseError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_flag() {
+        let token = CancellationToken::new();
+        let worker = token.clone();
+        assert!(worker.check().is_ok());
+
+        token.cancel();
+        assert!(worker.is_cancelled());
+        assert!(worker.check().is_err());
+    }
+}
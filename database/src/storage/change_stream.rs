@@ -0,0 +1,100 @@
+// In-process change stream
+//
+// Lets applications react to writes, e.g. to invalidate caches or sync another system,
+// without polling. Each subscriber gets its own bounded channel and the engine sends
+// one event per successful insert, update, or delete, in the order they happened.
+//
+// Writes never wait for subscribers. A subscriber whose channel fills up because it is
+// not keeping up is dropped instead: its receiver still yields the events already
+// buffered and then reports the channel as disconnected, so a lagging consumer learns
+// that it missed events rather than silently skipping some. It can subscribe again and
+// resynchronize from a scan.
+
+use crate::storage::storage_engine::DocumentId;
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+
+/// Events a subscriber's channel holds before the subscriber is dropped as lagging
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 1024;
+
+/// A successful write, as sent to `StorageEngine::subscribe` receivers.
+///
+/// Updates and moves can relocate a document, so `Updated` carries the document's new
+/// id; its `object_id` is unchanged and identifies the document across relocations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeEvent {
+    Inserted(DocumentId),
+    Updated(DocumentId),
+    Deleted(DocumentId),
+}
+
+impl ChangeEvent {
+    /// The id the event is about
+    pub fn document_id(&self) -> DocumentId {
+        match self {
+            ChangeEvent::Inserted(id) | ChangeEvent::Updated(id) | ChangeEvent::Deleted(id) => *id,
+        }
+    }
+}
+
+/// The senders of every live subscription
+#[derive(Debug, Default)]
+pub struct ChangeStream {
+    subscribers: Vec<SyncSender<ChangeEvent>>,
+}
+
+impl ChangeStream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A new subscription whose channel holds at most `capacity` unread events
+    pub fn subscribe(&mut self, capacity: usize) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Whether anyone is listening, so callers can skip work only events need
+    pub fn has_subscribers(&self) -> bool {
+        !self.subscribers.is_empty()
+    }
+
+    /// Sends `event` to every subscriber without blocking, dropping subscribers whose
+    /// channel is full or whose receiver is gone.
+    pub fn publish(&mut self, event: ChangeEvent) {
+        self.subscribers.retain(|sender| match sender.try_send(event) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) | Err(TrySendError::Disconnected(_)) => false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::TryRecvError;
+
+    #[test]
+    fn test_lagging_subscriber_is_dropped_after_buffered_events() {
+        let mut stream = ChangeStream::new();
+        let lagging = stream.subscribe(2);
+        let keeping_up = stream.subscribe(2);
+
+        for slot in 0..3 {
+            let event = ChangeEvent::Inserted(DocumentId::new(1, slot));
+            stream.publish(event);
+            assert_eq!(keeping_up.try_recv(), Ok(event));
+        }
+        assert!(stream.has_subscribers());
+
+        // The first two events were buffered; the third did not fit
+        assert_eq!(lagging.try_recv(), Ok(ChangeEvent::Inserted(DocumentId::new(1, 0))));
+        assert_eq!(lagging.try_recv(), Ok(ChangeEvent::Inserted(DocumentId::new(1, 1))));
+        assert_eq!(lagging.try_recv(), Err(TryRecvError::Disconnected));
+
+        // A receiver that is dropped is forgotten
+        drop(keeping_up);
+        stream.publish(ChangeEvent::Deleted(DocumentId::new(1, 0)));
+        assert!(!stream.has_subscribers());
+    }
+}
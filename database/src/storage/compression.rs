@@ -0,0 +1,159 @@
+// Optional compression of stored documents
+//
+// A slot holds either a document's BSON bytes or a compressed copy of them, so
+// compressed and uncompressed documents can share a file and compression can be turned
+// on or off at any time. A compressed record starts with four zero bytes followed by a
+// zstd frame. BSON starts with the document's length, which is never less than five, so
+// the marker cannot be mistaken for a BSON document.
+//
+// Compression needs the `compression` feature. Without it, documents are always stored
+// uncompressed and reading a compressed one fails.
+
+use crate::error::DatabaseError;
+use crate::result::Result;
+use std::borrow::Cow;
+use std::io::Read;
+
+const COMPRESSED_MARKER: [u8; 4] = [0; 4];
+
+/// How `StorageEngine` stores serialized documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Store the BSON bytes as they are.
+    #[default]
+    None,
+    /// Compress each document with zstd at the given level (1 to 22; 3 is zstd's
+    /// default). A document that would not get smaller is stored uncompressed.
+    #[cfg(feature = "compression")]
+    Zstd(i32),
+}
+
+/// Whether a stored record holds a compressed document
+pub fn is_compressed(stored: &[u8]) -> bool {
+    stored.len() > COMPRESSED_MARKER.len() && stored.starts_with(&COMPRESSED_MARKER)
+}
+
+/// The record to store for the BSON bytes of a document
+pub fn encode(document_bytes: &[u8], compression: Compression) -> Result<Cow<'_, [u8]>> {
+    match compression {
+        Compression::None => Ok(Cow::Borrowed(document_bytes)),
+        #[cfg(feature = "compression")]
+        Compression::Zstd(level) => {
+            let mut stored = COMPRESSED_MARKER.to_vec();
+            zstd::stream::copy_encode(document_bytes, &mut stored, level)?;
+            if stored.len() < document_bytes.len() {
+                Ok(Cow::Owned(stored))
+            } else {
+                Ok(Cow::Borrowed(document_bytes))
+            }
+        }
+    }
+}
+
+/// The BSON bytes of a stored record, decompressing it if needed
+pub fn decode(stored: &[u8]) -> Result<Cow<'_, [u8]>> {
+    if !is_compressed(stored) {
+        return Ok(Cow::Borrowed(stored));
+    }
+    decompress(&stored[COMPRESSED_MARKER.len()..]).map(Cow::Owned)
+}
+
+/// A reader over the BSON bytes of a stored record, decompressing it as it is read
+pub fn reader(stored: &[u8]) -> Result<Box<dyn Read + '_>> {
+    if !is_compressed(stored) {
+        return Ok(Box::new(stored));
+    }
+    decompressing_reader(&stored[COMPRESSED_MARKER.len()..])
+}
+
+#[cfg(feature = "compression")]
+fn decompressing_reader(frame: &[u8]) -> Result<Box<dyn Read + '_>> {
+    Ok(Box::new(zstd::stream::read::Decoder::with_buffer(frame)?))
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompressing_reader(_frame: &[u8]) -> Result<Box<dyn Read + '_>> {
+    Err(feature_missing())
+}
+
+#[cfg(feature = "compression")]
+fn decompress(frame: &[u8]) -> Result<Vec<u8>> {
+    zstd::stream::decode_all(frame)
+        .map_err(|e| DatabaseError::Corruption(format!("undecodable compressed document: {}", e)))
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress(_frame: &[u8]) -> Result<Vec<u8>> {
+    Err(feature_missing())
+}
+
+#[cfg(not(feature = "compression"))]
+fn feature_missing() -> DatabaseError {
+    DatabaseError::Storage(
+        "document is compressed, but this build lacks the `compression` feature".to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::bson::serialize_document;
+    use crate::{Document, Value};
+
+    fn text_document() -> Vec<u8> {
+        let mut doc = Document::new();
+        doc.set("body", Value::String("lorem ipsum ".repeat(200)));
+        serialize_document(&doc).unwrap()
+    }
+
+    #[test]
+    fn test_uncompressed_records_pass_through() {
+        let bson = text_document();
+        let stored = encode(&bson, Compression::None).unwrap();
+        assert!(matches!(stored, Cow::Borrowed(_)));
+        assert!(!is_compressed(&stored));
+        assert_eq!(decode(&stored).unwrap(), &bson[..]);
+    }
+
+    #[test]
+    fn test_reader_yields_bson() {
+        let bson = text_document();
+        let mut read = Vec::new();
+        reader(&encode(&bson, Compression::default()).unwrap())
+            .unwrap()
+            .read_to_end(&mut read)
+            .unwrap();
+        assert_eq!(read, bson);
+
+        #[cfg(feature = "compression")]
+        {
+            let mut read = Vec::new();
+            reader(&encode(&bson, Compression::Zstd(3)).unwrap())
+                .unwrap()
+                .read_to_end(&mut read)
+                .unwrap();
+            assert_eq!(read, bson);
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_zstd_round_trip() {
+        let bson = text_document();
+        let stored = encode(&bson, Compression::Zstd(3)).unwrap();
+        assert!(is_compressed(&stored));
+        assert!(stored.len() < bson.len() / 4);
+        assert_eq!(decode(&stored).unwrap(), &bson[..]);
+
+        // Too small to shrink, so kept as BSON
+        let tiny = serialize_document(&Document::new()).unwrap();
+        assert_eq!(encode(&tiny, Compression::Zstd(3)).unwrap(), &tiny[..]);
+    }
+
+    #[cfg(not(feature = "compression"))]
+    #[test]
+    fn test_compressed_record_needs_feature() {
+        let stored = [0, 0, 0, 0, 0x28, 0xb5, 0x2f, 0xfd];
+        assert!(matches!(decode(&stored), Err(DatabaseError::Storage(_))));
+    }
+}
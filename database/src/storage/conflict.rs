@@ -0,0 +1,146 @@
+// Conflict resolution for upserts and imports
+//
+// When an incoming document collides with a stored one, either by `_id` or by the value
+// of a unique index, a ConflictResolver decides what ends up stored. Any
+// `FnMut(&Document, &Document) -> Resolution` closure is a resolver, and the common
+// policies are provided as `KeepExisting`, `TakeIncoming` and `DeepMerge`.
+
+use crate::Document;
+use crate::document::types::Value;
+
+/// What to do with an incoming document that collides with a stored one
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// Replace the stored document with this one. The stored `_id` is kept.
+    Replace(Document),
+    /// Leave the stored document as it is and drop the incoming one
+    Skip,
+}
+
+/// Decides between a stored document and an incoming one that collides with it
+pub trait ConflictResolver {
+    fn resolve(&mut self, existing: &Document, incoming: &Document) -> Resolution;
+}
+
+impl<F> ConflictResolver for F
+where
+    F: FnMut(&Document, &Document) -> Resolution,
+{
+    fn resolve(&mut self, existing: &Document, incoming: &Document) -> Resolution {
+        self(existing, incoming)
+    }
+}
+
+/// Keeps the stored document and drops the incoming one
+#[derive(Debug, Clone, Copy, Default)]
+pub struct KeepExisting;
+
+impl ConflictResolver for KeepExisting {
+    fn resolve(&mut self, _existing: &Document, _incoming: &Document) -> Resolution {
+        Resolution::Skip
+    }
+}
+
+/// Replaces the stored document with the incoming one
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TakeIncoming;
+
+impl ConflictResolver for TakeIncoming {
+    fn resolve(&mut self, _existing: &Document, incoming: &Document) -> Resolution {
+        Resolution::Replace(incoming.clone())
+    }
+}
+
+/// Merges the incoming document into the stored one.
+///
+/// Fields only in the stored document are kept and fields only in the incoming one are
+/// added. Where both have a field, nested objects are merged the same way, key by key,
+/// and any other value is taken from the incoming document; arrays are replaced, not
+/// concatenated.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DeepMerge;
+
+impl ConflictResolver for DeepMerge {
+    fn resolve(&mut self, existing: &Document, incoming: &Document) -> Resolution {
+        let mut merged = existing.clone();
+        for (key, value) in incoming.iter() {
+            let value = merge_value(merged.get(key), value);
+            merged.set(key.as_str(), value);
+        }
+        Resolution::Replace(merged)
+    }
+}
+
+fn merge_value(existing: Option<&Value>, incoming: &Value) -> Value {
+    match (existing, incoming) {
+        (Some(Value::Object(old)), Value::Object(new)) => {
+            let mut merged = old.clone();
+            for (key, value) in new {
+                let value = merge_value(merged.get(key), value);
+                merged.insert(key.clone(), value);
+            }
+            Value::Object(merged)
+        }
+        _ => incoming.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_deep_merge_nested_objects() {
+        let mut existing = Document::new();
+        existing.set("name", Value::String("old".to_string()));
+        existing.set("kept", Value::I32(1));
+        existing.set(
+            "address",
+            Value::Object(BTreeMap::from([
+                ("city".to_string(), Value::String("Oslo".to_string())),
+                ("zip".to_string(), Value::String("0150".to_string())),
+            ])),
+        );
+        existing.set("tags", Value::Array(vec![Value::I32(1)]));
+
+        let mut incoming = Document::new();
+        incoming.set("name", Value::String("new".to_string()));
+        incoming.set(
+            "address",
+            Value::Object(BTreeMap::from([(
+                "city".to_string(),
+                Value::String("Bergen".to_string()),
+            )])),
+        );
+        incoming.set("tags", Value::Array(vec![Value::I32(2)]));
+
+        let Resolution::Replace(merged) = DeepMerge.resolve(&existing, &incoming) else {
+            panic!("DeepMerge always replaces");
+        };
+        assert_eq!(merged.id(), existing.id());
+        assert_eq!(merged.get("name"), Some(&Value::String("new".to_string())));
+        assert_eq!(merged.get("kept"), Some(&Value::I32(1)));
+        assert_eq!(
+            merged.get_path("address.city"),
+            Some(&Value::String("Bergen".to_string()))
+        );
+        assert_eq!(
+            merged.get_path("address.zip"),
+            Some(&Value::String("0150".to_string()))
+        );
+        assert_eq!(merged.get("tags"), Some(&Value::Array(vec![Value::I32(2)])));
+    }
+
+    #[test]
+    fn test_closure_resolver() {
+        let mut calls = 0;
+        let mut resolver = |_: &Document, _: &Document| {
+            calls += 1;
+            Resolution::Skip
+        };
+        let doc = Document::new();
+        assert_eq!(resolver.resolve(&doc, &doc), Resolution::Skip);
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,198 @@
+// Document cache
+//
+// Decoded documents keyed by their location, so repeated reads of hot documents skip the
+// page lookup and BSON decoding. Entries are evicted least recently used first. The
+// storage engine keeps the cache coherent by replacing or dropping an entry on every write
+// to its slot.
+
+use crate::Document;
+use crate::storage::storage_engine::DocumentId;
+use std::collections::{BTreeMap, HashMap};
+
+/// Whether a write puts the written document in the cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CachePolicy {
+    /// Cache the written document, so reading it back is a hit. Suits single writes
+    /// that are likely to be read again soon.
+    #[default]
+    WriteThrough,
+    /// Leave the written document out of the cache (dropping any stale entry for its
+    /// slot), so a bulk load does not evict the documents actually being read.
+    WriteAround,
+}
+
+/// Hit and miss counts since the cache was created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Documents currently cached
+    pub entries: usize,
+}
+
+#[derive(Debug)]
+pub struct DocumentCache {
+    capacity: usize,
+    // Document and the tick it was last used at
+    entries: HashMap<DocumentId, (Document, u64)>,
+    // Tick -> document, oldest first
+    recency: BTreeMap<u64, DocumentId>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl DocumentCache {
+    /// A cache holding at most `capacity` documents. A capacity of zero caches nothing.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains(&self, document_id: &DocumentId) -> bool {
+        self.entries.contains_key(document_id)
+    }
+
+    /// Returns a copy of the cached document, counting a hit or a miss.
+    pub fn get(&mut self, document_id: &DocumentId) -> Option<Document> {
+        let tick = self.next_tick();
+        match self.entries.get_mut(document_id) {
+            Some((document, last_used)) => {
+                self.recency.remove(last_used);
+                self.recency.insert(tick, *document_id);
+                *last_used = tick;
+                self.hits += 1;
+                Some(document.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Caches `document` at `document_id`, evicting the least recently used entry if full.
+    pub fn insert(&mut self, document_id: DocumentId, document: Document) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.remove(&document_id);
+        while self.entries.len() >= self.capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+        }
+
+        let tick = self.next_tick();
+        self.recency.insert(tick, document_id);
+        self.entries.insert(document_id, (document, tick));
+    }
+
+    /// Drops the entry for `document_id`, if any.
+    pub fn remove(&mut self, document_id: &DocumentId) {
+        if let Some((_, last_used)) = self.entries.remove(document_id) {
+            self.recency.remove(&last_used);
+        }
+    }
+
+    /// Records a write of `document` to `document_id` according to `policy`.
+    pub fn write(&mut self, document_id: DocumentId, document: &Document, policy: CachePolicy) {
+        match policy {
+            CachePolicy::WriteThrough => self.insert(document_id, document.clone()),
+            CachePolicy::WriteAround => self.remove(&document_id),
+        }
+    }
+
+    /// Drops every entry, keeping the statistics.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.entries.len(),
+        }
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn named(name: &str) -> Document {
+        let mut doc = Document::new();
+        doc.set("name", crate::Value::String(name.to_string()));
+        doc
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = DocumentCache::new(2);
+        let a = DocumentId::new(1, 0);
+        let b = DocumentId::new(1, 1);
+        let c = DocumentId::new(1, 2);
+
+        cache.insert(a, named("a"));
+        cache.insert(b, named("b"));
+        // Touch `a` so `b` becomes the oldest
+        assert!(cache.get(&a).is_some());
+        cache.insert(c, named("c"));
+
+        assert!(cache.contains(&a));
+        assert!(!cache.contains(&b));
+        assert!(cache.contains(&c));
+        assert_eq!(cache.len(), 2);
+    }
+
+    #[test]
+    fn test_write_policies() {
+        let mut cache = DocumentCache::new(4);
+        let id = DocumentId::new(1, 0);
+        let doc = named("x");
+
+        cache.write(id, &doc, CachePolicy::WriteThrough);
+        assert!(cache.contains(&id));
+
+        cache.write(id, &doc, CachePolicy::WriteAround);
+        assert!(!cache.contains(&id));
+
+        assert_eq!(cache.get(&id), None);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (0, 1, 0));
+    }
+
+    #[test]
+    fn test_zero_capacity_caches_nothing() {
+        let mut cache = DocumentCache::new(0);
+        let id = DocumentId::new(1, 0);
+        cache.write(id, &named("x"), CachePolicy::WriteThrough);
+        assert!(cache.is_empty());
+    }
+}
@@ -1,17 +1,74 @@
 use crate::error::DatabaseError;
 use crate::storage::page::{Page, PAGE_SIZE};
+use crate::storage::page_layout::{PageLayout, SlotLayout};
 use fs2::FileExt;
 use serde::{Deserialize, Serialize};
-use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom, Write};
-use std::path::Path;
-
-const DATABASE_VERSION: u8 = 1;
+use std::cell::Cell;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+// Version 2 moved the file header into a reserved page 0
+const DATABASE_VERSION: u8 = 2;
+const MAGIC: [u8; 8] = *b"RUSTDB\0\0";
+
+/// Page 0 holds the file header and never stores documents.
+pub const HEADER_PAGE_ID: u64 = 0;
+/// The first page `allocate_page` hands out.
+pub const FIRST_DATA_PAGE_ID: u64 = 1;
+
+// The free-space directory follows the file header in page 0: a clean flag, the number
+// of pages covered as a little-endian u64, then one bucket byte per data page
+const FREE_SPACE_DIRECTORY_OFFSET: u64 = 256;
+const FREE_SPACE_DIRECTORY_PREFIX: usize = 1 + 8;
+/// Most data pages the free-space directory stored in the header page can cover
+pub const FREE_SPACE_DIRECTORY_CAPACITY: usize =
+    PAGE_SIZE - FREE_SPACE_DIRECTORY_OFFSET as usize - FREE_SPACE_DIRECTORY_PREFIX;
+
+/// Controls when page writes are forced to stable storage with fsync.
+///
+/// Page writes land in the OS page cache first; only an fsync guarantees they survive
+/// a crash or power loss. Each fsync is expensive, so the policy trades durability for
+/// write throughput.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FsyncPolicy {
+    /// Never fsync. Fastest, but any write the OS has not flushed on its own can be
+    /// lost on a crash, including writes made before a checkpoint.
+    Never,
+    /// Fsync only on an explicit checkpoint. Everything up to the last checkpoint is
+    /// durable; writes after it can be lost. Page writes themselves stay cheap.
+    #[default]
+    OnCheckpoint,
+    /// Fsync after every page write. Each written page is durable as soon as the write
+    /// returns, at the cost of one fsync per page write-back.
+    EveryWrite,
+    /// Fsync after a page write once at least this long has passed since the previous
+    /// fsync, and on checkpoint. Bounds the loss window to roughly one interval while
+    /// amortizing the fsync cost over many writes.
+    Interval(Duration),
+}
 
+/// The contents of page 0.
+///
+/// Written at the start of the header page, which is otherwise zero. The page is
+/// never read through `read_page`, so it carries no page header or checksum.
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 struct FileHeader {
+    magic: [u8; 8],
     version: u8,
-    page_count: u64,
+    page_size: u32,
+    /// The id the next page appended to the file gets, which is also the number of
+    /// pages in the file, header page included. Authoritative: the file's length is
+    /// never used to derive it.
+    next_page_id: u64,
+    /// First page of the free list, or 0 when it is empty. Each free page holds the id
+    /// of the next one (see `Page::new_free`).
+    free_list_head: u64,
+    /// Root page of the catalog, or 0 when there is none.
+    catalog_root: u64,
     /// Reserved space for future metadata.
     #[serde(with = "u8_64_serde")]
     metadata: [u8; 64],
@@ -64,8 +121,12 @@ mod u8_64_serde {
 impl FileHeader {
     fn new() -> Self {
         Self {
+            magic: MAGIC,
             version: DATABASE_VERSION,
-            page_count: 0,
+            page_size: PAGE_SIZE as u32,
+            next_page_id: FIRST_DATA_PAGE_ID,
+            free_list_head: 0,
+            catalog_root: 0,
             metadata: [0; 64],
         }
     }
@@ -74,28 +135,132 @@ impl FileHeader {
     fn size() -> u64 {
         bincode::serialized_size(&Self::new()).unwrap()
     }
+
+    fn validate(&self) -> Result<(), DatabaseError> {
+        if self.magic != MAGIC {
+            return Err(DatabaseError::Storage(
+                "Not a database file: bad magic number in header page".to_string(),
+            ));
+        }
+        if self.version != DATABASE_VERSION {
+            return Err(DatabaseError::Storage(format!(
+                "Incompatible database version. Expected {}, found {}",
+                DATABASE_VERSION, self.version
+            )));
+        }
+        if self.page_size != PAGE_SIZE as u32 {
+            return Err(DatabaseError::Storage(format!(
+                "Incompatible page size. Expected {}, found {}",
+                PAGE_SIZE, self.page_size
+            )));
+        }
+        if self.next_page_id < FIRST_DATA_PAGE_ID {
+            return Err(DatabaseError::Storage(
+                "Corrupt header page: next page id points at the header".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Byte offset of a page in the file; page 0 starts at offset 0
+fn page_offset(page_id: u64) -> u64 {
+    page_id * PAGE_SIZE as u64
+}
+
+// Temporary file `create` initializes before renaming it to `path`
+fn creating_path(path: &Path) -> Result<PathBuf, DatabaseError> {
+    let name = path.file_name().ok_or_else(|| {
+        DatabaseError::Storage(format!("{} does not name a file", path.display()))
+    })?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(".creating");
+    Ok(path.with_file_name(temp_name))
+}
+
+// Makes a rename into the directory holding `path` durable
+#[cfg(unix)]
+fn sync_parent_directory(path: &Path) -> Result<(), DatabaseError> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(parent)?.sync_all()?;
+    Ok(())
+}
+
+// Directories cannot be opened for syncing here; the rename is still atomic
+#[cfg(not(unix))]
+fn sync_parent_directory(_path: &Path) -> Result<(), DatabaseError> {
+    Ok(())
 }
 
 pub struct DatabaseFile {
     file: File,
     header: FileHeader,
+    fsync_policy: FsyncPolicy,
+    // Slot directory layout of the data pages `allocate_page` creates
+    slot_layout: SlotLayout,
+    last_sync: Cell<Instant>,
+    write_count: u64,
+    read_count: u64,
+    sync_count: Cell<u64>,
+    // Whether the free-space directory in page 0 is marked clean
+    free_space_directory_clean: bool,
+    // Called with the id of every page written through `write_page`
+    write_observer: Option<Box<dyn FnMut(u64) + Send>>,
 }
 
 impl DatabaseFile {
+    /// Creates a new database file holding only the header page.
+    ///
+    /// The header page is written and synced to a temporary file next to `path`, which
+    /// is then renamed into place. A crash part-way through leaves at most that
+    /// temporary file behind, never a half-initialized database at `path`; the next
+    /// `create` overwrites it. The rename is only atomic within one filesystem, which
+    /// is why the temporary file lives in the same directory as `path`.
+    ///
+    /// Fails if `path` already exists.
     pub fn create(path: &Path) -> Result<Self, DatabaseError> {
+        if path.exists() {
+            return Err(DatabaseError::Io(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", path.display()),
+            )));
+        }
+
+        let temp_path = creating_path(path)?;
+        let result = Self::create_at(&temp_path).and_then(|db_file| {
+            fs::rename(&temp_path, path)?;
+            sync_parent_directory(path)?;
+            Ok(db_file)
+        });
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    // Writes a fresh header page to `path`, replacing whatever an earlier interrupted
+    // create left there
+    fn create_at(path: &Path) -> Result<Self, DatabaseError> {
         let file = OpenOptions::new()
             .read(true)
             .write(true)
-            .create_new(true)
+            .create(true)
+            .truncate(true)
             .open(path)?;
 
         // Lock the file exclusively to prevent other processes from using it.
         file.try_lock_exclusive()
-            .map_err(|e| DatabaseError::Io(e.into()))?;
+            .map_err(DatabaseError::Io)?;
 
         let header = FileHeader::new();
-        let mut db_file = Self { file, header };
+        let mut db_file = Self::with_header(file, header);
 
+        // Reserve the whole header page
+        db_file.file.set_len(page_offset(FIRST_DATA_PAGE_ID))?;
         db_file.write_header()?;
         db_file.sync()?;
 
@@ -105,33 +270,40 @@ impl DatabaseFile {
     /// Opens an existing database file.
     ///
     /// This will open the file, acquire an exclusive lock, and read and validate
-    /// the file header.
+    /// the header page: its magic number, version and page size must match this build.
     pub fn open(path: &Path) -> Result<Self, DatabaseError> {
         let file = OpenOptions::new().read(true).write(true).open(path)?;
 
         // Lock the file exclusively.
         file.try_lock_exclusive()
-            .map_err(|e| DatabaseError::Io(e.into()))?;
+            .map_err(DatabaseError::Io)?;
 
-        let mut db_file = Self {
-            file,
-            // Header will be read from file.
-            header: FileHeader::new(),
-        };
+        // Header will be read from file.
+        let mut db_file = Self::with_header(file, FileHeader::new());
 
         db_file.read_header()?;
-
-        if db_file.header.version != DATABASE_VERSION {
-            return Err(DatabaseError::Storage(format!(
-                "Incompatible database version. Expected {}, found {}",
-                DATABASE_VERSION, db_file.header.version
-            )));
-        }
+        db_file.header.validate()?;
+        db_file.free_space_directory_clean = db_file.read_free_space_directory()?.is_some();
 
         Ok(db_file)
     }
 
-    /// Reads the file header from disk.
+    fn with_header(file: File, header: FileHeader) -> Self {
+        Self {
+            file,
+            header,
+            fsync_policy: FsyncPolicy::default(),
+            slot_layout: SlotLayout::default(),
+            last_sync: Cell::new(Instant::now()),
+            write_count: 0,
+            read_count: 0,
+            sync_count: Cell::new(0),
+            free_space_directory_clean: false,
+            write_observer: None,
+        }
+    }
+
+    /// Reads the file header from page 0.
     fn read_header(&mut self) -> Result<(), DatabaseError> {
         let mut buffer = vec![0; FileHeader::size() as usize];
         self.file.seek(SeekFrom::Start(0))?;
@@ -140,7 +312,7 @@ impl DatabaseFile {
         Ok(())
     }
 
-    /// Writes the file header to disk.
+    /// Writes the file header to page 0.
     fn write_header(&mut self) -> Result<(), DatabaseError> {
         let buffer = bincode::serialize(&self.header).map_err(DatabaseError::Bincode)?;
         self.file.seek(SeekFrom::Start(0))?;
@@ -149,58 +321,104 @@ impl DatabaseFile {
     }
 
     /// Reads a specific page from the disk.
+    ///
+    /// Fails for the header page, which is not a regular page.
     pub fn read_page(&mut self, page_id: u64) -> Result<Page, DatabaseError> {
-        if page_id >= self.header.page_count {
-            return Err(DatabaseError::Storage(format!(
-                "Attempted to read non-existent page {}",
-                page_id
-            )));
-        }
-        let offset = FileHeader::size() + page_id * PAGE_SIZE as u64;
-        self.file.seek(SeekFrom::Start(offset))?;
+        self.check_data_page(page_id, "read")?;
+        self.file.seek(SeekFrom::Start(page_offset(page_id)))?;
 
         let mut buffer = [0u8; PAGE_SIZE];
         self.file.read_exact(&mut buffer)?;
+        self.read_count += 1;
 
         Page::from_bytes(buffer)
     }
 
+    /// Reads a page like `read_page` but without verifying its checksum, so a damaged
+    /// page can still be inspected.
+    pub fn read_page_unchecked(&mut self, page_id: u64) -> Result<Page, DatabaseError> {
+        self.check_data_page(page_id, "read")?;
+        self.file.seek(SeekFrom::Start(page_offset(page_id)))?;
+
+        let mut buffer = [0u8; PAGE_SIZE];
+        self.file.read_exact(&mut buffer)?;
+        self.read_count += 1;
+
+        Ok(Page::from_bytes_unchecked(buffer))
+    }
+
     /// Writes a page to the disk at a specific page ID.
+    ///
+    /// Fails for the header page, which is only written through the header accessors.
     pub fn write_page(&mut self, page_id: u64, page: &Page) -> Result<(), DatabaseError> {
-        if page_id >= self.header.page_count {
+        self.check_data_page(page_id, "write to")?;
+        self.invalidate_free_space_directory()?;
+        self.file.seek(SeekFrom::Start(page_offset(page_id)))?;
+        self.file.write_all(&page.to_bytes())?;
+        self.write_count += 1;
+        if let Some(observer) = &mut self.write_observer {
+            observer(page_id);
+        }
+
+        match self.fsync_policy {
+            FsyncPolicy::EveryWrite => self.sync()?,
+            FsyncPolicy::Interval(interval) if self.last_sync.get().elapsed() >= interval => {
+                self.sync()?
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn check_data_page(&self, page_id: u64, action: &str) -> Result<(), DatabaseError> {
+        if page_id == HEADER_PAGE_ID {
             return Err(DatabaseError::Storage(format!(
-                "Attempted to write to non-existent page {}",
-                page_id
+                "Attempted to {} page {}, which is the file header",
+                action, page_id
+            )));
+        }
+        if page_id >= self.header.next_page_id {
+            return Err(DatabaseError::Storage(format!(
+                "Attempted to {} non-existent page {}",
+                action, page_id
             )));
         }
-        let offset = FileHeader::size() + page_id * PAGE_SIZE as u64;
-        self.file.seek(SeekFrom::Start(offset))?;
-        self.file.write_all(&page.to_bytes())?;
         Ok(())
     }
 
     /// Allocates a new page in the database file.
     ///
-    /// This creates a new page with proper headers and checksum, writes it to disk,
-    /// and increments the page count in the header.
-    /// Returns the new page ID.
+    /// Reuses the first page of the free list if there is one; otherwise the page is
+    /// appended and the next page id in the header advances. Either way the header is
+    /// written before the page, so a crash in between can at worst leak the page, never
+    /// hand out a page the header still lists as free.
+    /// Returns the new page ID, which is never the header page.
     pub fn allocate_page(&mut self) -> Result<u64, DatabaseError> {
-        use crate::storage::page::PageType;
-        
-        let new_page_id = self.header.page_count;
+        let new_page_id = match self.header.free_list_head {
+            0 => self.header.next_page_id,
+            head => head,
+        };
         
-        // Create a new, properly initialized page with valid headers and checksum
-        let new_page = Page::new(new_page_id, PageType::Data);
+        // Create a new data page with an empty slot directory and a valid checksum
+        let new_page = PageLayout::new_page_with_layout(new_page_id, self.slot_layout);
+        self.invalidate_free_space_directory()?;
         
-        // Update header first to reflect the new page count
-        self.header.page_count += 1;
+        // Update header first to reflect the new page count or free list
+        if new_page_id == self.header.free_list_head {
+            let next_free = self.read_page(new_page_id)?.next_free_page().ok_or_else(|| {
+                DatabaseError::Corruption(format!(
+                    "free list head {} is not a free page",
+                    new_page_id
+                ))
+            })?;
+            self.header.free_list_head = next_free;
+        } else {
+            self.header.next_page_id += 1;
+        }
         self.write_header()?;
         
-        // Calculate the offset for the new page
-        let offset = FileHeader::size() + new_page_id * PAGE_SIZE as u64;
-        
         // Write the new page to the correct file offset
-        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.seek(SeekFrom::Start(page_offset(new_page_id)))?;
         self.file.write_all(&new_page.to_bytes())?;
         
         Ok(new_page_id)
@@ -209,12 +427,234 @@ impl DatabaseFile {
     /// Flushes all in-memory changes to the disk.
     pub fn sync(&self) -> Result<(), DatabaseError> {
         self.file.sync_all()?;
+        self.sync_count.set(self.sync_count.get() + 1);
+        self.last_sync.set(Instant::now());
         Ok(())
     }
 
-    /// Returns the number of pages in the file.
+    /// Syncs the file if the fsync policy calls for it at a checkpoint.
+    ///
+    /// `EveryWrite` has already synced each page as it was written, and `Never` never
+    /// syncs, so only `OnCheckpoint` and `Interval` issue an fsync here.
+    pub fn checkpoint(&self) -> Result<(), DatabaseError> {
+        match self.fsync_policy {
+            FsyncPolicy::OnCheckpoint | FsyncPolicy::Interval(_) => self.sync(),
+            FsyncPolicy::Never | FsyncPolicy::EveryWrite => Ok(()),
+        }
+    }
+
+    /// Returns the fsync policy applied to page writes.
+    pub fn fsync_policy(&self) -> FsyncPolicy {
+        self.fsync_policy
+    }
+
+    /// Sets the fsync policy applied to subsequent page writes.
+    pub fn set_fsync_policy(&mut self, policy: FsyncPolicy) {
+        self.fsync_policy = policy;
+    }
+
+    /// Returns the slot directory layout of newly allocated pages.
+    pub fn slot_layout(&self) -> SlotLayout {
+        self.slot_layout
+    }
+
+    /// Sets the slot directory layout of pages allocated from now on. Existing pages
+    /// keep the layout they were created with.
+    pub fn set_slot_layout(&mut self, layout: SlotLayout) {
+        self.slot_layout = layout;
+    }
+
+    /// Returns the number of pages written through `write_page` since the file was opened.
+    pub fn write_count(&self) -> u64 {
+        self.write_count
+    }
+
+    /// Sets a callback run with the page id after each successful `write_page`, e.g. to
+    /// trace the order pages reach the file. Replaces any earlier observer.
+    pub fn set_write_observer<F>(&mut self, observer: F)
+    where
+        F: FnMut(u64) + Send + 'static,
+    {
+        self.write_observer = Some(Box::new(observer));
+    }
+
+    /// Returns the number of pages read through `read_page` since the file was opened.
+    pub fn read_count(&self) -> u64 {
+        self.read_count
+    }
+
+    /// Returns the number of fsyncs issued since the file was opened.
+    pub fn sync_count(&self) -> u64 {
+        self.sync_count.get()
+    }
+
+    /// Returns the number of pages in the file, including the header page.
     pub fn page_count(&self) -> u64 {
-        self.header.page_count
+        self.header.next_page_id
+    }
+
+    /// Returns the number of whole pages the file holds, including the header page.
+    /// Less than `page_count` when the file was cut short, e.g. by a crash while a new
+    /// page was being written.
+    pub fn stored_page_count(&self) -> Result<u64, DatabaseError> {
+        Ok(self.file.metadata()?.len() / PAGE_SIZE as u64)
+    }
+
+    /// Returns the ids of the pages that can hold documents, in file order.
+    pub fn data_page_ids(&self) -> Range<u64> {
+        FIRST_DATA_PAGE_ID..self.header.next_page_id
+    }
+
+    /// Puts `page_id` on the free list, so the next `allocate_page` reuses it instead of
+    /// growing the file. The list is kept in the header page and the freed pages, so it
+    /// survives reopening.
+    ///
+    /// A freed page keeps its place among `data_page_ids` until it is reused, but holds
+    /// no documents; only free pages nothing else refers to. The storage engine does not
+    /// free pages itself. Fails for a page that is already free.
+    pub fn free_page(&mut self, page_id: u64) -> Result<(), DatabaseError> {
+        if self.read_page(page_id)?.next_free_page().is_some() {
+            return Err(DatabaseError::Storage(format!(
+                "Page {} is already free",
+                page_id
+            )));
+        }
+        // The page links to the old head before the header points at it
+        self.write_page(page_id, &Page::new_free(page_id, self.header.free_list_head))?;
+        self.set_free_list_head(page_id)
+    }
+
+    /// Returns the first page of the free list, or 0 when it is empty.
+    pub fn free_list_head(&self) -> u64 {
+        self.header.free_list_head
+    }
+
+    /// Records a new free-list head in the header page. The page must be a free page
+    /// (see `Page::new_free`), or 0; `free_page` maintains the list for you.
+    pub fn set_free_list_head(&mut self, page_id: u64) -> Result<(), DatabaseError> {
+        self.header.free_list_head = page_id;
+        self.write_header()
+    }
+
+    /// Returns the root page of the catalog, or 0 when there is none.
+    pub fn catalog_root(&self) -> u64 {
+        self.header.catalog_root
+    }
+
+    /// Records a new catalog root in the header page.
+    pub fn set_catalog_root(&mut self, page_id: u64) -> Result<(), DatabaseError> {
+        self.header.catalog_root = page_id;
+        self.write_header()
+    }
+
+    /// Returns the free-space directory stored in the header page, one bucket byte per
+    /// data page, if it is marked clean and covers exactly the current data pages.
+    ///
+    /// The directory is marked stale before any page is written after it was stored,
+    /// so a clean directory matches the pages on disk.
+    pub fn read_free_space_directory(&mut self) -> Result<Option<Vec<u8>>, DatabaseError> {
+        let mut prefix = [0u8; FREE_SPACE_DIRECTORY_PREFIX];
+        self.file.seek(SeekFrom::Start(FREE_SPACE_DIRECTORY_OFFSET))?;
+        self.file.read_exact(&mut prefix)?;
+
+        let covered = u64::from_le_bytes(prefix[1..].try_into().unwrap());
+        if prefix[0] != 1 || covered != self.data_page_count() {
+            return Ok(None);
+        }
+        let mut buckets = vec![0u8; covered as usize];
+        self.file.read_exact(&mut buckets)?;
+        Ok(Some(buckets))
+    }
+
+    /// Stores the free-space directory in the header page and marks it clean.
+    ///
+    /// `buckets` must hold one entry per current data page. A directory larger than
+    /// `FREE_SPACE_DIRECTORY_CAPACITY` is not stored, leaving the stored one stale.
+    pub fn write_free_space_directory(&mut self, buckets: &[u8]) -> Result<(), DatabaseError> {
+        if buckets.len() > FREE_SPACE_DIRECTORY_CAPACITY
+            || buckets.len() as u64 != self.data_page_count()
+        {
+            return self.invalidate_free_space_directory();
+        }
+        let mut buffer = Vec::with_capacity(FREE_SPACE_DIRECTORY_PREFIX + buckets.len());
+        buffer.push(1);
+        buffer.extend_from_slice(&(buckets.len() as u64).to_le_bytes());
+        buffer.extend_from_slice(buckets);
+        self.file.seek(SeekFrom::Start(FREE_SPACE_DIRECTORY_OFFSET))?;
+        self.file.write_all(&buffer)?;
+        self.free_space_directory_clean = true;
+        Ok(())
+    }
+
+    fn data_page_count(&self) -> u64 {
+        self.header.next_page_id - FIRST_DATA_PAGE_ID
+    }
+
+    // Marks the stored directory stale ahead of a page write that may change free space
+    fn invalidate_free_space_directory(&mut self) -> Result<(), DatabaseError> {
+        if self.free_space_directory_clean {
+            self.file.seek(SeekFrom::Start(FREE_SPACE_DIRECTORY_OFFSET))?;
+            self.file.write_all(&[0])?;
+            self.free_space_directory_clean = false;
+        }
+        Ok(())
+    }
+
+    /// Removes every data page, leaving a valid empty database file.
+    ///
+    /// The header is written and synced with no data pages before the file is shrunk,
+    /// so a crash in between leaves an empty database with unused trailing bytes rather
+    /// than a header pointing at missing pages. The free list and catalog are reset too,
+    /// since the pages they pointed at are gone.
+    pub fn truncate(&mut self) -> Result<(), DatabaseError> {
+        self.invalidate_free_space_directory()?;
+        self.header.next_page_id = FIRST_DATA_PAGE_ID;
+        self.header.free_list_head = 0;
+        self.header.catalog_root = 0;
+        self.write_header()?;
+        self.sync()?;
+
+        self.file.set_len(page_offset(FIRST_DATA_PAGE_ID))?;
+        self.sync()
+    }
+
+    /// Copies this database into a new file at `dest`, page by page.
+    ///
+    /// Every page is read through `read_page`, so its checksum is verified before it
+    /// is written to the copy. A corrupt source page aborts the backup with an error
+    /// rather than silently producing a corrupt copy; the partial copy is removed.
+    /// The destination must not exist yet and is a standalone database file once
+    /// this returns.
+    pub fn backup(&mut self, dest: &Path) -> Result<(), DatabaseError> {
+        let mut backup_file = DatabaseFile::create(dest)?;
+
+        if let Err(e) = self.copy_pages_to(&mut backup_file) {
+            drop(backup_file);
+            let _ = std::fs::remove_file(dest);
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    fn copy_pages_to(&mut self, backup_file: &mut DatabaseFile) -> Result<(), DatabaseError> {
+        for page_id in self.data_page_ids() {
+            let page = self.read_page(page_id).map_err(|e| {
+                DatabaseError::Storage(format!(
+                    "Backup aborted: page {} failed verification: {}",
+                    page_id, e
+                ))
+            })?;
+
+            let backup_page_id = backup_file.allocate_page()?;
+            backup_file.write_page(backup_page_id, &page)?;
+        }
+
+        // Page ids are preserved, so the header's page pointers stay valid in the copy
+        backup_file.header.free_list_head = self.header.free_list_head;
+        backup_file.header.catalog_root = self.header.catalog_root;
+        backup_file.write_header()?;
+        backup_file.sync()
     }
 }
 
@@ -232,14 +672,126 @@ mod tests {
         // Create
         {
             let db_file = DatabaseFile::create(&path).unwrap();
-            assert_eq!(db_file.page_count(), 0);
+            assert_eq!(db_file.page_count(), 1);
+            assert!(db_file.data_page_ids().is_empty());
         }
 
         // Open
         {
             let db_file = DatabaseFile::open(&path).unwrap();
+            assert_eq!(db_file.header.magic, MAGIC);
             assert_eq!(db_file.header.version, DATABASE_VERSION);
-            assert_eq!(db_file.page_count(), 0);
+            assert_eq!(db_file.header.page_size, PAGE_SIZE as u32);
+            assert_eq!(db_file.page_count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_header_page_is_reserved() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db");
+        let mut db_file = DatabaseFile::create(&path).unwrap();
+
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), PAGE_SIZE as u64);
+        assert!(db_file.read_page(HEADER_PAGE_ID).is_err());
+        assert!(db_file
+            .write_page(HEADER_PAGE_ID, &Page::new(HEADER_PAGE_ID, PageType::Data))
+            .is_err());
+
+        assert_eq!(db_file.allocate_page().unwrap(), FIRST_DATA_PAGE_ID);
+        assert_eq!(db_file.data_page_ids(), FIRST_DATA_PAGE_ID..2);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 2 * PAGE_SIZE as u64);
+    }
+
+    #[test]
+    fn test_header_fields_persist() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db");
+
+        {
+            let mut db_file = DatabaseFile::create(&path).unwrap();
+            for _ in 0..3 {
+                db_file.allocate_page().unwrap();
+            }
+            db_file.free_page(2).unwrap();
+            db_file.set_catalog_root(3).unwrap();
+        }
+
+        let mut db_file = DatabaseFile::open(&path).unwrap();
+        assert_eq!(db_file.page_count(), 4);
+        assert_eq!(db_file.free_list_head(), 2);
+        assert_eq!(db_file.catalog_root(), 3);
+        assert_eq!(db_file.allocate_page().unwrap(), 2);
+        assert_eq!(db_file.allocate_page().unwrap(), 4);
+    }
+
+    #[test]
+    fn test_next_page_id_is_read_from_header_not_file_length() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db");
+        {
+            let mut db_file = DatabaseFile::create(&path).unwrap();
+            for _ in 0..3 {
+                db_file.allocate_page().unwrap();
+            }
+        }
+        // Trailing bytes, e.g. from a crash part-way through growing the file
+        let file = OpenOptions::new().write(true).open(&path).unwrap();
+        file.set_len(page_offset(10)).unwrap();
+        drop(file);
+
+        let mut db_file = DatabaseFile::open(&path).unwrap();
+        assert_eq!(db_file.page_count(), 4);
+        assert_eq!(db_file.allocate_page().unwrap(), 4);
+        assert_eq!(db_file.page_count(), 5);
+    }
+
+    #[test]
+    fn test_freed_pages_are_reused_across_reopen() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db");
+        {
+            let mut db_file = DatabaseFile::create(&path).unwrap();
+            for _ in 0..4 {
+                db_file.allocate_page().unwrap();
+            }
+            db_file.free_page(2).unwrap();
+            db_file.free_page(4).unwrap();
+            assert!(db_file.free_page(2).is_err());
+            assert_eq!(db_file.free_list_head(), 4);
+        }
+
+        let mut db_file = DatabaseFile::open(&path).unwrap();
+        assert_eq!(db_file.free_list_head(), 4);
+        assert_eq!(db_file.read_page(4).unwrap().next_free_page(), Some(2));
+
+        // Freed pages come back most recently freed first, as fresh data pages, and only
+        // then does the file grow
+        assert_eq!(db_file.allocate_page().unwrap(), 4);
+        assert_eq!(db_file.read_page(4).unwrap().next_free_page(), None);
+        assert_eq!(db_file.free_list_head(), 2);
+        drop(db_file);
+        let mut db_file = DatabaseFile::open(&path).unwrap();
+        assert_eq!(db_file.allocate_page().unwrap(), 2);
+        assert_eq!(db_file.free_list_head(), 0);
+        assert_eq!(db_file.allocate_page().unwrap(), 5);
+        assert_eq!(db_file.page_count(), 6);
+    }
+
+    #[test]
+    fn test_open_rejects_bad_header() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db");
+        drop(DatabaseFile::create(&path).unwrap());
+
+        {
+            let mut file = OpenOptions::new().write(true).open(&path).unwrap();
+            file.write_all(b"NOTADB").unwrap();
+        }
+
+        match DatabaseFile::open(&path) {
+            Err(DatabaseError::Storage(msg)) => assert!(msg.contains("magic")),
+            other => panic!("expected a bad magic error, got {:?}", other.map(|_| ())),
         }
     }
 
@@ -251,8 +803,8 @@ mod tests {
 
         // Allocate a page
         let page_id = db_file.allocate_page().unwrap();
-        assert_eq!(page_id, 0);
-        assert_eq!(db_file.page_count(), 1);
+        assert_eq!(page_id, FIRST_DATA_PAGE_ID);
+        assert_eq!(db_file.page_count(), 2);
 
         // Create a new page and write it
         let page_to_write = Page::new(page_id, PageType::Data);
@@ -277,17 +829,17 @@ mod tests {
 
         for i in 0..num_pages {
             let page_id = db_file.allocate_page().unwrap();
-            assert_eq!(page_id, i);
+            assert_eq!(page_id, FIRST_DATA_PAGE_ID + i);
             let page = Page::new(page_id, PageType::Index);
             db_file.write_page(page_id, &page).unwrap();
             pages.push(page);
         }
 
-        assert_eq!(db_file.page_count(), num_pages);
+        assert_eq!(db_file.page_count(), num_pages + 1);
 
-        for i in 0..num_pages {
-            let page_read = db_file.read_page(i).unwrap();
-            assert_eq!(pages[i as usize].to_bytes(), page_read.to_bytes());
+        for (page, page_id) in pages.iter().zip(db_file.data_page_ids()) {
+            let page_read = db_file.read_page(page_id).unwrap();
+            assert_eq!(page.to_bytes(), page_read.to_bytes());
         }
     }
 
@@ -297,7 +849,7 @@ mod tests {
         let path = temp_dir.path().join("test.db");
         let mut db_file = DatabaseFile::create(&path).unwrap();
 
-        let result = db_file.read_page(0);
+        let result = db_file.read_page(FIRST_DATA_PAGE_ID);
         assert!(result.is_err());
     }
 
@@ -308,4 +860,114 @@ mod tests {
         let db_file = DatabaseFile::create(&path).unwrap();
         assert!(db_file.sync().is_ok());
     }
+
+    #[test]
+    fn test_truncate() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db");
+
+        {
+            let mut db_file = DatabaseFile::create(&path).unwrap();
+            for _ in 0..3 {
+                db_file.allocate_page().unwrap();
+            }
+            db_file.truncate().unwrap();
+            assert_eq!(db_file.page_count(), 1);
+            assert!(db_file.read_page(FIRST_DATA_PAGE_ID).is_err());
+        }
+
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), PAGE_SIZE as u64);
+
+        let mut db_file = DatabaseFile::open(&path).unwrap();
+        assert_eq!(db_file.page_count(), 1);
+        assert_eq!(db_file.allocate_page().unwrap(), FIRST_DATA_PAGE_ID);
+    }
+
+    #[test]
+    fn test_backup_fails_on_corrupt_page() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db");
+        let backup_path = temp_dir.path().join("backup.db");
+        let mut db_file = DatabaseFile::create(&path).unwrap();
+
+        let page_id = db_file.allocate_page().unwrap();
+
+        // Flip a byte in the page body behind the checksum's back.
+        let offset = page_offset(page_id) + 100;
+        db_file.file.seek(SeekFrom::Start(offset)).unwrap();
+        db_file.file.write_all(&[0xAB]).unwrap();
+
+        let result = db_file.backup(&backup_path);
+        assert!(result.is_err());
+        assert!(!backup_path.exists());
+    }
+
+    #[test]
+    fn test_free_space_directory_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db");
+        assert!(FileHeader::size() <= FREE_SPACE_DIRECTORY_OFFSET);
+
+        {
+            let mut db_file = DatabaseFile::create(&path).unwrap();
+            // Never stored
+            assert_eq!(db_file.read_free_space_directory().unwrap(), None);
+            db_file.allocate_page().unwrap();
+            db_file.allocate_page().unwrap();
+            db_file.write_free_space_directory(&[7, 9]).unwrap();
+            assert_eq!(db_file.read_free_space_directory().unwrap(), Some(vec![7, 9]));
+            // Must cover every data page
+            db_file.write_free_space_directory(&[7]).unwrap();
+            assert_eq!(db_file.read_free_space_directory().unwrap(), None);
+            db_file.write_free_space_directory(&[7, 9]).unwrap();
+        }
+
+        let mut db_file = DatabaseFile::open(&path).unwrap();
+        assert_eq!(db_file.read_free_space_directory().unwrap(), Some(vec![7, 9]));
+        assert_eq!(db_file.header.next_page_id, 3);
+
+        // Writing a page marks it stale
+        let page = db_file.read_page(FIRST_DATA_PAGE_ID).unwrap();
+        db_file.write_page(FIRST_DATA_PAGE_ID, &page).unwrap();
+        assert_eq!(db_file.read_free_space_directory().unwrap(), None);
+    }
+
+    #[test]
+    fn test_create_renames_initialized_file_into_place() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db");
+        let db_file = DatabaseFile::create(&path).unwrap();
+
+        assert!(!creating_path(&path).unwrap().exists());
+        assert_eq!(fs::metadata(&path).unwrap().len(), PAGE_SIZE as u64);
+        drop(db_file);
+        let db_file = DatabaseFile::open(&path).unwrap();
+        assert_eq!(db_file.header, FileHeader::new());
+
+        // An existing database is never replaced
+        match DatabaseFile::create(&path) {
+            Err(DatabaseError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::AlreadyExists),
+            other => panic!("expected AlreadyExists, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn test_interrupted_create_leaves_no_database() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.db");
+
+        // A crash after writing part of the header leaves only the temporary file
+        let temp_path = creating_path(&path).unwrap();
+        fs::write(&temp_path, &MAGIC[..4]).unwrap();
+        assert!(!path.exists());
+        match DatabaseFile::open(&path) {
+            Err(DatabaseError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::NotFound),
+            other => panic!("expected NotFound, got {:?}", other.map(|_| ())),
+        }
+
+        // Creating again replaces the leftover
+        drop(DatabaseFile::create(&path).unwrap());
+        assert!(!temp_path.exists());
+        DatabaseFile::open(&path).unwrap();
+    }
 }
@@ -0,0 +1,170 @@
+// Free-space directory
+//
+// Records roughly how many bytes each data page has free, so an insert can go straight
+// to a page with room instead of probing pages one by one. Free space is kept as a
+// one-byte bucket per page, rounded down, so a page found through the directory has at
+// least the space asked for. Pages are also grouped by bucket, which makes finding a
+// page a walk over at most BUCKET_COUNT buckets however large the file grows.
+//
+// The directory is a hint: a page it suggests is still checked before the document is
+// written, and a suggestion that turns out wrong only costs that check. It is persisted
+// in the header page at checkpoints and rebuilt by scanning the data pages when the
+// stored copy is missing or stale.
+
+use crate::storage::file::FIRST_DATA_PAGE_ID;
+use crate::storage::page::PAGE_SIZE;
+use std::collections::{BTreeSet, HashSet};
+
+/// Number of free-space buckets a page can be in
+pub const BUCKET_COUNT: usize = 256;
+/// Bytes of free space each bucket stands for
+pub const BUCKET_SIZE: usize = PAGE_SIZE / BUCKET_COUNT;
+
+#[derive(Debug, Clone)]
+pub struct FreeSpaceMap {
+    // Bucket of each data page, indexed by page id - FIRST_DATA_PAGE_ID
+    buckets: Vec<u8>,
+    // Page ids in each bucket
+    pages_by_bucket: Vec<BTreeSet<u64>>,
+}
+
+impl Default for FreeSpaceMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FreeSpaceMap {
+    pub fn new() -> Self {
+        Self {
+            buckets: Vec::new(),
+            pages_by_bucket: vec![BTreeSet::new(); BUCKET_COUNT],
+        }
+    }
+
+    /// A directory from one bucket per data page, as returned by `buckets`
+    pub fn from_buckets(buckets: Vec<u8>) -> Self {
+        let mut map = Self::new();
+        for (i, bucket) in buckets.iter().enumerate() {
+            map.pages_by_bucket[*bucket as usize].insert(FIRST_DATA_PAGE_ID + i as u64);
+        }
+        map.buckets = buckets;
+        map
+    }
+
+    /// One bucket per data page, in page order
+    pub fn buckets(&self) -> &[u8] {
+        &self.buckets
+    }
+
+    /// Number of data pages covered
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+        self.pages_by_bucket.iter_mut().for_each(BTreeSet::clear);
+    }
+
+    /// Records that `page_id` has `free_bytes` free. Pages between the last covered page
+    /// and `page_id` are covered as full.
+    pub fn set(&mut self, page_id: u64, free_bytes: usize) {
+        let Some(index) = page_id.checked_sub(FIRST_DATA_PAGE_ID) else {
+            return;
+        };
+        let index = index as usize;
+        while self.buckets.len() <= index {
+            let page = FIRST_DATA_PAGE_ID + self.buckets.len() as u64;
+            self.pages_by_bucket[0].insert(page);
+            self.buckets.push(0);
+        }
+
+        let bucket = Self::bucket_of(free_bytes);
+        let old = std::mem::replace(&mut self.buckets[index], bucket);
+        self.pages_by_bucket[old as usize].remove(&page_id);
+        self.pages_by_bucket[bucket as usize].insert(page_id);
+    }
+
+    /// Free bytes recorded for `page_id`, rounded down to a bucket boundary, or `None`
+    /// for a page the directory does not cover.
+    pub fn free_space(&self, page_id: u64) -> Option<usize> {
+        let index = page_id.checked_sub(FIRST_DATA_PAGE_ID)? as usize;
+        self.buckets
+            .get(index)
+            .map(|bucket| *bucket as usize * BUCKET_SIZE)
+    }
+
+    /// The lowest-numbered page with at least `needed` bytes free, skipping `exclude`
+    pub fn first_fit(&self, needed: usize, exclude: &HashSet<u64>) -> Option<u64> {
+        self.fitting_buckets(needed)
+            .filter_map(|pages| pages.iter().find(|page_id| !exclude.contains(page_id)))
+            .min()
+            .copied()
+    }
+
+    /// A page in the fullest bucket that still has at least `needed` bytes free,
+    /// skipping `exclude`
+    pub fn best_fit(&self, needed: usize, exclude: &HashSet<u64>) -> Option<u64> {
+        self.fitting_buckets(needed)
+            .find_map(|pages| pages.iter().find(|page_id| !exclude.contains(page_id)))
+            .copied()
+    }
+
+    // Buckets whose pages all have at least `needed` bytes free, fullest first
+    fn fitting_buckets(&self, needed: usize) -> impl Iterator<Item = &BTreeSet<u64>> {
+        let lowest = needed.div_ceil(BUCKET_SIZE);
+        self.pages_by_bucket.iter().skip(lowest)
+    }
+
+    fn bucket_of(free_bytes: usize) -> u8 {
+        (free_bytes / BUCKET_SIZE).min(BUCKET_COUNT - 1) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_round_down() {
+        let mut map = FreeSpaceMap::new();
+        map.set(3, BUCKET_SIZE * 10 + 5);
+        assert_eq!(map.free_space(3), Some(BUCKET_SIZE * 10));
+        // Pages before it are covered as full
+        assert_eq!(map.free_space(1), Some(0));
+        assert_eq!(map.free_space(4), None);
+        assert_eq!(map.len(), 3);
+
+        // Exactly ten buckets' worth fits; one byte more does not
+        let none = HashSet::new();
+        assert_eq!(map.first_fit(BUCKET_SIZE * 10, &none), Some(3));
+        assert_eq!(map.first_fit(BUCKET_SIZE * 10 + 1, &none), None);
+    }
+
+    #[test]
+    fn test_first_and_best_fit() {
+        let mut map = FreeSpaceMap::new();
+        map.set(1, 3000);
+        map.set(2, 2000);
+        map.set(3, 6000);
+
+        let none = HashSet::new();
+        assert_eq!(map.first_fit(1500, &none), Some(1));
+        assert_eq!(map.best_fit(1500, &none), Some(2));
+        assert_eq!(map.best_fit(2500, &none), Some(1));
+        assert_eq!(map.first_fit(5000, &none), Some(3));
+        assert_eq!(map.first_fit(7000, &none), None);
+
+        let tried = HashSet::from([1]);
+        assert_eq!(map.first_fit(1500, &tried), Some(2));
+
+        map.set(2, 100);
+        assert_eq!(map.best_fit(1500, &none), Some(1));
+        assert_eq!(FreeSpaceMap::from_buckets(map.buckets().to_vec()).best_fit(1500, &none), Some(1));
+    }
+}
@@ -0,0 +1,389 @@
+// Secondary indexes
+//
+// An OrderedIndex maps the values of one field to the documents holding them, kept in
+// sorted order so equality lookups and range scans do not need a full collection scan.
+// Indexes live in memory and are rebuilt from the documents by `StorageEngine::create_index`.
+//
+// Indexes are multikey: an array value is indexed under each of its distinct elements
+// rather than as a whole, so a document with `tags: ["rust", "db"]` is found by looking
+// up either tag. Only the top level of the array is expanded; a nested array is one key.
+// A document listing the same element twice is indexed under it once, so it is returned
+// once by lookups and ranges.
+
+use crate::document::types::Value;
+use crate::storage::storage_engine::DocumentId;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::ops::Bound;
+
+/// A `Value` with a total ordering, so it can key a `BTreeMap`.
+///
+/// Ordered by `Value::total_cmp`, so `I32(30)` and `I64(30)` are the same key.
+#[derive(Debug, Clone)]
+pub struct IndexKey(pub Value);
+
+impl PartialEq for IndexKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for IndexKey {}
+
+impl PartialOrd for IndexKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for IndexKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Size and selectivity of one index, as reported by `StorageEngine::index_stats`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexStats {
+    pub field: String,
+    pub unique: bool,
+    /// Index entries: one per document holding the field, or one per distinct element
+    /// for an array value
+    pub entries: usize,
+    /// Distinct values among those documents
+    pub distinct_keys: usize,
+    /// `distinct_keys / entries`: 1.0 when every value is different, close to 0 when
+    /// a few values repeat across many documents. 0.0 for an empty index.
+    pub selectivity: f64,
+    /// Pages the index occupies in the database file. Always zero for now, since
+    /// indexes live in memory.
+    pub pages: u64,
+}
+
+/// Sorted index over one (possibly dotted) field path
+#[derive(Debug, Clone)]
+pub struct OrderedIndex {
+    field: String,
+    unique: bool,
+    entries: BTreeMap<IndexKey, Vec<DocumentId>>,
+}
+
+impl OrderedIndex {
+    pub fn new(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            unique: false,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// An index that allows at most one document per value
+    pub fn new_unique(field: &str) -> Self {
+        Self {
+            unique: true,
+            ..Self::new(field)
+        }
+    }
+
+    /// The field path this index covers
+    pub fn field(&self) -> &str {
+        &self.field
+    }
+
+    pub fn is_unique(&self) -> bool {
+        self.unique
+    }
+
+    /// The keys a field value is indexed under: each distinct element of an array, in
+    /// key order, or the value itself for anything else. An empty array has no keys.
+    pub fn keys(value: &Value) -> Vec<&Value> {
+        match value {
+            Value::Array(elements) => {
+                let mut keys: Vec<&Value> = elements.iter().collect();
+                keys.sort_by(|a, b| a.total_cmp(b));
+                keys.dedup_by(|a, b| a.total_cmp(b) == Ordering::Equal);
+                keys
+            }
+            _ => vec![value],
+        }
+    }
+
+    /// Whether storing `value` for `document_id` would break uniqueness.
+    ///
+    /// Always false for non-unique indexes. A document may keep its own value. For an
+    /// array, every element must be free.
+    pub fn violates_unique(&self, value: &Value, document_id: Option<DocumentId>) -> bool {
+        self.unique
+            && Self::keys(value).into_iter().any(|key| {
+                self.get(key)
+                    .iter()
+                    .any(|existing| Some(*existing) != document_id)
+            })
+    }
+
+    /// Number of distinct keys in the index
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of entries, counting every document under each key
+    pub fn entry_count(&self) -> usize {
+        self.entries.values().map(Vec::len).sum()
+    }
+
+    pub fn stats(&self) -> IndexStats {
+        let entries = self.entry_count();
+        let distinct_keys = self.len();
+        IndexStats {
+            field: self.field.clone(),
+            unique: self.unique,
+            entries,
+            distinct_keys,
+            selectivity: if entries == 0 {
+                0.0
+            } else {
+                distinct_keys as f64 / entries as f64
+            },
+            pages: 0,
+        }
+    }
+
+    /// Remove every entry, keeping the index definition
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Indexes `document_id` under each of `value`'s keys
+    pub fn insert(&mut self, value: &Value, document_id: DocumentId) {
+        for key in Self::keys(value) {
+            self.entries
+                .entry(IndexKey(key.clone()))
+                .or_default()
+                .push(document_id);
+        }
+    }
+
+    pub fn remove(&mut self, value: &Value, document_id: DocumentId) {
+        for key in Self::keys(value) {
+            let key = IndexKey(key.clone());
+            if let Some(ids) = self.entries.get_mut(&key) {
+                ids.retain(|id| *id != document_id);
+                if ids.is_empty() {
+                    self.entries.remove(&key);
+                }
+            }
+        }
+    }
+
+    /// Documents whose value equals `value`, or whose array value contains it
+    pub fn get(&self, value: &Value) -> &[DocumentId] {
+        self.entries
+            .get(&IndexKey(value.clone()))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Documents whose value lies between `lo` and `hi`, in key order.
+    ///
+    /// A `None` bound leaves that end of the range open; `inclusive` says whether
+    /// each bound itself matches. A document with several array elements in range is
+    /// returned once, at its lowest one.
+    pub fn range(
+        &self,
+        lo: Option<&Value>,
+        hi: Option<&Value>,
+        inclusive: (bool, bool),
+    ) -> Vec<DocumentId> {
+        // BTreeMap::range panics on inverted or empty-exclusive bounds
+        if let (Some(lo), Some(hi)) = (lo, hi) {
+            match lo.total_cmp(hi) {
+                Ordering::Greater => return Vec::new(),
+                Ordering::Equal if !(inclusive.0 && inclusive.1) => return Vec::new(),
+                _ => {}
+            }
+        }
+
+        let lower = Self::bound(lo, inclusive.0);
+        let upper = Self::bound(hi, inclusive.1);
+
+        let mut seen = HashSet::new();
+        self.entries
+            .range((lower, upper))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .filter(|id| seen.insert(*id))
+            .collect()
+    }
+
+    /// Whether `value` lies within the given bounds, using the index's ordering
+    pub fn in_range(
+        value: &Value,
+        lo: Option<&Value>,
+        hi: Option<&Value>,
+        inclusive: (bool, bool),
+    ) -> bool {
+        let above = match lo {
+            Some(lo) => match value.total_cmp(lo) {
+                Ordering::Greater => true,
+                Ordering::Equal => inclusive.0,
+                Ordering::Less => false,
+            },
+            None => true,
+        };
+        let below = match hi {
+            Some(hi) => match value.total_cmp(hi) {
+                Ordering::Less => true,
+                Ordering::Equal => inclusive.1,
+                Ordering::Greater => false,
+            },
+            None => true,
+        };
+        above && below
+    }
+
+    fn bound(value: Option<&Value>, inclusive: bool) -> Bound<IndexKey> {
+        match value {
+            Some(v) if inclusive => Bound::Included(IndexKey(v.clone())),
+            Some(v) => Bound::Excluded(IndexKey(v.clone())),
+            None => Bound::Unbounded,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index_key_numeric_ordering() {
+        assert_eq!(IndexKey(Value::I32(30)), IndexKey(Value::I64(30)));
+        assert_eq!(IndexKey(Value::I32(30)), IndexKey(Value::F64(30.0)));
+        assert!(IndexKey(Value::I32(2)) < IndexKey(Value::F64(2.5)));
+        assert!(IndexKey(Value::I64(i64::MAX)) > IndexKey(Value::I64(i64::MAX - 1)));
+        assert!(IndexKey(Value::F64(f64::NAN)) < IndexKey(Value::I32(i32::MIN)));
+    }
+
+    #[test]
+    fn test_index_key_type_ordering() {
+        assert!(IndexKey(Value::Null) < IndexKey(Value::I32(0)));
+        assert!(IndexKey(Value::I32(1_000)) < IndexKey(Value::String("a".to_string())));
+        assert!(IndexKey(Value::String("z".to_string())) < IndexKey(Value::Bool(false)));
+    }
+
+    #[test]
+    fn test_range_bounds() {
+        let mut index = OrderedIndex::new("age");
+        for age in 0..10 {
+            index.insert(&Value::I32(age), DocumentId::new(0, age as u16));
+        }
+
+        let slots = |ids: Vec<DocumentId>| ids.iter().map(|id| id.slot_id()).collect::<Vec<_>>();
+
+        let closed = index.range(Some(&Value::I32(3)), Some(&Value::I32(5)), (true, true));
+        assert_eq!(slots(closed), vec![3, 4, 5]);
+
+        let half_open = index.range(Some(&Value::I32(7)), None, (false, true));
+        assert_eq!(slots(half_open), vec![8, 9]);
+
+        let empty = index.range(Some(&Value::I32(5)), Some(&Value::I32(5)), (true, false));
+        assert!(empty.is_empty());
+
+        let inverted = index.range(Some(&Value::I32(6)), Some(&Value::I32(2)), (true, true));
+        assert!(inverted.is_empty());
+    }
+
+    #[test]
+    fn test_violates_unique() {
+        let mut index = OrderedIndex::new_unique("email");
+        let a = DocumentId::new(0, 0);
+        let b = DocumentId::new(0, 1);
+        let email = Value::String("a@example.com".to_string());
+
+        assert!(!index.violates_unique(&email, None));
+        index.insert(&email, a);
+        assert!(index.violates_unique(&email, None));
+        assert!(index.violates_unique(&email, Some(b)));
+        assert!(!index.violates_unique(&email, Some(a)));
+
+        let mut plain = OrderedIndex::new("email");
+        plain.insert(&email, a);
+        assert!(!plain.violates_unique(&email, None));
+    }
+
+    #[test]
+    fn test_insert_remove() {
+        let mut index = OrderedIndex::new("name");
+        let a = DocumentId::new(0, 0);
+        let b = DocumentId::new(0, 1);
+        let name = Value::String("x".to_string());
+
+        index.insert(&name, a);
+        index.insert(&name, b);
+        assert_eq!(index.get(&name), &[a, b]);
+
+        index.remove(&name, a);
+        assert_eq!(index.get(&name), &[b]);
+
+        index.remove(&name, b);
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut index = OrderedIndex::new("active");
+        assert_eq!(index.stats().selectivity, 0.0);
+
+        for slot in 0..4 {
+            index.insert(&Value::Bool(slot % 2 == 0), DocumentId::new(1, slot));
+        }
+        let stats = index.stats();
+        assert_eq!(stats.entries, 4);
+        assert_eq!(stats.distinct_keys, 2);
+        assert_eq!(stats.selectivity, 0.5);
+        assert_eq!(stats.pages, 0);
+    }
+
+    #[test]
+    fn test_array_values_are_multikey() {
+        let mut index = OrderedIndex::new("tags");
+        let tags = Value::Array(vec![
+            Value::String("rust".to_string()),
+            Value::String("db".to_string()),
+            Value::String("rust".to_string()),
+        ]);
+        let a = DocumentId::new(1, 0);
+        index.insert(&tags, a);
+        index.insert(&Value::String("db".to_string()), DocumentId::new(1, 1));
+
+        assert_eq!(index.get(&Value::String("rust".to_string())), &[a]);
+        assert_eq!(index.get(&Value::String("db".to_string())).len(), 2);
+        assert!(index.get(&tags).is_empty());
+        assert_eq!(index.entry_count(), 3);
+        assert_eq!(index.range(None, None, (true, true)).len(), 2);
+
+        index.remove(&tags, a);
+        assert!(index.get(&Value::String("rust".to_string())).is_empty());
+        assert_eq!(index.entry_count(), 1);
+
+        // Empty arrays are not indexed
+        index.insert(&Value::Array(vec![]), DocumentId::new(1, 2));
+        assert_eq!(index.entry_count(), 1);
+    }
+
+    #[test]
+    fn test_unique_multikey_checks_every_element() {
+        let mut index = OrderedIndex::new_unique("codes");
+        let a = DocumentId::new(1, 0);
+        index.insert(&Value::Array(vec![Value::I32(1), Value::I32(2)]), a);
+
+        let overlapping = Value::Array(vec![Value::I32(3), Value::I32(2)]);
+        assert!(index.violates_unique(&overlapping, Some(DocumentId::new(1, 1))));
+        assert!(!index.violates_unique(&overlapping, Some(a)));
+        // Repeating an element within one document is not a violation
+        let repeated = Value::Array(vec![Value::I32(5), Value::I32(5)]);
+        assert!(!index.violates_unique(&repeated, None));
+    }
+}
@@ -0,0 +1,100 @@
+// Consistency checks for a database file
+//
+// A crash can leave a page half written (torn), the file shorter than its header says,
+// or the free list pointing at a page that was never freed. Such damage otherwise
+// surfaces much later, as a failed query. `StorageEngine::check_integrity` reads every
+// page and reports what it finds, and `StorageEngine::repair` applies the fixes that
+// never invent data: restoring missing pages as empty ones, dropping a broken free list,
+// and removing unreadable documents from damaged pages. `StorageEngine::open_with_options`
+// can run either while opening.
+
+use std::fmt;
+use std::path::PathBuf;
+
+/// How `StorageEngine::open_with_options` opens a database.
+///
+/// The default opens as `StorageEngine::new` does, without reading any page up front.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenOptions {
+    /// Check every page while opening and fail with `DatabaseError::IntegrityCheckFailed`
+    /// if any problem is found. Costs a read of the whole file.
+    pub verify: bool,
+    /// Check every page while opening and repair what can be repaired; fails with
+    /// `DatabaseError::IntegrityCheckFailed` only if problems remain afterwards. Implies
+    /// the check `verify` runs.
+    pub repair: bool,
+    /// Write-ahead log to attach as `StorageEngine::with_wal` does. Its records are
+    /// replayed before any check, since they may hold intact copies of torn pages.
+    pub wal: Option<PathBuf>,
+}
+
+/// One thing wrong with a database file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityProblem {
+    /// The file ends before the pages the header records: pages `first` onwards, `count`
+    /// of them, are missing or cut short. Repair writes them back as empty pages.
+    MissingPages { first: u64, count: u64 },
+    /// The free list reaches `page_id`, which is not a free page for `reason`. Repair
+    /// empties the list; the pages on it stay unused.
+    BrokenFreeList { page_id: u64, reason: String },
+    /// A data page fails its checksum or holds an unreadable slot directory or document.
+    /// Repair keeps the documents that still decode, at their ids, and removes the rest;
+    /// a page whose slot directory is unreadable loses all of its documents.
+    CorruptPage { page_id: u64, reason: String },
+}
+
+impl fmt::Display for IntegrityProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegrityProblem::MissingPages { first, count } => write!(
+                f,
+                "pages {} to {} are missing from the end of the file",
+                first,
+                first + count - 1
+            ),
+            IntegrityProblem::BrokenFreeList { page_id, reason } => {
+                write!(f, "free list is broken at page {}: {}", page_id, reason)
+            }
+            IntegrityProblem::CorruptPage { page_id, reason } => {
+                write!(f, "page {} is corrupt: {}", page_id, reason)
+            }
+        }
+    }
+}
+
+/// What a check or repair found, in the order it was found
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IntegrityReport {
+    pub problems: Vec<IntegrityProblem>,
+}
+
+impl IntegrityReport {
+    /// Whether no problem was found
+    pub fn is_clean(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// The pages reported as corrupt, in page order
+    pub fn corrupt_pages(&self) -> Vec<u64> {
+        self.problems
+            .iter()
+            .filter_map(|problem| match problem {
+                IntegrityProblem::CorruptPage { page_id, .. } => Some(*page_id),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for IntegrityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            return write!(f, "no problems found");
+        }
+        write!(f, "{} problem(s) found", self.problems.len())?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
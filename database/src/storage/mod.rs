@@ -1,5 +1,14 @@
 pub mod buffer_pool;
+pub mod cancellation;
+pub mod change_stream;
+pub mod compression;
+pub mod conflict;
+pub mod document_cache;
 pub mod file;
+pub mod free_space;
+pub mod index;
+pub mod integrity;
 pub mod page;
 pub mod page_layout;
-pub mod storage_engine;
\ No newline at end of file
+pub mod storage_engine;
+pub mod wal;
\ No newline at end of file
@@ -75,6 +75,26 @@ impl Page {
         page
     }
 
+    /// Creates a page on the file's free list, holding the id of the next free page
+    /// (0 at the end of the list) as a little-endian u64 right after the header.
+    pub fn new_free(page_id: u64, next_free: u64) -> Self {
+        let mut page = Page::new(page_id, PageType::Free);
+        let link = &mut page.data[PAGE_HEADER_SIZE..PAGE_HEADER_SIZE + 8];
+        link.copy_from_slice(&next_free.to_le_bytes());
+        let checksum = page.calculate_checksum();
+        page.set_checksum(checksum);
+        page
+    }
+
+    /// The next free page a free-list page links to, or `None` for any other page.
+    pub fn next_free_page(&self) -> Option<u64> {
+        if self.data[mem::offset_of!(PageHeader, page_type)] != PageType::Free as u8 {
+            return None;
+        }
+        let link = &self.data[PAGE_HEADER_SIZE..PAGE_HEADER_SIZE + 8];
+        Some(u64::from_le_bytes(link.try_into().unwrap()))
+    }
+
     /// Deserializes a page from a byte array.
     ///
     /// This function takes a raw byte array, creates a Page from it, and verifies its
@@ -87,6 +107,12 @@ impl Page {
         Ok(page)
     }
 
+    /// Wraps a byte array as a page without verifying its checksum, for inspecting and
+    /// salvaging damaged pages.
+    pub fn from_bytes_unchecked(data: [u8; PAGE_SIZE]) -> Self {
+        Page { data }
+    }
+
     /// Serializes the page into a byte array for writing to disk.
     pub fn to_bytes(&self) -> [u8; PAGE_SIZE] {
         self.data
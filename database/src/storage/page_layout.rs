@@ -1,21 +1,43 @@
 use crate::error::DatabaseError;
-use crate::storage::page::{PAGE_SIZE, Page};
-use std::mem;
+use crate::storage::page::{PAGE_SIZE, Page, PageType};
 
 pub type SlotId = u16;
 
 // Page layout constants
 const SLOT_DIRECTORY_OFFSET: usize = PAGE_SIZE - 4; // Last 4 bytes for slot directory header
+const SLOT_DIRECTORY_HEADER_SIZE: usize = 4; // slot_count: u16, free_space_offset: u16
 const MAX_SLOTS_PER_PAGE: u16 = 1000;
 const SLOT_SIZE: usize = 4; // Each slot is 4 bytes (offset: u16, length: u16)
 const TOMBSTONE_MARKER: u16 = 0xFFFF;
+// Slot counts never reach the high bit (see MAX_SLOTS_PER_PAGE), so it marks stable pages
+const STABLE_LAYOUT_FLAG: u16 = 0x8000;
+
+/// How a page places its slot directory entries.
+///
+/// Either way the directory sits at the end of the page and grows toward the document
+/// data by `SLOT_SIZE` bytes per slot, so both layouts fit the same documents; they
+/// differ only in where each entry lives. Every page records its layout, so pages of
+/// both kinds can share a file and are read back the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SlotLayout {
+    /// Entries in slot order from the start of the directory. The start moves each time
+    /// a slot is added, so every existing entry is copied down one position, which is
+    /// O(slots) per new slot. Pages written before layouts were selectable use this.
+    #[default]
+    Shifting,
+    /// Slot `n` at a fixed position, the `n + 1`th entry counting back from the directory
+    /// header. Adding a slot writes only its own entry, and trimming trailing slots only
+    /// clears theirs, which suits append-heavy workloads that fill pages with many small
+    /// documents.
+    Stable,
+}
 
 /// Slot directory header stored at the end of the page
-#[repr(C)]
 #[derive(Debug)]
 struct SlotDirectoryHeader {
     slot_count: u16,
     free_space_offset: u16, // Pointer to start of free space
+    layout: SlotLayout,     // Stored as STABLE_LAYOUT_FLAG in the slot count
 }
 
 /// Individual slot entry (offset and length of document)
@@ -47,15 +69,47 @@ impl SlotEntry {
     }
 }
 
+/// What one pass over the slot directory learns about a page, so an operation that
+/// needs several of these does not walk the directory once for each
+#[derive(Debug, Clone)]
+struct SlotSummary {
+    /// Bytes held by live documents
+    used_space: usize,
+    /// End of the last live document, where the free tail of the page starts
+    high_water: u16,
+    /// First tombstoned or empty slot, if any
+    reusable_slot: Option<SlotId>,
+    /// Start and end of every live document, ordered by start. The gaps between them are
+    /// the holes left by deletes, moves and shrinking updates.
+    live_extents: Vec<(u16, u16)>,
+}
+
+#[cfg(test)]
+thread_local! {
+    // Slot entries read on this thread, so tests can bound the directory walks per operation
+    static SLOT_READS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    // Slot entries moved to make room for a new slot on this thread
+    static SLOT_SHIFTS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
 /// Page layout manager for document storage with slot directory
 pub struct PageLayout;
 
 impl PageLayout {
     /// Initialize a new page for document storage
     pub fn initialize_page(page: &mut Page) -> Result<(), DatabaseError> {
+        Self::initialize_page_with_layout(page, SlotLayout::default())
+    }
+
+    /// Initialize a new page for document storage with the given slot directory layout
+    pub fn initialize_page_with_layout(
+        page: &mut Page,
+        layout: SlotLayout,
+    ) -> Result<(), DatabaseError> {
         let header = SlotDirectoryHeader {
             slot_count: 0,
             free_space_offset: Self::get_header_size() as u16,
+            layout,
         };
 
         Self::write_slot_directory_header(page, &header)?;
@@ -63,7 +117,38 @@ impl PageLayout {
         Ok(())
     }
 
+    /// Create a data page with an empty slot directory and a valid checksum, ready for
+    /// inserts without calling `initialize_page`. Like every new page, its data area is
+    /// all zeros.
+    pub fn new_page(page_id: u64) -> Page {
+        Self::new_page_with_layout(page_id, SlotLayout::default())
+    }
+
+    /// Like `new_page`, with the given slot directory layout
+    pub fn new_page_with_layout(page_id: u64, layout: SlotLayout) -> Page {
+        let mut page = Page::new(page_id, PageType::Data);
+        Self::initialize_page_with_layout(&mut page, layout)
+            .expect("initializing a fresh page cannot fail");
+        let checksum = page.calculate_checksum();
+        page.set_checksum(checksum);
+        page
+    }
+
+    /// The slot directory layout the page was initialized with
+    pub fn slot_layout(page: &Page) -> Result<SlotLayout, DatabaseError> {
+        Ok(Self::read_slot_directory_header(page)?.layout)
+    }
+
+    /// Largest document an empty page can hold. Anything bigger fails to insert on any page.
+    pub fn max_document_size() -> usize {
+        Self::get_usable_page_size(1) - SLOT_SIZE
+    }
+
     /// Insert a document into the page and return its slot ID
+    ///
+    /// A document that fits in the page's free space, but in no single hole, is placed
+    /// after compacting the page once, which gathers the space deleted documents left.
+    /// Slot ids survive compaction, though trailing free slots are trimmed.
     pub fn insert_document(
         page: &mut Page,
         document_bytes: &[u8],
@@ -80,10 +165,10 @@ impl PageLayout {
         }
 
         let header = Self::read_slot_directory_header(page)?;
+        let summary = Self::summarize_slots(page, &header)?;
 
         // Find an empty or tombstoned slot
-        let (slot_id, is_new_slot) = if let Some(slot_id) = Self::find_reusable_slot(page, &header)?
-        {
+        let (slot_id, is_new_slot) = if let Some(slot_id) = summary.reusable_slot {
             (slot_id, false)
         } else {
             // Need a new slot
@@ -104,14 +189,24 @@ impl PageLayout {
 
         // Check if we have enough space (including space for new slot if needed)
         let required_space = doc_size + if is_new_slot { SLOT_SIZE } else { 0 };
-        if !Self::has_sufficient_space_with_count(page, required_space, final_slot_count)? {
+        if summary.used_space + required_space > Self::get_usable_page_size(final_slot_count) {
             return Err(DatabaseError::Storage(
                 "Insufficient space on page".to_string(),
             ));
         }
 
         // Find space for the document
-        let doc_offset = Self::find_free_space_with_count(page, doc_size, final_slot_count)?;
+        let doc_offset = match Self::find_free_space(&summary, doc_size, final_slot_count) {
+            Ok(offset) => offset,
+            // Retrying after a compaction cannot compact again, so this recurses once
+            Err(err) => {
+                return if Self::compact_page(page)? {
+                    Self::insert_document(page, document_bytes)
+                } else {
+                    Err(err)
+                };
+            }
+        };
 
         // Write the document data
         Self::write_document_data(page, doc_offset, document_bytes)?;
@@ -119,30 +214,38 @@ impl PageLayout {
         // Update slot entry
         let slot_entry = SlotEntry::new(doc_offset, doc_size as u16);
 
+        let new_header = SlotDirectoryHeader {
+            slot_count: final_slot_count,
+            free_space_offset: header.free_space_offset,
+            layout: header.layout,
+        };
+
         // Update header if we added a new slot
         if is_new_slot {
-            // When adding a new slot, we need to shift all existing slots down
-            // because the slot directory grows downward - do this BEFORE writing the new slot
-            Self::shift_slot_directory_for_new_slot(page, header.slot_count)?;
-
-            let new_header = SlotDirectoryHeader {
-                slot_count: final_slot_count,
-                free_space_offset: header.free_space_offset,
-            };
+            // In the shifting layout all existing slots move down because the slot
+            // directory grows downward - do this BEFORE writing the new slot
+            if header.layout == SlotLayout::Shifting {
+                Self::shift_slot_directory_for_new_slot(page, header.slot_count)?;
+            }
             Self::write_slot_directory_header(page, &new_header)?;
         }
 
         // Write slot entry using the final slot count for correct offset calculation
-        Self::write_slot_entry_with_count(page, slot_id, &slot_entry, final_slot_count)?;
+        Self::write_slot_entry_with_header(page, slot_id, &slot_entry, &new_header)?;
 
-        // Update page free space
-        Self::update_page_free_space(page)?;
+        // A reused slot held no live bytes, so the new document is the only change
+        Self::set_page_free_space(page, final_slot_count, summary.used_space + doc_size);
 
         Ok(slot_id)
     }
 
     /// Get a document by its slot ID - returns owned data
     pub fn get_document(page: &Page, slot_id: SlotId) -> Result<Vec<u8>, DatabaseError> {
+        Self::get_document_slice(page, slot_id).map(<[u8]>::to_vec)
+    }
+
+    /// Get a document by its slot ID, borrowed from the page
+    pub fn get_document_slice(page: &Page, slot_id: SlotId) -> Result<&[u8], DatabaseError> {
         let header = Self::read_slot_directory_header(page)?;
 
         if slot_id >= header.slot_count {
@@ -161,7 +264,7 @@ impl PageLayout {
             return Err(DatabaseError::Storage("Empty slot".to_string()));
         }
 
-        Self::read_document_data_owned(page, slot_entry.offset, slot_entry.length)
+        Self::read_document_data(page, slot_entry.offset, slot_entry.length)
     }
 
     /// Delete a document by marking it with a tombstone
@@ -186,7 +289,7 @@ impl PageLayout {
 
         // Mark slot as tombstone
         let tombstone_entry = SlotEntry::tombstone();
-        Self::write_slot_entry_with_count(page, slot_id, &tombstone_entry, header.slot_count)?;
+        Self::write_slot_entry_with_header(page, slot_id, &tombstone_entry, &header)?;
 
         // Update page free space
         Self::update_page_free_space(page)?;
@@ -236,33 +339,36 @@ impl PageLayout {
         }
 
         // Check if we have space for the larger document
+        let summary = Self::summarize_slots(page, &header)?;
         let space_freed = slot_entry.length as usize;
         let space_needed = new_size;
-        let net_space_needed = if space_needed > space_freed {
-            space_needed - space_freed
-        } else {
-            0
-        };
+        let net_space_needed = space_needed.saturating_sub(space_freed);
 
-        if !Self::has_sufficient_space(page, net_space_needed)? {
+        if summary.used_space + net_space_needed > Self::get_usable_page_size(header.slot_count) {
             return Ok(false); // Doesn't fit
         }
 
         // Find new space for the document
-        let new_offset = Self::find_free_space(page, new_size)?;
+        let new_offset = Self::find_free_space(&summary, new_size, header.slot_count)?;
 
         // Write new document data
         Self::write_document_data(page, new_offset, new_data)?;
 
         // Update slot entry
         let updated_entry = SlotEntry::new(new_offset, new_size as u16);
-        Self::write_slot_entry(page, slot_id, &updated_entry)?;
+        Self::write_slot_entry_with_header(page, slot_id, &updated_entry, &header)?;
 
-        Self::update_page_free_space(page)?;
+        let used_space = summary.used_space - space_freed + new_size;
+        Self::set_page_free_space(page, header.slot_count, used_space);
         Ok(true)
     }
 
     /// Compact the page by removing fragmentation
+    ///
+    /// Every byte between the live documents and the slot directory is zeroed afterwards,
+    /// so deleted documents do not linger in the page, or on disk once it is written back,
+    /// and the free region of a compacted page always reads as zeros. This costs one fill
+    /// of the freed region, small next to copying the live documents.
     pub fn compact_page(page: &mut Page) -> Result<bool, DatabaseError> {
         let header = Self::read_slot_directory_header(page)?;
 
@@ -277,7 +383,7 @@ impl PageLayout {
                 had_tombstones = true;
             } else if !slot_entry.is_empty() {
                 let doc_data =
-                    Self::read_document_data_owned(page, slot_entry.offset, slot_entry.length)?;
+                    Self::read_document_data(page, slot_entry.offset, slot_entry.length)?.to_vec();
                 documents.push((slot_id, doc_data));
             }
         }
@@ -307,18 +413,56 @@ impl PageLayout {
             current_offset += doc_data.len() as u16;
         }
 
+        // Wipe what deleted and moved documents left behind
+        let directory_start = Self::get_slot_directory_start(header.slot_count);
+        Self::get_page_data_mut(page)[current_offset as usize..directory_start].fill(0);
+
         // Update free space offset
         let new_header = SlotDirectoryHeader {
             slot_count: header.slot_count,
             free_space_offset: current_offset,
+            layout: header.layout,
         };
         Self::write_slot_directory_header(page, &new_header)?;
 
+        Self::trim_slot_directory(page)?;
         Self::update_page_free_space(page)?;
 
         Ok(true)
     }
 
+    /// Compact the page if more than `threshold` of its slots are tombstones.
+    ///
+    /// `threshold` is a fraction of the slot directory, so 0.5 compacts once over half the
+    /// slots belong to deleted documents. Returns whether the page was compacted.
+    pub fn compact_if_tombstoned(page: &mut Page, threshold: f64) -> Result<bool, DatabaseError> {
+        let header = Self::read_slot_directory_header(page)?;
+        if header.slot_count == 0 {
+            return Ok(false);
+        }
+
+        let tombstones = Self::tombstone_count(page)?;
+        if tombstones as f64 / header.slot_count as f64 > threshold {
+            Self::compact_page(page)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Get the number of slots holding deleted documents
+    pub fn tombstone_count(page: &Page) -> Result<u16, DatabaseError> {
+        let header = Self::read_slot_directory_header(page)?;
+        let mut count = 0;
+
+        for slot_id in 0..header.slot_count {
+            if Self::read_slot_entry(page, slot_id)?.is_tombstone() {
+                count += 1;
+            }
+        }
+
+        Ok(count)
+    }
+
     /// Get page utilization percentage
     pub fn get_utilization_percentage(page: &Page) -> Result<f32, DatabaseError> {
         let header = Self::read_slot_directory_header(page)?;
@@ -347,6 +491,35 @@ impl PageLayout {
         Ok(count)
     }
 
+    /// Get the slot IDs of all live (non-deleted) documents in slot order
+    pub fn get_live_slots(page: &Page) -> Result<Vec<SlotId>, DatabaseError> {
+        let header = Self::read_slot_directory_header(page)?;
+        let mut slots = Vec::new();
+
+        for slot_id in 0..header.slot_count {
+            let slot_entry = Self::read_slot_entry(page, slot_id)?;
+            if !slot_entry.is_tombstone() && !slot_entry.is_empty() {
+                slots.push(slot_id);
+            }
+        }
+
+        Ok(slots)
+    }
+
+    /// Get the number of bytes compaction would recover on this page
+    ///
+    /// This is the space below the last live document that no live document occupies:
+    /// bytes left behind by deleted documents plus gaps left by shrinking updates.
+    /// Deleted bytes past the last live document are already reused by new inserts,
+    /// so they are not counted.
+    pub fn dead_space(page: &Page) -> Result<usize, DatabaseError> {
+        let header = Self::read_slot_directory_header(page)?;
+        let summary = Self::summarize_slots(page, &header)?;
+        let live_end = summary.high_water as usize;
+
+        Ok((live_end - Self::get_header_size()).saturating_sub(summary.used_space))
+    }
+
     // Helper methods
 
     fn get_header_size() -> usize {
@@ -359,105 +532,74 @@ impl PageLayout {
     fn get_usable_page_size(slot_count: u16) -> usize {
         PAGE_SIZE
             - Self::get_header_size()
-            - mem::size_of::<SlotDirectoryHeader>()
+            - SLOT_DIRECTORY_HEADER_SIZE
             - (slot_count as usize * SLOT_SIZE)
     }
 
-    fn has_sufficient_space(page: &Page, required_space: usize) -> Result<bool, DatabaseError> {
-        let header = Self::read_slot_directory_header(page)?;
-        let usable_space = Self::get_usable_page_size(header.slot_count);
-        let used_space = Self::get_used_space(page)?;
-
-        Ok(usable_space >= used_space + required_space)
-    }
-
-    fn has_sufficient_space_with_count(
-        page: &Page,
-        required_size: usize,
-        slot_count: u16,
-    ) -> Result<bool, DatabaseError> {
-        let used_space = Self::get_used_space(page)?;
-        let usable_space = Self::get_usable_page_size(slot_count);
-        Ok(used_space + required_size <= usable_space)
-    }
-
     fn get_used_space(page: &Page) -> Result<usize, DatabaseError> {
         let header = Self::read_slot_directory_header(page)?;
-        let mut used_space = 0;
-
-        for slot_id in 0..header.slot_count {
-            let slot_entry = Self::read_slot_entry(page, slot_id)?;
-            if !slot_entry.is_tombstone() && !slot_entry.is_empty() {
-                used_space += slot_entry.length as usize;
-            }
-        }
-
-        Ok(used_space)
+        Ok(Self::summarize_slots(page, &header)?.used_space)
     }
 
-    fn find_reusable_slot(
+    /// Walk the slot directory once, collecting everything inserts and updates need
+    fn summarize_slots(
         page: &Page,
         header: &SlotDirectoryHeader,
-    ) -> Result<Option<SlotId>, DatabaseError> {
-        for slot_id in 0..header.slot_count {
-            let slot_entry = Self::read_slot_entry(page, slot_id)?;
-            if slot_entry.is_tombstone() || slot_entry.is_empty() {
-                return Ok(Some(slot_id));
-            }
-        }
-        Ok(None)
-    }
-
-    fn find_free_space(page: &Page, size: usize) -> Result<u16, DatabaseError> {
-        let header = Self::read_slot_directory_header(page)?;
-
-        // Simple strategy: allocate from the end of used space
-        // In a more sophisticated implementation, this would find holes created by deletions
-        let mut max_offset = Self::get_header_size() as u16;
+    ) -> Result<SlotSummary, DatabaseError> {
+        let mut summary = SlotSummary {
+            used_space: 0,
+            high_water: Self::get_header_size() as u16,
+            reusable_slot: None,
+            live_extents: Vec::new(),
+        };
 
         for slot_id in 0..header.slot_count {
-            let slot_entry = Self::read_slot_entry(page, slot_id)?;
-            if !slot_entry.is_tombstone() && !slot_entry.is_empty() {
-                let end_offset = slot_entry.offset + slot_entry.length;
-                if end_offset > max_offset {
-                    max_offset = end_offset;
-                }
+            let slot_entry = Self::read_slot_entry_with_header(page, slot_id, header)?;
+            if slot_entry.is_tombstone() || slot_entry.is_empty() {
+                summary.reusable_slot.get_or_insert(slot_id);
+            } else {
+                summary.used_space += slot_entry.length as usize;
+                summary.high_water = summary.high_water.max(slot_entry.offset + slot_entry.length);
+                summary
+                    .live_extents
+                    .push((slot_entry.offset, slot_entry.offset + slot_entry.length));
             }
         }
+        summary.live_extents.sort_unstable();
 
-        let available_space =
-            Self::get_slot_directory_start(header.slot_count) - max_offset as usize;
-        if available_space < size {
-            return Err(DatabaseError::Storage(
-                "Insufficient contiguous space".to_string(),
-            ));
-        }
-
-        Ok(max_offset)
+        Ok(summary)
     }
 
-    fn find_free_space_with_count(
-        page: &Page,
+    /// Where to write `size` bytes on a page whose directory will hold `slot_count` slots.
+    ///
+    /// The smallest hole between live documents that fits wins, so space freed below the
+    /// last live document is reused before the free tail of the page shrinks and fewer
+    /// pages need compacting. Holes are only used while the directory, grown to
+    /// `slot_count`, still ends past the last live document.
+    fn find_free_space(
+        summary: &SlotSummary,
         size: usize,
         slot_count: u16,
     ) -> Result<u16, DatabaseError> {
-        let header = Self::read_slot_directory_header(page)?;
-
-        // Simple strategy: allocate from the end of used space
-        // In a more sophisticated implementation, this would find holes created by deletions
-        let mut max_offset = Self::get_header_size() as u16;
-
-        for slot_id in 0..header.slot_count {
-            let slot_entry = Self::read_slot_entry(page, slot_id)?;
-            if !slot_entry.is_tombstone() && !slot_entry.is_empty() {
-                let end_offset = slot_entry.offset + slot_entry.length;
-                if end_offset > max_offset {
-                    max_offset = end_offset;
+        let max_offset = summary.high_water;
+        let directory_start = Self::get_slot_directory_start(slot_count);
+
+        if max_offset as usize <= directory_start {
+            let mut best_fit: Option<(usize, u16)> = None;
+            let mut hole_start = Self::get_header_size() as u16;
+            for &(start, end) in &summary.live_extents {
+                let hole = start.saturating_sub(hole_start) as usize;
+                if hole >= size && best_fit.is_none_or(|(best, _)| hole < best) {
+                    best_fit = Some((hole, hole_start));
                 }
+                hole_start = hole_start.max(end);
+            }
+            if let Some((_, offset)) = best_fit {
+                return Ok(offset);
             }
         }
 
-        let available_space = Self::get_slot_directory_start(slot_count) - max_offset as usize;
+        let available_space = directory_start.saturating_sub(max_offset as usize);
         if available_space < size {
             return Err(DatabaseError::Storage(
                 "Insufficient contiguous space".to_string(),
@@ -471,16 +613,68 @@ impl PageLayout {
         SLOT_DIRECTORY_OFFSET - (slot_count as usize * SLOT_SIZE)
     }
 
+    /// Where a slot's entry lives in a directory described by `header`
+    fn get_slot_offset(header: &SlotDirectoryHeader, slot_id: SlotId) -> usize {
+        match header.layout {
+            SlotLayout::Shifting => {
+                Self::get_slot_directory_start(header.slot_count) + (slot_id as usize * SLOT_SIZE)
+            }
+            SlotLayout::Stable => SLOT_DIRECTORY_OFFSET - ((slot_id as usize + 1) * SLOT_SIZE),
+        }
+    }
+
     fn update_page_free_space(page: &mut Page) -> Result<(), DatabaseError> {
-        let used_space = Self::get_used_space(page)?;
         let header = Self::read_slot_directory_header(page)?;
-        let usable_space = Self::get_usable_page_size(header.slot_count);
-        let free_space = usable_space.saturating_sub(used_space) as u16;
-
-        page.update_free_space(free_space);
+        let used_space = Self::summarize_slots(page, &header)?.used_space;
+        Self::set_page_free_space(page, header.slot_count, used_space);
         Ok(())
     }
 
+    fn set_page_free_space(page: &mut Page, slot_count: u16, used_space: usize) {
+        let usable_space = Self::get_usable_page_size(slot_count);
+        page.update_free_space(usable_space.saturating_sub(used_space) as u16);
+    }
+
+    /// Drop unused slots from the end of the directory, handing their bytes back to documents.
+    /// Slots before the last live one keep their ids.
+    fn trim_slot_directory(page: &mut Page) -> Result<(), DatabaseError> {
+        let header = Self::read_slot_directory_header(page)?;
+
+        let mut new_slot_count = header.slot_count;
+        while new_slot_count > 0 {
+            let slot_entry = Self::read_slot_entry(page, new_slot_count - 1)?;
+            if !slot_entry.is_tombstone() && !slot_entry.is_empty() {
+                break;
+            }
+            new_slot_count -= 1;
+        }
+        if new_slot_count == header.slot_count {
+            return Ok(());
+        }
+
+        let data = Self::get_page_data_mut(page);
+        let old_dir_start = Self::get_slot_directory_start(header.slot_count);
+        let new_dir_start = Self::get_slot_directory_start(new_slot_count);
+
+        // The directory shrinks toward the end of the page, so copy the last slot first.
+        // Stable entries do not move; the dropped ones are the lowest and just get cleared.
+        if header.layout == SlotLayout::Shifting {
+            for slot_id in (0..new_slot_count as usize).rev() {
+                let old_offset = old_dir_start + slot_id * SLOT_SIZE;
+                let new_offset = new_dir_start + slot_id * SLOT_SIZE;
+                data.copy_within(old_offset..old_offset + SLOT_SIZE, new_offset);
+            }
+        }
+        data[old_dir_start..new_dir_start].fill(0);
+
+        let new_header = SlotDirectoryHeader {
+            slot_count: new_slot_count,
+            free_space_offset: header.free_space_offset,
+            layout: header.layout,
+        };
+        Self::write_slot_directory_header(page, &new_header)
+    }
+
     /// Shift the slot directory entries to make room for a new slot
     fn shift_slot_directory_for_new_slot(
         page: &mut Page,
@@ -515,17 +709,20 @@ impl PageLayout {
             data[new_offset..new_offset + SLOT_SIZE].copy_from_slice(&slot_bytes);
         }
 
+        #[cfg(test)]
+        SLOT_SHIFTS.with(|shifts| shifts.set(shifts.get() + old_slot_count as usize));
+
         Ok(())
     }
 
     // Low-level data access methods
 
     fn read_slot_directory_header(page: &Page) -> Result<SlotDirectoryHeader, DatabaseError> {
-        let data = page.to_bytes();
+        let data = Self::get_page_data(page);
         let header_bytes = &data
-            [SLOT_DIRECTORY_OFFSET..SLOT_DIRECTORY_OFFSET + mem::size_of::<SlotDirectoryHeader>()];
+            [SLOT_DIRECTORY_OFFSET..SLOT_DIRECTORY_OFFSET + SLOT_DIRECTORY_HEADER_SIZE];
 
-        if header_bytes.len() < mem::size_of::<SlotDirectoryHeader>() {
+        if header_bytes.len() < SLOT_DIRECTORY_HEADER_SIZE {
             return Err(DatabaseError::Storage(
                 "Invalid slot directory header".to_string(),
             ));
@@ -533,10 +730,22 @@ impl PageLayout {
 
         let slot_count = u16::from_le_bytes([header_bytes[0], header_bytes[1]]);
         let free_space_offset = u16::from_le_bytes([header_bytes[2], header_bytes[3]]);
+        // A damaged page must not send slot lookups outside the page
+        if slot_count & !STABLE_LAYOUT_FLAG > MAX_SLOTS_PER_PAGE {
+            return Err(DatabaseError::Storage(
+                "Invalid slot directory header".to_string(),
+            ));
+        }
+        let layout = if slot_count & STABLE_LAYOUT_FLAG != 0 {
+            SlotLayout::Stable
+        } else {
+            SlotLayout::Shifting
+        };
 
         Ok(SlotDirectoryHeader {
-            slot_count,
+            slot_count: slot_count & !STABLE_LAYOUT_FLAG,
             free_space_offset,
+            layout,
         })
     }
 
@@ -546,9 +755,13 @@ impl PageLayout {
     ) -> Result<(), DatabaseError> {
         let data = Self::get_page_data_mut(page);
         let header_bytes = &mut data
-            [SLOT_DIRECTORY_OFFSET..SLOT_DIRECTORY_OFFSET + mem::size_of::<SlotDirectoryHeader>()];
+            [SLOT_DIRECTORY_OFFSET..SLOT_DIRECTORY_OFFSET + SLOT_DIRECTORY_HEADER_SIZE];
 
-        header_bytes[0..2].copy_from_slice(&header.slot_count.to_le_bytes());
+        let slot_count = match header.layout {
+            SlotLayout::Shifting => header.slot_count,
+            SlotLayout::Stable => header.slot_count | STABLE_LAYOUT_FLAG,
+        };
+        header_bytes[0..2].copy_from_slice(&slot_count.to_le_bytes());
         header_bytes[2..4].copy_from_slice(&header.free_space_offset.to_le_bytes());
 
         Ok(())
@@ -556,10 +769,20 @@ impl PageLayout {
 
     fn read_slot_entry(page: &Page, slot_id: SlotId) -> Result<SlotEntry, DatabaseError> {
         let header = Self::read_slot_directory_header(page)?;
-        let slot_offset =
-            Self::get_slot_directory_start(header.slot_count) + (slot_id as usize * SLOT_SIZE);
+        Self::read_slot_entry_with_header(page, slot_id, &header)
+    }
 
-        let data = page.to_bytes();
+    fn read_slot_entry_with_header(
+        page: &Page,
+        slot_id: SlotId,
+        header: &SlotDirectoryHeader,
+    ) -> Result<SlotEntry, DatabaseError> {
+        #[cfg(test)]
+        SLOT_READS.with(|reads| reads.set(reads.get() + 1));
+
+        let slot_offset = Self::get_slot_offset(header, slot_id);
+
+        let data = Self::get_page_data(page);
         if slot_offset + SLOT_SIZE > data.len() {
             return Err(DatabaseError::Storage("Invalid slot offset".to_string()));
         }
@@ -577,8 +800,7 @@ impl PageLayout {
         entry: &SlotEntry,
     ) -> Result<(), DatabaseError> {
         let header = Self::read_slot_directory_header(page)?;
-        let slot_offset =
-            Self::get_slot_directory_start(header.slot_count) + (slot_id as usize * SLOT_SIZE);
+        let slot_offset = Self::get_slot_offset(&header, slot_id);
 
         let data = Self::get_page_data_mut(page);
         if slot_offset + SLOT_SIZE > data.len() {
@@ -592,14 +814,13 @@ impl PageLayout {
         Ok(())
     }
 
-    fn write_slot_entry_with_count(
+    fn write_slot_entry_with_header(
         page: &mut Page,
         slot_id: SlotId,
         entry: &SlotEntry,
-        slot_count: u16,
+        header: &SlotDirectoryHeader,
     ) -> Result<(), DatabaseError> {
-        let slot_offset =
-            Self::get_slot_directory_start(slot_count) + (slot_id as usize * SLOT_SIZE);
+        let slot_offset = Self::get_slot_offset(header, slot_id);
 
         let data = Self::get_page_data_mut(page);
         if slot_offset + SLOT_SIZE > data.len() {
@@ -613,12 +834,8 @@ impl PageLayout {
         Ok(())
     }
 
-    fn read_document_data_owned(
-        page: &Page,
-        offset: u16,
-        length: u16,
-    ) -> Result<Vec<u8>, DatabaseError> {
-        let data = page.to_bytes();
+    fn read_document_data(page: &Page, offset: u16, length: u16) -> Result<&[u8], DatabaseError> {
+        let data = Self::get_page_data(page);
         let start = offset as usize;
         let end = start + length as usize;
 
@@ -628,7 +845,7 @@ impl PageLayout {
             ));
         }
 
-        Ok(data[start..end].to_vec())
+        Ok(&data[start..end])
     }
 
     fn write_document_data(page: &mut Page, offset: u16, data: &[u8]) -> Result<(), DatabaseError> {
@@ -646,6 +863,12 @@ impl PageLayout {
         Ok(())
     }
 
+    // Borrow the page bytes; `Page::to_bytes` would copy the whole page on every read
+    fn get_page_data(page: &Page) -> &[u8; PAGE_SIZE] {
+        // Same layout assumption as `get_page_data_mut`
+        unsafe { &*(page as *const Page as *const [u8; PAGE_SIZE]) }
+    }
+
     // Helper method to get mutable access to page data
     fn get_page_data_mut(page: &mut Page) -> &mut [u8; PAGE_SIZE] {
         // This is unsafe but necessary for direct page manipulation
@@ -656,18 +879,9 @@ impl PageLayout {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::storage::page::PageType;
 
     fn create_test_page() -> Page {
-        let mut page = Page::new(1, PageType::Data);
-        // Zero out the slot directory area to ensure clean state
-        let data = unsafe { &mut *((&mut page) as *mut Page as *mut [u8; PAGE_SIZE]) };
-
-        // Initialize slot directory header
-        let header_offset = SLOT_DIRECTORY_OFFSET;
-        data[header_offset..header_offset + 4].copy_from_slice(&[0, 0, 16, 0]); // slot_count=0, free_space_offset=16
-
-        page
+        PageLayout::new_page(1)
     }
 
     #[test]
@@ -682,6 +896,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_new_page_is_ready_for_use() {
+        let page = PageLayout::new_page(7);
+
+        assert_eq!(page.get_header().page_id(), 7);
+        assert!(page.verify_checksum());
+        assert_eq!(PageLayout::get_document_count(&page).unwrap(), 0);
+
+        let header = PageLayout::read_slot_directory_header(&page).unwrap();
+        assert_eq!(header.slot_count, 0);
+        assert_eq!(header.free_space_offset, PageLayout::get_header_size() as u16);
+        assert_eq!(
+            page.get_free_space() as usize,
+            PageLayout::get_usable_page_size(0)
+        );
+
+        let restored = Page::from_bytes(page.to_bytes()).expect("checksum should be valid");
+        assert_eq!(PageLayout::get_document_count(&restored).unwrap(), 0);
+    }
+
     #[test]
     fn test_insert_and_get_document() {
         let mut page = create_test_page();
@@ -840,6 +1074,55 @@ mod tests {
         assert_eq!(PageLayout::get_document_count(&page).unwrap(), 3);
     }
 
+    #[test]
+    fn test_get_live_slots() {
+        let mut page = create_test_page();
+        assert!(PageLayout::get_live_slots(&page).unwrap().is_empty());
+
+        let slot1 = PageLayout::insert_document(&mut page, b"one").unwrap();
+        let slot2 = PageLayout::insert_document(&mut page, b"two").unwrap();
+        let slot3 = PageLayout::insert_document(&mut page, b"three").unwrap();
+        PageLayout::delete_document(&mut page, slot2).unwrap();
+
+        assert_eq!(PageLayout::get_live_slots(&page).unwrap(), vec![slot1, slot3]);
+    }
+
+    #[test]
+    fn test_dead_space() {
+        let mut page = create_test_page();
+        assert_eq!(PageLayout::dead_space(&page).unwrap(), 0);
+
+        let doc1 = b"Doc1";
+        let doc2 = b"Document2";
+        let doc3 = b"Doc3";
+        let doc4 = b"Document4";
+        let doc5 = b"Doc5";
+
+        PageLayout::insert_document(&mut page, doc1).unwrap();
+        let slot2 = PageLayout::insert_document(&mut page, doc2).unwrap();
+        let slot3 = PageLayout::insert_document(&mut page, doc3).unwrap();
+        let slot4 = PageLayout::insert_document(&mut page, doc4).unwrap();
+        PageLayout::insert_document(&mut page, doc5).unwrap();
+        assert_eq!(PageLayout::dead_space(&page).unwrap(), 0);
+
+        PageLayout::delete_document(&mut page, slot2).unwrap();
+        PageLayout::delete_document(&mut page, slot4).unwrap();
+        assert_eq!(
+            PageLayout::dead_space(&page).unwrap(),
+            doc2.len() + doc4.len()
+        );
+
+        // Shrinking a document in place leaves a gap behind it
+        PageLayout::update_document(&mut page, slot3, b"D3").unwrap();
+        assert_eq!(
+            PageLayout::dead_space(&page).unwrap(),
+            doc2.len() + doc4.len() + 2
+        );
+
+        PageLayout::compact_page(&mut page).unwrap();
+        assert_eq!(PageLayout::dead_space(&page).unwrap(), 0);
+    }
+
     #[test]
     fn test_utilization_percentage() {
         let mut page = create_test_page();
@@ -921,4 +1204,276 @@ mod tests {
             assert_eq!(retrieved.len(), size);
         }
     }
+
+    #[test]
+    fn test_compaction_trims_trailing_slots() {
+        let mut page = create_test_page();
+        let slots: Vec<_> = (0..10u8)
+            .map(|i| PageLayout::insert_document(&mut page, &[i; 20]).unwrap())
+            .collect();
+        let free_before = page.get_free_space();
+
+        // Leave slots 0 and 2 live, with a hole at 1 and a run of dead slots after 2
+        for &slot in slots.iter().filter(|&&slot| slot != 0 && slot != 2) {
+            PageLayout::delete_document(&mut page, slot).unwrap();
+        }
+        assert_eq!(PageLayout::tombstone_count(&page).unwrap(), 8);
+
+        assert!(PageLayout::compact_page(&mut page).unwrap());
+
+        assert_eq!(PageLayout::read_slot_directory_header(&page).unwrap().slot_count, 3);
+        assert_eq!(PageLayout::get_live_slots(&page).unwrap(), vec![0, 2]);
+        assert_eq!(PageLayout::get_document(&page, 0).unwrap(), vec![0u8; 20]);
+        assert_eq!(PageLayout::get_document(&page, 2).unwrap(), vec![2u8; 20]);
+        assert!(PageLayout::get_document(&page, 5).is_err());
+
+        // Both the deleted documents' bytes and the 7 dropped slot entries are free again
+        assert_eq!(
+            page.get_free_space() as usize,
+            free_before as usize + 8 * 20 + 7 * SLOT_SIZE
+        );
+
+        // The hole is reused first, then the directory grows again
+        assert_eq!(PageLayout::insert_document(&mut page, b"new").unwrap(), 1);
+        assert_eq!(PageLayout::insert_document(&mut page, b"newer").unwrap(), 3);
+        assert_eq!(PageLayout::get_document(&page, 2).unwrap(), vec![2u8; 20]);
+        assert_eq!(PageLayout::get_document(&page, 3).unwrap(), b"newer");
+    }
+
+    #[test]
+    fn test_compaction_zeroes_deleted_document_bytes() {
+        let mut page = create_test_page();
+        // A fresh page has nothing but zeros past its header
+        let data_start = PageLayout::get_header_size();
+        let directory_start = PageLayout::get_slot_directory_start(0);
+        assert!(page.to_bytes()[data_start..directory_start].iter().all(|&b| b == 0));
+
+        PageLayout::insert_document(&mut page, &[0x11; 40]).unwrap();
+        let secret = PageLayout::insert_document(&mut page, &[0x5E; 64]).unwrap();
+        PageLayout::insert_document(&mut page, &[0x33; 40]).unwrap();
+        PageLayout::delete_document(&mut page, secret).unwrap();
+        // Deleting only tombstones the slot; the bytes are still there
+        assert!(page.to_bytes().windows(64).any(|w| w == [0x5E; 64]));
+
+        assert!(PageLayout::compact_page(&mut page).unwrap());
+        let bytes = page.to_bytes();
+        assert!(!bytes.contains(&0x5E));
+        // Both survivors are packed at the start, and everything after them up to the
+        // slot directory is zero
+        let live_end = data_start + 80;
+        assert_eq!(&bytes[data_start..data_start + 40], &[0x11; 40]);
+        assert_eq!(&bytes[data_start + 40..live_end], &[0x33; 40]);
+        let directory_start = PageLayout::get_slot_directory_start(3);
+        assert!(bytes[live_end..directory_start].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_freed_slot_directory_space_is_usable() {
+        let mut page = create_test_page();
+        let mut count = 0u16;
+        while PageLayout::insert_document(&mut page, b"tiny").is_ok() {
+            count += 1;
+        }
+
+        // Deleting the trailing documents alone frees only their bytes; the slot
+        // entries stay until compaction drops them
+        for slot in 1..count {
+            PageLayout::delete_document(&mut page, slot).unwrap();
+        }
+        // Only fits once the directory is back down to two slots
+        let big = vec![7u8; PageLayout::get_usable_page_size(2) - 4 - SLOT_SIZE];
+        assert!(PageLayout::insert_document(&mut page, &big).is_err());
+
+        assert!(PageLayout::compact_if_tombstoned(&mut page, 0.5).unwrap());
+        assert_eq!(PageLayout::insert_document(&mut page, &big).unwrap(), 1);
+        assert_eq!(PageLayout::get_document(&page, 0).unwrap(), b"tiny");
+    }
+
+    #[test]
+    fn test_compact_if_tombstoned_respects_threshold() {
+        let mut page = create_test_page();
+        for i in 0..4u8 {
+            PageLayout::insert_document(&mut page, &[i; 8]).unwrap();
+        }
+        PageLayout::delete_document(&mut page, 1).unwrap();
+        PageLayout::delete_document(&mut page, 3).unwrap();
+
+        // Exactly half the slots are tombstones, which does not exceed 0.5
+        assert!(!PageLayout::compact_if_tombstoned(&mut page, 0.5).unwrap());
+        assert_eq!(PageLayout::tombstone_count(&page).unwrap(), 2);
+
+        assert!(PageLayout::compact_if_tombstoned(&mut page, 0.25).unwrap());
+        assert_eq!(PageLayout::tombstone_count(&page).unwrap(), 0);
+        assert_eq!(PageLayout::get_live_slots(&page).unwrap(), vec![0, 2]);
+
+        let mut empty = create_test_page();
+        assert!(!PageLayout::compact_if_tombstoned(&mut empty, 0.0).unwrap());
+    }
+
+    fn slot_reads() -> usize {
+        SLOT_READS.with(|reads| reads.get())
+    }
+
+    #[test]
+    fn test_insert_walks_slot_directory_once() {
+        let mut page = create_test_page();
+        let doc = [7u8; 8];
+        let mut inserted = 0u16;
+
+        loop {
+            let before = slot_reads();
+            match PageLayout::insert_document(&mut page, &doc) {
+                Ok(slot_id) => assert_eq!(slot_id, inserted),
+                Err(_) => break,
+            }
+            // One read per existing slot, not one pass per space check
+            assert_eq!(slot_reads() - before, inserted as usize);
+            inserted += 1;
+        }
+        assert!(inserted > 500, "expected the page to hold many small documents");
+
+        // Reusing a slot still takes a single pass
+        PageLayout::delete_document(&mut page, 3).unwrap();
+        let before = slot_reads();
+        assert_eq!(PageLayout::insert_document(&mut page, &doc).unwrap(), 3);
+        assert_eq!(slot_reads() - before, inserted as usize);
+    }
+
+    #[test]
+    fn test_free_space_tracks_inserts_and_updates() {
+        let mut page = create_test_page();
+        let a = PageLayout::insert_document(&mut page, &[1u8; 100]).unwrap();
+        PageLayout::insert_document(&mut page, &[2u8; 50]).unwrap();
+        assert_eq!(
+            page.get_free_space() as usize,
+            PageLayout::get_usable_page_size(2) - 150
+        );
+
+        assert!(PageLayout::update_document(&mut page, a, &[3u8; 300]).unwrap());
+        assert_eq!(
+            page.get_free_space() as usize,
+            PageLayout::get_usable_page_size(2) - 350
+        );
+        assert_eq!(PageLayout::get_document(&page, a).unwrap(), vec![3u8; 300]);
+        assert_eq!(PageLayout::dead_space(&page).unwrap(), 100);
+    }
+
+    fn slot_shifts() -> usize {
+        SLOT_SHIFTS.with(|shifts| shifts.get())
+    }
+
+    // Fills the page with two-byte documents numbered by insertion order
+    fn fill_page(page: &mut Page) -> u16 {
+        let mut inserted = 0u16;
+        while let Ok(slot_id) = PageLayout::insert_document(page, &inserted.to_le_bytes()) {
+            assert_eq!(slot_id, inserted);
+            inserted += 1;
+        }
+        inserted
+    }
+
+    #[test]
+    fn test_stable_layout_adds_slots_without_shifting() {
+        let mut shifting = create_test_page();
+        let before = slot_shifts();
+        let capacity = fill_page(&mut shifting);
+        assert!(slot_shifts() - before > capacity as usize);
+
+        let mut page = PageLayout::new_page_with_layout(1, SlotLayout::Stable);
+        assert_eq!(PageLayout::slot_layout(&page).unwrap(), SlotLayout::Stable);
+        let before = slot_shifts();
+        // Same space per slot, so the same number of documents fit
+        assert_eq!(fill_page(&mut page), capacity);
+        assert_eq!(slot_shifts(), before);
+
+        for slot_id in 0..capacity {
+            assert_eq!(PageLayout::get_document(&page, slot_id).unwrap(), slot_id.to_le_bytes());
+        }
+        assert_eq!(page.get_free_space(), shifting.get_free_space());
+
+        // Compaction trims the trailing slots and keeps the rest where they were
+        for slot_id in (1..capacity).step_by(2).chain(capacity - 10..capacity) {
+            let _ = PageLayout::delete_document(&mut page, slot_id);
+        }
+        assert!(PageLayout::compact_page(&mut page).unwrap());
+        assert_eq!(PageLayout::slot_layout(&page).unwrap(), SlotLayout::Stable);
+        let live = PageLayout::get_live_slots(&page).unwrap();
+        assert_eq!(live.len(), (capacity as usize - 10).div_ceil(2));
+        for &slot_id in &live {
+            assert_eq!(PageLayout::get_document(&page, slot_id).unwrap(), slot_id.to_le_bytes());
+        }
+
+        // Freed slots are reused first, then new ones are added, still without shifting
+        let before = slot_shifts();
+        let refilled = (0..).take_while(|_| PageLayout::insert_document(&mut page, b"new").is_ok());
+        assert!(refilled.count() > 10);
+        assert_eq!(slot_shifts(), before);
+        for &slot_id in &live {
+            assert_eq!(PageLayout::get_document(&page, slot_id).unwrap(), slot_id.to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn test_insert_reuses_best_fitting_hole() {
+        let mut page = create_test_page();
+        let docs: Vec<Vec<u8>> = [100, 300, 100, 50, 100]
+            .iter()
+            .map(|&n| vec![n as u8; n])
+            .collect();
+        let slots: Vec<SlotId> = docs
+            .iter()
+            .map(|doc| PageLayout::insert_document(&mut page, doc).unwrap())
+            .collect();
+        PageLayout::delete_document(&mut page, slots[1]).unwrap();
+        PageLayout::delete_document(&mut page, slots[3]).unwrap();
+        assert_eq!(PageLayout::dead_space(&page).unwrap(), 350);
+
+        // Both holes fit, and the smaller one is taken
+        let small = PageLayout::insert_document(&mut page, &[7; 40]).unwrap();
+        assert_eq!(PageLayout::dead_space(&page).unwrap(), 310);
+
+        // Only the larger one fits
+        let large = PageLayout::insert_document(&mut page, &[8; 280]).unwrap();
+        assert_eq!(PageLayout::dead_space(&page).unwrap(), 30);
+
+        // Nothing fits a hole, so the page grows
+        let tail = PageLayout::insert_document(&mut page, &[9; 60]).unwrap();
+        assert_eq!(PageLayout::dead_space(&page).unwrap(), 30);
+
+        for slot_id in [slots[0], slots[2], slots[4]] {
+            assert_eq!(PageLayout::get_document(&page, slot_id).unwrap(), docs[slot_id as usize]);
+        }
+        assert_eq!(PageLayout::get_document(&page, small).unwrap(), [7; 40]);
+        assert_eq!(PageLayout::get_document(&page, large).unwrap(), [8; 280]);
+        assert_eq!(PageLayout::get_document(&page, tail).unwrap(), [9; 60]);
+    }
+
+    #[test]
+    fn test_insert_compacts_when_only_total_space_fits() {
+        let mut page = create_test_page();
+        let mut slots = Vec::new();
+        while let Ok(slot_id) = PageLayout::insert_document(&mut page, &[slots.len() as u8; 100]) {
+            slots.push(slot_id);
+        }
+        for &slot_id in slots.iter().skip(1).step_by(2) {
+            PageLayout::delete_document(&mut page, slot_id).unwrap();
+        }
+        assert_eq!(PageLayout::tombstone_count(&page).unwrap() as usize, slots.len() / 2);
+
+        // Far more than any hole or the tail holds, but exactly what the page has free
+        let size = page.get_free_space() as usize;
+        assert!(size > 100 * (slots.len() / 2 - 1));
+        let slot_id = PageLayout::insert_document(&mut page, &vec![0xEE; size]).unwrap();
+        assert_eq!(PageLayout::get_document(&page, slot_id).unwrap(), vec![0xEE; size]);
+        assert_eq!(PageLayout::tombstone_count(&page).unwrap(), 0);
+        // Only the trimmed trailing slot's entry is left over
+        assert_eq!(page.get_free_space() as usize, SLOT_SIZE);
+
+        for &survivor in slots.iter().step_by(2) {
+            assert_eq!(PageLayout::get_document(&page, survivor).unwrap(), [survivor as u8; 100]);
+        }
+
+        // Full again, with one slot entry's worth to spare
+        assert!(PageLayout::insert_document(&mut page, b"xxxxx").is_err());
+    }
 }
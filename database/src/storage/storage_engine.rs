@@ -11,207 +11,2338 @@
 
 use crate::{
     Document,
-    document::bson::{deserialize_document, serialize_document},
-    storage::{buffer_pool::BufferPool, file::DatabaseFile, page_layout::PageLayout},
+    document::{CREATED_FIELD, UPDATED_FIELD},
+    document::validator::DocumentValidator,
+    document::bson::{
+        MAX_DOCUMENT_SIZE, deserialize_document, peek_field,
+        serialize_document,
+    },
+    document::object_id::ObjectId,
+    document::predicate::Predicate,
+    document::schema::InferredSchema,
+    error::DatabaseError,
+    document::types::{NonFiniteFloatMode, Value},
+    storage::{
+        buffer_pool::{BufferPool, BufferPoolStats},
+        cancellation::CancellationToken,
+        change_stream::{ChangeEvent, ChangeStream, DEFAULT_CHANNEL_CAPACITY},
+        compression::{self, Compression},
+        conflict::{ConflictResolver, Resolution},
+        document_cache::{CachePolicy, CacheStats, DocumentCache},
+        file::{DatabaseFile, FIRST_DATA_PAGE_ID, FsyncPolicy},
+        free_space::FreeSpaceMap,
+        index::{IndexStats, OrderedIndex},
+        integrity::{IntegrityProblem, IntegrityReport, OpenOptions},
+        page::{PAGE_SIZE, Page},
+        page_layout::{PageLayout, SlotId, SlotLayout},
+        wal::WriteAheadLog,
+    },
 };
-use anyhow::Result;
+use crate::result::Result;
+pub use crate::document::id::DocumentId;
+use chrono::{DateTime, Duration, Utc};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+use std::io::{ErrorKind, Read};
+use std::ops::ControlFlow;
 use std::path::Path;
+use std::sync::mpsc::Receiver;
+use std::time::Instant;
 
-#[derive(Debug, Clone, Copy)]
-pub struct DocumentId {
-    page_id: u64,
-    slot_id: u16,
+/// Chooses which existing page an insert goes into.
+///
+/// `FirstFit` and `BestFit` pick pages through the free-space directory, so any page in
+/// the file is a candidate without being read first. Whatever the strategy, an insert
+/// that fits no candidate page gets a freshly allocated page at the end of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PackingStrategy {
+    /// The lowest-numbered page with room. Cheap, and keeps the front of the file dense.
+    #[default]
+    FirstFit,
+    /// The page with the least free space that still fits the document, to within the
+    /// free-space directory's bucket size. Packs pages tightest.
+    BestFit,
+    /// Only the last page in the file. Writes stay sequential, but space freed by
+    /// deletes in earlier pages is never reused until a vacuum.
+    AppendOnly,
 }
 
-impl DocumentId {
-    /// Create a new DocumentId
-    pub fn new(page_id: u64, slot_id: u16) -> Self {
-        Self { page_id, slot_id }
+/// How much checking the write path does before storing a document.
+///
+/// Lower levels exist for trusted bulk loads, where the data is known to be valid and
+/// validation time dominates. Every level but `None` rejects documents whose field names
+/// would break storage, such as a data field called `_id`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationLevel {
+    /// Run `DocumentValidator::validate_document`: size, field count, nesting depth,
+    /// field names, and values.
+    #[default]
+    Full,
+    /// Only check the document size limit and
+    /// `DocumentValidator::validate_storable_names`: no reserved top-level names and no
+    /// null bytes in any name.
+    Cheap,
+    /// Store documents as given.
+    None,
+}
+
+/// Where `StorageEngine::move_document` puts a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveTarget {
+    /// An existing page, which must have room for the document.
+    Page(u64),
+    /// A freshly allocated page at the end of the file.
+    NewPage,
+}
+
+/// How `StorageEngine::scan` treats damaged pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ScanOptions {
+    /// Skip pages that fail their checksum or cannot be decoded, reporting them in
+    /// `ScanResult::corrupt_pages`, instead of failing the whole scan. Off by default.
+    pub skip_corrupt: bool,
+}
+
+/// Documents returned by `StorageEngine::scan`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ScanResult {
+    /// Every readable live document, in physical (page, slot) order
+    pub documents: Vec<(DocumentId, Document)>,
+    /// Pages skipped as corrupt, in page order. Always empty for strict scans.
+    pub corrupt_pages: Vec<u64>,
+}
+
+/// Bounds on what the collecting query APIs (`scan_all_with_options`,
+/// `find_range_with_options`) return.
+///
+/// `limit` is applied first: a query stops once it has `limit` results, so a limit no
+/// larger than `max_buffered_docs` can never trip the cap. Without a limit, a query
+/// whose results would exceed `max_buffered_docs` fails with
+/// `DatabaseError::TooManyResults` rather than buffering them all; visit the documents
+/// with `scan_with` instead to process any number of them in constant memory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct QueryOptions {
+    /// Return at most this many results
+    pub limit: Option<usize>,
+    /// Fail instead of holding more than this many result documents in memory
+    pub max_buffered_docs: Option<usize>,
+}
+
+/// A document `StorageEngine::import_bson` could not import.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportError {
+    /// Byte offset of the document's length prefix in the input
+    pub offset: u64,
+    pub message: String,
+}
+
+/// Outcome of `StorageEngine::import_bson`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    /// Ids of the imported documents, in input order
+    pub imported: Vec<DocumentId>,
+    /// Documents that were skipped, in input order
+    pub errors: Vec<ImportError>,
+    /// Stored documents a conflict resolver chose to keep over an incoming one, in input
+    /// order. Always empty for a plain `import_bson`.
+    pub kept: Vec<DocumentId>,
+}
+
+impl ImportReport {
+    /// Number of documents imported
+    pub fn imported_count(&self) -> usize {
+        self.imported.len()
+    }
+}
+
+/// Outcome of `StorageEngine::upsert`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    /// Nothing collided, so the document was inserted
+    Inserted(DocumentId),
+    /// The stored document was replaced with the resolver's result
+    Replaced(DocumentId),
+    /// The resolver kept the stored document unchanged
+    Kept(DocumentId),
+}
+
+impl UpsertOutcome {
+    /// Id of the document now stored
+    pub fn document_id(&self) -> DocumentId {
+        match self {
+            Self::Inserted(id) | Self::Replaced(id) | Self::Kept(id) => *id,
+        }
+    }
+}
+
+/// What one `compact_step` call did.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CompactionProgress {
+    /// Pages examined in this step
+    pub pages_visited: usize,
+    /// Pages that had tombstones and were rewritten
+    pub pages_compacted: usize,
+    /// Whether this step reached the last page; the next step starts a new pass at the first data page
+    pub pass_complete: bool,
+}
+
+/// When writes trigger a `checkpoint` on their own.
+///
+/// Both triggers are checked after every write, which is the only point an automatic
+/// checkpoint runs; an idle engine does not checkpoint until its next write. Either
+/// trigger alone is enough. Both are off by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AutoCheckpoint {
+    /// Checkpoint once this much time has passed since the last checkpoint
+    pub interval: Option<std::time::Duration>,
+    /// Checkpoint once the write-ahead log holds at least this many bytes. Ignored
+    /// without a write-ahead log.
+    pub wal_bytes: Option<u64>,
+}
+
+/// Document storage over a paged file.
+///
+/// Reads go through the buffer pool, which holds the only in-memory copy of each page,
+/// and writes modify those cached pages in place. The optional document cache (see
+/// `with_document_cache`) is updated or invalidated by every write to a slot, so every
+/// read reflects all writes made earlier through the same engine (read-your-writes),
+/// whether or not they have been flushed to disk. An update that fails leaves the
+/// document, its cached copy and its index entries as they were.
+pub struct StorageEngine {
+    pub database_file: DatabaseFile,
+    buffer_pool: BufferPool,
+    // Secondary indexes keyed by field path, kept in sync by insert/update/delete
+    indexes: BTreeMap<String, OrderedIndex>,
+    packing_strategy: PackingStrategy,
+    // Whether inserts and updates stamp `_created`/`_updated`
+    timestamps: bool,
+    validation_level: ValidationLevel,
+    // Next page `compact_step` will examine
+    compaction_cursor: u64,
+    // Fraction of tombstoned slots past which an insert compacts the page first
+    tombstone_threshold: Option<f64>,
+    // Decoded documents by location; caches nothing unless given a capacity
+    document_cache: DocumentCache,
+    // Whether single writes and bulk writes populate the document cache
+    cache_policy: CachePolicy,
+    bulk_cache_policy: CachePolicy,
+    // Redo log of the page images each write produced, emptied by checkpoints
+    wal: Option<WriteAheadLog>,
+    auto_checkpoint: AutoCheckpoint,
+    last_checkpoint: Instant,
+    // Free bytes of every data page, updated after each write and stored by checkpoints
+    free_space: FreeSpaceMap,
+    // Pages scans load ahead of the page they are on
+    read_ahead: usize,
+    // How new and rewritten documents are stored
+    compression: Compression,
+    // Subscribers to insert/update/delete events
+    changes: ChangeStream,
+}
+
+impl StorageEngine {
+    pub fn new(database_path: &Path, buffer_pool_size: usize) -> Result<Self> {
+        let mut database_file = DatabaseFile::open(database_path)?;
+        let buffer_pool = BufferPool::new(buffer_pool_size);
+        let stored_free_space = database_file.read_free_space_directory()?;
+        let mut engine = Self {
+            database_file,
+            buffer_pool,
+            indexes: BTreeMap::new(),
+            packing_strategy: PackingStrategy::default(),
+            timestamps: false,
+            validation_level: ValidationLevel::default(),
+            compaction_cursor: FIRST_DATA_PAGE_ID,
+            tombstone_threshold: None,
+            document_cache: DocumentCache::new(0),
+            cache_policy: CachePolicy::WriteThrough,
+            bulk_cache_policy: CachePolicy::WriteAround,
+            wal: None,
+            auto_checkpoint: AutoCheckpoint::default(),
+            last_checkpoint: Instant::now(),
+            free_space: FreeSpaceMap::new(),
+            read_ahead: 0,
+            compression: Compression::default(),
+            changes: ChangeStream::new(),
+        };
+        // A directory left stale by a crash, or never stored, is rebuilt from the pages
+        match stored_free_space {
+            Some(buckets) => engine.free_space = FreeSpaceMap::from_buckets(buckets),
+            None => engine.rebuild_free_space_directory()?,
+        }
+        Ok(engine)
+    }
+
+    /// Opens the database at `database_path` like `new`, optionally attaching a
+    /// write-ahead log and checking or repairing the file first; see `OpenOptions`.
+    ///
+    /// With `verify`, any problem fails the open with
+    /// `DatabaseError::IntegrityCheckFailed`, whose report lists each one. With
+    /// `repair`, the problems `repair` can fix are fixed and the open only fails if
+    /// some remain. Call `check_integrity` or `repair` on an open engine instead to see
+    /// what a repair would change or changed.
+    pub fn open_with_options(
+        database_path: &Path,
+        buffer_pool_size: usize,
+        options: &OpenOptions,
+    ) -> Result<Self> {
+        let mut engine = Self::new(database_path, buffer_pool_size)?;
+        if let Some(wal_path) = &options.wal {
+            engine = engine.with_wal(wal_path)?;
+        }
+
+        let report = if options.repair {
+            // Whatever a repair could not fix shows up in a second check
+            if engine.repair()?.is_clean() {
+                IntegrityReport::default()
+            } else {
+                engine.check_integrity()?
+            }
+        } else if options.verify {
+            engine.check_integrity()?
+        } else {
+            IntegrityReport::default()
+        };
+        if !report.is_clean() {
+            return Err(DatabaseError::IntegrityCheckFailed(report));
+        }
+        Ok(engine)
+    }
+
+    /// Sets how inserts and updates validate documents. See `ValidationLevel`.
+    pub fn with_validation_level(mut self, level: ValidationLevel) -> Self {
+        self.validation_level = level;
+        self
+    }
+
+    /// Changes the validation level on an open engine, e.g. around a bulk load.
+    pub fn set_validation_level(&mut self, level: ValidationLevel) {
+        self.validation_level = level;
+    }
+
+    /// Returns the validation level in effect.
+    pub fn validation_level(&self) -> ValidationLevel {
+        self.validation_level
+    }
+
+    /// Maintain `_created` and `_updated` timestamps on stored documents.
+    ///
+    /// Inserts set both fields to the current time. Updates keep the stored `_created`,
+    /// whatever the new document holds, and set `_updated` to the current time, or one
+    /// millisecond past the previous `_updated` if the clock has not moved on, so it
+    /// strictly advances with every update. Use `DocumentValidator::with_timestamp_fields`
+    /// to accept these otherwise reserved fields.
+    pub fn with_timestamps(mut self, enabled: bool) -> Self {
+        self.timestamps = enabled;
+        self
+    }
+
+    /// Whether `_created`/`_updated` timestamps are maintained.
+    pub fn timestamps_enabled(&self) -> bool {
+        self.timestamps
+    }
+
+    /// Compact a page before inserting into it once more than `threshold` (a fraction
+    /// between 0 and 1) of its slots are tombstones.
+    ///
+    /// Compaction frees the deleted documents' bytes and drops dead slots from the end
+    /// of the slot directory; live slot ids are kept. Off by default, leaving
+    /// tombstones to `vacuum` and `compact_step`.
+    pub fn with_tombstone_compaction_threshold(mut self, threshold: f64) -> Self {
+        self.tombstone_threshold = Some(threshold);
+        self
+    }
+
+    /// Returns the tombstone compaction threshold, if one is set.
+    pub fn tombstone_compaction_threshold(&self) -> Option<f64> {
+        self.tombstone_threshold
+    }
+
+    /// Sets how inserts pick a page. See `PackingStrategy` for the tradeoffs.
+    pub fn with_packing_strategy(mut self, strategy: PackingStrategy) -> Self {
+        self.packing_strategy = strategy;
+        self
+    }
+
+    /// Returns the packing strategy in effect.
+    pub fn packing_strategy(&self) -> PackingStrategy {
+        self.packing_strategy
+    }
+
+    /// Sets when page write-backs are fsynced. See `FsyncPolicy` for the tradeoffs.
+    pub fn with_fsync_policy(mut self, policy: FsyncPolicy) -> Self {
+        self.database_file.set_fsync_policy(policy);
+        self
+    }
+
+    /// Returns the fsync policy in effect.
+    pub fn fsync_policy(&self) -> FsyncPolicy {
+        self.database_file.fsync_policy()
+    }
+
+    /// Sets the slot directory layout of pages allocated from now on; see `SlotLayout`.
+    /// `Stable` avoids moving a page's slot entries on every insert that adds a slot,
+    /// which is where append-heavy workloads of small documents spend their time. Pages
+    /// record their layout, so existing pages keep theirs and are read back either way.
+    pub fn with_slot_layout(mut self, layout: SlotLayout) -> Self {
+        self.database_file.set_slot_layout(layout);
+        self
+    }
+
+    /// Returns the slot directory layout of newly allocated pages.
+    pub fn slot_layout(&self) -> SlotLayout {
+        self.database_file.slot_layout()
+    }
+
+    /// Makes full scans read up to `pages` pages ahead of the one they are on.
+    ///
+    /// When a scan reaches a page that is not in the buffer pool, it reads that page and
+    /// the next `pages` pages in one ascending batch, so the pages after it are already
+    /// in memory when the scan gets there. The batch is read before the current page is
+    /// processed, not in the background. Each page read ahead holds a buffer pool slot,
+    /// so the window costs up to `pages * PAGE_SIZE` bytes of pool memory and is capped
+    /// at the pool's unpinned capacity. Off (zero) by default.
+    pub fn with_read_ahead(mut self, pages: usize) -> Self {
+        self.read_ahead = pages;
+        self
+    }
+
+    /// Returns the scan read-ahead window in pages.
+    pub fn read_ahead(&self) -> usize {
+        self.read_ahead
+    }
+
+    /// Sets how documents are stored from now on. Documents already stored keep the
+    /// form they were written in, and both forms are read back transparently. Stored
+    /// uncompressed by default; see `Compression`.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Returns how documents are stored.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Subscribes to the engine's writes: every successful insert, update, and delete
+    /// sends a `ChangeEvent` with the affected id, in the order the writes happened.
+    ///
+    /// Writes never block on subscribers. A receiver that falls
+    /// `DEFAULT_CHANNEL_CAPACITY` events behind is dropped, after which it yields the
+    /// events it holds and then reports the channel as disconnected. See
+    /// `subscribe_with_capacity` for a different limit.
+    pub fn subscribe(&mut self) -> Receiver<ChangeEvent> {
+        self.subscribe_with_capacity(DEFAULT_CHANNEL_CAPACITY)
+    }
+
+    /// `subscribe` with a receiver that may fall `capacity` events behind.
+    pub fn subscribe_with_capacity(&mut self, capacity: usize) -> Receiver<ChangeEvent> {
+        self.changes.subscribe(capacity)
+    }
+
+    /// Writes back the least recently used dirty pages after a write leaves more than
+    /// `fraction` (between 0 and 1) of the buffer pool dirty, so that misses rarely have
+    /// to write a page back before loading another. Pages are written only after the
+    /// write-ahead log has them. Off by default. See `BufferPool::flush_excess_dirty`.
+    pub fn with_dirty_high_water_mark(mut self, fraction: f64) -> Self {
+        self.buffer_pool = self.buffer_pool.with_dirty_high_water_mark(fraction);
+        self
+    }
+
+    /// Returns the dirty high-water mark, if one is set.
+    pub fn dirty_high_water_mark(&self) -> Option<f64> {
+        self.buffer_pool.dirty_high_water_mark()
+    }
+
+    /// Returns the buffer pool's occupancy and hit counters.
+    pub fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.buffer_pool.get_stats()
+    }
+
+    /// Keeps up to `capacity` decoded documents in memory, so repeated `get_document`
+    /// calls for them skip the page lookup and decoding. Off (capacity zero) by default.
+    pub fn with_document_cache(mut self, capacity: usize) -> Self {
+        self.document_cache = DocumentCache::new(capacity);
+        self
+    }
+
+    /// Sets whether single writes (`insert_document`, `insert_raw`, `update_document`)
+    /// populate the document cache. Write-through by default.
+    pub fn with_cache_policy(mut self, policy: CachePolicy) -> Self {
+        self.cache_policy = policy;
+        self
+    }
+
+    /// Returns the cache policy for single writes.
+    pub fn cache_policy(&self) -> CachePolicy {
+        self.cache_policy
+    }
+
+    /// Sets whether bulk writes (`insert_many`, `import_bson`) populate the document
+    /// cache. Write-around by default, since bulk-loaded documents are rarely read back
+    /// straight away and would otherwise evict the ones that are.
+    pub fn with_bulk_cache_policy(mut self, policy: CachePolicy) -> Self {
+        self.bulk_cache_policy = policy;
+        self
+    }
+
+    /// Returns the cache policy for bulk writes.
+    pub fn bulk_cache_policy(&self) -> CachePolicy {
+        self.bulk_cache_policy
+    }
+
+    /// Returns the document cache's hit and miss counts.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.document_cache.stats()
+    }
+
+    /// Logs every write to the write-ahead log at `wal_path`, so writes survive a crash
+    /// even before their pages are written back.
+    ///
+    /// Each write commits one record holding the new images of the pages it changed,
+    /// and returns once that record is durable. Until then those pages are not evicted,
+    /// so the data file never holds a change the log lacks. Any records already in the
+    /// log, left by an engine that stopped without a checkpoint, are replayed into the
+    /// database file first and the log is then emptied. Call this before any other use of
+    /// the engine.
+    pub fn with_wal(mut self, wal_path: &Path) -> Result<Self> {
+        let wal = WriteAheadLog::open(wal_path)?;
+        let records = WriteAheadLog::read_records(wal_path)?;
+        for record in &records {
+            self.replay_wal_record(&record.payload)?;
+        }
+        if !records.is_empty() {
+            // Opening may have cached pages, e.g. to rebuild the free-space directory,
+            // and the replay wrote past them. Nothing has been written through them yet.
+            self.buffer_pool.discard_all();
+            self.document_cache.clear();
+            self.rebuild_free_space_directory()?;
+        }
+        self.database_file.sync()?;
+        wal.truncate()?;
+        self.wal = Some(wal);
+        // A page changed by a write reaches the data file only after its log record
+        self.buffer_pool = self.buffer_pool.with_no_steal();
+        Ok(self)
+    }
+
+    /// Sets when writes checkpoint automatically. See `AutoCheckpoint` for the triggers.
+    pub fn with_auto_checkpoint(mut self, auto_checkpoint: AutoCheckpoint) -> Self {
+        self.auto_checkpoint = auto_checkpoint;
+        self
+    }
+
+    /// Returns the automatic checkpoint triggers in effect.
+    pub fn auto_checkpoint(&self) -> AutoCheckpoint {
+        self.auto_checkpoint
+    }
+
+    /// Bytes currently held by the write-ahead log, if there is one.
+    pub fn wal_size(&self) -> Option<u64> {
+        self.wal.as_ref().map(WriteAheadLog::size)
+    }
+
+    /// Writes back all dirty pages and, depending on the fsync policy, syncs the file.
+    ///
+    /// With a write-ahead log the file is always synced, since the log is emptied
+    /// afterwards and the pages must not depend on it any more.
+    pub fn checkpoint(&mut self) -> Result<()> {
+        self.buffer_pool.flush_all(&mut self.database_file)?;
+        self.database_file
+            .write_free_space_directory(self.free_space.buckets())?;
+        match &self.wal {
+            Some(wal) => {
+                self.database_file.sync()?;
+                wal.truncate()?;
+            }
+            None => self.database_file.checkpoint()?,
+        }
+        self.last_checkpoint = Instant::now();
+        Ok(())
+    }
+
+    // Records the free space of the pages the finished write changed and logs them,
+    // writes back dirty pages past the high-water mark, then checkpoints if a trigger
+    // is due
+    fn after_write(&mut self) -> Result<()> {
+        let page_ids = self.buffer_pool.take_modified();
+
+        let mut payload = Vec::new();
+        for page_id in page_ids {
+            let page = self
+                .buffer_pool
+                .pin_page(page_id, &mut self.database_file)?;
+            self.free_space
+                .set(page_id, page.get_free_space() as usize);
+            if self.wal.is_some() {
+                let checksum = page.calculate_checksum();
+                page.set_checksum(checksum);
+                payload.extend_from_slice(&page_id.to_le_bytes());
+                payload.extend_from_slice(&page.to_bytes());
+            }
+            self.buffer_pool.unpin_page(page_id, false);
+        }
+        if let Some(wal) = &self.wal
+            && !payload.is_empty()
+        {
+            wal.commit(&payload)?;
+        }
+        self.buffer_pool
+            .flush_excess_dirty(&mut self.database_file)?;
+        self.checkpoint_if_due()
+    }
+
+    /// Free bytes the free-space directory records for `page_id`, rounded down to a
+    /// multiple of `free_space::BUCKET_SIZE`, or `None` for a page it does not cover.
+    ///
+    /// Inserts consult the directory to find a page with room. It is updated after
+    /// every write and stored in the header page at each checkpoint.
+    pub fn free_space(&self, page_id: u64) -> Option<usize> {
+        self.free_space.free_space(page_id)
+    }
+
+    /// Rebuilds the free-space directory by reading every data page.
+    ///
+    /// Opening a database does this on its own when the stored directory is stale,
+    /// e.g. after a crash. A page that cannot be read, or that is on the free list, is
+    /// recorded as full, so inserts avoid it.
+    pub fn rebuild_free_space_directory(&mut self) -> Result<()> {
+        self.free_space.clear();
+        for page_id in self.database_file.data_page_ids() {
+            let free_bytes = match self.buffer_pool.pin_page(page_id, &mut self.database_file) {
+                Ok(page) => {
+                    // A page on the free list is handed out by `allocate_page`, not filled
+                    let free_bytes = match page.next_free_page() {
+                        Some(_) => 0,
+                        None => page.get_free_space() as usize,
+                    };
+                    self.buffer_pool.unpin_page(page_id, false);
+                    free_bytes
+                }
+                Err(DatabaseError::InvalidChecksum | DatabaseError::Corruption(_)) => 0,
+                // Past the end of a file that was cut short
+                Err(DatabaseError::Io(err)) if err.kind() == ErrorKind::UnexpectedEof => 0,
+                Err(err) => return Err(err),
+            };
+            self.free_space.set(page_id, free_bytes);
+        }
+        Ok(())
+    }
+
+    /// Reads every page of the file and reports what is wrong with it; see
+    /// `IntegrityProblem` for what is checked. Dirty pages are written back first, so
+    /// the check covers every write made so far. Changes nothing else.
+    pub fn check_integrity(&mut self) -> Result<IntegrityReport> {
+        self.buffer_pool.flush_all(&mut self.database_file)?;
+        let mut report = IntegrityReport::default();
+
+        let page_count = self.database_file.page_count();
+        let stored = self.database_file.stored_page_count()?.min(page_count);
+        if stored < page_count {
+            report.problems.push(IntegrityProblem::MissingPages {
+                first: stored,
+                count: page_count - stored,
+            });
+        }
+
+        let mut visited = HashSet::new();
+        let mut next = self.database_file.free_list_head();
+        while next != 0 {
+            let reason = if !(FIRST_DATA_PAGE_ID..stored).contains(&next) {
+                "not a data page of the file".to_string()
+            } else if !visited.insert(next) {
+                "the list loops back to it".to_string()
+            } else {
+                let page = self.database_file.read_page_unchecked(next)?;
+                match page.next_free_page() {
+                    Some(following) if page.verify_checksum() => {
+                        next = following;
+                        continue;
+                    }
+                    Some(_) => "checksum mismatch".to_string(),
+                    None => "the page is in use".to_string(),
+                }
+            };
+            report.problems.push(IntegrityProblem::BrokenFreeList { page_id: next, reason });
+            break;
+        }
+
+        for page_id in FIRST_DATA_PAGE_ID..stored {
+            let page = self.database_file.read_page_unchecked(page_id)?;
+            if let Some(reason) = Self::page_problem(&page) {
+                report.problems.push(IntegrityProblem::CorruptPage { page_id, reason });
+            }
+        }
+        Ok(report)
+    }
+
+    /// Checks the file like `check_integrity` and fixes what it found, returning that
+    /// report. Every fix only removes what cannot be read, never guesses at lost data;
+    /// see `IntegrityProblem` for what each one does. Afterwards the free-space
+    /// directory and any indexes are rebuilt and a checkpoint is taken.
+    pub fn repair(&mut self) -> Result<IntegrityReport> {
+        let report = self.check_integrity()?;
+        if report.is_clean() {
+            return Ok(report);
+        }
+        // The check wrote back every dirty page, so nothing cached is lost
+        self.buffer_pool.discard_all();
+        self.document_cache.clear();
+
+        for problem in &report.problems {
+            match problem {
+                IntegrityProblem::MissingPages { first, count } => {
+                    for page_id in *first..first + count {
+                        let page = PageLayout::new_page_with_layout(page_id, self.slot_layout());
+                        self.database_file.write_page(page_id, &page)?;
+                    }
+                }
+                IntegrityProblem::BrokenFreeList { .. } => {
+                    self.database_file.set_free_list_head(0)?;
+                }
+                IntegrityProblem::CorruptPage { page_id, .. } => {
+                    let page = self.database_file.read_page_unchecked(*page_id)?;
+                    // A damaged free page may have broken the list through it
+                    if page.next_free_page().is_some() {
+                        self.database_file.set_free_list_head(0)?;
+                    }
+                    let page = self.salvage_page(*page_id, page);
+                    self.database_file.write_page(*page_id, &page)?;
+                }
+            }
+        }
+
+        self.rebuild_free_space_directory()?;
+        self.reindex_all()?;
+        self.checkpoint()?;
+        Ok(report)
+    }
+
+    // Why `page` cannot be trusted, if it cannot
+    fn page_problem(page: &Page) -> Option<String> {
+        if !page.verify_checksum() {
+            return Some("checksum mismatch".to_string());
+        }
+        if page.next_free_page().is_some() {
+            return None;
+        }
+        let slots = match PageLayout::get_live_slots(page) {
+            Ok(slots) => slots,
+            Err(err) => return Some(format!("unreadable slot directory: {}", err)),
+        };
+        slots.into_iter().find_map(|slot_id| {
+            Self::decode_slot(page, slot_id)
+                .err()
+                .map(|err| format!("slot {}: {}", slot_id, err))
+        })
+    }
+
+    fn decode_slot(page: &Page, slot_id: SlotId) -> Result<Document> {
+        let stored = PageLayout::get_document_slice(page, slot_id)?;
+        Ok(deserialize_document(&compression::decode(stored)?)?)
+    }
+
+    // `page` with every document that does not decode removed and a fresh checksum, or
+    // an empty page if its slot directory is unreadable
+    fn salvage_page(&self, page_id: u64, mut page: Page) -> Page {
+        let slots = if page.next_free_page().is_some() {
+            None
+        } else {
+            PageLayout::get_live_slots(&page).ok()
+        };
+        let Some(slots) = slots else {
+            return PageLayout::new_page_with_layout(page_id, self.slot_layout());
+        };
+        for slot_id in slots {
+            if Self::decode_slot(&page, slot_id).is_err()
+                && PageLayout::delete_document(&mut page, slot_id).is_err()
+            {
+                return PageLayout::new_page_with_layout(page_id, self.slot_layout());
+            }
+        }
+        let checksum = page.calculate_checksum();
+        page.set_checksum(checksum);
+        page
+    }
+
+    fn checkpoint_if_due(&mut self) -> Result<()> {
+        let wal_full = match (&self.wal, self.auto_checkpoint.wal_bytes) {
+            (Some(wal), Some(max_bytes)) => wal.size() >= max_bytes,
+            _ => false,
+        };
+        let interval_passed = self
+            .auto_checkpoint
+            .interval
+            .is_some_and(|interval| self.last_checkpoint.elapsed() >= interval);
+        if wal_full || interval_passed {
+            self.checkpoint()?;
+        }
+        Ok(())
+    }
+
+    // Writes the page images of one log record into the database file
+    fn replay_wal_record(&mut self, payload: &[u8]) -> Result<()> {
+        let entries = payload.chunks(8 + PAGE_SIZE);
+        for entry in entries {
+            let (page_id, image) = entry.split_at(8.min(entry.len()));
+            let image: [u8; PAGE_SIZE] = image.try_into().map_err(|_| {
+                DatabaseError::Corruption(format!(
+                    "write-ahead log record holds a truncated page image of {} bytes",
+                    image.len()
+                ))
+            })?;
+            let page_id = u64::from_le_bytes(page_id.try_into().unwrap());
+            let page = Page::from_bytes(image)?;
+            while self.database_file.page_count() <= page_id {
+                self.database_file.allocate_page()?;
+            }
+            self.database_file.write_page(page_id, &page)?;
+        }
+        Ok(())
+    }
+
+    pub fn insert_document(&mut self, document: &Document) -> Result<DocumentId> {
+        self.insert_with_cache_policy(document, self.cache_policy)
+    }
+
+    fn insert_with_cache_policy(
+        &mut self,
+        document: &Document,
+        cache_policy: CachePolicy,
+    ) -> Result<DocumentId> {
+        self.validate(document)?;
+
+        let stamped;
+        let document = if self.timestamps {
+            let now = Self::now_millis();
+            stamped = {
+                let mut stamped = document.clone();
+                stamped.set(CREATED_FIELD, Value::DateTime(now));
+                stamped.set(UPDATED_FIELD, Value::DateTime(now));
+                stamped
+            };
+            &stamped
+        } else {
+            document
+        };
+
+        // 1. Serialize the document to BSON bytes. A document without fields still
+        // carries its `_id`, so it is never the empty slice PageLayout rejects.
+        let document_bytes = serialize_document(document)
+            .map_err(|e| DatabaseError::Document(format!("Failed to serialize document: {}", e)))?;
+
+        self.check_unique(document, None)?;
+
+        // 2. Store it, compressed if enabled, in the first page with room or a fresh page
+        let stored_bytes = compression::encode(&document_bytes, self.compression)?;
+        let document_id = self
+            .insert_document_internal(&stored_bytes)?
+            .tagged(document);
+
+        // 3. Keep secondary indexes and the document cache in sync
+        self.index_document(document_id, document);
+        self.document_cache.write(document_id, document, cache_policy);
+        self.changes.publish(ChangeEvent::Inserted(document_id));
+        self.after_write()?;
+
+        Ok(document_id)
+    }
+
+    pub fn get_document(&mut self, document_id: &DocumentId) -> Result<Document> {
+        if let Some(document) = self.document_cache.get(document_id) {
+            Self::verify_slot(document_id, document.id())?;
+            return Ok(document);
+        }
+
+        let page = self
+            .buffer_pool
+            .pin_page(document_id.page_id(), &mut self.database_file)?;
+        let document_bytes = PageLayout::get_document(page, document_id.slot_id());
+        self.buffer_pool.unpin_page(document_id.page_id(), false);
+
+        let document = deserialize_document(&compression::decode(&document_bytes?)?)?;
+        Self::verify_slot(document_id, document.id())?;
+        self.document_cache.insert(*document_id, document.clone());
+        Ok(document)
+    }
+
+    /// Returns the stored BSON bytes of a document without decoding it, e.g. to forward
+    /// them as-is. Only the `_id` is read, to verify ids handed out by the engine. A
+    /// compressed document is decompressed, so this is always plain BSON.
+    pub fn get_document_raw(
+        &mut self,
+        document_id: &DocumentId,
+    ) -> Result<Vec<u8>> {
+        let page = self
+            .buffer_pool
+            .pin_page(document_id.page_id(), &mut self.database_file)?;
+        let document_bytes = PageLayout::get_document(page, document_id.slot_id());
+        self.buffer_pool.unpin_page(document_id.page_id(), false);
+        let document_bytes = compression::decode(&document_bytes?)?.into_owned();
+
+        if document_id.object_id().is_some() {
+            let stored_id = peek_field(&document_bytes, "_id")
+                .map_err(|e| DatabaseError::Corruption(format!("undecodable _id: {}", e)))?;
+            Self::verify_slot(document_id, &stored_id.unwrap_or(Value::Null))?;
+        }
+        Ok(document_bytes)
+    }
+
+    /// Returns a reader over the BSON bytes of a document, read straight from its slot,
+    /// e.g. to decode it with a `BsonDecoder` without first copying it into a buffer.
+    ///
+    /// The reader borrows the engine, which keeps the page in memory while it is read.
+    /// A compressed document is decompressed as it is read, except that checking the
+    /// `_id` of an id the engine handed out decompresses it once up front. Documents are
+    /// never split across pages, so the reader covers a single slot.
+    pub fn read_document_streaming(
+        &mut self,
+        document_id: &DocumentId,
+    ) -> Result<impl Read + '_> {
+        let page = self
+            .buffer_pool
+            .get_page(document_id.page_id(), &mut self.database_file)?;
+        let stored = PageLayout::get_document_slice(page, document_id.slot_id())?;
+        if document_id.object_id().is_some() {
+            let stored_id = peek_field(&compression::decode(stored)?, "_id")
+                .map_err(|e| DatabaseError::Corruption(format!("undecodable _id: {}", e)))?;
+            Self::verify_slot(document_id, &stored_id.unwrap_or(Value::Null))?;
+        }
+        compression::reader(stored)
+    }
+
+    /// Returns the stored size of a document in bytes, read from its slot without
+    /// decoding or copying it. Only the `_id` is read, to verify ids handed out by the
+    /// engine. Documents are never split across pages, so this is all the space the
+    /// document takes apart from its slot entry. For a compressed document this is its
+    /// compressed size, and checking its `_id` means decompressing it.
+    pub fn document_size(
+        &mut self,
+        document_id: &DocumentId,
+    ) -> Result<usize> {
+        let page = self
+            .buffer_pool
+            .pin_page(document_id.page_id(), &mut self.database_file)?;
+        let size = PageLayout::get_document_slice(page, document_id.slot_id()).and_then(
+            |document_bytes| {
+                if document_id.object_id().is_some() {
+                    let stored_id = peek_field(&compression::decode(document_bytes)?, "_id")
+                        .map_err(|e| {
+                            DatabaseError::Corruption(format!("undecodable _id: {}", e))
+                        })?;
+                    Self::verify_slot(document_id, &stored_id.unwrap_or(Value::Null))?;
+                }
+                Ok(document_bytes.len())
+            },
+        );
+        self.buffer_pool.unpin_page(document_id.page_id(), false);
+        size
+    }
+
+    /// Fetches the document a `Value::DocumentRef` points at.
+    ///
+    /// A reference whose target was deleted, whose page no longer exists, or whose slot
+    /// now holds a different document fails with `DatabaseError::DanglingReference`.
+    /// Any other kind of value is a `DatabaseError::Validation` error.
+    pub fn resolve_ref(&mut self, value: &Value) -> Result<Document> {
+        let Value::DocumentRef(target) = value else {
+            return Err(DatabaseError::Validation(format!(
+                "cannot resolve a {} value, expected a document reference",
+                value.type_name()
+            )));
+        };
+        if !self.database_file.data_page_ids().contains(&target.page_id()) {
+            return Err(DatabaseError::DanglingReference(format!(
+                "{} points at a page that does not exist",
+                target
+            )));
+        }
+
+        let document_bytes = self.get_document_raw(target).map_err(|e| match e {
+            DatabaseError::Storage(msg) | DatabaseError::Corruption(msg) => {
+                DatabaseError::DanglingReference(format!("{}: {}", target, msg))
+            }
+            other => other,
+        })?;
+        deserialize_document(&document_bytes)
+            .map_err(|e| DatabaseError::Corruption(format!("undecodable document: {}", e)))
+    }
+
+    /// Stores an already serialized BSON document, returning its id.
+    ///
+    /// The bytes are checked to be one well-formed document and validated at the engine's
+    /// validation level, then stored exactly as given apart from compression: unlike
+    /// `insert_document`, no timestamps are added. Indexes are updated as usual.
+    pub fn insert_raw(&mut self, document_bytes: &[u8]) -> Result<DocumentId> {
+        let document = deserialize_document(document_bytes)
+            .map_err(|e| DatabaseError::Validation(format!("malformed BSON document: {}", e)))?;
+        let declared = document_bytes
+            .get(..4)
+            .map(|prefix| u32::from_le_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]));
+        if declared != Some(document_bytes.len() as u32) {
+            return Err(DatabaseError::Validation(format!(
+                "malformed BSON document: length prefix does not match the {} bytes given",
+                document_bytes.len()
+            )));
+        }
+        self.validate(&document)?;
+        self.check_unique(&document, None)?;
+
+        let stored_bytes = compression::encode(document_bytes, self.compression)?;
+        let document_id = self
+            .insert_document_internal(&stored_bytes)?
+            .tagged(&document);
+        self.index_document(document_id, &document);
+        self.document_cache.write(document_id, &document, self.cache_policy);
+        self.changes.publish(ChangeEvent::Inserted(document_id));
+        self.after_write()?;
+        Ok(document_id)
+    }
+
+    // A tagged id whose slot now holds another document was left dangling by a delete,
+    // move, or compaction and the slot has since been reused
+    fn verify_slot(
+        document_id: &DocumentId,
+        stored_id: &Value,
+    ) -> Result<()> {
+        match document_id.object_id() {
+            Some(expected) if *stored_id != Value::ObjectId(expected) => {
+                Err(DatabaseError::Corruption(format!(
+                    "stale document id: slot {} of page {} was issued for _id {} but holds _id {}",
+                    document_id.slot_id(), document_id.page_id(), expected, stored_id
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn update_document(
+        &mut self,
+        document_id: &DocumentId,
+        new_document: &Document,
+    ) -> Result<DocumentId> {
+        self.validate(new_document)?;
+
+        // Reading the current version also verifies that `document_id` still names it
+        let old_document = self.get_document(document_id)?;
+
+        let mut new_document = if self.timestamps {
+            Self::stamp_update(&old_document, new_document)
+        } else {
+            new_document.clone()
+        };
+        // Keep the stored `_id` so ids already handed out for this document stay valid
+        new_document.replace_id(old_document.id().clone());
+
+        self.check_unique(&new_document, Some(*document_id))?;
+
+        let new_document_id = match self.update_document_storage(document_id, &new_document) {
+            Ok(new_document_id) => new_document_id.tagged(&new_document),
+            Err(err) => {
+                // Whatever the failure left in the slot, the next read goes to the page
+                self.document_cache.remove(document_id);
+                return Err(err);
+            }
+        };
+
+        if !self.indexes.is_empty() {
+            self.unindex_document(*document_id, &old_document);
+            self.index_document(new_document_id, &new_document);
+        }
+        if new_document_id != *document_id {
+            self.document_cache.remove(document_id);
+        }
+        self.document_cache.write(new_document_id, &new_document, self.cache_policy);
+        self.changes.publish(ChangeEvent::Updated(new_document_id));
+        self.after_write()?;
+
+        Ok(new_document_id)
+    }
+
+    /// Overlays the fields of `changes` on the stored document with `Document::merge`
+    /// and stores the result, returning its id like `update_document`. Fields `changes`
+    /// does not mention, and the `_id`, are kept as stored.
+    pub fn update_fields(
+        &mut self,
+        document_id: &DocumentId,
+        changes: &Document,
+    ) -> Result<DocumentId> {
+        let mut document = self.get_document(document_id)?;
+        document.merge(changes);
+        self.update_document(document_id, &document)
+    }
+
+    /// Inserts `document`, or, if it collides with a stored document, lets `resolver`
+    /// decide between the two.
+    ///
+    /// A collision is a stored document with the same `_id`, or failing that, one with
+    /// the same value for a unique index. A replacement keeps the stored `_id`. Finding
+    /// an `_id` collision scans the whole collection unless `_id` is indexed, so index
+    /// it (see `create_unique_index`) before upserting or importing many documents.
+    pub fn upsert(
+        &mut self,
+        document: &Document,
+        resolver: &mut dyn ConflictResolver,
+    ) -> Result<UpsertOutcome> {
+        self.upsert_with_cache_policy(document, resolver, self.cache_policy)
+    }
+
+    fn upsert_with_cache_policy(
+        &mut self,
+        document: &Document,
+        resolver: &mut dyn ConflictResolver,
+        cache_policy: CachePolicy,
+    ) -> Result<UpsertOutcome> {
+        let Some(existing_id) = self.find_conflict(document)? else {
+            return self
+                .insert_with_cache_policy(document, cache_policy)
+                .map(UpsertOutcome::Inserted);
+        };
+
+        let existing = self.get_document(&existing_id)?;
+        match resolver.resolve(&existing, document) {
+            Resolution::Replace(resolved) => self
+                .update_document(&existing_id, &resolved)
+                .map(UpsertOutcome::Replaced),
+            Resolution::Skip => Ok(UpsertOutcome::Kept(existing_id)),
+        }
+    }
+
+    // The stored document `document` collides with, by `_id` or unique index
+    fn find_conflict(&mut self, document: &Document) -> Result<Option<DocumentId>> {
+        // Only an unindexed `_id` needs the scan
+        let found = match self.indexes.get("_id") {
+            Some(index) => index.get(document.id()).first().copied(),
+            None => {
+                let mut found = None;
+                self.scan_pages(&CancellationToken::new(), false, |document_id, stored| {
+                    if stored.id() == document.id() {
+                        found = Some(document_id);
+                        return ControlFlow::Break(());
+                    }
+                    ControlFlow::Continue(())
+                })?;
+                found
+            }
+        };
+        if found.is_some() {
+            return Ok(found);
+        }
+
+        Ok(self
+            .indexes
+            .values()
+            .filter(|index| index.is_unique())
+            .find_map(|index| {
+                let value = Self::index_key(document, index.field())?;
+                index.get(value).first().copied()
+            }))
+    }
+
+    /// Inserts `document` with `id` as its `_id`, whatever id it carried, so applications
+    /// can assign deterministic ids. Ids are only checked for uniqueness with a unique
+    /// index on `_id` (see `create_unique_index`); with one, an id already stored fails
+    /// with `DatabaseError::DuplicateKey`.
+    pub fn insert_with_id(&mut self, id: ObjectId, document: &Document) -> Result<DocumentId> {
+        let mut document = document.clone();
+        document.replace_id(Value::ObjectId(id));
+        self.insert_document(&document)
+    }
+
+    /// Inserts each document in order, returning their ids.
+    ///
+    /// Documents are validated at the engine's current level; for trusted bulk loads,
+    /// lower it first with `set_validation_level`. Stops at the first failure, leaving
+    /// the documents before it inserted. The documents are cached according to the
+    /// engine's bulk cache policy.
+    pub fn insert_many(&mut self, documents: &[Document]) -> Result<Vec<DocumentId>> {
+        self.insert_many_with_cache_policy(documents, self.bulk_cache_policy)
+    }
+
+    /// `insert_many` with an explicit cache policy, e.g. write-through for a batch that
+    /// is about to be read back.
+    pub fn insert_many_with_cache_policy(
+        &mut self,
+        documents: &[Document],
+        cache_policy: CachePolicy,
+    ) -> Result<Vec<DocumentId>> {
+        documents
+            .iter()
+            .map(|document| self.insert_with_cache_policy(document, cache_policy))
+            .collect()
+    }
+
+    fn validate(&self, document: &Document) -> Result<()> {
+        if self.validation_level == ValidationLevel::None {
+            return Ok(());
+        }
+
+        let mut validator = DocumentValidator::new();
+        if self.timestamps {
+            validator = validator.with_timestamp_fields();
+        }
+        let result = match self.validation_level {
+            ValidationLevel::Cheap => validator
+                .validate_size(document)
+                .and_then(|()| validator.validate_storable_names(document)),
+            _ => validator.validate_document(document),
+        };
+        Ok(result?)
     }
 
-    /// Get the page ID where the document is stored
-    pub fn page_id(&self) -> u64 {
-        self.page_id
+    // BSON stores DateTime with millisecond precision; truncate so stored and returned values agree
+    fn now_millis() -> DateTime<Utc> {
+        let now = Utc::now();
+        DateTime::from_timestamp_millis(now.timestamp_millis()).unwrap_or(now)
+    }
+
+    // Carries `_created` over from the stored version and advances `_updated`
+    fn stamp_update(old_document: &Document, new_document: &Document) -> Document {
+        let mut stamped = new_document.clone();
+        match old_document.get(CREATED_FIELD) {
+            Some(created) => stamped.set(CREATED_FIELD, created.clone()),
+            None => {
+                stamped.remove(CREATED_FIELD);
+            }
+        }
+
+        let mut updated = Self::now_millis();
+        if let Some(previous) = old_document.updated_at()
+            && updated <= previous
+        {
+            updated = previous + Duration::milliseconds(1);
+        }
+        stamped.set(UPDATED_FIELD, Value::DateTime(updated));
+        stamped
+    }
+
+    fn update_document_storage(
+        &mut self,
+        document_id: &DocumentId,
+        new_document: &Document,
+    ) -> Result<DocumentId> {
+        // 1. Serialize the new document, compressing it if enabled
+        let new_document_bytes = serialize_document(new_document)
+            .map_err(|e| DatabaseError::Document(format!("Failed to serialize document: {}", e)))?;
+        let new_document_bytes = compression::encode(&new_document_bytes, self.compression)?;
+        let new_size = new_document_bytes.len();
+
+        // 2. Pin the original page
+        let page = self
+            .buffer_pool
+            .pin_page(document_id.page_id(), &mut self.database_file)?;
+
+        // 3. Get the old document size for comparison
+        let old_document_bytes = PageLayout::get_document(page, document_id.slot_id())?;
+        let old_size = old_document_bytes.len();
+
+        // 4. Check if new document fits in the same slot
+        if new_size <= old_size {
+            // Case 1: New document fits in same slot (in-place update)
+            PageLayout::update_document(page, document_id.slot_id(), &new_document_bytes)?;
+            self.buffer_pool.unpin_page(document_id.page_id(), true); // Mark as dirty
+            Ok(*document_id) // Return same DocumentId
+        } else {
+            // Case 2: New document doesn't fit, need to relocate
+
+            // First, check if the same page has enough free space
+            let available_space = page.get_free_space() as usize;
+            if new_size <= available_space + old_size {
+                // Can fit on same page after deleting old document
+                PageLayout::delete_document(page, document_id.slot_id())?;
+                let new_slot_id = PageLayout::insert_document(page, &new_document_bytes)?;
+                self.buffer_pool.unpin_page(document_id.page_id(), true);
+
+                Ok(DocumentId::new(document_id.page_id(), new_slot_id))
+            } else {
+                // Need to move to different page. The new version is stored before the
+                // old slot is tombstoned, so a failed insert, e.g. of a document too large
+                // for any page, leaves the original in place.
+                self.buffer_pool.unpin_page(document_id.page_id(), false);
+                let new_document_id = self.insert_document_internal(&new_document_bytes)?;
+
+                let page = self
+                    .buffer_pool
+                    .pin_page(document_id.page_id(), &mut self.database_file)?;
+                let deleted = PageLayout::delete_document(page, document_id.slot_id());
+                self.buffer_pool.unpin_page(document_id.page_id(), true);
+                deleted?;
+                Ok(new_document_id)
+            }
+        }
+    }
+
+    /// Relocates a document, e.g. to defragment or group related documents, returning its new id.
+    ///
+    /// The stored bytes are copied unchanged, so timestamps are not touched. The original
+    /// slot is tombstoned only after the copy succeeds, and indexes follow the document.
+    /// Subscribers see the move as an update with the new id. Moving a document to the
+    /// page it is already on leaves it where it is.
+    pub fn move_document(
+        &mut self,
+        document_id: &DocumentId,
+        target: MoveTarget,
+    ) -> Result<DocumentId> {
+        let page = self
+            .buffer_pool
+            .pin_page(document_id.page_id(), &mut self.database_file)?;
+        let document_bytes = PageLayout::get_document(page, document_id.slot_id());
+        self.buffer_pool.unpin_page(document_id.page_id(), false);
+        let document_bytes = document_bytes?;
+        let document = deserialize_document(&compression::decode(&document_bytes)?)?;
+        Self::verify_slot(document_id, document.id())?;
+
+        let target_page_id = match target {
+            MoveTarget::Page(page_id) if page_id == document_id.page_id() => {
+                return Ok(*document_id);
+            }
+            MoveTarget::Page(page_id) if !self.database_file.data_page_ids().contains(&page_id) => {
+                return Err(DatabaseError::Storage(format!(
+                    "Cannot move document to page {}: data pages are {:?}",
+                    page_id,
+                    self.database_file.data_page_ids()
+                )));
+            }
+            MoveTarget::Page(page_id) => page_id,
+            MoveTarget::NewPage => self.allocate_page()?,
+        };
+
+        let page = self
+            .buffer_pool
+            .pin_page(target_page_id, &mut self.database_file)?;
+        if page.next_free_page().is_some() {
+            self.buffer_pool.unpin_page(target_page_id, false);
+            return Err(DatabaseError::Storage(format!(
+                "Cannot move document to page {}: the page is on the free list",
+                target_page_id
+            )));
+        }
+        let inserted = PageLayout::insert_document(page, &document_bytes);
+        self.buffer_pool.unpin_page(target_page_id, inserted.is_ok());
+        let new_document_id = DocumentId::new(target_page_id, inserted?).tagged(&document);
+
+        let page = self
+            .buffer_pool
+            .pin_page(document_id.page_id(), &mut self.database_file)?;
+        PageLayout::delete_document(page, document_id.slot_id())?;
+        self.buffer_pool.unpin_page(document_id.page_id(), true);
+        self.document_cache.remove(document_id);
+        self.document_cache.remove(&new_document_id);
+
+        if !self.indexes.is_empty() {
+            self.unindex_document(*document_id, &document);
+            self.index_document(new_document_id, &document);
+        }
+        self.changes.publish(ChangeEvent::Updated(new_document_id));
+        self.after_write()?;
+
+        Ok(new_document_id)
+    }
+
+    pub fn delete_document(&mut self, document_id: &DocumentId) -> Result<()> {
+        let old_document = self.get_document_if_needed(document_id)?;
+
+        // 1. Pin the page containing the document
+        let page = self
+            .buffer_pool
+            .pin_page(document_id.page_id(), &mut self.database_file)?;
+
+        // 2. Mark the document slot as deleted (tombstone)
+        PageLayout::delete_document(page, document_id.slot_id())?;
+
+        // 3. Mark page as dirty and unpin
+        self.buffer_pool.unpin_page(document_id.page_id(), true);
+        self.document_cache.remove(document_id);
+
+        // 4. Drop the document from secondary indexes
+        if let Some(old_document) = old_document {
+            self.unindex_document(*document_id, &old_document);
+        }
+        self.changes.publish(ChangeEvent::Deleted(*document_id));
+        self.after_write()?;
+
+        Ok(())
+    }
+
+    /// Imports a `.bson` dump: documents stored back to back, each starting with its
+    /// little-endian 4-byte length, as written by `serialize_document` and other tools.
+    ///
+    /// Reads until EOF, inserting each document as it goes. A document that cannot be
+    /// decoded or inserted is recorded in the report with its byte offset and skipped.
+    /// A bad length prefix or a truncated final document is recorded too, but ends the
+    /// import, since the following document boundaries are lost. Fails only if reading
+    /// the input does. Imported documents are cached according to the bulk cache policy.
+    pub fn import_bson<R: Read>(
+        &mut self,
+        reader: R,
+    ) -> Result<ImportReport> {
+        self.import_bson_with(reader, |engine, document| {
+            engine
+                .insert_with_cache_policy(document, engine.bulk_cache_policy)
+                .map(UpsertOutcome::Inserted)
+        })
+    }
+
+    /// `import_bson` that upserts each document instead of inserting it, so a document
+    /// colliding with a stored one by `_id` or unique index goes to `resolver`.
+    ///
+    /// Stored documents the resolver keeps are listed in the report's `kept`; replaced
+    /// ones are listed in `imported` with the rest.
+    pub fn import_bson_with_resolver<R: Read>(
+        &mut self,
+        reader: R,
+        resolver: &mut dyn ConflictResolver,
+    ) -> Result<ImportReport> {
+        self.import_bson_with(reader, |engine, document| {
+            engine.upsert_with_cache_policy(document, resolver, engine.bulk_cache_policy)
+        })
+    }
+
+    fn import_bson_with<R, F>(
+        &mut self,
+        mut reader: R,
+        mut store: F,
+    ) -> Result<ImportReport>
+    where
+        R: Read,
+        F: FnMut(&mut Self, &Document) -> Result<UpsertOutcome>,
+    {
+        let mut report = ImportReport::default();
+        let mut offset = 0u64;
+
+        loop {
+            let mut length_bytes = [0u8; 4];
+            let read = Self::read_up_to(&mut reader, &mut length_bytes)?;
+            if read == 0 {
+                break;
+            }
+            let fail = |message: String| ImportError { offset, message };
+            if read < length_bytes.len() {
+                report
+                    .errors
+                    .push(fail(format!("truncated length prefix: {} of 4 bytes", read)));
+                break;
+            }
+
+            let length = u32::from_le_bytes(length_bytes) as usize;
+            if !(5..=MAX_DOCUMENT_SIZE).contains(&length) {
+                report
+                    .errors
+                    .push(fail(format!("invalid document length {}", length)));
+                break;
+            }
+
+            let mut document_bytes = vec![0u8; length];
+            document_bytes[..4].copy_from_slice(&length_bytes);
+            let read = Self::read_up_to(&mut reader, &mut document_bytes[4..])?;
+            if read < length - 4 {
+                report.errors.push(fail(format!(
+                    "truncated document: expected {} bytes, found {}",
+                    length,
+                    read + 4
+                )));
+                break;
+            }
+
+            let stored = deserialize_document(&document_bytes)
+                .map_err(|e| e.to_string())
+                .and_then(|document| store(self, &document).map_err(|e| e.to_string()));
+            match stored {
+                Ok(UpsertOutcome::Kept(document_id)) => report.kept.push(document_id),
+                Ok(outcome) => report.imported.push(outcome.document_id()),
+                Err(message) => report.errors.push(fail(message)),
+            }
+            offset += length as u64;
+        }
+
+        Ok(report)
     }
 
-    /// Get the slot ID within the page where the document is stored
-    pub fn slot_id(&self) -> u16 {
-        self.slot_id
+    // Fills `buf` unless EOF comes first, returning how many bytes were read
+    fn read_up_to<R: Read>(reader: &mut R, buf: &mut [u8]) -> std::io::Result<usize> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            match reader.read(&mut buf[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(filled)
     }
-}
 
-pub struct StorageEngine {
-    pub database_file: DatabaseFile,
-    buffer_pool: BufferPool,
-}
+    /// Builds a sorted index on `field` (a dotted path) over all existing documents.
+    ///
+    /// The index is kept up to date by later inserts, updates, and deletes. Documents
+    /// without the field are not indexed. Creating an index that already exists
+    /// rebuilds it.
+    pub fn create_index(&mut self, field: &str) -> Result<()> {
+        let index = self.build_index(field, false)?;
+        self.indexes.insert(field.to_string(), index);
+        Ok(())
+    }
 
-impl StorageEngine {
-    pub fn new(database_path: &Path, buffer_pool_size: usize) -> Result<Self> {
-        let database_file = DatabaseFile::open(database_path)?;
-        let buffer_pool = BufferPool::new(buffer_pool_size);
-        Ok(Self {
-            database_file,
-            buffer_pool,
-        })
+    /// Builds a unique index on `field`, after which inserts and updates that would give
+    /// two documents the same value fail with `DatabaseError::DuplicateKey`.
+    ///
+    /// Fails with `DuplicateKey` if existing documents already share a value. Documents
+    /// without the field are not constrained.
+    pub fn create_unique_index(&mut self, field: &str) -> Result<()> {
+        let index = self.build_index(field, true)?;
+        self.indexes.insert(field.to_string(), index);
+        Ok(())
     }
 
-    pub fn insert_document(&mut self, document: &Document) -> Result<DocumentId> {
-        // 1. Serialize the document to BSON bytes
-        let document_bytes = serialize_document(document)
-            .map_err(|e| anyhow::anyhow!("Failed to serialize document: {}", e))?;
-        let document_size = document_bytes.len();
+    /// Drops the index on `field` and rebuilds it from the stored documents, e.g. to
+    /// recover from an index that no longer matches the data.
+    ///
+    /// A unique index stays unique. If the rebuild fails, for instance because
+    /// documents now share a value of a unique index, the field is left without an
+    /// index. Fails with `DatabaseError::Index` if `field` has no index.
+    pub fn reindex(&mut self, field: &str) -> Result<()> {
+        let Some(index) = self.indexes.remove(field) else {
+            return Err(DatabaseError::Index(format!("No index on field '{}'", field)));
+        };
+        let index = self.build_index(field, index.is_unique())?;
+        self.indexes.insert(field.to_string(), index);
+        Ok(())
+    }
 
-        // 2. Try to find an existing page with enough free space
-        let page_ids = self.buffer_pool.get_all_page_ids();
-        for page_id in page_ids {
-            // Pin the page to get mutable access
-            if let Ok(page) = self.buffer_pool.pin_page(page_id, &mut self.database_file) {
-                let free_space = page.get_free_space() as usize;
+    /// Rebuilds every index with `reindex`, stopping at the first failure.
+    pub fn reindex_all(&mut self) -> Result<()> {
+        let fields: Vec<String> = self.indexes.keys().cloned().collect();
+        for field in fields {
+            self.reindex(&field)?;
+        }
+        Ok(())
+    }
 
-                // Check if document can fit in this page
-                if document_size <= free_space {
-                    // Insert the document using PageLayout
-                    match PageLayout::insert_document(page, &document_bytes) {
-                        Ok(slot_id) => {
-                            // Mark the page as dirty and unpin it
-                            self.buffer_pool.unpin_page(page_id, true); // true = is_dirty
-                            return Ok(DocumentId {
-                                page_id: page_id,
-                                slot_id,
-                            });
-                        }
-                        Err(_) => {
-                            // Failed to insert, unpin the page without marking dirty
-                            self.buffer_pool.unpin_page(page_id, false);
-                            continue;
-                        }
-                    }
+    fn build_index(&mut self, field: &str, unique: bool) -> Result<OrderedIndex> {
+        let mut index = if unique {
+            OrderedIndex::new_unique(field)
+        } else {
+            OrderedIndex::new(field)
+        };
+        for (document_id, document) in self.scan_all()? {
+            if let Some(value) = Self::index_key(&document, field) {
+                if index.violates_unique(value, Some(document_id)) {
+                    return Err(DatabaseError::DuplicateKey {
+                        field: field.to_string(),
+                        value: value.clone(),
+                    });
                 }
-                // Page doesn't have enough space, unpin it
-                self.buffer_pool.unpin_page(page_id, false);
+                index.insert(value, document_id);
             }
         }
+        Ok(index)
+    }
 
-        // Page doesen't exist, or not enough space? Allocate more space and insert a fresh page.
-        let new_page_id = self.database_file.allocate_page()?;
+    /// Returns the index on `field`, if one has been created.
+    pub fn index(&self, field: &str) -> Option<&OrderedIndex> {
+        self.indexes.get(field)
+    }
 
-        let page = self
-            .buffer_pool
-            .pin_page(new_page_id, &mut self.database_file)?;
+    /// Size and selectivity of every index, ordered by field, to help decide which
+    /// indexes are worth keeping. A low selectivity means each lookup still matches
+    /// a large share of the indexed documents.
+    pub fn index_stats(&self) -> Vec<IndexStats> {
+        self.indexes.values().map(OrderedIndex::stats).collect()
+    }
 
-        let slot_id = PageLayout::insert_document(page, &document_bytes)?;
+    /// Returns documents whose `field` equals `value`, or whose array `field` contains
+    /// it, using the index on `field` when there is one. See `find_range`.
+    pub fn find_by_field(
+        &mut self,
+        field: &str,
+        value: &Value,
+    ) -> Result<Vec<(DocumentId, Document)>> {
+        self.find_range(field, Some(value.clone()), Some(value.clone()), (true, true))
+    }
 
-        self.buffer_pool.unpin_page(new_page_id, true);
+    /// Returns documents whose `field` lies between `lo` and `hi`, ordered by that field.
+    ///
+    /// A `None` bound leaves that end open, and `inclusive` says whether each bound
+    /// matches itself. Uses the index on `field` when there is one; otherwise falls back
+    /// to a full scan with the same ordering rules (see `IndexKey`), returned in
+    /// physical order. An array field matches when any of its elements is in range, as
+    /// with a multikey index, and the document is returned once.
+    pub fn find_range(
+        &mut self,
+        field: &str,
+        lo: Option<Value>,
+        hi: Option<Value>,
+        inclusive: (bool, bool),
+    ) -> Result<Vec<(DocumentId, Document)>> {
+        self.find_range_cancellable(field, lo, hi, inclusive, &CancellationToken::new())
+    }
 
-        Ok(DocumentId {
-            page_id: new_page_id,
-            slot_id: slot_id,
-        })
+    /// `find_range` that stops with `DatabaseError::Cancelled` once `cancel` is set.
+    ///
+    /// The token is checked before each page of a fallback scan, and before each
+    /// document fetched through an index.
+    pub fn find_range_cancellable(
+        &mut self,
+        field: &str,
+        lo: Option<Value>,
+        hi: Option<Value>,
+        inclusive: (bool, bool),
+        cancel: &CancellationToken,
+    ) -> Result<Vec<(DocumentId, Document)>> {
+        self.find_range_bounded(field, lo, hi, inclusive, cancel, &QueryOptions::default())
     }
 
-    pub fn get_document(&mut self, document_id: &DocumentId) -> Result<Document> {
-        let page = self
-            .buffer_pool
-            .pin_page(document_id.page_id, &mut self.database_file)?;
-        let document_bytes = PageLayout::get_document(page, document_id.slot_id)?;
-        self.buffer_pool.unpin_page(document_id.page_id(), false);
+    /// `find_range` bounded by `options`. See `QueryOptions` for how `limit` and
+    /// `max_buffered_docs` interact.
+    ///
+    /// Through an index the cap is checked before any document is read; a fallback scan
+    /// stops as soon as the limit is reached or the cap is exceeded.
+    pub fn find_range_with_options(
+        &mut self,
+        field: &str,
+        lo: Option<Value>,
+        hi: Option<Value>,
+        inclusive: (bool, bool),
+        options: &QueryOptions,
+    ) -> Result<Vec<(DocumentId, Document)>> {
+        let cancel = CancellationToken::new();
+        self.find_range_bounded(field, lo, hi, inclusive, &cancel, options)
+    }
 
-        Ok(deserialize_document(&document_bytes)?)
+    fn find_range_bounded(
+        &mut self,
+        field: &str,
+        lo: Option<Value>,
+        hi: Option<Value>,
+        inclusive: (bool, bool),
+        cancel: &CancellationToken,
+        options: &QueryOptions,
+    ) -> Result<Vec<(DocumentId, Document)>> {
+        let Some(index) = self.indexes.get(field) else {
+            return self.collect_bounded(cancel, options, |document| {
+                Self::index_key(document, field).is_some_and(|value| {
+                    OrderedIndex::keys(value).into_iter().any(|key| {
+                        OrderedIndex::in_range(key, lo.as_ref(), hi.as_ref(), inclusive)
+                    })
+                })
+            });
+        };
+
+        let mut document_ids = index.range(lo.as_ref(), hi.as_ref(), inclusive);
+        if let Some(limit) = options.limit {
+            document_ids.truncate(limit);
+        }
+        if let Some(max_buffered) = options.max_buffered_docs
+            && document_ids.len() > max_buffered
+        {
+            return Err(DatabaseError::TooManyResults { max_buffered });
+        }
+        let mut documents = Vec::with_capacity(document_ids.len());
+        for document_id in document_ids {
+            cancel.check()?;
+            let document = self.get_document(&document_id)?;
+            documents.push((document_id, document));
+        }
+        Ok(documents)
     }
 
-    pub fn update_document(
+    /// Returns every document satisfying `predicate`, in physical order, bounded by
+    /// `options`. Always a full scan; predicates do not use indexes.
+    pub fn find_matching(
         &mut self,
-        document_id: &DocumentId,
-        new_document: &Document,
-    ) -> Result<DocumentId> {
-        // 1. Serialize the new document
-        let new_document_bytes = serialize_document(new_document)
-            .map_err(|e| anyhow::anyhow!("Failed to serialize document: {}", e))?;
-        let new_size = new_document_bytes.len();
+        predicate: &Predicate,
+        options: &QueryOptions,
+    ) -> Result<Vec<(DocumentId, Document)>> {
+        self.collect_bounded(&CancellationToken::new(), options, |document| {
+            predicate.matches(document)
+        })
+    }
 
-        // 2. Pin the original page
-        let page = self
-            .buffer_pool
-            .pin_page(document_id.page_id, &mut self.database_file)?;
+    /// Folds every document satisfying `predicate`, or every document without one, into
+    /// an accumulator, in physical order, without buffering them. Counts, sums and the
+    /// like are built on this; see `sum_field` and friends. Always a full scan.
+    pub fn aggregate<T, F>(
+        &mut self,
+        predicate: Option<&Predicate>,
+        init: T,
+        mut fold: F,
+    ) -> Result<T>
+    where
+        F: FnMut(T, &Document) -> T,
+    {
+        let mut accumulator = Some(init);
+        self.scan_pages(&CancellationToken::new(), false, |_, document| {
+            if predicate.is_none_or(|predicate| predicate.matches(&document)) {
+                let current = accumulator.take().expect("accumulator is always restored");
+                accumulator = Some(fold(current, &document));
+            }
+            ControlFlow::Continue(())
+        })?;
+        Ok(accumulator.expect("accumulator is always restored"))
+    }
 
-        // 3. Get the old document size for comparison
-        let old_document_bytes = PageLayout::get_document(page, document_id.slot_id)?;
-        let old_size = old_document_bytes.len();
+    /// Every document satisfying `predicate`, or every document without one, as JSON
+    /// (see `Document::to_json`), in physical order. Non-finite floats use the Extended
+    /// JSON form, so no document fails to convert.
+    ///
+    /// Meant for dashboards and debugging on small collections: the whole result is
+    /// built in memory, taking several times the collection's stored size.
+    pub fn dump_json(&mut self, predicate: Option<&Predicate>) -> Result<Vec<serde_json::Value>> {
+        let mut dump = Vec::new();
+        let mut failure = None;
+        self.scan_pages(&CancellationToken::new(), false, |_, document| {
+            if predicate.is_some_and(|predicate| !predicate.matches(&document)) {
+                return ControlFlow::Continue(());
+            }
+            match document.to_json(NonFiniteFloatMode::ExtendedJson) {
+                Ok(json) => {
+                    dump.push(json);
+                    ControlFlow::Continue(())
+                }
+                Err(err) => {
+                    failure = Some(err);
+                    ControlFlow::Break(())
+                }
+            }
+        })?;
+        if let Some(err) = failure {
+            return Err(err);
+        }
+        Ok(dump)
+    }
 
-        // 4. Check if new document fits in the same slot
-        if new_size <= old_size {
-            // Case 1: New document fits in same slot (in-place update)
-            PageLayout::update_document(page, document_id.slot_id, &new_document_bytes)?;
-            self.buffer_pool.unpin_page(document_id.page_id, true); // Mark as dirty
-            Ok(*document_id) // Return same DocumentId
-        } else {
-            // Case 2: New document doesn't fit, need to relocate
+    /// Sum of the numeric values at `path` over the documents matching `predicate`.
+    /// Documents where the field is missing or not a number are skipped, so the sum of
+    /// no values is 0.
+    pub fn sum_field(&mut self, predicate: Option<&Predicate>, path: &str) -> Result<f64> {
+        self.aggregate(predicate, 0.0, |sum, document| {
+            sum + Self::numeric_field(document, path).unwrap_or(0.0)
+        })
+    }
 
-            // First, check if the same page has enough free space
-            let available_space = page.get_free_space() as usize;
-            if new_size <= available_space + old_size {
-                // Can fit on same page after deleting old document
-                PageLayout::delete_document(page, document_id.slot_id)?;
-                let new_slot_id = PageLayout::insert_document(page, &new_document_bytes)?;
-                self.buffer_pool.unpin_page(document_id.page_id, true);
+    /// Mean of the numeric values at `path` over the documents matching `predicate`,
+    /// skipping documents as `sum_field` does. `None` when no document has a number there.
+    pub fn avg_field(
+        &mut self,
+        predicate: Option<&Predicate>,
+        path: &str,
+    ) -> Result<Option<f64>> {
+        let (sum, count) = self.aggregate(predicate, (0.0, 0usize), |(sum, count), document| {
+            match Self::numeric_field(document, path) {
+                Some(value) => (sum + value, count + 1),
+                None => (sum, count),
+            }
+        })?;
+        Ok((count > 0).then(|| sum / count as f64))
+    }
 
-                Ok(DocumentId::new(document_id.page_id, new_slot_id))
-            } else {
-                // Need to move to different page
+    /// Smallest value at `path` over the documents matching `predicate`, by
+    /// `Value::total_cmp`. Missing and null fields are skipped.
+    pub fn min_field(
+        &mut self,
+        predicate: Option<&Predicate>,
+        path: &str,
+    ) -> Result<Option<Value>> {
+        self.extreme_field(predicate, path, Ordering::Less)
+    }
 
-                // Mark old slot as deleted (tombstone)
-                PageLayout::delete_document(page, document_id.slot_id)?;
-                self.buffer_pool.unpin_page(document_id.page_id, true);
+    /// Largest value at `path` over the documents matching `predicate`, by
+    /// `Value::total_cmp`. Missing and null fields are skipped.
+    pub fn max_field(
+        &mut self,
+        predicate: Option<&Predicate>,
+        path: &str,
+    ) -> Result<Option<Value>> {
+        self.extreme_field(predicate, path, Ordering::Greater)
+    }
 
-                // Insert into new location (reuse insert_document logic)
-                self.insert_document_internal(&new_document_bytes)
+    // The value that compares `wanted` against every other; the first one seen on ties
+    fn extreme_field(
+        &mut self,
+        predicate: Option<&Predicate>,
+        path: &str,
+        wanted: Ordering,
+    ) -> Result<Option<Value>> {
+        self.aggregate(predicate, None, |best: Option<Value>, document| {
+            match document.get_path(path) {
+                Some(value)
+                    if !value.is_null()
+                        && best.as_ref().is_none_or(|best| value.total_cmp(best) == wanted) =>
+                {
+                    Some(value.clone())
+                }
+                _ => best,
             }
+        })
+    }
+
+    fn numeric_field(document: &Document, path: &str) -> Option<f64> {
+        document
+            .get_path(path)
+            .filter(|value| value.is_number())
+            .and_then(Value::as_f64)
+    }
+
+    // Reads the current version of a document only when indexes need its old values
+    // or a tagged id has to be verified
+    fn get_document_if_needed(&mut self, document_id: &DocumentId) -> Result<Option<Document>> {
+        if self.indexes.is_empty() && document_id.object_id().is_none() {
+            return Ok(None);
         }
+        self.get_document(document_id).map(Some)
     }
 
-    pub fn delete_document(&mut self, document_id: &DocumentId) -> Result<()> {
-        // 1. Pin the page containing the document
-        let page = self
-            .buffer_pool
-            .pin_page(document_id.page_id, &mut self.database_file)?;
+    // `document_id` is the document being replaced, which may keep its own values
+    fn check_unique(
+        &self,
+        document: &Document,
+        document_id: Option<DocumentId>,
+    ) -> Result<()> {
+        for index in self.indexes.values() {
+            if let Some(value) = Self::index_key(document, index.field())
+                && index.violates_unique(value, document_id)
+            {
+                return Err(DatabaseError::DuplicateKey {
+                    field: index.field().to_string(),
+                    value: value.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
 
-        // 2. Mark the document slot as deleted (tombstone)
-        PageLayout::delete_document(page, document_id.slot_id)?;
+    // The value an index on `field` holds for `document`. `_id` is kept apart from the
+    // other fields, so it is looked up separately.
+    fn index_key<'a>(document: &'a Document, field: &str) -> Option<&'a Value> {
+        if field == "_id" {
+            Some(document.id())
+        } else {
+            document.get_path(field)
+        }
+    }
 
-        // 3. Mark page as dirty and unpin
-        self.buffer_pool.unpin_page(document_id.page_id, true);
+    fn index_document(&mut self, document_id: DocumentId, document: &Document) {
+        for index in self.indexes.values_mut() {
+            if let Some(value) = Self::index_key(document, index.field()) {
+                index.insert(value, document_id);
+            }
+        }
+    }
 
-        Ok(())
+    fn unindex_document(&mut self, document_id: DocumentId, document: &Document) {
+        for index in self.indexes.values_mut() {
+            if let Some(value) = Self::index_key(document, index.field()) {
+                index.remove(value, document_id);
+            }
+        }
     }
 
     // Compacts pages and cleans tombstones. Returns number of pages cleaned.
     pub fn vacuum(&mut self) -> Result<usize> {
-        self.buffer_pool.flush_all(&mut self.database_file)?; // Clear buffer_pool (LRU cache) before reformatting.
+        self.vacuum_cancellable(&CancellationToken::new())
+    }
+
+    /// `vacuum` that stops with `DatabaseError::Cancelled` once `cancel` is set.
+    ///
+    /// Checked before each page; pages already compacted stay compacted.
+    pub fn vacuum_cancellable(&mut self, cancel: &CancellationToken) -> Result<usize> {
+        self.buffer_pool.clear(&mut self.database_file)?; // Clear buffer_pool (LRU cache) before reformatting.
 
-        let total_pages = self.database_file.page_count();
         let mut pages_cleaned: usize = 0;
-        for page_id in 0..total_pages {
+        for page_id in self.database_file.data_page_ids() {
+            cancel.check()?;
             let mut page = self.database_file.read_page(page_id)?;
+            if page.next_free_page().is_some() {
+                continue;
+            }
             let was_compacted = PageLayout::compact_page(&mut page)?;
             if was_compacted {
                 let checksum = page.calculate_checksum(); // Since bytes are changed, recompute CRC32 hash to ensure data integrity.
                 page.set_checksum(checksum);
                 self.database_file.write_page(page_id, &page)?;
+                self.free_space.set(page_id, page.get_free_space() as usize);
                 pages_cleaned += 1;
             }
         }
 
+        // Pages were rewritten behind the log's back, so retire the images it holds
+        if self.wal.is_some() {
+            self.checkpoint()?;
+        }
+
         Ok(pages_cleaned)
     }
 
-    // Helper function to avoid code duplication
+    /// Compacts at most `max_pages` pages, continuing from where the previous call stopped.
+    ///
+    /// Unlike `vacuum`, which rewrites every page in one go, this lets background
+    /// maintenance spread compaction over many short ticks. Pages are compacted in the
+    /// buffer pool and written back like any other dirty page, and slot ids are kept, so
+    /// document ids stay valid. After the last page the next call wraps around to the first data page.
+    pub fn compact_step(&mut self, max_pages: usize) -> Result<CompactionProgress> {
+        let page_count = self.database_file.page_count();
+        // The file may have shrunk since the last step
+        if self.compaction_cursor >= page_count {
+            self.compaction_cursor = FIRST_DATA_PAGE_ID;
+        }
+
+        let mut progress = CompactionProgress::default();
+        while progress.pages_visited < max_pages && self.compaction_cursor < page_count {
+            let page_id = self.compaction_cursor;
+            let page = self
+                .buffer_pool
+                .pin_page(page_id, &mut self.database_file)?;
+            let was_compacted = match page.next_free_page() {
+                Some(_) => Ok(false),
+                None => PageLayout::compact_page(page),
+            };
+            self.buffer_pool
+                .unpin_page(page_id, matches!(was_compacted, Ok(true)));
+            if was_compacted? {
+                progress.pages_compacted += 1;
+            }
+            progress.pages_visited += 1;
+            self.compaction_cursor += 1;
+        }
+
+        if self.compaction_cursor >= page_count {
+            self.compaction_cursor = FIRST_DATA_PAGE_ID;
+            progress.pass_complete = true;
+        }
+        self.after_write()?;
+        Ok(progress)
+    }
+
+    /// The page the next `compact_step` will start from.
+    pub fn compaction_cursor(&self) -> u64 {
+        self.compaction_cursor
+    }
+
+    /// Ids of every data page, in file order, for storage inspection tools.
+    pub fn page_ids(&self) -> Vec<u64> {
+        self.database_file.data_page_ids().collect()
+    }
+
+    /// Slots of the live documents on `page_id`, in slot order. Tombstoned slots are
+    /// left out. Fails with `DatabaseError::Storage` if `page_id` is not a data page.
+    pub fn slots_on_page(
+        &mut self,
+        page_id: u64,
+    ) -> Result<Vec<SlotId>> {
+        if !self.database_file.data_page_ids().contains(&page_id) {
+            return Err(DatabaseError::Storage(format!(
+                "Page {} is not a data page: data pages are {:?}",
+                page_id,
+                self.database_file.data_page_ids()
+            )));
+        }
+        let page = self
+            .buffer_pool
+            .pin_page(page_id, &mut self.database_file)?;
+        let slots = match page.next_free_page() {
+            Some(_) => Ok(Vec::new()),
+            None => PageLayout::get_live_slots(page),
+        };
+        self.buffer_pool.unpin_page(page_id, false);
+        slots
+    }
+
+    /// Returns the number of live documents.
+    pub fn count(&mut self) -> Result<usize> {
+        let mut total = 0;
+        for page_id in self.database_file.data_page_ids() {
+            let page = self
+                .buffer_pool
+                .pin_page(page_id, &mut self.database_file)?;
+            let count = match page.next_free_page() {
+                Some(_) => Ok(0),
+                None => PageLayout::get_document_count(page),
+            };
+            self.buffer_pool.unpin_page(page_id, false);
+            total += count? as usize;
+        }
+        Ok(total)
+    }
+
+    /// Removes every document and page, leaving a valid empty database.
+    ///
+    /// Cached pages are dropped without being written back, since their contents are
+    /// being thrown away anyway. Index definitions are kept but emptied. Subscribers get a
+    /// delete event for every document removed.
+    pub fn truncate(&mut self) -> Result<()> {
+        // Only scanned when someone is listening. Truncating is a way out of a damaged
+        // database, so unreadable pages are skipped rather than failing it.
+        let mut removed = Vec::new();
+        if self.changes.has_subscribers() {
+            self.scan_pages(&CancellationToken::new(), true, |document_id, _| {
+                removed.push(document_id);
+                ControlFlow::Continue(())
+            })?;
+        }
+        self.buffer_pool.discard_all();
+        self.document_cache.clear();
+        self.free_space.clear();
+        self.indexes.values_mut().for_each(OrderedIndex::clear);
+        // Replaying the log would bring the documents back, so it is emptied first. A
+        // crash before the data file is cut then leaves it as of the last checkpoint,
+        // rather than an emptied file that recovery writes old records back into.
+        if let Some(wal) = &self.wal {
+            wal.truncate()?;
+        }
+        self.database_file.truncate()?;
+        for document_id in removed {
+            self.changes.publish(ChangeEvent::Deleted(document_id));
+        }
+        Ok(())
+    }
+
+    /// Returns every live document in physical (page, slot) order.
+    pub fn scan_all(&mut self) -> Result<Vec<(DocumentId, Document)>> {
+        self.scan_all_cancellable(&CancellationToken::new())
+    }
+
+    /// `scan_all` that stops with `DatabaseError::Cancelled` once `cancel` is set.
+    pub fn scan_all_cancellable(
+        &mut self,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<(DocumentId, Document)>> {
+        self.collect_bounded(cancel, &QueryOptions::default(), |_| true)
+    }
+
+    /// `scan_all` bounded by `options`, stopping as soon as the limit is reached or the
+    /// cap is exceeded. See `QueryOptions` for how the two interact.
+    pub fn scan_all_with_options(
+        &mut self,
+        options: &QueryOptions,
+    ) -> Result<Vec<(DocumentId, Document)>> {
+        self.collect_bounded(&CancellationToken::new(), options, |_| true)
+    }
+
+    // Scans for documents passing `filter`, honoring `options`
+    fn collect_bounded<F>(
+        &mut self,
+        cancel: &CancellationToken,
+        options: &QueryOptions,
+        filter: F,
+    ) -> Result<Vec<(DocumentId, Document)>>
+    where
+        F: Fn(&Document) -> bool,
+    {
+        let mut documents = Vec::new();
+        let mut overflowed = false;
+        self.scan_pages(cancel, false, |document_id, document| {
+            if !filter(&document) {
+                return ControlFlow::Continue(());
+            }
+            if Some(documents.len()) == options.limit {
+                return ControlFlow::Break(());
+            }
+            if Some(documents.len()) == options.max_buffered_docs {
+                overflowed = true;
+                return ControlFlow::Break(());
+            }
+            documents.push((document_id, document));
+            ControlFlow::Continue(())
+        })?;
+
+        match options.max_buffered_docs {
+            Some(max_buffered) if overflowed => {
+                Err(DatabaseError::TooManyResults { max_buffered })
+            }
+            _ => Ok(documents),
+        }
+    }
+
+    /// Returns every live document, optionally skipping damaged pages.
+    ///
+    /// With the default options this fails on the first bad page, like `scan_all`. With
+    /// `skip_corrupt` set, a page that fails its checksum or holds undecodable documents
+    /// is reported in `corrupt_pages` and contributes no documents, so the readable rest
+    /// of a partially corrupt database can still be recovered. I/O errors still fail.
+    pub fn scan(&mut self, options: &ScanOptions) -> Result<ScanResult> {
+        let mut documents = Vec::new();
+        let corrupt_pages = self.scan_pages(
+            &CancellationToken::new(),
+            options.skip_corrupt,
+            |document_id, document| {
+                documents.push((document_id, document));
+                ControlFlow::Continue(())
+            },
+        )?;
+        Ok(ScanResult {
+            documents,
+            corrupt_pages,
+        })
+    }
+
+    /// Visits every live document in physical (page, slot) order without buffering them.
+    ///
+    /// `cancel` is checked before each page is read, so cancellation takes effect at the
+    /// next page boundary: documents of the page being visited are still delivered.
+    pub fn scan_with<F>(&mut self, cancel: &CancellationToken, mut visit: F) -> Result<()>
+    where
+        F: FnMut(DocumentId, Document),
+    {
+        self.scan_pages(cancel, false, |document_id, document| {
+            visit(document_id, document);
+            ControlFlow::Continue(())
+        })
+        .map(|_| ())
+    }
+
+    // Returns the pages skipped as corrupt, which is always empty unless `skip_corrupt`.
+    // The scan ends early once `visit` breaks.
+    fn scan_pages<F>(
+        &mut self,
+        cancel: &CancellationToken,
+        skip_corrupt: bool,
+        mut visit: F,
+    ) -> Result<Vec<u64>>
+    where
+        F: FnMut(DocumentId, Document) -> ControlFlow<()>,
+    {
+        let mut corrupt_pages = Vec::new();
+        let page_ids = self.database_file.data_page_ids();
+        for page_id in page_ids.clone() {
+            cancel.check()?;
+            if self.read_ahead > 0 && !self.buffer_pool.contains_page(page_id) {
+                let window_end = (page_id + 1 + self.read_ahead as u64).min(page_ids.end);
+                self.buffer_pool
+                    .prefetch(page_id..window_end, &mut self.database_file);
+            }
+
+            // A page is decoded in full before any of it is delivered, so a skipped page
+            // contributes nothing
+            match self.read_page_documents(page_id) {
+                Ok(documents) => {
+                    for (document_id, document) in documents {
+                        if visit(document_id, document).is_break() {
+                            return Ok(corrupt_pages);
+                        }
+                    }
+                }
+                Err(err) if skip_corrupt && Self::is_corruption(&err) => {
+                    corrupt_pages.push(page_id);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(corrupt_pages)
+    }
+
+    /// Iterates over every live document in physical (page, slot) order, reading one page
+    /// at a time as it goes.
+    ///
+    /// Unlike `scan_all`, a failure does not end the scan: a document that cannot be
+    /// decoded is yielded as an error in its place, and a page that cannot be read as one
+    /// error in place of its documents, after which iteration carries on with the rest.
+    /// Deleted and empty slots are skipped.
+    pub fn iter_documents(&mut self) -> impl Iterator<Item = Result<(DocumentId, Document)>> + '_ {
+        let mut page_ids = self.database_file.data_page_ids();
+        let mut pending = Vec::<(DocumentId, Vec<u8>)>::new().into_iter();
+        std::iter::from_fn(move || {
+            loop {
+                if let Some((document_id, document_bytes)) = pending.next() {
+                    return Some(Self::decode_record(document_id, &document_bytes));
+                }
+                let page_id = page_ids.next()?;
+                match self.read_page_records(page_id) {
+                    Ok(records) => pending = records.into_iter(),
+                    Err(err) => return Some(Err(err)),
+                }
+            }
+        })
+    }
+
+    fn read_page_documents(&mut self, page_id: u64) -> Result<Vec<(DocumentId, Document)>> {
+        self.read_page_records(page_id)?
+            .into_iter()
+            .map(|(document_id, document_bytes)| Self::decode_record(document_id, &document_bytes))
+            .collect()
+    }
+
+    // The stored bytes of every live slot on a page, undecoded
+    fn read_page_records(&mut self, page_id: u64) -> Result<Vec<(DocumentId, Vec<u8>)>> {
+        let page = self
+            .buffer_pool
+            .pin_page(page_id, &mut self.database_file)?;
+
+        let mut records = Vec::new();
+        // A page on the free list holds no documents, only the link to the next one
+        let slots = match page.next_free_page() {
+            Some(_) => Ok(Vec::new()),
+            None => PageLayout::get_live_slots(page),
+        };
+        let result = slots.and_then(|slots| {
+            for slot_id in slots {
+                let document_bytes = PageLayout::get_document(page, slot_id)?;
+                records.push((DocumentId::new(page_id, slot_id), document_bytes));
+            }
+            Ok(())
+        });
+        self.buffer_pool.unpin_page(page_id, false);
+        result?;
+        Ok(records)
+    }
+
+    fn decode_record(document_id: DocumentId, stored: &[u8]) -> Result<(DocumentId, Document)> {
+        let document = deserialize_document(&compression::decode(stored)?)?;
+        Ok((document_id.tagged(&document), document))
+    }
+
+    // Damaged page contents, as opposed to I/O failures, cancellation, or a full pool
+    fn is_corruption(err: &DatabaseError) -> bool {
+        matches!(
+            err,
+            DatabaseError::Bson(_)
+                | DatabaseError::InvalidChecksum
+                | DatabaseError::Corruption(_)
+                | DatabaseError::Storage(_)
+        )
+    }
+
+    /// Returns every live document ordered by its stored `_id`.
+    ///
+    /// ObjectIds start with their creation time, so this is roughly insertion order
+    /// regardless of where updates and vacuums have moved documents. The whole result
+    /// is buffered in memory and sorted, so prefer `scan_all` for large collections
+    /// when order does not matter.
+    pub fn scan_by_id(&mut self) -> Result<Vec<(DocumentId, Document)>> {
+        let mut documents = self.scan_all()?;
+        documents.sort_by(|(_, a), (_, b)| a.id().total_cmp(b.id()));
+        Ok(documents)
+    }
+
+    /// Infers the de-facto schema from up to `sample_limit` documents (all if `None`).
+    ///
+    /// Documents are sampled in physical order, so a limit favours older pages, and
+    /// pages past the last sampled document are not read.
+    pub fn infer_schema(&mut self, sample_limit: Option<usize>) -> Result<InferredSchema> {
+        let mut documents = Vec::new();
+        if sample_limit != Some(0) {
+            self.scan_pages(&CancellationToken::new(), false, |_, document| {
+                documents.push(document);
+                if Some(documents.len()) == sample_limit {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            })?;
+        }
+        Ok(InferredSchema::from_documents(&documents))
+    }
+
+    /// Returns the total bytes `vacuum` would recover across all pages.
+    ///
+    /// Maintenance jobs can compare this against the file size to decide whether a
+    /// vacuum is worth running. See `PageLayout::dead_space` for what is counted.
+    pub fn reclaimable_bytes(&mut self) -> Result<usize> {
+        let mut total = 0;
+        for page_id in self.database_file.data_page_ids() {
+            let page = self
+                .buffer_pool
+                .pin_page(page_id, &mut self.database_file)?;
+            let dead_space = match page.next_free_page() {
+                Some(_) => Ok(0),
+                None => PageLayout::dead_space(page),
+            };
+            self.buffer_pool.unpin_page(page_id, false);
+            total += dead_space?;
+        }
+        Ok(total)
+    }
+
+    /// Writes a consistent copy of the database to `dest` without shutting down.
+    ///
+    /// Dirty pages are flushed first so the copy reflects every completed write, then
+    /// the file is copied page by page with checksum verification.
+    pub fn backup(&mut self, dest: &Path) -> Result<()> {
+        self.checkpoint()?;
+        self.database_file.backup(dest)
+    }
+
+    /// Writes a defragmented copy of the database to a new file at `dest`.
+    ///
+    /// Every live document is read and inserted into a fresh database, packed first-fit
+    /// with no tombstones or gaps, so the copy is as small as the data allows. Each
+    /// document keeps its `_id`, but gets a new `DocumentId`; ids handed out by this
+    /// engine do not apply to the copy. Indexes live in memory and are not part of the
+    /// file, so create them again on the engine that opens `dest`.
+    ///
+    /// `dest` must not exist yet. On failure the partial copy is removed. Meant to be
+    /// run offline: writes made through other handles meanwhile are not copied.
+    pub fn compact_to(&mut self, dest: &Path) -> Result<()> {
+        drop(DatabaseFile::create(dest)?);
+        let result = self.copy_documents_to(dest);
+        if result.is_err() {
+            let _ = std::fs::remove_file(dest);
+        }
+        result
+    }
+
+    fn copy_documents_to(&mut self, dest: &Path) -> Result<()> {
+        let capacity = self.buffer_pool.get_stats().capacity;
+        let mut copy = StorageEngine::new(dest, capacity)?
+            .with_validation_level(ValidationLevel::None)
+            .with_packing_strategy(PackingStrategy::FirstFit)
+            .with_compression(self.compression)
+            .with_slot_layout(self.slot_layout());
+
+        let mut failure = None;
+        self.scan_pages(&CancellationToken::new(), false, |_, document| {
+            match copy.insert_document(&document) {
+                Ok(_) => ControlFlow::Continue(()),
+                Err(err) => {
+                    failure = Some(err);
+                    ControlFlow::Break(())
+                }
+            }
+        })?;
+        if let Some(err) = failure {
+            return Err(err);
+        }
+        copy.checkpoint()?;
+        copy.database_file.sync()
+    }
+
+    // Stores the bytes on the first candidate page with room, falling back to a freshly
+    // allocated page once every candidate is full
     fn insert_document_internal(&mut self, document_bytes: &[u8]) -> Result<DocumentId> {
         let document_size = document_bytes.len();
+        // Documents are not split across pages, so one that cannot fit on an empty page
+        // would only leave a new, unused page behind
+        let max_size = PageLayout::max_document_size();
+        if document_size > max_size {
+            return Err(DatabaseError::Storage(format!(
+                "Document of {} bytes does not fit on a page (at most {} bytes)",
+                document_size, max_size
+            )));
+        }
 
-        // Try to find an existing page with enough free space
-        let page_ids = self.buffer_pool.get_all_page_ids();
-        for page_id in page_ids {
+        // Try the existing pages the packing strategy allows, in its order of preference.
+        // The free-space directory can be stale, so a page may turn out not to fit.
+        let mut tried = HashSet::new();
+        while let Some(page_id) = self.candidate_page(document_size, &tried) {
+            tried.insert(page_id);
             if let Ok(page) = self.buffer_pool.pin_page(page_id, &mut self.database_file) {
+                if page.next_free_page().is_some() {
+                    self.buffer_pool.unpin_page(page_id, false);
+                    self.free_space.set(page_id, 0);
+                    continue;
+                }
+                let compacted = match self.tombstone_threshold {
+                    Some(threshold) => PageLayout::compact_if_tombstoned(page, threshold),
+                    None => Ok(false),
+                };
+                let compacted = match compacted {
+                    Ok(compacted) => compacted,
+                    Err(e) => {
+                        self.buffer_pool.unpin_page(page_id, false);
+                        return Err(e);
+                    }
+                };
                 let free_space = page.get_free_space() as usize;
 
                 if document_size <= free_space {
@@ -221,17 +2352,17 @@ impl StorageEngine {
                             return Ok(DocumentId::new(page_id, slot_id));
                         }
                         Err(_) => {
-                            self.buffer_pool.unpin_page(page_id, false);
+                            self.buffer_pool.unpin_page(page_id, compacted);
                             continue;
                         }
                     }
                 }
-                self.buffer_pool.unpin_page(page_id, false);
+                self.buffer_pool.unpin_page(page_id, compacted);
             }
         }
 
         // Need a new page
-        let new_page_id = self.database_file.allocate_page()?;
+        let new_page_id = self.allocate_page()?;
         let page = self
             .buffer_pool
             .pin_page(new_page_id, &mut self.database_file)?;
@@ -240,4 +2371,81 @@ impl StorageEngine {
 
         Ok(DocumentId::new(new_page_id, slot_id))
     }
+
+    // Allocates a page through the file. A reused page may still be cached as it was
+    // before it was freed, so any cached copy is dropped in favour of the fresh page.
+    fn allocate_page(&mut self) -> Result<u64> {
+        let page_id = self.database_file.allocate_page()?;
+        self.buffer_pool.discard_page(page_id);
+        Ok(page_id)
+    }
+
+    // The existing page an insert of `document_size` bytes should try next, skipping
+    // the pages already tried
+    fn candidate_page(&self, document_size: usize, tried: &HashSet<u64>) -> Option<u64> {
+        match self.packing_strategy {
+            PackingStrategy::FirstFit => self.free_space.first_fit(document_size, tried),
+            PackingStrategy::BestFit => self.free_space.best_fit(document_size, tried),
+            PackingStrategy::AppendOnly => self
+                .database_file
+                .data_page_ids()
+                .last()
+                .filter(|page_id| !tried.contains(page_id)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_reindex_repairs_corrupted_index() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("test.db");
+        drop(DatabaseFile::create(&path).unwrap());
+        let mut engine = StorageEngine::new(&path, 10).unwrap();
+
+        for i in 0..30 {
+            let mut doc = Document::new();
+            doc.set("n", Value::I32(i));
+            doc.set("age", Value::I32(20 + i % 10));
+            engine.insert_document(&doc).unwrap();
+        }
+        engine.create_index("age").unwrap();
+        engine.create_unique_index("n").unwrap();
+
+        // Lose some entries and add one for a document that does not exist
+        let index = engine.indexes.get_mut("age").unwrap();
+        for document_id in index.get(&Value::I32(25)).to_vec() {
+            index.remove(&Value::I32(25), document_id);
+        }
+        index.insert(&Value::I32(99), DocumentId::new(1, 500));
+        let found = engine
+            .find_range("age", Some(Value::I32(25)), Some(Value::I32(25)), (true, true))
+            .unwrap();
+        assert!(found.is_empty());
+
+        engine.reindex_all().unwrap();
+        assert!(engine.index("n").unwrap().is_unique());
+
+        let documents = engine.scan_all().unwrap();
+        let index = engine.index("age").unwrap();
+        for age in 20..30 {
+            let mut expected: Vec<DocumentId> = documents
+                .iter()
+                .filter(|(_, doc)| doc.get("age") == Some(&Value::I32(age)))
+                .map(|(document_id, _)| *document_id)
+                .collect();
+            expected.sort();
+            let mut indexed = index.get(&Value::I32(age)).to_vec();
+            indexed.sort();
+            assert_eq!(indexed, expected);
+        }
+        assert!(index.get(&Value::I32(99)).is_empty());
+        assert_eq!(index.entry_count(), 30);
+
+        assert!(matches!(engine.reindex("name"), Err(DatabaseError::Index(_))));
+    }
 }
@@ -0,0 +1,384 @@
+// Write-ahead log with group commit
+//
+// Each committed record is appended to the log and fsynced before `commit` returns, so
+// it survives a crash. An fsync costs the same whether it covers one record or a hundred,
+// so committers share them: while one committer (the leader) flushes, everyone else who
+// commits queues their record and waits, and the next flush covers the whole queue.
+//
+// On disk every record is `lsn (u64) | length (u32) | crc32 (u32) | payload`, all little
+// endian. A crash mid-write leaves a torn last record, which fails its checksum and is
+// dropped on recovery; it was never acknowledged, so nothing committed is lost.
+
+use crate::error::DatabaseError;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+use std::sync::{Condvar, Mutex, MutexGuard};
+use std::time::Duration;
+
+/// Log sequence number: the position of a record in the log, starting at 1
+pub type Lsn = u64;
+
+const RECORD_HEADER_SIZE: usize = 8 + 4 + 4;
+
+/// A record read back from the log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalRecord {
+    pub lsn: Lsn,
+    pub payload: Vec<u8>,
+}
+
+struct WalState {
+    // Encoded records waiting for the next flush
+    pending: Vec<u8>,
+    last_lsn: Lsn,
+    durable_lsn: Lsn,
+    flushing: bool,
+    sync_count: u64,
+    // Bytes of intact records in the file
+    size: u64,
+    // Set once a flush fails; the log cannot tell which records reached the disk
+    failure: Option<String>,
+}
+
+/// Append-only log whose `commit` returns once the record is durable.
+///
+/// Shared between threads by reference (e.g. in an `Arc`); every method takes `&self`.
+///
+/// Group commit trades latency for throughput. A leader waits up to the batch window
+/// before flushing so that more committers can join its fsync, so a commit can take up
+/// to the window plus one fsync, plus the tail of any flush already in progress. With
+/// the default window of zero, batching still happens naturally: records committed
+/// while a flush is running all share the next one.
+pub struct WriteAheadLog {
+    file: Mutex<File>,
+    state: Mutex<WalState>,
+    flushed: Condvar,
+    batch_window: Duration,
+}
+
+impl WriteAheadLog {
+    /// Opens the log at `path`, creating it if needed.
+    ///
+    /// A torn record at the end, left by a crash during a flush, is cut off so new
+    /// records follow the last intact one.
+    pub fn open(path: &Path) -> Result<Self, DatabaseError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let (records, valid_len) = decode_records(&bytes);
+        if valid_len < bytes.len() {
+            file.set_len(valid_len as u64)?;
+            file.sync_all()?;
+        }
+        let last_lsn = records.last().map_or(0, |record| record.lsn);
+
+        Ok(Self {
+            file: Mutex::new(file),
+            state: Mutex::new(WalState {
+                pending: Vec::new(),
+                last_lsn,
+                durable_lsn: last_lsn,
+                flushing: false,
+                sync_count: 0,
+                size: valid_len as u64,
+                failure: None,
+            }),
+            flushed: Condvar::new(),
+            batch_window: Duration::ZERO,
+        })
+    }
+
+    /// Sets how long a flush leader waits for more committers before it fsyncs.
+    pub fn with_batch_window(mut self, window: Duration) -> Self {
+        self.batch_window = window;
+        self
+    }
+
+    pub fn batch_window(&self) -> Duration {
+        self.batch_window
+    }
+
+    /// Appends `payload` and returns its LSN once it is durable on disk.
+    ///
+    /// Fails if the flush covering the record fails; after that every commit fails,
+    /// since the log no longer knows which records made it to disk.
+    pub fn commit(&self, payload: &[u8]) -> Result<Lsn, DatabaseError> {
+        let length = u32::try_from(payload.len()).map_err(|_| {
+            DatabaseError::Storage(format!("WAL record too large: {} bytes", payload.len()))
+        })?;
+
+        let mut state = self.lock_state();
+        Self::check_failure(&state)?;
+        state.last_lsn += 1;
+        let lsn = state.last_lsn;
+        state.pending.extend_from_slice(&lsn.to_le_bytes());
+        state.pending.extend_from_slice(&length.to_le_bytes());
+        state
+            .pending
+            .extend_from_slice(&crc32fast::hash(payload).to_le_bytes());
+        state.pending.extend_from_slice(payload);
+
+        loop {
+            Self::check_failure(&state)?;
+            if state.durable_lsn >= lsn {
+                return Ok(lsn);
+            }
+            if state.flushing {
+                state = self
+                    .flushed
+                    .wait(state)
+                    .unwrap_or_else(|poisoned| poisoned.into_inner());
+                continue;
+            }
+            state = self.flush_as_leader(state);
+        }
+    }
+
+    // Flushes everything queued, letting other committers queue more in the meantime
+    fn flush_as_leader<'a>(
+        &'a self,
+        mut state: MutexGuard<'a, WalState>,
+    ) -> MutexGuard<'a, WalState> {
+        state.flushing = true;
+        if !self.batch_window.is_zero() {
+            drop(state);
+            std::thread::sleep(self.batch_window);
+            state = self.lock_state();
+        }
+        let batch = std::mem::take(&mut state.pending);
+        let batch_lsn = state.last_lsn;
+        drop(state);
+
+        let result = {
+            let mut file = self
+                .file
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            file.write_all(&batch).and_then(|_| file.sync_data())
+        };
+
+        let mut state = self.lock_state();
+        state.flushing = false;
+        match result {
+            Ok(()) => {
+                state.durable_lsn = batch_lsn;
+                state.sync_count += 1;
+                state.size += batch.len() as u64;
+            }
+            Err(e) => state.failure = Some(e.to_string()),
+        }
+        self.flushed.notify_all();
+        state
+    }
+
+    fn check_failure(state: &WalState) -> Result<(), DatabaseError> {
+        match &state.failure {
+            Some(message) => Err(DatabaseError::Storage(format!(
+                "write-ahead log unusable after a failed flush: {}",
+                message
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, WalState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The LSN of the newest durable record, or 0 if there is none.
+    pub fn durable_lsn(&self) -> Lsn {
+        self.lock_state().durable_lsn
+    }
+
+    /// Number of fsyncs issued since the log was opened.
+    pub fn sync_count(&self) -> u64 {
+        self.lock_state().sync_count
+    }
+
+    /// Bytes of durable records in the log file.
+    pub fn size(&self) -> u64 {
+        self.lock_state().size
+    }
+
+    /// Empties the log, e.g. once a checkpoint has made everything it records durable
+    /// elsewhere. LSNs keep counting up from where they were.
+    ///
+    /// Waits for a flush in progress to finish first. Records committed after the
+    /// truncation are kept, so only call this once every record committed before it
+    /// is no longer needed.
+    pub fn truncate(&self) -> Result<(), DatabaseError> {
+        let mut state = self.lock_state();
+        while state.flushing {
+            state = self
+                .flushed
+                .wait(state)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        Self::check_failure(&state)?;
+
+        let file = self
+            .file
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        file.set_len(0)?;
+        file.sync_all()?;
+        state.size = 0;
+        Ok(())
+    }
+
+    /// Reads every intact record from the log at `path`, oldest first.
+    ///
+    /// Stops at the first torn or corrupt record; anything after it was never reported
+    /// as committed.
+    pub fn read_records(path: &Path) -> Result<Vec<WalRecord>, DatabaseError> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        Ok(decode_records(&bytes).0)
+    }
+}
+
+// The intact records at the start of `bytes`, and how many bytes they span
+fn decode_records(bytes: &[u8]) -> (Vec<WalRecord>, usize) {
+    let mut records = Vec::new();
+    let mut offset = 0;
+
+    while let Some(header) = bytes.get(offset..offset + RECORD_HEADER_SIZE) {
+        let lsn = u64::from_le_bytes(header[..8].try_into().unwrap());
+        let length = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+        let checksum = u32::from_le_bytes(header[12..16].try_into().unwrap());
+
+        let start = offset + RECORD_HEADER_SIZE;
+        let Some(payload) = bytes.get(start..start + length) else {
+            break;
+        };
+        let expected_lsn = records
+            .last()
+            .map_or(lsn, |record: &WalRecord| record.lsn + 1);
+        if crc32fast::hash(payload) != checksum || lsn != expected_lsn {
+            break;
+        }
+
+        records.push(WalRecord {
+            lsn,
+            payload: payload.to_vec(),
+        });
+        offset = start + length;
+    }
+
+    (records, offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequential_commits_are_durable() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.wal");
+
+        {
+            let wal = WriteAheadLog::open(&path).unwrap();
+            assert_eq!(wal.commit(b"first").unwrap(), 1);
+            assert_eq!(wal.commit(b"second").unwrap(), 2);
+            assert_eq!(wal.durable_lsn(), 2);
+            // Nobody else was committing, so nothing could be batched
+            assert_eq!(wal.sync_count(), 2);
+        }
+
+        let records = WriteAheadLog::read_records(&path).unwrap();
+        assert_eq!(
+            records,
+            vec![
+                WalRecord {
+                    lsn: 1,
+                    payload: b"first".to_vec()
+                },
+                WalRecord {
+                    lsn: 2,
+                    payload: b"second".to_vec()
+                },
+            ]
+        );
+
+        let wal = WriteAheadLog::open(&path).unwrap();
+        assert_eq!(wal.durable_lsn(), 2);
+        assert_eq!(wal.commit(b"third").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_torn_tail_is_dropped() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.wal");
+
+        {
+            let wal = WriteAheadLog::open(&path).unwrap();
+            wal.commit(b"kept").unwrap();
+            wal.commit(b"torn").unwrap();
+        }
+        let len = std::fs::metadata(&path).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_len(len - 2)
+            .unwrap();
+
+        let records = WriteAheadLog::read_records(&path).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].payload, b"kept");
+
+        // Reopening cuts the torn record off, so the next one gets its LSN
+        let wal = WriteAheadLog::open(&path).unwrap();
+        assert_eq!(wal.commit(b"next").unwrap(), 2);
+        let records = WriteAheadLog::read_records(&path).unwrap();
+        assert_eq!(records[1].payload, b"next");
+    }
+
+    #[test]
+    fn test_corrupt_record_ends_recovery() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.wal");
+
+        {
+            let wal = WriteAheadLog::open(&path).unwrap();
+            for payload in [b"aaaa", b"bbbb", b"cccc"] {
+                wal.commit(payload).unwrap();
+            }
+        }
+        let mut bytes = std::fs::read(&path).unwrap();
+        let second_payload = 2 * RECORD_HEADER_SIZE + 4;
+        bytes[second_payload] ^= 0xFF;
+        std::fs::write(&path, &bytes).unwrap();
+
+        let records = WriteAheadLog::read_records(&path).unwrap();
+        assert_eq!(records.len(), 1);
+    }
+
+    #[test]
+    fn test_truncate_empties_log() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("test.wal");
+
+        let wal = WriteAheadLog::open(&path).unwrap();
+        wal.commit(b"old").unwrap();
+        assert_eq!(wal.size(), (RECORD_HEADER_SIZE + 3) as u64);
+
+        wal.truncate().unwrap();
+        assert_eq!(wal.size(), 0);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 0);
+
+        assert_eq!(wal.commit(b"new").unwrap(), 2);
+        let records = WriteAheadLog::read_records(&path).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].payload, b"new");
+    }
+}
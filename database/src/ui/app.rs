@@ -5,6 +5,7 @@ use crate::{
         file::DatabaseFile,
     },
     bson::{serialize_document, deserialize_document},
+    document::types::NonFiniteFloatMode,
     Document, Value,
 };
 use std::{path::Path, time::Instant};
@@ -126,13 +127,34 @@ impl DatabaseApp {
     }
 
     fn refresh_documents(&mut self) {
-        self.set_status("Document list refreshed.", egui::Color32::from_rgb(100, 180, 220));
+        let Some(ref mut engine) = self.storage_engine else {
+            return;
+        };
+        let mut documents = Vec::new();
+        let mut unreadable = 0;
+        for result in engine.iter_documents() {
+            match result {
+                Ok(entry) => documents.push(entry),
+                Err(_) => unreadable += 1,
+            }
+        }
+        self.documents = documents;
+        self.selected_doc_index = None;
+        self.edit_mode = false;
+        if unreadable == 0 {
+            self.set_status(&format!("Loaded {} documents.", self.documents.len()), egui::Color32::from_rgb(100, 180, 220));
+        } else {
+            self.set_status(
+                &format!("Loaded {} documents; {} could not be read.", self.documents.len(), unreadable),
+                egui::Color32::from_rgb(220, 80, 80),
+            );
+        }
     }
 
     fn insert_document_from_json(&mut self) {
         if let Some(ref mut engine) = self.storage_engine {
             let json_input = self.json_input.clone();
-            match Self::parse_json_to_document(&json_input) {
+            match Document::from_json(&json_input) {
                 Ok(document) => {
                     match engine.insert_document(&document) {
                         Ok(doc_id) => {
@@ -153,40 +175,6 @@ impl DatabaseApp {
         }
     }
 
-    fn parse_json_to_document(json_str: &str) -> Result<Document, Box<dyn std::error::Error>> {
-        let json_value: serde_json::Value = serde_json::from_str(json_str)?;
-        let mut document = Document::new();
-        if let serde_json::Value::Object(map) = json_value {
-            for (key, value) in map {
-                document.set(&key, Self::json_value_to_db_value(value));
-            }
-        }
-        Ok(document)
-    }
-
-    fn json_value_to_db_value(value: serde_json::Value) -> Value {
-        match value {
-            serde_json::Value::String(s) => Value::String(s),
-            serde_json::Value::Number(n) => {
-                if let Some(i) = n.as_i64() {
-                    if i >= i32::MIN as i64 && i <= i32::MAX as i64 {
-                        Value::I32(i as i32)
-                    } else {
-                        Value::I64(i)
-                    }
-                } else if let Some(f) = n.as_f64() {
-                    Value::F64(f)
-                } else {
-                    Value::String(n.to_string())
-                }
-            }
-            serde_json::Value::Bool(b) => Value::Bool(b),
-            serde_json::Value::Null => Value::Null,
-            serde_json::Value::Array(_) => Value::String(value.to_string()),
-            serde_json::Value::Object(_) => Value::String(value.to_string()),
-        }
-    }
-
     fn document_to_json_string(document: &Document) -> String {
         let mut json_obj = serde_json::Map::new();
         for (key, value) in document.iter() {
@@ -200,9 +188,10 @@ impl DatabaseApp {
             Value::String(s) => serde_json::Value::String(s.clone()),
             Value::I32(i) => serde_json::Value::Number(serde_json::Number::from(*i)),
             Value::I64(i) => serde_json::Value::Number(serde_json::Number::from(*i)),
-            Value::F64(f) => serde_json::Value::Number(
-                serde_json::Number::from_f64(*f).unwrap_or_else(|| serde_json::Number::from(0)),
-            ),
+            // NaN and infinities become {"$numberDouble": ...} instead of being zeroed
+            Value::F64(_) => value
+                .to_json(NonFiniteFloatMode::ExtendedJson)
+                .unwrap_or(serde_json::Value::Null),
             Value::Bool(b) => serde_json::Value::Bool(*b),
             Value::Null => serde_json::Value::Null,
             Value::ObjectId(oid) => serde_json::Value::String(oid.to_string()),
@@ -210,46 +199,51 @@ impl DatabaseApp {
             Value::Object(_) => serde_json::Value::String(format!("{}", value)),
             Value::DateTime(dt) => serde_json::Value::String(dt.to_rfc3339()),
             Value::Binary(_) => serde_json::Value::String(format!("{}", value)),
+            Value::DocumentRef(_) => serde_json::Value::String(format!("{}", value)),
         }
     }
 
     fn delete_selected_document(&mut self) {
-        if let Some(index) = self.selected_doc_index {
-            if let Some(ref mut engine) = self.storage_engine {
-                let (doc_id, _) = &self.documents[index];
-                match engine.delete_document(doc_id) {
-                    Ok(_) => {
-                        self.documents.remove(index);
-                        self.selected_doc_index = None;
-                        self.edit_mode = false;
-                        self.active_tab = ActiveTab::Insert;
-                        self.set_status("Document deleted.", egui::Color32::from_rgb(100, 220, 120));
-                    }
-                    Err(e) => self.set_status(&format!("Delete failed: {}", e), egui::Color32::from_rgb(220, 80, 80)),
+        if let Some(index) = self.selected_doc_index
+            && let Some(ref mut engine) = self.storage_engine
+        {
+            let (doc_id, _) = &self.documents[index];
+            match engine.delete_document(doc_id) {
+                Ok(_) => {
+                    self.documents.remove(index);
+                    self.selected_doc_index = None;
+                    self.edit_mode = false;
+                    self.active_tab = ActiveTab::Insert;
+                    self.set_status("Document deleted.", egui::Color32::from_rgb(100, 220, 120));
                 }
+                Err(e) => self.set_status(&format!("Delete failed: {}", e), egui::Color32::from_rgb(220, 80, 80)),
             }
         }
     }
 
     fn update_selected_document(&mut self) {
-        if let Some(index) = self.selected_doc_index {
-            if let Some(ref mut engine) = self.storage_engine {
-                let edit_json = self.edit_json.clone();
-                match Self::parse_json_to_document(&edit_json) {
-                    Ok(new_document) => {
-                        let (doc_id, _) = &self.documents[index];
-                        let doc_id_copy = *doc_id;
-                        match engine.update_document(&doc_id_copy, &new_document) {
-                            Ok(new_doc_id) => {
-                                self.documents[index] = (new_doc_id, new_document);
-                                self.edit_mode = false;
-                                self.set_status("Document updated.", egui::Color32::from_rgb(100, 220, 120));
-                            }
-                            Err(e) => self.set_status(&format!("Update failed: {}", e), egui::Color32::from_rgb(220, 80, 80)),
+        if let Some(index) = self.selected_doc_index
+            && let Some(ref mut engine) = self.storage_engine
+        {
+            let edit_json = self.edit_json.clone();
+            match Document::from_json(&edit_json) {
+                Ok(changes) => {
+                    let (doc_id, _) = &self.documents[index];
+                    let doc_id_copy = *doc_id;
+                    // Only the edited fields are written; fields the editor did not show are kept
+                    let updated = engine
+                        .update_fields(&doc_id_copy, &changes)
+                        .and_then(|new_doc_id| Ok((new_doc_id, engine.get_document(&new_doc_id)?)));
+                    match updated {
+                        Ok((new_doc_id, new_document)) => {
+                            self.documents[index] = (new_doc_id, new_document);
+                            self.edit_mode = false;
+                            self.set_status("Document updated.", egui::Color32::from_rgb(100, 220, 120));
                         }
+                        Err(e) => self.set_status(&format!("Update failed: {}", e), egui::Color32::from_rgb(220, 80, 80)),
                     }
-                    Err(e) => self.set_status(&format!("Invalid JSON: {}", e), egui::Color32::from_rgb(220, 80, 80)),
                 }
+                Err(e) => self.set_status(&format!("Invalid JSON: {}", e), egui::Color32::from_rgb(220, 80, 80)),
             }
         }
     }
@@ -365,9 +359,9 @@ impl DatabaseApp {
                 ));
 
                 let fraction = (result.micros / max) as f32;
-                let fill_w = (fraction * available as f32).max(2.0);
+                let fill_w = (fraction * available).max(2.0);
 
-                let (rect, _) = ui.allocate_exact_size(egui::vec2(available as f32, bar_height), egui::Sense::hover());
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(available, bar_height), egui::Sense::hover());
                 let bar_rect = egui::Rect::from_min_size(
                     rect.min + egui::vec2(0.0, 4.0),
                     egui::vec2(fill_w, bar_height - 8.0),
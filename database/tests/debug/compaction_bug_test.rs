@@ -1,10 +1,8 @@
 /// Test to reproduce the compaction bug
-use database::storage::{page::{Page, PageType}, page_layout::PageLayout};
+use database::storage::{page::Page, page_layout::PageLayout};
 
 fn create_test_page() -> Page {
-    let mut page = Page::new(1, PageType::Data);
-    PageLayout::initialize_page(&mut page).expect("Failed to initialize page");
-    page
+    PageLayout::new_page(1)
 }
 
 #[test]
@@ -64,7 +62,7 @@ fn test_compaction_preserves_tombstones() {
         } else {
             // This document should still exist
             println!("Checking active document at index {} (slot {}): OK", i, slot);
-            let retrieved = result.expect(&format!("Document at index {} (slot {}) should still be accessible after compaction", i, slot));
+            let retrieved = result.unwrap_or_else(|_| panic!("Document at index {} (slot {}) should still be accessible after compaction", i, slot));
             assert_eq!(retrieved, *expected_doc, "Document at index {} (slot {}) corrupted during compaction", i, slot);
         }
     }
@@ -0,0 +1,84 @@
+use database::{
+    document::predicate::Predicate,
+    storage::storage_engine::StorageEngine,
+    Document, Value,
+};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn populate(engine: &mut StorageEngine) {
+    let products = [
+        ("Electronics", Some(Value::F64(199.5))),
+        ("Electronics", Some(Value::I32(50))),
+        ("Books", Some(Value::I32(12))),
+        ("Electronics", Some(Value::I64(300))),
+        ("Books", Some(Value::F64(8.25))),
+        // Skipped by the numeric aggregates
+        ("Electronics", Some(Value::String("call us".to_string()))),
+        ("Electronics", None),
+    ];
+    for (category, price) in products {
+        let mut doc = Document::new();
+        doc.set("category", Value::String(category.to_string()));
+        if let Some(price) = price {
+            doc.set("price", price);
+        }
+        engine.insert_document(&doc).expect("insert failed");
+    }
+}
+
+fn electronics() -> Predicate {
+    Predicate::eq("category", Value::String("Electronics".to_string()))
+}
+
+#[test]
+fn test_sum_and_average_over_filtered_documents() {
+    let (mut engine, _dir) = create_engine();
+    populate(&mut engine);
+
+    let sum = engine.sum_field(Some(&electronics()), "price").expect("sum failed");
+    assert_eq!(sum, 199.5 + 50.0 + 300.0);
+    let avg = engine.avg_field(Some(&electronics()), "price").expect("avg failed");
+    assert_eq!(avg, Some((199.5 + 50.0 + 300.0) / 3.0));
+
+    // Without a predicate every document counts
+    let sum = engine.sum_field(None, "price").expect("sum failed");
+    assert_eq!(sum, 199.5 + 50.0 + 12.0 + 300.0 + 8.25);
+
+    // Nothing to average
+    let nothing = Predicate::eq("category", Value::String("Toys".to_string()));
+    assert_eq!(engine.sum_field(Some(&nothing), "price").unwrap(), 0.0);
+    assert_eq!(engine.avg_field(Some(&nothing), "price").unwrap(), None);
+}
+
+#[test]
+fn test_min_max_and_custom_fold() {
+    let (mut engine, _dir) = create_engine();
+    populate(&mut engine);
+
+    let books = Predicate::eq("category", Value::String("Books".to_string()));
+    assert_eq!(engine.min_field(Some(&books), "price").unwrap(), Some(Value::F64(8.25)));
+    assert_eq!(engine.max_field(Some(&books), "price").unwrap(), Some(Value::I32(12)));
+    // Strings sort after numbers
+    assert_eq!(
+        engine.max_field(Some(&electronics()), "price").unwrap(),
+        Some(Value::String("call us".to_string()))
+    );
+    assert_eq!(engine.min_field(None, "missing").unwrap(), None);
+
+    let count = engine
+        .aggregate(Some(&electronics()), 0, |count, _| count + 1)
+        .expect("aggregate failed");
+    assert_eq!(count, 5);
+}
@@ -0,0 +1,168 @@
+use database::{
+    storage::storage_engine::{AutoCheckpoint, StorageEngine},
+    Document, Value,
+};
+use std::path::Path;
+use std::time::Duration;
+use tempfile::tempdir;
+
+// One logged write holds a page image and a little framing
+const PAGE_RECORD: u64 = 8 * 1024 + 64;
+
+fn create_database(dir: &Path) {
+    let _db_file = database::storage::file::DatabaseFile::create(&dir.join("test.db"))
+        .expect("Failed to create database file");
+}
+
+fn open_engine(dir: &Path, auto_checkpoint: AutoCheckpoint) -> StorageEngine {
+    StorageEngine::new(&dir.join("test.db"), 4)
+        .expect("Failed to create storage engine")
+        .with_wal(&dir.join("test.wal"))
+        .expect("Failed to open WAL")
+        .with_auto_checkpoint(auto_checkpoint)
+}
+
+fn make_doc(i: i32) -> Document {
+    let mut doc = Document::new();
+    doc.set("i", Value::I32(i));
+    doc.set("padding", Value::String("x".repeat(300)));
+    doc
+}
+
+#[test]
+fn test_wal_size_threshold_bounds_the_log() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    create_database(temp_dir.path());
+    let threshold = 4 * PAGE_RECORD;
+    let mut engine = open_engine(
+        temp_dir.path(),
+        AutoCheckpoint {
+            wal_bytes: Some(threshold),
+            ..Default::default()
+        },
+    );
+
+    let mut largest = 0;
+    let mut truncations = 0;
+    let mut previous = 0;
+    for i in 0..100 {
+        engine.insert_document(&make_doc(i)).expect("insert failed");
+        let size = std::fs::metadata(temp_dir.path().join("test.wal"))
+            .expect("WAL should exist")
+            .len();
+        assert_eq!(engine.wal_size(), Some(size));
+        largest = largest.max(size);
+        if size < previous {
+            truncations += 1;
+        }
+        previous = size;
+    }
+
+    assert!(largest < threshold, "WAL grew to {} bytes", largest);
+    assert!(truncations >= 10, "only {} checkpoints ran", truncations);
+}
+
+#[test]
+fn test_writes_since_last_checkpoint_are_recovered() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    create_database(temp_dir.path());
+
+    {
+        let mut engine = open_engine(
+            temp_dir.path(),
+            AutoCheckpoint {
+                wal_bytes: Some(4 * PAGE_RECORD),
+                ..Default::default()
+            },
+        );
+        for i in 0..53 {
+            engine.insert_document(&make_doc(i)).expect("insert failed");
+        }
+        // Dropped without a final checkpoint: dirty pages still in the buffer pool are
+        // lost, and only the log has them
+        assert!(engine.wal_size().unwrap() > 0);
+    }
+
+    let mut engine = open_engine(temp_dir.path(), AutoCheckpoint::default());
+    assert_eq!(engine.wal_size(), Some(0));
+    let mut seen: Vec<i32> = engine
+        .scan_all()
+        .expect("scan failed")
+        .into_iter()
+        .map(|(_, doc)| doc.get("i").and_then(Value::as_i32).unwrap())
+        .collect();
+    seen.sort_unstable();
+    assert_eq!(seen, (0..53).collect::<Vec<_>>());
+}
+
+// Every `i` of the documents a reopened engine finds, in order
+fn recovered(dir: &Path) -> Vec<i32> {
+    let mut engine = open_engine(dir, AutoCheckpoint::default());
+    let mut seen: Vec<i32> = engine
+        .scan_all()
+        .expect("scan failed")
+        .into_iter()
+        .map(|(_, doc)| doc.get("i").and_then(Value::as_i32).unwrap())
+        .collect();
+    seen.sort_unstable();
+    seen
+}
+
+#[test]
+fn test_writes_without_any_checkpoint_are_recovered_through_evictions() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    create_database(temp_dir.path());
+
+    {
+        // Two pages of pool, so writes evict dirty pages while later ones are logged
+        let mut engine = StorageEngine::new(&temp_dir.path().join("test.db"), 2)
+            .expect("Failed to create storage engine")
+            .with_wal(&temp_dir.path().join("test.wal"))
+            .expect("Failed to open WAL");
+        for i in 0..60 {
+            engine.insert_document(&make_doc(i)).expect("insert failed");
+        }
+        assert!(engine.page_ids().len() > 2, "the writes should span fresh pages");
+        assert!(engine.buffer_pool_stats().dirty_evictions > 0);
+    }
+
+    assert_eq!(recovered(temp_dir.path()), (0..60).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_writes_after_truncate_are_recovered() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    create_database(temp_dir.path());
+
+    {
+        let mut engine = open_engine(temp_dir.path(), AutoCheckpoint::default());
+        for i in 0..20 {
+            engine.insert_document(&make_doc(i)).expect("insert failed");
+        }
+        engine.truncate().expect("truncate failed");
+        for i in 100..105 {
+            engine.insert_document(&make_doc(i)).expect("insert failed");
+        }
+    }
+
+    assert_eq!(recovered(temp_dir.path()), (100..105).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_interval_checkpoints() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    create_database(temp_dir.path());
+    let mut engine = open_engine(
+        temp_dir.path(),
+        AutoCheckpoint {
+            interval: Some(Duration::ZERO),
+            ..Default::default()
+        },
+    );
+
+    // Every write is past the interval, so each one checkpoints straight away
+    for i in 0..5 {
+        engine.insert_document(&make_doc(i)).expect("insert failed");
+        assert_eq!(engine.wal_size(), Some(0));
+    }
+}
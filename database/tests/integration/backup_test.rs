@@ -0,0 +1,75 @@
+use database::{storage::storage_engine::StorageEngine, Document, Value};
+use tempfile::tempdir;
+
+fn make_doc(i: i32) -> Document {
+    let mut doc = Document::new();
+    doc.set("name", Value::String(format!("doc_{}", i)));
+    doc.set("value", Value::I32(i));
+    doc
+}
+
+#[test]
+fn test_backup_matches_source() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+    let backup_path = temp_dir.path().join("backup.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let mut engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+
+    let mut ids = vec![];
+    for i in 0..50 {
+        ids.push(engine.insert_document(&make_doc(i)).expect("insert failed"));
+    }
+
+    // Deleted documents must stay deleted in the copy.
+    for id in ids.iter().step_by(5) {
+        engine.delete_document(id).expect("delete failed");
+    }
+
+    engine.backup(&backup_path).expect("backup failed");
+
+    let mut backup_engine =
+        StorageEngine::new(&backup_path, 10).expect("Failed to open backup");
+
+    assert_eq!(
+        engine.database_file.page_count(),
+        backup_engine.database_file.page_count()
+    );
+
+    for (i, id) in ids.iter().enumerate() {
+        let source = engine.get_document(id);
+        let copy = backup_engine.get_document(id);
+
+        if i % 5 == 0 {
+            assert!(source.is_err());
+            assert!(copy.is_err());
+        } else {
+            let source = source.expect("source get failed");
+            let copy = copy.expect("backup get failed");
+            assert_eq!(source.get("name"), copy.get("name"));
+            assert_eq!(source.get("value"), copy.get("value"));
+        }
+    }
+}
+
+#[test]
+fn test_backup_refuses_existing_destination() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+    let backup_path = temp_dir.path().join("backup.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+    std::fs::write(&backup_path, b"existing").expect("Failed to write file");
+
+    let mut engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    engine.insert_document(&make_doc(1)).expect("insert failed");
+
+    assert!(engine.backup(&backup_path).is_err());
+    assert_eq!(std::fs::read(&backup_path).unwrap(), b"existing");
+}
@@ -1,9 +1,12 @@
+use database::error::DatabaseError;
+use database::page_layout::PageLayout;
 use database::storage::buffer_pool::BufferPool;
 use database::storage::file::DatabaseFile;
 use database::storage::storage_engine::StorageEngine;
 use database::{Document, Value};
 use std::path::Path;
 use std::fs;
+use std::sync::{Arc, Mutex};
 
 #[cfg(test)]
 mod tests {
@@ -157,6 +160,213 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_buffer_pool_exhausted_when_all_pinned() -> Result<(), Box<dyn std::error::Error>> {
+        let (temp_path, mut db_file) = create_temp_database()?;
+        let mut pool = BufferPool::new(3);
+
+        let page_ids: Vec<u64> = (0..4)
+            .map(|_| db_file.allocate_page())
+            .collect::<Result<_, _>>()?;
+        for &page_id in &page_ids[..3] {
+            pool.pin_page(page_id, &mut db_file)?;
+        }
+
+        match pool.pin_page(page_ids[3], &mut db_file) {
+            Err(DatabaseError::BufferPoolExhausted { capacity, pinned }) => {
+                assert_eq!(capacity, 3);
+                assert_eq!(pinned, 3);
+            }
+            Err(e) => panic!("expected BufferPoolExhausted, got {}", e),
+            Ok(_) => panic!("expected BufferPoolExhausted, got a page"),
+        }
+        assert!(pool.get_page(page_ids[3], &mut db_file).is_err());
+        assert_eq!(pool.get_stats().pages_in_pool, 3);
+
+        // Unpinning one page lets the load proceed by evicting it
+        pool.unpin_page(page_ids[0], false);
+        pool.pin_page(page_ids[3], &mut db_file)?;
+        assert_eq!(pool.get_stats().pages_in_pool, 3);
+        assert!(!pool.contains_page(page_ids[0]));
+
+        cleanup_file(&temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_buffer_pool_slack_allows_bounded_overcommit() -> Result<(), Box<dyn std::error::Error>> {
+        let (temp_path, mut db_file) = create_temp_database()?;
+        let mut pool = BufferPool::new(2).with_slack(1);
+
+        let page_ids: Vec<u64> = (0..4)
+            .map(|_| db_file.allocate_page())
+            .collect::<Result<_, _>>()?;
+        for &page_id in &page_ids[..3] {
+            pool.pin_page(page_id, &mut db_file)?;
+        }
+        assert_eq!(pool.get_stats().pages_in_pool, 3);
+
+        assert!(matches!(
+            pool.pin_page(page_ids[3], &mut db_file),
+            Err(DatabaseError::BufferPoolExhausted { .. })
+        ));
+
+        // Once pages are unpinned the pool shrinks back to capacity
+        for &page_id in &page_ids[..3] {
+            pool.unpin_page(page_id, false);
+        }
+        pool.get_page(page_ids[3], &mut db_file)?;
+        assert_eq!(pool.get_stats().pages_in_pool, 2);
+
+        cleanup_file(&temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_steal_keeps_unlogged_pages_out_of_the_file() -> Result<(), Box<dyn std::error::Error>> {
+        let (temp_path, mut db_file) = create_temp_database()?;
+        let mut pool = BufferPool::new(1).with_no_steal();
+
+        let page_ids: Vec<u64> = (0..3)
+            .map(|_| db_file.allocate_page())
+            .collect::<Result<_, _>>()?;
+        let original = db_file.read_page(page_ids[0])?.to_bytes();
+        PageLayout::insert_document(pool.pin_page(page_ids[0], &mut db_file)?, &[7; 50])?;
+        pool.unpin_page(page_ids[0], true);
+
+        // The changed page is the only one to evict, so the pool grows instead
+        pool.pin_page(page_ids[1], &mut db_file)?;
+        pool.unpin_page(page_ids[1], false);
+        assert_eq!(pool.get_stats().pages_in_pool, 2);
+        assert!(pool.contains_page(page_ids[0]));
+        assert_eq!(db_file.read_page_unchecked(page_ids[0])?.to_bytes(), original);
+
+        // Once the change is taken, say into a log record, it may be written back
+        assert_eq!(pool.take_modified(), vec![page_ids[0]]);
+        pool.pin_page(page_ids[2], &mut db_file)?;
+        assert_eq!(pool.get_stats().pages_in_pool, 1);
+        assert_ne!(db_file.read_page_unchecked(page_ids[0])?.to_bytes(), original);
+
+        cleanup_file(&temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pin_pages_rejects_working_set_larger_than_pool() -> Result<(), Box<dyn std::error::Error>> {
+        let (temp_path, mut db_file) = create_temp_database()?;
+        let mut pool = BufferPool::new(2);
+
+        let page_ids: Vec<u64> = (0..3)
+            .map(|_| db_file.allocate_page())
+            .collect::<Result<_, _>>()?;
+
+        match pool.pin_pages(&page_ids, &mut db_file) {
+            Err(DatabaseError::WorkingSetTooLarge { needed, capacity }) => {
+                assert_eq!((needed, capacity), (3, 2));
+            }
+            Err(e) => panic!("expected WorkingSetTooLarge, got {}", e),
+            Ok(()) => panic!("pinned more pages than the pool holds"),
+        }
+        // Nothing was loaded or left pinned by the rejected operation
+        assert_eq!(pool.get_stats().pages_in_pool, 0);
+        assert_eq!(pool.get_stats().pinned_pages, 0);
+
+        // The same operation fits once the pool is large enough
+        pool.resize(3, &mut db_file)?;
+        pool.pin_pages(&page_ids, &mut db_file)?;
+        assert_eq!(pool.get_stats().pinned_pages, 3);
+
+        cleanup_file(&temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pin_pages_counts_pages_already_pinned() -> Result<(), Box<dyn std::error::Error>> {
+        let (temp_path, mut db_file) = create_temp_database()?;
+        let mut pool = BufferPool::new(2);
+
+        let page_ids: Vec<u64> = (0..3)
+            .map(|_| db_file.allocate_page())
+            .collect::<Result<_, _>>()?;
+        pool.pin_page(page_ids[0], &mut db_file)?;
+
+        assert!(matches!(
+            pool.pin_pages(&page_ids[1..], &mut db_file),
+            Err(DatabaseError::WorkingSetTooLarge { needed: 3, capacity: 2 })
+        ));
+        // Re-pinning a page already held does not add to the demand
+        pool.pin_pages(&page_ids[..2], &mut db_file)?;
+        assert_eq!(pool.get_stats().pinned_pages, 2);
+
+        cleanup_file(&temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_eviction_ties_break_by_load_order() -> Result<(), Box<dyn std::error::Error>> {
+        let (temp_path, mut db_file) = create_temp_database()?;
+        let page_ids: Vec<u64> = (0..5)
+            .map(|_| db_file.allocate_page())
+            .collect::<Result<_, _>>()?;
+
+        // Pages loaded in one batch and never used are equally cold; the one loaded
+        // first goes first, every time
+        for _ in 0..10 {
+            let mut pool = BufferPool::new(3);
+            assert_eq!(pool.prefetch(page_ids[..3].iter().copied(), &mut db_file), 3);
+            assert_eq!(pool.eviction_candidate(), Some(page_ids[0]));
+
+            pool.get_page(page_ids[3], &mut db_file)?;
+            assert!(!pool.contains_page(page_ids[0]));
+            assert_eq!(pool.eviction_candidate(), Some(page_ids[1]));
+
+            // Pinned pages are passed over
+            pool.pin_page(page_ids[1], &mut db_file)?;
+            assert_eq!(pool.eviction_candidate(), Some(page_ids[2]));
+            pool.get_page(page_ids[4], &mut db_file)?;
+            assert!(!pool.contains_page(page_ids[2]));
+            pool.validate_consistency()?;
+        }
+
+        cleanup_file(&temp_path);
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_writes_pages_in_ascending_order() -> Result<(), Box<dyn std::error::Error>> {
+        let (temp_path, mut db_file) = create_temp_database()?;
+        let page_ids: Vec<u64> = (0..12)
+            .map(|_| db_file.allocate_page())
+            .collect::<Result<_, _>>()?;
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let observed = Arc::clone(&written);
+        db_file.set_write_observer(move |page_id| observed.lock().unwrap().push(page_id));
+
+        // Dirty the pages in a scrambled order, twice: once for flush_all, once for clear
+        let dirtied = [7, 2, 11, 0, 5, 9, 3].map(|i| page_ids[i]);
+        let mut expected = dirtied.to_vec();
+        expected.sort_unstable();
+        let mut pool = BufferPool::new(page_ids.len());
+        for flush_with_clear in [false, true] {
+            for page_id in dirtied {
+                pool.pin_page(page_id, &mut db_file)?;
+                pool.unpin_page(page_id, true);
+            }
+            if flush_with_clear {
+                pool.clear(&mut db_file)?;
+            } else {
+                pool.flush_all(&mut db_file)?;
+            }
+
+            assert_eq!(std::mem::take(&mut *written.lock().unwrap()), expected);
+            assert_eq!(pool.get_stats().dirty_pages, 0);
+            assert!(page_ids.iter().all(|&page_id| !pool.is_dirty(page_id)));
+        }
+
+        cleanup_file(&temp_path);
+        Ok(())
+    }
+
     #[test]
     fn test_storage_engine_integration() -> Result<(), Box<dyn std::error::Error>> {
         let (temp_path, mut storage_engine) = setup_storage_engine()?;
@@ -0,0 +1,103 @@
+use database::error::DatabaseError;
+use database::storage::cancellation::CancellationToken;
+use database::{storage::storage_engine::StorageEngine, Document, Value};
+use std::sync::mpsc;
+use std::thread;
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+// Roughly four documents per page
+fn fill_pages(engine: &mut StorageEngine, count: i32) {
+    for i in 0..count {
+        let mut doc = Document::new();
+        doc.set("n", Value::I32(i));
+        doc.set("padding", Value::String("x".repeat(1800)));
+        engine.insert_document(&doc).expect("insert failed");
+    }
+}
+
+fn is_cancelled(err: &DatabaseError) -> bool {
+    matches!(err, DatabaseError::Cancelled)
+}
+
+#[test]
+fn test_scan_cancelled_from_another_thread() {
+    let (mut engine, _dir) = create_engine();
+    fill_pages(&mut engine, 40);
+    let total = engine.count().expect("count failed");
+
+    let token = CancellationToken::new();
+    let (first_page_tx, first_page_rx) = mpsc::channel();
+    let (cancelled_tx, cancelled_rx) = mpsc::channel();
+
+    let scan_token = token.clone();
+    let scan = thread::spawn(move || {
+        let mut first_page = None;
+        let mut visited = 0;
+        let result = engine.scan_with(&scan_token, |document_id, _| {
+            visited += 1;
+            // Hand control to the canceller once the first page has started, and wait
+            // until the token is set so the outcome does not depend on timing
+            if first_page.is_none() {
+                first_page = Some(document_id.page_id());
+                first_page_tx.send(()).unwrap();
+                cancelled_rx.recv().unwrap();
+            }
+        });
+        (result, visited)
+    });
+
+    let canceller = thread::spawn(move || {
+        first_page_rx.recv().unwrap();
+        token.cancel();
+        cancelled_tx.send(()).unwrap();
+    });
+
+    canceller.join().unwrap();
+    let (result, visited) = scan.join().unwrap();
+
+    let err = result.expect_err("scan should have been cancelled");
+    assert!(is_cancelled(&err), "unexpected error: {}", err);
+    assert!(visited > 0);
+    assert!(visited < total, "scan processed all {} documents", total);
+}
+
+#[test]
+fn test_cancelled_token_stops_before_first_page() {
+    let (mut engine, _dir) = create_engine();
+    fill_pages(&mut engine, 8);
+
+    let token = CancellationToken::new();
+    token.cancel();
+
+    let err = engine.scan_all_cancellable(&token).expect_err("scan should fail");
+    assert!(is_cancelled(&err));
+
+    let err = engine
+        .find_range_cancellable("n", Some(Value::I32(0)), None, (true, true), &token)
+        .expect_err("find should fail");
+    assert!(is_cancelled(&err));
+
+    engine.create_index("n").expect("create_index failed");
+    let err = engine
+        .find_range_cancellable("n", Some(Value::I32(0)), None, (true, true), &token)
+        .expect_err("indexed find should fail");
+    assert!(is_cancelled(&err));
+
+    let err = engine.vacuum_cancellable(&token).expect_err("vacuum should fail");
+    assert!(is_cancelled(&err));
+
+    // Nothing was lost by the aborted operations
+    assert_eq!(engine.scan_all().expect("scan failed").len(), 8);
+}
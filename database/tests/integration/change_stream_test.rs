@@ -0,0 +1,84 @@
+use database::storage::change_stream::ChangeEvent;
+use database::storage::storage_engine::StorageEngine;
+use database::{Document, Value};
+use std::sync::mpsc::TryRecvError;
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn padded_doc(bytes: usize) -> Document {
+    let mut doc = Document::new();
+    doc.set("padding", Value::String("x".repeat(bytes)));
+    doc
+}
+
+#[test]
+fn test_writes_are_published_in_order() {
+    let (mut engine, _dir) = create_engine();
+    let changes = engine.subscribe();
+
+    let inserted = engine.insert_document(&padded_doc(10)).expect("insert failed");
+    let updated = engine
+        .update_document(&inserted, &padded_doc(20))
+        .expect("update failed");
+    engine.delete_document(&updated).expect("delete failed");
+
+    let events: Vec<ChangeEvent> = changes.try_iter().collect();
+    assert_eq!(
+        events,
+        vec![
+            ChangeEvent::Inserted(inserted),
+            ChangeEvent::Updated(updated),
+            ChangeEvent::Deleted(updated),
+        ]
+    );
+    // Ids keep the document's `_id` across the relocation
+    assert_eq!(events[1].document_id().object_id(), inserted.object_id());
+}
+
+#[test]
+fn test_failed_writes_publish_nothing() {
+    let (mut engine, _dir) = create_engine();
+    engine.create_unique_index("padding").expect("create index failed");
+    let id = engine.insert_document(&padded_doc(10)).expect("insert failed");
+
+    let changes = engine.subscribe();
+    assert!(engine.insert_document(&padded_doc(10)).is_err());
+    engine.delete_document(&id).expect("delete failed");
+    assert!(engine.delete_document(&id).is_err());
+
+    assert_eq!(changes.try_recv(), Ok(ChangeEvent::Deleted(id)));
+    assert_eq!(changes.try_recv(), Err(TryRecvError::Empty));
+}
+
+#[test]
+fn test_lagging_subscriber_is_disconnected() {
+    let (mut engine, _dir) = create_engine();
+    let lagging = engine.subscribe_with_capacity(2);
+    let keeping_up = engine.subscribe();
+
+    let ids: Vec<_> = (0..3)
+        .map(|_| engine.insert_document(&padded_doc(10)).expect("insert failed"))
+        .collect();
+
+    // Writes went ahead; the lagging receiver gets what fit, then learns it fell behind
+    assert_eq!(lagging.try_recv(), Ok(ChangeEvent::Inserted(ids[0])));
+    assert_eq!(lagging.try_recv(), Ok(ChangeEvent::Inserted(ids[1])));
+    assert_eq!(lagging.try_recv(), Err(TryRecvError::Disconnected));
+    assert_eq!(keeping_up.try_iter().count(), 3);
+
+    // Truncating reports every removed document
+    engine.truncate().expect("truncate failed");
+    let deleted: Vec<ChangeEvent> = keeping_up.try_iter().collect();
+    assert_eq!(deleted, ids.iter().copied().map(ChangeEvent::Deleted).collect::<Vec<_>>());
+}
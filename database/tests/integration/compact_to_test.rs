@@ -0,0 +1,85 @@
+use database::storage::storage_engine::StorageEngine;
+use database::{Document, Value};
+use std::fs;
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn make_doc(i: i32) -> Document {
+    let mut doc = Document::new();
+    doc.set("n", Value::I32(i));
+    doc.set("padding", Value::String("x".repeat(300)));
+    doc
+}
+
+// Documents sorted by `n`, for comparing two databases
+fn sorted_documents(engine: &mut StorageEngine) -> Vec<Document> {
+    let mut documents: Vec<Document> = engine
+        .scan_all()
+        .expect("scan failed")
+        .into_iter()
+        .map(|(_, doc)| doc)
+        .collect();
+    documents.sort_by_key(|doc| doc.get("n").and_then(Value::as_i32));
+    documents
+}
+
+#[test]
+fn test_compact_to_writes_smaller_copy_of_survivors() {
+    let (mut engine, dir) = create_engine();
+    let ids: Vec<_> = (0..200)
+        .map(|i| engine.insert_document(&make_doc(i)).expect("insert failed"))
+        .collect();
+    // Delete every other document, leaving holes in every page
+    for id in ids.iter().step_by(2) {
+        engine.delete_document(id).expect("delete failed");
+    }
+    engine.checkpoint().expect("checkpoint failed");
+
+    let dest = dir.path().join("compacted.db");
+    engine.compact_to(&dest).expect("compaction failed");
+
+    let source_size = fs::metadata(dir.path().join("test.db")).unwrap().len();
+    let dest_size = fs::metadata(&dest).unwrap().len();
+    assert!(
+        dest_size < source_size,
+        "copy is {} bytes, source {}",
+        dest_size,
+        source_size
+    );
+
+    let mut copy = StorageEngine::new(&dest, 10).expect("open copy failed");
+    let survivors = sorted_documents(&mut engine);
+    assert_eq!(survivors.len(), 100);
+    assert_eq!(sorted_documents(&mut copy), survivors);
+
+    // Retrievable by id and queryable through a fresh index
+    for (document_id, doc) in copy.scan_all().expect("scan failed") {
+        assert_eq!(copy.get_document(&document_id).expect("get failed"), doc);
+    }
+    copy.create_index("n").expect("create index failed");
+    let found = copy.find_by_field("n", &Value::I32(41)).expect("find failed");
+    assert_eq!(found.len(), 1);
+    assert!(copy.find_by_field("n", &Value::I32(40)).expect("find failed").is_empty());
+}
+
+#[test]
+fn test_compact_to_refuses_existing_destination() {
+    let (mut engine, dir) = create_engine();
+    engine.insert_document(&make_doc(1)).expect("insert failed");
+
+    let dest = dir.path().join("taken.db");
+    fs::write(&dest, b"keep me").unwrap();
+    assert!(engine.compact_to(&dest).is_err());
+    assert_eq!(fs::read(&dest).unwrap(), b"keep me");
+}
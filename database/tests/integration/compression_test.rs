@@ -0,0 +1,88 @@
+use database::bson::serialize_document;
+use database::storage::compression::Compression;
+use database::storage::storage_engine::StorageEngine;
+use database::{Document, Value};
+use std::path::Path;
+use tempfile::tempdir;
+
+fn create_engine(path: &Path, compression: Compression) -> StorageEngine {
+    let _db_file = database::storage::file::DatabaseFile::create(path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    StorageEngine::new(path, 10)
+        .expect("Failed to create storage engine")
+        .with_compression(compression)
+}
+
+fn text_doc(i: i32) -> Document {
+    let mut doc = Document::new();
+    doc.set("n", Value::I32(i));
+    doc.set("body", Value::String("the quick brown fox ".repeat(150)));
+    doc
+}
+
+#[test]
+fn test_compressed_documents_take_less_space() {
+    let dir = tempdir().unwrap();
+    let mut plain = create_engine(&dir.path().join("plain.db"), Compression::None);
+    let mut compressed = create_engine(&dir.path().join("zstd.db"), Compression::Zstd(3));
+    assert_eq!(compressed.compression(), Compression::Zstd(3));
+
+    for i in 0..20 {
+        let doc = text_doc(i);
+        let plain_id = plain.insert_document(&doc).expect("insert failed");
+        let compressed_id = compressed.insert_document(&doc).expect("insert failed");
+
+        let plain_size = plain.document_size(&plain_id).expect("size failed");
+        let compressed_size = compressed.document_size(&compressed_id).expect("size failed");
+        assert!(compressed_size * 4 < plain_size, "{} vs {}", compressed_size, plain_size);
+
+        // Decompressed back to exactly the BSON that was written
+        let bson = serialize_document(&doc).unwrap();
+        assert_eq!(compressed.get_document_raw(&compressed_id).expect("raw failed"), bson);
+        assert_eq!(compressed.get_document(&compressed_id).expect("get failed"), doc);
+    }
+    plain.checkpoint().expect("checkpoint failed");
+    compressed.checkpoint().expect("checkpoint failed");
+    assert!(compressed.page_ids().len() < plain.page_ids().len());
+}
+
+#[test]
+fn test_compressed_and_plain_documents_coexist() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.db");
+    let mut engine = create_engine(&path, Compression::Zstd(3));
+    let compressed_id = engine.insert_document(&text_doc(1)).expect("insert failed");
+    engine.checkpoint().expect("checkpoint failed");
+    drop(engine);
+
+    // Reopened without compression: old documents still read, new ones are plain
+    let mut engine = StorageEngine::new(&path, 10).expect("reopen failed");
+    assert_eq!(engine.compression(), Compression::None);
+    let plain_id = engine.insert_document(&text_doc(2)).expect("insert failed");
+    assert!(
+        engine.document_size(&compressed_id).unwrap() * 4
+            < engine.document_size(&plain_id).unwrap()
+    );
+
+    let mut numbers: Vec<i32> = engine
+        .scan_all()
+        .expect("scan failed")
+        .into_iter()
+        .filter_map(|(_, doc)| doc.get("n").and_then(Value::as_i32))
+        .collect();
+    numbers.sort();
+    assert_eq!(numbers, vec![1, 2]);
+
+    // Rewriting a compressed document with compression off stores it plain
+    let updated = engine
+        .update_document(&compressed_id, &text_doc(3))
+        .expect("update failed");
+    let doc = engine.get_document(&updated).expect("get failed");
+    assert_eq!(doc.get("n"), Some(&Value::I32(3)));
+    assert_eq!(
+        engine.document_size(&updated).unwrap(),
+        engine.document_size(&plain_id).unwrap()
+    );
+}
@@ -0,0 +1,170 @@
+use database::{
+    bson::serialize_document,
+    storage::conflict::{DeepMerge, KeepExisting, Resolution, TakeIncoming},
+    storage::storage_engine::{StorageEngine, UpsertOutcome},
+    Document, Value,
+};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn address(fields: &[(&str, &str)]) -> Value {
+    Value::Object(
+        fields
+            .iter()
+            .map(|(k, v)| (k.to_string(), Value::String(v.to_string())))
+            .collect::<BTreeMap<_, _>>(),
+    )
+}
+
+// A stored document and an incoming one with the same `_id`
+fn stored_and_incoming(engine: &mut StorageEngine) -> (Document, Vec<u8>) {
+    let mut stored = Document::new();
+    stored.set("name", Value::String("old".to_string()));
+    stored.set("visits", Value::I32(3));
+    stored.set("address", address(&[("city", "Oslo"), ("zip", "0150")]));
+    engine.insert_document(&stored).unwrap();
+
+    let mut incoming = stored.clone();
+    incoming.remove("visits");
+    incoming.set("name", Value::String("new".to_string()));
+    incoming.set("address", address(&[("city", "Bergen")]));
+    (stored, serialize_document(&incoming).unwrap())
+}
+
+#[test]
+fn test_import_keep_existing() {
+    let (mut engine, _temp_dir) = create_engine();
+    let (stored, dump) = stored_and_incoming(&mut engine);
+
+    let report = engine
+        .import_bson_with_resolver(Cursor::new(dump), &mut KeepExisting)
+        .unwrap();
+    assert!(report.imported.is_empty());
+    assert!(report.errors.is_empty());
+    assert_eq!(report.kept.len(), 1);
+
+    assert_eq!(engine.get_document(&report.kept[0]).unwrap(), stored);
+    assert_eq!(engine.count().unwrap(), 1);
+}
+
+#[test]
+fn test_import_take_incoming() {
+    let (mut engine, _temp_dir) = create_engine();
+    let (stored, dump) = stored_and_incoming(&mut engine);
+
+    let report = engine
+        .import_bson_with_resolver(Cursor::new(dump), &mut TakeIncoming)
+        .unwrap();
+    assert_eq!(report.imported_count(), 1);
+    assert!(report.kept.is_empty());
+
+    let doc = engine.get_document(&report.imported[0]).unwrap();
+    assert_eq!(doc.id(), stored.id());
+    assert_eq!(doc.get("name"), Some(&Value::String("new".to_string())));
+    assert_eq!(doc.get("visits"), None);
+    assert_eq!(doc.get("address"), Some(&address(&[("city", "Bergen")])));
+    assert_eq!(engine.count().unwrap(), 1);
+}
+
+#[test]
+fn test_import_deep_merge() {
+    let (mut engine, _temp_dir) = create_engine();
+    let (stored, dump) = stored_and_incoming(&mut engine);
+
+    let report = engine
+        .import_bson_with_resolver(Cursor::new(dump), &mut DeepMerge)
+        .unwrap();
+    assert_eq!(report.imported_count(), 1);
+
+    let doc = engine.get_document(&report.imported[0]).unwrap();
+    assert_eq!(doc.id(), stored.id());
+    assert_eq!(doc.get("name"), Some(&Value::String("new".to_string())));
+    assert_eq!(doc.get("visits"), Some(&Value::I32(3)));
+    assert_eq!(
+        doc.get("address"),
+        Some(&address(&[("city", "Bergen"), ("zip", "0150")]))
+    );
+    assert_eq!(engine.count().unwrap(), 1);
+}
+
+#[test]
+fn test_upsert_unique_index_conflict() {
+    let (mut engine, _temp_dir) = create_engine();
+    engine.create_unique_index("email").unwrap();
+
+    let mut stored = Document::new();
+    stored.set("email", Value::String("a@example.com".to_string()));
+    stored.set("name", Value::String("old".to_string()));
+    engine.insert_document(&stored).unwrap();
+
+    // Different `_id`, same unique email
+    let mut incoming = Document::new();
+    incoming.set("email", Value::String("a@example.com".to_string()));
+    incoming.set("name", Value::String("new".to_string()));
+
+    let outcome = engine.upsert(&incoming, &mut TakeIncoming).unwrap();
+    let UpsertOutcome::Replaced(document_id) = outcome else {
+        panic!("expected a replacement, got {:?}", outcome);
+    };
+    let doc = engine.get_document(&document_id).unwrap();
+    assert_eq!(doc.id(), stored.id());
+    assert_eq!(doc.get("name"), Some(&Value::String("new".to_string())));
+
+    // A closure resolver that refuses
+    let mut seen = None;
+    let outcome = engine
+        .upsert(&incoming, &mut |existing: &Document, _: &Document| {
+            seen = existing.get("name").cloned();
+            Resolution::Skip
+        })
+        .unwrap();
+    assert!(matches!(outcome, UpsertOutcome::Kept(_)));
+    assert_eq!(seen, Some(Value::String("new".to_string())));
+
+    // No collision
+    incoming.set("email", Value::String("b@example.com".to_string()));
+    let outcome = engine.upsert(&incoming, &mut KeepExisting).unwrap();
+    assert!(matches!(outcome, UpsertOutcome::Inserted(_)));
+    assert_eq!(engine.count().unwrap(), 2);
+}
+
+#[test]
+fn test_upsert_looks_up_indexed_id_without_scanning() {
+    let (mut engine, _temp_dir) = create_engine();
+    engine.create_unique_index("_id").unwrap();
+
+    // Enough documents for several pages
+    let mut last = Document::new();
+    for i in 0..40 {
+        last = Document::new();
+        last.set("i", Value::I32(i));
+        last.set("padding", Value::String("x".repeat(1000)));
+        engine.insert_document(&last).unwrap();
+    }
+    assert!(engine.page_ids().len() > 2);
+
+    let mut incoming = Document::with_id(*last.get_id().unwrap());
+    incoming.set("i", Value::I32(-1));
+    let before = engine.buffer_pool_stats();
+    let outcome = engine.upsert(&incoming, &mut TakeIncoming).unwrap();
+    let after = engine.buffer_pool_stats();
+    assert!(matches!(outcome, UpsertOutcome::Replaced(_)));
+
+    // Reading and rewriting the stored document touches only its own page
+    let page_reads = (after.hits + after.misses) - (before.hits + before.misses);
+    assert!(page_reads < engine.page_ids().len() as u64, "{} page reads", page_reads);
+    assert_eq!(engine.count().unwrap(), 40);
+}
@@ -0,0 +1,83 @@
+use database::storage::storage_engine::{DocumentId, StorageEngine};
+use database::{Document, Value};
+use std::path::Path;
+use tempfile::tempdir;
+
+const POOL_PAGES: usize = 10;
+
+fn padded_doc(n: i32) -> Document {
+    let mut doc = Document::new();
+    doc.set("n", Value::I32(n));
+    doc.set("padding", Value::String("x".repeat(5000)));
+    doc
+}
+
+// A checkpointed database with one document on each of 30 pages
+fn populated_database(path: &Path) -> Vec<DocumentId> {
+    database::storage::file::DatabaseFile::create(path).expect("Failed to create database file");
+    let mut engine = StorageEngine::new(path, POOL_PAGES).expect("Failed to create storage engine");
+    let ids: Vec<DocumentId> = (0..30)
+        .map(|n| engine.insert_document(&padded_doc(n)).expect("insert failed"))
+        .collect();
+    engine.checkpoint().expect("checkpoint failed");
+    ids
+}
+
+// Updates each document in place, dirtying the pages one after another
+fn update_all(
+    engine: &mut StorageEngine,
+    ids: &[DocumentId],
+    mut after_each: impl FnMut(&StorageEngine),
+) {
+    for (n, id) in ids.iter().enumerate() {
+        engine
+            .update_document(id, &padded_doc(n as i32 + 100))
+            .expect("update failed");
+        after_each(engine);
+    }
+}
+
+#[test]
+fn test_without_high_water_mark_evictions_write_back() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.db");
+    let ids = populated_database(&path);
+    let mut engine = StorageEngine::new(&path, POOL_PAGES).expect("reopen failed");
+    assert_eq!(engine.dirty_high_water_mark(), None);
+
+    update_all(&mut engine, &ids, |_| {});
+    let stats = engine.buffer_pool_stats();
+    assert_eq!(stats.dirty_pages, POOL_PAGES);
+    assert_eq!(stats.early_flushes, 0);
+    assert_eq!(stats.dirty_evictions, (ids.len() - POOL_PAGES) as u64);
+}
+
+#[test]
+fn test_high_water_mark_flushes_before_pool_is_dirty() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.db");
+    let ids = populated_database(&path);
+    let mut engine = StorageEngine::new(&path, POOL_PAGES)
+        .expect("reopen failed")
+        .with_dirty_high_water_mark(0.5);
+    assert_eq!(engine.dirty_high_water_mark(), Some(0.5));
+
+    update_all(&mut engine, &ids, |engine| {
+        // Never more than half the pool is left dirty
+        assert!(engine.buffer_pool_stats().dirty_pages <= POOL_PAGES / 2);
+    });
+    let stats = engine.buffer_pool_stats();
+    assert!(stats.early_flushes > 0);
+    // Write-back happened ahead of time, so every eviction found a clean page
+    assert_eq!(stats.dirty_evictions, 0);
+    assert_eq!(stats.early_flushes + stats.dirty_pages as u64, ids.len() as u64);
+
+    // Pages written early and at the checkpoint together hold every update
+    engine.checkpoint().expect("checkpoint failed");
+    drop(engine);
+    let mut engine = StorageEngine::new(&path, POOL_PAGES).expect("reopen failed");
+    for (n, id) in ids.iter().enumerate() {
+        let doc = engine.get_document(id).expect("get failed");
+        assert_eq!(doc.get("n"), Some(&Value::I32(n as i32 + 100)));
+    }
+}
@@ -0,0 +1,121 @@
+use database::{
+    page_layout::PageLayout,
+    storage::document_cache::CachePolicy,
+    storage::storage_engine::StorageEngine,
+    Document, Value,
+};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10)
+        .expect("Failed to create storage engine")
+        .with_document_cache(16);
+    (engine, temp_dir)
+}
+
+fn named(name: &str) -> Document {
+    let mut doc = Document::new();
+    doc.set("name", Value::String(name.to_string()));
+    doc
+}
+
+#[test]
+fn test_single_insert_is_cached() {
+    let (mut engine, _temp_dir) = create_engine();
+    assert_eq!(engine.cache_policy(), CachePolicy::WriteThrough);
+
+    let doc = named("alice");
+    let doc_id = engine.insert_document(&doc).expect("insert failed");
+
+    let retrieved = engine.get_document(&doc_id).expect("get failed");
+    assert_eq!(retrieved, doc);
+    let stats = engine.cache_stats();
+    assert_eq!((stats.hits, stats.misses), (1, 0));
+}
+
+#[test]
+fn test_bulk_insert_bypasses_cache() {
+    let (mut engine, _temp_dir) = create_engine();
+    assert_eq!(engine.bulk_cache_policy(), CachePolicy::WriteAround);
+
+    let docs = vec![named("a"), named("b"), named("c")];
+    let ids = engine.insert_many(&docs).expect("insert_many failed");
+    assert_eq!(engine.cache_stats().entries, 0);
+
+    assert_eq!(engine.get_document(&ids[0]).expect("get failed"), docs[0]);
+    let stats = engine.cache_stats();
+    assert_eq!((stats.hits, stats.misses), (0, 1));
+
+    // The miss populated the cache
+    engine.get_document(&ids[0]).expect("get failed");
+    assert_eq!(engine.cache_stats().hits, 1);
+}
+
+#[test]
+fn test_policy_overrides() {
+    let (engine, _temp_dir) = create_engine();
+    let mut engine = engine
+        .with_cache_policy(CachePolicy::WriteAround)
+        .with_bulk_cache_policy(CachePolicy::WriteThrough);
+
+    let single = engine.insert_document(&named("single")).expect("insert failed");
+    let bulk = engine.insert_many(&[named("bulk")]).expect("insert_many failed");
+    let explicit = engine
+        .insert_many_with_cache_policy(&[named("explicit")], CachePolicy::WriteAround)
+        .expect("insert_many failed");
+
+    engine.get_document(&single).expect("get failed");
+    engine.get_document(&bulk[0]).expect("get failed");
+    engine.get_document(&explicit[0]).expect("get failed");
+    let stats = engine.cache_stats();
+    assert_eq!((stats.hits, stats.misses), (1, 2));
+}
+
+#[test]
+fn test_writes_keep_cache_coherent() {
+    let (mut engine, _temp_dir) = create_engine();
+
+    let doc = named("before");
+    let doc_id = engine.insert_document(&doc).expect("insert failed");
+
+    // A write-around update must not leave the old version cached
+    let mut engine = engine.with_cache_policy(CachePolicy::WriteAround);
+    let mut updated = doc.clone();
+    updated.set("name", Value::String("after".to_string()));
+    let doc_id = engine
+        .update_document(&doc_id, &updated)
+        .expect("update failed");
+    assert_eq!(
+        engine.get_document(&doc_id).expect("get failed").get("name"),
+        Some(&Value::String("after".to_string()))
+    );
+
+    engine.delete_document(&doc_id).expect("delete failed");
+    assert!(engine.get_document(&doc_id).is_err());
+    assert_eq!(engine.cache_stats().entries, 0);
+}
+
+#[test]
+fn test_failed_update_drops_cached_version() {
+    let (mut engine, _temp_dir) = create_engine();
+
+    let doc = named("before");
+    let doc_id = engine.insert_document(&doc).expect("insert failed");
+
+    let mut updated = doc.clone();
+    updated.set("padding", Value::String("x".repeat(PageLayout::max_document_size())));
+    assert!(engine.update_document(&doc_id, &updated).is_err());
+
+    // The update read the cached version; the next read goes back to the page, which
+    // still holds the original
+    assert_eq!(engine.get_document(&doc_id).expect("get failed"), doc);
+    let stats = engine.cache_stats();
+    assert_eq!((stats.hits, stats.misses), (1, 1));
+}
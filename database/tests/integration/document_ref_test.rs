@@ -0,0 +1,160 @@
+use database::{
+    error::DatabaseError,
+    storage::storage_engine::{DocumentId, StorageEngine},
+    Document, Value,
+};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn named(name: &str) -> Document {
+    let mut doc = Document::new();
+    doc.set("name", Value::String(name.to_string()));
+    doc
+}
+
+fn insert_referrer(engine: &mut StorageEngine, target: DocumentId) -> DocumentId {
+    let mut doc = named("referrer");
+    doc.set("author", Value::DocumentRef(target));
+    engine.insert_document(&doc).expect("insert failed")
+}
+
+fn assert_dangling(result: Result<Document, DatabaseError>) {
+    match result {
+        Err(DatabaseError::DanglingReference(_)) => {}
+        other => panic!("Expected DanglingReference, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_resolve_ref_fetches_target() {
+    let (mut engine, _temp_dir) = create_engine();
+    let target = engine.insert_document(&named("alice")).expect("insert failed");
+    let referrer = insert_referrer(&mut engine, target);
+
+    let stored = engine.get_document(&referrer).expect("get failed");
+    let reference = stored.get("author").expect("reference should be stored").clone();
+    assert_eq!(reference, Value::DocumentRef(target));
+
+    let resolved = engine.resolve_ref(&reference).expect("resolve failed");
+    assert_eq!(resolved.get("name"), Some(&Value::String("alice".to_string())));
+}
+
+#[test]
+fn test_resolve_ref_to_deleted_target_is_dangling() {
+    let (mut engine, _temp_dir) = create_engine();
+    let target = engine.insert_document(&named("alice")).expect("insert failed");
+    let referrer = insert_referrer(&mut engine, target);
+    engine.delete_document(&target).expect("delete failed");
+
+    let stored = engine.get_document(&referrer).expect("get failed");
+    assert_dangling(engine.resolve_ref(stored.get("author").unwrap()));
+}
+
+#[test]
+fn test_resolve_ref_to_reused_slot_is_dangling() {
+    let (mut engine, _temp_dir) = create_engine();
+    let target = engine.insert_document(&named("alice")).expect("insert failed");
+    let referrer = insert_referrer(&mut engine, target);
+    engine.delete_document(&target).expect("delete failed");
+
+    // The freed slot is reused by the next insert
+    let replacement = engine.insert_document(&named("bob")).expect("insert failed");
+    assert_eq!(replacement, target);
+
+    let stored = engine.get_document(&referrer).expect("get failed");
+    assert_dangling(engine.resolve_ref(stored.get("author").unwrap()));
+}
+
+#[test]
+fn test_resolve_ref_to_missing_page_is_dangling() {
+    let (mut engine, _temp_dir) = create_engine();
+    engine.insert_document(&named("alice")).expect("insert failed");
+
+    assert_dangling(engine.resolve_ref(&Value::DocumentRef(DocumentId::new(99, 0))));
+    assert_dangling(engine.resolve_ref(&Value::DocumentRef(DocumentId::new(0, 0))));
+}
+
+#[test]
+fn test_resolve_ref_rejects_non_reference() {
+    let (mut engine, _temp_dir) = create_engine();
+
+    match engine.resolve_ref(&Value::I32(1)) {
+        Err(DatabaseError::Validation(msg)) => assert!(msg.contains("i32")),
+        other => panic!("Expected a validation error, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_id_round_trips_through_bytes_and_hex() {
+    let (mut engine, _temp_dir) = create_engine();
+    let target = engine.insert_document(&named("alice")).expect("insert failed");
+
+    let hex = target.to_hex();
+    assert_eq!(hex.len(), 2 * target.to_bytes().len());
+    for parsed in [
+        DocumentId::from_hex(&hex).expect("parse failed"),
+        DocumentId::from_bytes(&target.to_bytes()).expect("parse failed"),
+    ] {
+        assert_eq!(parsed, target);
+        assert_eq!(parsed.object_id(), target.object_id());
+        let doc = engine.get_document(&parsed).expect("get failed");
+        assert_eq!(doc.get("name"), Some(&Value::String("alice".to_string())));
+    }
+
+    // Stored as a string in another document, it finds its way back
+    let mut doc = named("referrer");
+    doc.set("author", Value::String(hex));
+    let referrer = engine.insert_document(&doc).expect("insert failed");
+    let stored = engine.get_document(&referrer).expect("get failed");
+    let author = DocumentId::from_hex(stored.get("author").unwrap().as_str().unwrap())
+        .expect("parse failed");
+    let resolved = engine.get_document(&author).expect("get failed");
+    assert_eq!(resolved.get("name"), Some(&Value::String("alice".to_string())));
+
+    // Untagged ids round-trip as well
+    let untagged = DocumentId::new(u64::MAX, 7);
+    let parsed = DocumentId::from_hex(&untagged.to_hex()).expect("parse failed");
+    assert_eq!((parsed, parsed.object_id()), (untagged, None));
+}
+
+#[test]
+fn test_parsed_id_still_detects_reused_slot() {
+    let (mut engine, _temp_dir) = create_engine();
+    let target = engine.insert_document(&named("alice")).expect("insert failed");
+    let hex = target.to_hex();
+    engine.delete_document(&target).expect("delete failed");
+    assert_eq!(engine.insert_document(&named("bob")).expect("insert failed"), target);
+
+    let parsed = DocumentId::from_hex(&hex).expect("parse failed");
+    assert!(matches!(engine.get_document(&parsed), Err(DatabaseError::Corruption(_))));
+}
+
+#[test]
+fn test_malformed_id_is_rejected() {
+    let valid = DocumentId::new(1, 2).to_hex();
+    let mut bad_flag = DocumentId::new(1, 2).to_bytes();
+    bad_flag[10] = 2;
+
+    let too_long = format!("{}00", valid);
+    let bad_flag = hex::encode(bad_flag);
+    for input in ["", "not hex", &valid[..valid.len() - 2], &too_long, &bad_flag] {
+        match DocumentId::from_hex(input) {
+            Err(DatabaseError::Validation(msg)) => {
+                assert!(msg.contains("invalid document id"), "{}", msg)
+            }
+            other => panic!("Expected a validation error for {:?}, got {:?}", input, other),
+        }
+    }
+    assert!(matches!(DocumentId::from_bytes(&[0; 10]), Err(DatabaseError::Validation(_))));
+}
@@ -0,0 +1,99 @@
+use database::document::predicate::Predicate;
+use database::storage::storage_engine::StorageEngine;
+use database::{Document, Value};
+use serde_json::json;
+use std::collections::BTreeMap;
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn make_doc(name: &str, city: &str, tags: &[&str]) -> Document {
+    let mut address = BTreeMap::new();
+    address.insert("city".to_string(), Value::String(city.to_string()));
+    address.insert("zip".to_string(), Value::I32(12345));
+
+    let mut doc = Document::new();
+    doc.set("name", Value::String(name.to_string()));
+    doc.set("address", Value::Object(address));
+    doc.set(
+        "tags",
+        Value::Array(tags.iter().map(|tag| Value::String(tag.to_string())).collect()),
+    );
+    doc
+}
+
+// The `_id` of a dumped document
+fn dumped_id(json: &serde_json::Value) -> &str {
+    json["_id"].as_str().expect("dumped document lacks an _id")
+}
+
+#[test]
+fn test_dump_json_returns_every_document_with_nested_fields() {
+    let (mut engine, _dir) = create_engine();
+    let people = [("Alice", "London"), ("Bob", "Paris"), ("Carol", "Lisbon")];
+    let docs: Vec<Document> = people
+        .iter()
+        .map(|(name, city)| make_doc(name, city, &["ops"]))
+        .collect();
+    for doc in &docs {
+        engine.insert_document(doc).expect("insert failed");
+    }
+
+    let dump = engine.dump_json(None).expect("dump failed");
+    assert_eq!(dump.len(), docs.len());
+    for (doc, (name, city)) in docs.iter().zip(people) {
+        let id = doc.id().as_object_id().expect("inserted with an ObjectId").to_hex();
+        let json = dump
+            .iter()
+            .find(|json| dumped_id(json) == id)
+            .expect("document missing from dump");
+        assert_eq!(json["name"], json!(name));
+        assert_eq!(json["address"], json!({"city": city, "zip": 12345}));
+        assert_eq!(json["tags"], json!(["ops"]));
+    }
+
+    engine
+        .insert_document(&make_doc("Dave", "Oslo", &["admin", "dev"]))
+        .expect("insert failed");
+    let dump = engine.dump_json(None).expect("dump failed");
+    let dave = dump.iter().find(|json| json["name"] == "Dave").unwrap();
+    assert_eq!(
+        *dave,
+        json!({
+            "_id": dumped_id(dave),
+            "name": "Dave",
+            "address": {"city": "Oslo", "zip": 12345},
+            "tags": ["admin", "dev"],
+        })
+    );
+}
+
+#[test]
+fn test_dump_json_applies_predicate() {
+    let (mut engine, _dir) = create_engine();
+    assert!(engine.dump_json(None).expect("dump failed").is_empty());
+
+    for (name, city) in [("Alice", "London"), ("Bob", "Paris"), ("Dave", "London")] {
+        engine.insert_document(&make_doc(name, city, &[])).expect("insert failed");
+    }
+
+    let predicate = Predicate::eq("address.city", Value::String("London".to_string()));
+    let mut names: Vec<_> = engine
+        .dump_json(Some(&predicate))
+        .expect("dump failed")
+        .iter()
+        .map(|json| json["name"].as_str().unwrap().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["Alice", "Dave"]);
+}
@@ -0,0 +1,104 @@
+use database::{
+    document::predicate::Predicate,
+    storage::storage_engine::{QueryOptions, ScanOptions, StorageEngine},
+    Document, Value,
+};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+#[test]
+fn test_empty_document_round_trip() {
+    let (mut engine, _temp_dir) = create_engine();
+
+    let doc = Document::new();
+    assert!(doc.is_empty());
+    let doc_id = engine.insert_document(&doc).expect("insert failed");
+
+    let retrieved = engine.get_document(&doc_id).expect("get failed");
+    assert!(retrieved.is_empty());
+    assert_eq!(retrieved.len(), 0);
+    assert_eq!(retrieved.id(), doc.id());
+    assert_eq!(retrieved, doc);
+}
+
+#[test]
+fn test_empty_document_is_scanned_and_counted() {
+    let (mut engine, _temp_dir) = create_engine();
+
+    let mut named = Document::new();
+    named.set("name", Value::String("Alice".to_string()));
+    let empty = Document::new();
+
+    let named_id = engine.insert_document(&named).expect("insert failed");
+    let empty_id = engine.insert_document(&empty).expect("insert failed");
+
+    assert_eq!(engine.count().expect("count failed"), 2);
+
+    let scanned = engine
+        .scan(&ScanOptions::default())
+        .expect("scan failed")
+        .documents;
+    assert_eq!(scanned.len(), 2);
+    assert_eq!(scanned[0].0, named_id);
+    assert_eq!(scanned[1].0, empty_id);
+    assert!(scanned[1].1.is_empty());
+    assert_eq!(scanned[1].1.id(), empty.id());
+
+    // A field predicate never matches a document without fields
+    let found = engine
+        .find_matching(
+            &Predicate::eq_ignore_case("name", "alice"),
+            &QueryOptions::default(),
+        )
+        .expect("find failed");
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].0, named_id);
+}
+
+#[test]
+fn test_update_to_and_from_empty_document() {
+    let (mut engine, _temp_dir) = create_engine();
+
+    let mut doc = Document::new();
+    doc.set("name", Value::String("Bob".to_string()));
+    let doc_id = engine.insert_document(&doc).expect("insert failed");
+
+    // Clearing every field keeps the document and its id
+    let emptied = Document::with_id(*doc.get_id().unwrap());
+    let doc_id = engine
+        .update_document(&doc_id, &emptied)
+        .expect("update failed");
+    let retrieved = engine.get_document(&doc_id).expect("get failed");
+    assert!(retrieved.is_empty());
+    assert_eq!(retrieved.id(), doc.id());
+    assert_eq!(engine.count().expect("count failed"), 1);
+
+    // And fields can be added back
+    let doc_id = engine.update_document(&doc_id, &doc).expect("update failed");
+    let retrieved = engine.get_document(&doc_id).expect("get failed");
+    assert_eq!(retrieved.get("name"), Some(&Value::String("Bob".to_string())));
+}
+
+#[test]
+fn test_delete_empty_document() {
+    let (mut engine, _temp_dir) = create_engine();
+
+    let doc_id = engine
+        .insert_document(&Document::new())
+        .expect("insert failed");
+    engine.delete_document(&doc_id).expect("delete failed");
+
+    assert_eq!(engine.count().expect("count failed"), 0);
+    assert!(engine.get_document(&doc_id).is_err());
+}
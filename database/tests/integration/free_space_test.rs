@@ -0,0 +1,184 @@
+use database::storage::storage_engine::{DocumentId, StorageEngine};
+use database::{Document, Value};
+use std::path::Path;
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn padded_doc(bytes: usize) -> Document {
+    let mut doc = Document::new();
+    doc.set("padding", Value::String("x".repeat(bytes)));
+    doc
+}
+
+// Free space the directory records for every page
+fn directory(engine: &StorageEngine) -> Vec<(u64, Option<usize>)> {
+    engine
+        .page_ids()
+        .into_iter()
+        .map(|page_id| (page_id, engine.free_space(page_id)))
+        .collect()
+}
+
+// The directory must match one rebuilt from the pages themselves
+fn assert_directory_accurate(engine: &mut StorageEngine) {
+    let maintained = directory(engine);
+    engine.rebuild_free_space_directory().expect("rebuild failed");
+    assert_eq!(maintained, directory(engine));
+}
+
+// Deterministic pseudo-random sizes
+fn sizes(count: usize) -> Vec<usize> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            50 + (state % 3000) as usize
+        })
+        .collect()
+}
+
+#[test]
+fn test_inserts_go_to_pages_with_room_per_directory() {
+    let (mut engine, _dir) = create_engine();
+
+    for size in sizes(60) {
+        let before = directory(&engine);
+        let id = engine.insert_document(&padded_doc(size)).expect("insert failed");
+        let stored = engine.document_size(&id).expect("size failed");
+
+        match before.iter().find(|(page_id, _)| *page_id == id.page_id()) {
+            Some((_, free)) => {
+                assert!(free.unwrap() >= stored, "page {} lacked room", id.page_id());
+            }
+            // A new page, so no existing page had room
+            None => assert!(before.iter().all(|(_, free)| free.unwrap() < stored)),
+        }
+        // First fit: no lower page had room
+        for (page_id, free) in &before {
+            if *page_id < id.page_id() {
+                assert!(free.unwrap() < stored);
+            }
+        }
+    }
+    assert_directory_accurate(&mut engine);
+}
+
+#[test]
+fn test_directory_stays_accurate_through_churn() {
+    let (mut engine, _dir) = create_engine();
+    let mut live: Vec<DocumentId> = Vec::new();
+
+    for (step, size) in sizes(200).into_iter().enumerate() {
+        match step % 5 {
+            0 | 1 => {
+                live.push(engine.insert_document(&padded_doc(size)).expect("insert failed"));
+            }
+            2 if !live.is_empty() => {
+                let id = live.swap_remove(size % live.len());
+                engine.delete_document(&id).expect("delete failed");
+            }
+            3 if !live.is_empty() => {
+                // Shrinks the document, freeing space on its page
+                let index = size % live.len();
+                live[index] = engine
+                    .update_document(&live[index], &padded_doc(size % 40))
+                    .expect("update failed");
+            }
+            _ => {
+                engine.compact_step(2).expect("compaction failed");
+            }
+        }
+        if step % 20 == 0 {
+            assert_directory_accurate(&mut engine);
+        }
+    }
+    engine.vacuum().expect("vacuum failed");
+    assert_directory_accurate(&mut engine);
+    assert_eq!(engine.count().expect("count failed"), live.len());
+}
+
+fn reopen(path: &Path) -> StorageEngine {
+    StorageEngine::new(path, 10).expect("Failed to reopen storage engine")
+}
+
+#[test]
+fn test_directory_survives_reopen() {
+    let (mut engine, dir) = create_engine();
+    let path = dir.path().join("test.db");
+    for size in sizes(30) {
+        engine.insert_document(&padded_doc(size)).expect("insert failed");
+    }
+
+    // Stored at the checkpoint and loaded on open
+    engine.checkpoint().expect("checkpoint failed");
+    let expected = directory(&engine);
+    drop(engine);
+    let mut engine = reopen(&path);
+    assert_eq!(directory(&engine), expected);
+    assert!(engine
+        .database_file
+        .read_free_space_directory()
+        .expect("read failed")
+        .is_some());
+
+    // Writes after the checkpoint leave the stored copy stale, so it is rebuilt instead
+    engine.insert_document(&padded_doc(100)).expect("insert failed");
+    engine.vacuum().expect("vacuum failed");
+    let expected = directory(&engine);
+    drop(engine);
+    let mut engine = reopen(&path);
+    assert!(engine
+        .database_file
+        .read_free_space_directory()
+        .expect("read failed")
+        .is_none());
+    assert_eq!(directory(&engine), expected);
+    assert_directory_accurate(&mut engine);
+}
+
+#[test]
+fn test_wal_recovery_after_directory_rebuild() {
+    let dir = tempdir().expect("Failed to create temp directory");
+    let path = dir.path().join("test.db");
+    let wal_path = dir.path().join("test.wal");
+    drop(database::storage::file::DatabaseFile::create(&path).expect("create failed"));
+
+    // No checkpoint ever runs, so the page allocated for the first insert leaves the
+    // stored directory stale and opening rebuilds it before the log is replayed
+    {
+        let mut engine = StorageEngine::new(&path, 10)
+            .expect("Failed to create storage engine")
+            .with_wal(&wal_path)
+            .expect("Failed to open WAL");
+        for _ in 0..5 {
+            engine.insert_document(&padded_doc(100)).expect("insert failed");
+        }
+    }
+
+    let mut engine = StorageEngine::new(&path, 10)
+        .expect("Failed to reopen storage engine")
+        .with_wal(&wal_path)
+        .expect("Failed to open WAL");
+    assert_eq!(engine.count().expect("count failed"), 5);
+    assert_directory_accurate(&mut engine);
+
+    // A later write must land beside the recovered documents, not over them
+    engine.insert_document(&padded_doc(100)).expect("insert failed");
+    engine.checkpoint().expect("checkpoint failed");
+    drop(engine);
+    let mut engine = reopen(&path);
+    assert_eq!(engine.count().expect("count failed"), 6);
+}
@@ -0,0 +1,69 @@
+use database::{
+    storage::{file::FsyncPolicy, storage_engine::StorageEngine},
+    Document, Value,
+};
+use tempfile::tempdir;
+
+fn create_engine(policy: FsyncPolicy) -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    // A tiny pool forces dirty pages to be written back through eviction.
+    let engine = StorageEngine::new(&db_path, 2)
+        .expect("Failed to create storage engine")
+        .with_fsync_policy(policy);
+    (engine, temp_dir)
+}
+
+fn insert_large_docs(engine: &mut StorageEngine, count: usize) {
+    for i in 0..count {
+        let mut doc = Document::new();
+        doc.set("payload", Value::String(format!("{}{}", i, "x".repeat(3000))));
+        engine.insert_document(&doc).expect("insert failed");
+    }
+}
+
+#[test]
+fn test_never_policy_issues_no_fsyncs() {
+    let (mut engine, _dir) = create_engine(FsyncPolicy::Never);
+
+    insert_large_docs(&mut engine, 10);
+    engine.checkpoint().expect("checkpoint failed");
+
+    assert!(engine.database_file.write_count() > 0);
+    assert_eq!(engine.database_file.sync_count(), 0);
+}
+
+#[test]
+fn test_every_write_policy_fsyncs_each_write_back() {
+    let (mut engine, _dir) = create_engine(FsyncPolicy::EveryWrite);
+
+    insert_large_docs(&mut engine, 10);
+    engine.checkpoint().expect("checkpoint failed");
+
+    let writes = engine.database_file.write_count();
+    assert!(writes > 0);
+    assert_eq!(engine.database_file.sync_count(), writes);
+}
+
+#[test]
+fn test_on_checkpoint_policy_fsyncs_only_on_checkpoint() {
+    let (mut engine, _dir) = create_engine(FsyncPolicy::OnCheckpoint);
+
+    insert_large_docs(&mut engine, 10);
+    assert!(
+        engine.database_file.write_count() > 0,
+        "evictions should have written pages back"
+    );
+    assert_eq!(engine.database_file.sync_count(), 0);
+
+    engine.checkpoint().expect("checkpoint failed");
+    assert_eq!(engine.database_file.sync_count(), 1);
+
+    engine.checkpoint().expect("checkpoint failed");
+    assert_eq!(engine.database_file.sync_count(), 2);
+}
@@ -0,0 +1,79 @@
+use database::{
+    Document, Value,
+    storage::{
+        file::{DatabaseFile, FIRST_DATA_PAGE_ID, HEADER_PAGE_ID},
+        page::PAGE_SIZE,
+        storage_engine::StorageEngine,
+    },
+};
+use tempfile::tempdir;
+
+fn make_doc(i: i32) -> Document {
+    let mut doc = Document::new();
+    doc.set("n", Value::I32(i));
+    doc.set("padding", Value::String("x".repeat(500)));
+    doc
+}
+
+#[test]
+fn test_fresh_database_reserves_header_page() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let mut db_file = DatabaseFile::create(&db_path).expect("Failed to create database file");
+    assert_eq!(db_file.page_count(), 1);
+    assert!(db_file.data_page_ids().is_empty());
+    assert!(db_file.read_page(HEADER_PAGE_ID).is_err());
+    assert_eq!(std::fs::metadata(&db_path).unwrap().len(), PAGE_SIZE as u64);
+
+    assert_eq!(db_file.allocate_page().unwrap(), FIRST_DATA_PAGE_ID);
+}
+
+#[test]
+fn test_header_fields_persist_across_reopen() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    {
+        let mut db_file = DatabaseFile::create(&db_path).expect("Failed to create database file");
+        for _ in 0..4 {
+            db_file.allocate_page().expect("allocate failed");
+        }
+        db_file.free_page(3).expect("free page failed");
+        db_file
+            .set_catalog_root(2)
+            .expect("set catalog root failed");
+    }
+
+    let mut db_file = DatabaseFile::open(&db_path).expect("Failed to reopen database file");
+    assert_eq!(db_file.page_count(), 5);
+    assert_eq!(db_file.data_page_ids(), FIRST_DATA_PAGE_ID..5);
+    assert_eq!(db_file.free_list_head(), 3);
+    assert_eq!(db_file.catalog_root(), 2);
+    assert_eq!(db_file.allocate_page().unwrap(), 3);
+    assert_eq!(db_file.allocate_page().unwrap(), 5);
+}
+
+#[test]
+fn test_documents_never_land_on_header_page() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+    drop(DatabaseFile::create(&db_path).expect("Failed to create database file"));
+
+    let mut ids = Vec::new();
+    {
+        let mut engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+        for i in 0..40 {
+            ids.push(engine.insert_document(&make_doc(i)).expect("insert failed"));
+        }
+        assert!(ids.iter().all(|id| id.page_id() >= FIRST_DATA_PAGE_ID));
+        assert_eq!(ids[0].page_id(), FIRST_DATA_PAGE_ID);
+        engine.checkpoint().expect("checkpoint failed");
+    }
+
+    let mut engine = StorageEngine::new(&db_path, 10).expect("Failed to reopen storage engine");
+    let scanned = engine.scan_all().expect("scan failed");
+    assert_eq!(scanned.len(), 40);
+    assert!(scanned.iter().all(|(id, _)| id.page_id() != HEADER_PAGE_ID));
+    assert_eq!(engine.count().expect("count failed"), 40);
+}
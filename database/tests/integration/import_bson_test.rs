@@ -0,0 +1,115 @@
+use database::{
+    bson::serialize_document,
+    storage::storage_engine::StorageEngine,
+    Document, Value,
+};
+use std::io::Cursor;
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn make_doc(i: i32) -> Document {
+    let mut doc = Document::new();
+    doc.set("n", Value::I32(i));
+    doc.set("name", Value::String(format!("doc_{}", i)));
+    doc
+}
+
+fn dump(documents: &[Document]) -> Vec<u8> {
+    documents
+        .iter()
+        .flat_map(|doc| serialize_document(doc).expect("serialize failed"))
+        .collect()
+}
+
+#[test]
+fn test_import_back_to_back_documents() {
+    let (mut engine, _dir) = create_engine();
+    let documents: Vec<_> = (0..10).map(make_doc).collect();
+
+    let report = engine
+        .import_bson(Cursor::new(dump(&documents)))
+        .expect("import failed");
+
+    assert_eq!(report.imported_count(), documents.len());
+    assert!(report.errors.is_empty());
+    for (id, doc) in report.imported.iter().zip(&documents) {
+        assert_eq!(&engine.get_document(id).expect("get failed"), doc);
+    }
+    assert_eq!(engine.count().expect("count failed"), documents.len());
+}
+
+#[test]
+fn test_import_truncated_final_document() {
+    let (mut engine, _dir) = create_engine();
+    let documents: Vec<_> = (0..3).map(make_doc).collect();
+    let mut bytes = dump(&documents);
+    let last_offset = bytes.len() - serialize_document(&documents[2]).unwrap().len();
+    bytes.truncate(bytes.len() - 3);
+
+    let report = engine
+        .import_bson(Cursor::new(bytes))
+        .expect("import failed");
+
+    assert_eq!(report.imported_count(), 2);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].offset, last_offset as u64);
+    assert!(report.errors[0].message.contains("truncated document"));
+    assert_eq!(engine.count().expect("count failed"), 2);
+}
+
+#[test]
+fn test_import_skips_undecodable_document() {
+    let (mut engine, _dir) = create_engine();
+    let first = serialize_document(&make_doc(0)).unwrap();
+    let mut second = serialize_document(&make_doc(1)).unwrap();
+    // Corrupt the first element's type tag, keeping the framing intact
+    second[4] = 0x7F;
+    let third = serialize_document(&make_doc(2)).unwrap();
+    let bytes = [first.clone(), second, third].concat();
+
+    let report = engine
+        .import_bson(Cursor::new(bytes))
+        .expect("import failed");
+
+    assert_eq!(report.imported_count(), 2);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].offset, first.len() as u64);
+}
+
+#[test]
+fn test_import_invalid_length_prefix() {
+    let (mut engine, _dir) = create_engine();
+    let mut bytes = dump(&[make_doc(0)]);
+    let offset = bytes.len() as u64;
+    bytes.extend_from_slice(&2u32.to_le_bytes());
+
+    let report = engine
+        .import_bson(Cursor::new(bytes))
+        .expect("import failed");
+
+    assert_eq!(report.imported_count(), 1);
+    assert_eq!(report.errors.len(), 1);
+    assert_eq!(report.errors[0].offset, offset);
+    assert!(report.errors[0].message.contains("invalid document length"));
+}
+
+#[test]
+fn test_import_empty_input() {
+    let (mut engine, _dir) = create_engine();
+    let report = engine
+        .import_bson(Cursor::new(Vec::new()))
+        .expect("import failed");
+    assert_eq!(report.imported_count(), 0);
+    assert!(report.errors.is_empty());
+}
@@ -0,0 +1,306 @@
+use database::{
+    error::DatabaseError,
+    storage::storage_engine::{DocumentId, StorageEngine},
+    Document, Value,
+};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn make_person(age: i32) -> Document {
+    let mut doc = Document::new();
+    doc.set("age", Value::I32(age));
+    doc.set("name", Value::String(format!("person_{}", age)));
+    doc
+}
+
+fn populated_engine() -> (StorageEngine, tempfile::TempDir) {
+    let (mut engine, dir) = create_engine();
+    // Insert out of order, with a few duplicate ages and one document missing the field
+    for age in [45, 12, 33, 30, 40, 38, 30, 51, 29, 41, 35] {
+        engine.insert_document(&make_person(age)).expect("insert failed");
+    }
+    let mut no_age = Document::new();
+    no_age.set("name", Value::String("ageless".to_string()));
+    engine.insert_document(&no_age).expect("insert failed");
+
+    engine.create_index("age").expect("create index failed");
+    (engine, dir)
+}
+
+// Brute-force filter over every document, sorted like the index
+fn brute_force(
+    engine: &mut StorageEngine,
+    matches: impl Fn(i32) -> bool,
+) -> Vec<(i32, DocumentId)> {
+    let mut expected: Vec<(i32, DocumentId)> = engine
+        .scan_all()
+        .expect("scan failed")
+        .into_iter()
+        .filter_map(|(id, doc)| doc.get("age").and_then(Value::as_i32).map(|age| (age, id)))
+        .filter(|(age, _)| matches(*age))
+        .collect();
+    expected.sort_by_key(|(age, id)| (*age, id.page_id(), id.slot_id()));
+    expected
+}
+
+fn ages(results: &[(DocumentId, Document)]) -> Vec<(i32, DocumentId)> {
+    let mut ages: Vec<(i32, DocumentId)> = results
+        .iter()
+        .map(|(id, doc)| (doc.get("age").and_then(Value::as_i32).unwrap(), *id))
+        .collect();
+    ages.sort_by_key(|(age, id)| (*age, id.page_id(), id.slot_id()));
+    ages
+}
+
+#[test]
+fn test_find_range_closed() {
+    let (mut engine, _dir) = populated_engine();
+
+    let results = engine
+        .find_range("age", Some(Value::I32(30)), Some(Value::I32(40)), (true, true))
+        .expect("range failed");
+
+    assert_eq!(results.len(), 6);
+    assert_eq!(
+        ages(&results),
+        brute_force(&mut engine, |age| (30..=40).contains(&age))
+    );
+}
+
+#[test]
+fn test_find_range_half_open() {
+    let (mut engine, _dir) = populated_engine();
+
+    let results = engine
+        .find_range("age", Some(Value::I64(40)), None, (false, true))
+        .expect("range failed");
+    assert_eq!(ages(&results), brute_force(&mut engine, |age| age > 40));
+
+    let results = engine
+        .find_range("age", None, Some(Value::F64(30.0)), (true, false))
+        .expect("range failed");
+    assert_eq!(ages(&results), brute_force(&mut engine, |age| age < 30));
+}
+
+#[test]
+fn test_find_range_empty() {
+    let (mut engine, _dir) = populated_engine();
+
+    let results = engine
+        .find_range("age", Some(Value::I32(13)), Some(Value::I32(28)), (true, true))
+        .expect("range failed");
+    assert!(results.is_empty());
+
+    let results = engine
+        .find_range("age", Some(Value::I32(40)), Some(Value::I32(30)), (true, true))
+        .expect("range failed");
+    assert!(results.is_empty());
+}
+
+#[test]
+fn test_index_tracks_updates_and_deletes() {
+    let (mut engine, _dir) = create_engine();
+    engine.create_index("age").expect("create index failed");
+
+    let young = engine.insert_document(&make_person(20)).expect("insert failed");
+    let old = engine.insert_document(&make_person(70)).expect("insert failed");
+
+    // Grow the document so it has to move, and change the indexed value
+    let mut updated = make_person(35);
+    updated.set("bio", Value::String("x".repeat(500)));
+    let young = engine.update_document(&young, &updated).expect("update failed");
+    engine.delete_document(&old).expect("delete failed");
+
+    let in_range = |engine: &mut StorageEngine, lo: i32, hi: i32| {
+        engine
+            .find_range("age", Some(Value::I32(lo)), Some(Value::I32(hi)), (true, true))
+            .expect("range failed")
+    };
+
+    assert!(in_range(&mut engine, 0, 30).is_empty());
+    assert!(in_range(&mut engine, 60, 80).is_empty());
+
+    let results = in_range(&mut engine, 30, 40);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0, young);
+    assert_eq!(results[0].1.get("age"), Some(&Value::I32(35)));
+}
+
+#[test]
+fn test_find_range_without_index_matches_index() {
+    let (mut engine, _dir) = populated_engine();
+
+    let with_index = engine
+        .find_range("age", Some(Value::I32(33)), Some(Value::I32(45)), (false, true))
+        .expect("range failed");
+    let without_index = engine
+        .find_range("name", None, None, (true, true))
+        .expect("range failed");
+
+    assert_eq!(ages(&with_index), brute_force(&mut engine, |age| age > 33 && age <= 45));
+    assert_eq!(without_index.len(), 12);
+}
+
+fn make_user(email: &str) -> Document {
+    let mut doc = Document::new();
+    doc.set("email", Value::String(email.to_string()));
+    doc
+}
+
+fn assert_duplicate_key(result: database::result::Result<DocumentId>, expected_email: &str) {
+    let error = result.expect_err("expected a duplicate key error");
+    match error {
+        DatabaseError::DuplicateKey { field, value } => {
+            assert_eq!(field, "email");
+            assert_eq!(value, Value::String(expected_email.to_string()));
+        }
+        other => panic!("expected DuplicateKey, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_unique_index_rejects_duplicate_insert() {
+    let (mut engine, _dir) = create_engine();
+    engine.create_unique_index("email").expect("create index failed");
+
+    engine.insert_document(&make_user("a@example.com")).expect("insert failed");
+    assert_duplicate_key(
+        engine.insert_document(&make_user("a@example.com")),
+        "a@example.com",
+    );
+
+    engine.insert_document(&make_user("b@example.com")).expect("insert failed");
+    assert_eq!(engine.count().expect("count failed"), 2);
+}
+
+#[test]
+fn test_unique_index_allows_updating_own_value() {
+    let (mut engine, _dir) = create_engine();
+    engine.create_unique_index("email").expect("create index failed");
+
+    let id = engine.insert_document(&make_user("a@example.com")).expect("insert failed");
+
+    let mut same_email = make_user("a@example.com");
+    same_email.set("name", Value::String("Alice".to_string()));
+    engine.update_document(&id, &same_email).expect("update to own value failed");
+}
+
+#[test]
+fn test_unique_index_rejects_update_to_taken_value() {
+    let (mut engine, _dir) = create_engine();
+    engine.create_unique_index("email").expect("create index failed");
+
+    engine.insert_document(&make_user("a@example.com")).expect("insert failed");
+    let b = engine.insert_document(&make_user("b@example.com")).expect("insert failed");
+
+    assert_duplicate_key(
+        engine.update_document(&b, &make_user("a@example.com")),
+        "a@example.com",
+    );
+    assert_eq!(
+        engine.get_document(&b).expect("get failed").get("email"),
+        Some(&Value::String("b@example.com".to_string()))
+    );
+}
+
+#[test]
+fn test_create_unique_index_fails_on_existing_duplicates() {
+    let (mut engine, _dir) = create_engine();
+    engine.insert_document(&make_user("a@example.com")).expect("insert failed");
+    engine.insert_document(&make_user("a@example.com")).expect("insert failed");
+
+    assert!(engine.create_unique_index("email").is_err());
+    assert!(engine.index("email").is_none());
+}
+
+#[test]
+fn test_index_stats_reflect_cardinality() {
+    let (mut engine, _dir) = create_engine();
+    for i in 0..20 {
+        let mut doc = Document::new();
+        doc.set("active", Value::Bool(i % 2 == 0));
+        doc.set("user_id", Value::I32(i));
+        engine.insert_document(&doc).expect("insert failed");
+    }
+    engine.create_index("active").expect("create index failed");
+    engine.create_unique_index("user_id").expect("create index failed");
+
+    let stats = engine.index_stats();
+    assert_eq!(stats.len(), 2);
+    let (active, user_id) = (&stats[0], &stats[1]);
+
+    assert_eq!(active.field, "active");
+    assert_eq!(active.entries, 20);
+    assert_eq!(active.distinct_keys, 2);
+    assert_eq!(active.selectivity, 0.1);
+
+    assert_eq!(user_id.field, "user_id");
+    assert!(user_id.unique);
+    assert_eq!(user_id.entries, 20);
+    assert_eq!(user_id.distinct_keys, 20);
+    assert_eq!(user_id.selectivity, 1.0);
+
+    assert!(active.selectivity < user_id.selectivity);
+}
+
+fn tagged(name: &str, tags: &[&str]) -> Document {
+    let mut doc = Document::new();
+    doc.set("name", Value::String(name.to_string()));
+    doc.set(
+        "tags",
+        Value::Array(tags.iter().map(|t| Value::String(t.to_string())).collect()),
+    );
+    doc
+}
+
+fn names(results: &[(DocumentId, Document)]) -> Vec<String> {
+    let mut names: Vec<String> = results
+        .iter()
+        .map(|(_, doc)| doc.get("name").and_then(Value::to_str).unwrap())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_array_field_is_indexed_per_element() {
+    let (mut engine, _temp_dir) = create_engine();
+    engine.insert_document(&tagged("a", &["rust", "db"])).unwrap();
+    engine.insert_document(&tagged("b", &["go", "rust", "rust"])).unwrap();
+    engine.insert_document(&tagged("c", &["db"])).unwrap();
+    engine.insert_document(&tagged("d", &[])).unwrap();
+
+    let rust = Value::String("rust".to_string());
+    let scanned = engine.find_by_field("tags", &rust).unwrap();
+    engine.create_index("tags").unwrap();
+    let indexed = engine.find_by_field("tags", &rust).unwrap();
+
+    // "b" lists the tag twice but is returned once, with and without the index
+    assert_eq!(names(&indexed), vec!["a", "b"]);
+    assert_eq!(names(&scanned), names(&indexed));
+    assert_eq!(
+        names(&engine.find_by_field("tags", &Value::String("db".to_string())).unwrap()),
+        vec!["a", "c"]
+    );
+
+    // A range covering several elements of one document still returns it once
+    let all = engine.find_range("tags", None, None, (true, true)).unwrap();
+    assert_eq!(names(&all), vec!["a", "b", "c"]);
+
+    // Updates move the document between element keys
+    let b = Value::String("b".to_string());
+    let (b_id, _) = indexed.iter().find(|(_, doc)| doc.get("name") == Some(&b)).unwrap();
+    engine.update_document(b_id, &tagged("b", &["go"])).unwrap();
+    assert_eq!(names(&engine.find_by_field("tags", &rust).unwrap()), vec!["a"]);
+}
@@ -0,0 +1,81 @@
+use database::document::object_id::ObjectId;
+use database::error::DatabaseError;
+use database::storage::storage_engine::StorageEngine;
+use database::{Document, Value};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn named_doc(name: &str) -> Document {
+    let mut doc = Document::new();
+    doc.set("name", Value::String(name.to_string()));
+    doc
+}
+
+#[test]
+fn test_insert_with_id_stores_the_given_id() {
+    let (mut engine, dir) = create_engine();
+    let id = ObjectId::new();
+    // The document's own id is replaced
+    let doc = named_doc("alice");
+    assert_ne!(doc.get_id(), Some(&id));
+
+    let document_id = engine.insert_with_id(id, &doc).expect("insert failed");
+    let stored = engine.get_document(&document_id).expect("get failed");
+    assert_eq!(stored.get_id(), Some(&id));
+    assert_eq!(stored.get("name"), doc.get("name"));
+
+    // And it survives a reopen
+    engine.checkpoint().expect("checkpoint failed");
+    drop(engine);
+    let mut engine = StorageEngine::new(&dir.path().join("test.db"), 10).expect("reopen failed");
+    let stored = engine.get_document(&document_id).expect("get failed");
+    assert_eq!(stored.get_id(), Some(&id));
+}
+
+#[test]
+fn test_insert_with_id_rejects_duplicate_under_unique_index() {
+    let (mut engine, _dir) = create_engine();
+    let id = ObjectId::new();
+    engine.insert_with_id(id, &named_doc("first")).expect("insert failed");
+
+    // Without an index on `_id`, ids are not checked
+    engine.insert_with_id(id, &named_doc("second")).expect("insert failed");
+    assert!(matches!(
+        engine.create_unique_index("_id"),
+        Err(DatabaseError::DuplicateKey { .. })
+    ));
+
+    let (mut engine, _dir) = create_engine();
+    engine.create_unique_index("_id").expect("create index failed");
+    engine.insert_with_id(id, &named_doc("first")).expect("insert failed");
+    match engine.insert_with_id(id, &named_doc("second")) {
+        Err(DatabaseError::DuplicateKey { field, value }) => {
+            assert_eq!(field, "_id");
+            assert_eq!(value, Value::ObjectId(id));
+        }
+        other => panic!("expected DuplicateKey, got {:?}", other),
+    }
+    assert_eq!(engine.count().expect("count failed"), 1);
+
+    // A different id is accepted and found through the index
+    let other = ObjectId::new();
+    let document_id = engine
+        .insert_with_id(other, &named_doc("second"))
+        .expect("insert failed");
+    let found = engine
+        .find_by_field("_id", &Value::ObjectId(other))
+        .expect("find failed");
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].0, document_id);
+}
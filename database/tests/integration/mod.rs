@@ -1,11 +1,49 @@
 // Integration tests module
 // Tests that verify multiple components working together
 
+mod aggregate_test;
+mod auto_checkpoint_test;
+mod backup_test;
 mod buffer_pool_integration;
+mod cancellation_test;
+mod change_stream_test;
+mod compact_to_test;
+#[cfg(feature = "compression")]
+mod compression_test;
+mod conflict_test;
 mod crud_operations_test;
+mod dirty_high_water_test;
+mod document_cache_test;
+mod document_ref_test;
+mod dump_json_test;
+mod empty_document_test;
+mod free_space_test;
+mod fsync_policy_test;
+mod header_page_test;
+mod import_bson_test;
+mod index_test;
+mod insert_with_id_test;
+mod move_document_test;
+mod open_options_test;
+mod packing_strategy_test;
+mod page_allocation_test;
+mod page_inspection_test;
 mod page_layout_integration;
+mod predicate_test;
+mod query_options_test;
+mod raw_document_test;
+mod read_ahead_test;
+mod read_your_writes_test;
+mod scan_test;
+mod slot_layout_test;
+mod stale_id_test;
 mod storage_engine_extended_test;
+mod streaming_read_test;
 mod storage_engine_test;
+mod timestamps_test;
+mod update_fields_test;
+mod validation_level_test;
 mod week1_integration;
 mod vacuum_test;
+mod wal_test;
 mod week2_integration;
\ No newline at end of file
@@ -0,0 +1,86 @@
+use database::storage::storage_engine::{DocumentId, MoveTarget, StorageEngine};
+use database::{Document, Value};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn user(name: &str) -> Document {
+    let mut doc = Document::new();
+    doc.set("name", Value::String(name.to_string()));
+    doc
+}
+
+fn find_by_name(engine: &mut StorageEngine, name: &str) -> Vec<DocumentId> {
+    let name = Value::String(name.to_string());
+    engine
+        .find_range("name", Some(name.clone()), Some(name), (true, true))
+        .expect("find failed")
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect()
+}
+
+#[test]
+fn test_move_document_to_new_page() {
+    let (mut engine, _dir) = create_engine();
+    engine.create_index("name").expect("create_index failed");
+
+    let original = user("alice");
+    let old_id = engine.insert_document(&original).expect("insert failed");
+    engine.insert_document(&user("bob")).expect("insert failed");
+
+    let new_id = engine
+        .move_document(&old_id, MoveTarget::NewPage)
+        .expect("move failed");
+    assert_ne!(new_id.page_id(), old_id.page_id());
+
+    assert!(engine.get_document(&old_id).is_err(), "old slot should be tombstoned");
+    let moved = engine.get_document(&new_id).expect("moved document missing");
+    assert_eq!(moved, original);
+
+    assert_eq!(find_by_name(&mut engine, "alice"), vec![new_id]);
+    assert_eq!(engine.count().unwrap(), 2);
+}
+
+#[test]
+fn test_move_document_to_existing_page() {
+    let (mut engine, _dir) = create_engine();
+    let first = engine.insert_document(&user("alice")).expect("insert failed");
+    let moved_once = engine
+        .move_document(&first, MoveTarget::NewPage)
+        .expect("move failed");
+
+    let back = engine
+        .move_document(&moved_once, MoveTarget::Page(first.page_id()))
+        .expect("move back failed");
+    assert_eq!(back.page_id(), first.page_id());
+    assert_eq!(find_by_name(&mut engine, "alice"), vec![back]);
+
+    // Moving onto its own page is a no-op
+    let same = engine
+        .move_document(&back, MoveTarget::Page(back.page_id()))
+        .expect("no-op move failed");
+    assert_eq!(same, back);
+}
+
+#[test]
+fn test_move_document_to_missing_page_keeps_original() {
+    let (mut engine, _dir) = create_engine();
+    let id = engine.insert_document(&user("alice")).expect("insert failed");
+
+    assert!(engine.move_document(&id, MoveTarget::Page(99)).is_err());
+    assert_eq!(
+        engine.get_document(&id).expect("original should survive").get("name"),
+        Some(&Value::String("alice".to_string()))
+    );
+}
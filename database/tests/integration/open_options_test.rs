@@ -0,0 +1,133 @@
+use database::error::DatabaseError;
+use database::storage::integrity::{IntegrityProblem, OpenOptions};
+use database::storage::page::PAGE_SIZE;
+use database::storage::storage_engine::{DocumentId, StorageEngine};
+use database::{Document, Value};
+use std::fs::{self, OpenOptions as FileOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::Path;
+use tempfile::tempdir;
+
+// Two documents per page, checkpointed so they are all on disk
+fn populated_database(path: &Path) -> Vec<DocumentId> {
+    database::storage::file::DatabaseFile::create(path).expect("Failed to create database file");
+    let mut engine = StorageEngine::new(path, 10).expect("Failed to create storage engine");
+    let ids = (0..10)
+        .map(|i| {
+            let mut doc = Document::new();
+            doc.set("n", Value::I32(i));
+            doc.set("padding", Value::String("x".repeat(3500)));
+            engine.insert_document(&doc).expect("insert failed")
+        })
+        .collect();
+    engine.checkpoint().expect("checkpoint failed");
+    ids
+}
+
+// Overwrites bytes of a page on disk, as a torn write would
+fn scribble(path: &Path, page_id: u64, offset: usize, bytes: &[u8]) {
+    let mut file = FileOptions::new().write(true).open(path).unwrap();
+    file.seek(SeekFrom::Start(page_id * PAGE_SIZE as u64 + offset as u64)).unwrap();
+    file.write_all(bytes).unwrap();
+}
+
+fn verify() -> OpenOptions {
+    OpenOptions {
+        verify: true,
+        ..OpenOptions::default()
+    }
+}
+
+fn repair() -> OpenOptions {
+    OpenOptions {
+        repair: true,
+        ..OpenOptions::default()
+    }
+}
+
+#[test]
+fn test_corrupt_page_fails_verify_and_is_repaired() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.db");
+    let ids = populated_database(&path);
+    let damaged = ids[4];
+    assert_eq!(damaged.slot_id(), 0);
+
+    // Clobber the length prefix of the first document on its page
+    scribble(&path, damaged.page_id(), 16, &[0xFF; 4]);
+
+    // Opening without verification does not read the page
+    StorageEngine::open_with_options(&path, 10, &OpenOptions::default()).expect("open failed");
+
+    let report = match StorageEngine::open_with_options(&path, 10, &verify()) {
+        Err(DatabaseError::IntegrityCheckFailed(report)) => report,
+        other => panic!("expected a failed integrity check, got {:?}", other.map(|_| ())),
+    };
+    assert_eq!(report.corrupt_pages(), [damaged.page_id()]);
+    let message = DatabaseError::IntegrityCheckFailed(report).to_string();
+    assert!(message.contains(&format!("page {} is corrupt", damaged.page_id())), "{}", message);
+
+    let mut engine = StorageEngine::open_with_options(&path, 10, &repair()).expect("repair failed");
+    assert!(engine.check_integrity().expect("check failed").is_clean());
+
+    // Only the damaged document is gone; its neighbour on the page keeps its id
+    assert_eq!(engine.count().expect("count failed"), ids.len() - 1);
+    assert!(engine.get_document(&damaged).is_err());
+    for (i, id) in ids.iter().enumerate().filter(|(_, id)| **id != damaged) {
+        let doc = engine.get_document(id).expect("get failed");
+        assert_eq!(doc.get("n"), Some(&Value::I32(i as i32)));
+    }
+
+    // Usable for writes, and the repair stuck
+    let mut doc = Document::new();
+    doc.set("n", Value::I32(100));
+    let id = engine.insert_document(&doc).expect("insert failed");
+    engine.checkpoint().expect("checkpoint failed");
+    drop(engine);
+    let mut engine = StorageEngine::open_with_options(&path, 10, &verify()).expect("reopen failed");
+    assert_eq!(engine.get_document(&id).expect("get failed"), doc);
+}
+
+#[test]
+fn test_truncated_file_and_broken_free_list_are_repaired() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.db");
+    let ids = populated_database(&path);
+    let last_page = ids.last().unwrap().page_id();
+
+    // Cut the last page short and point the free list at a page in use
+    let file = FileOptions::new().write(true).open(&path).unwrap();
+    file.set_len(last_page * PAGE_SIZE as u64 + 100).unwrap();
+    drop(file);
+    {
+        let mut db_file = database::storage::file::DatabaseFile::open(&path).unwrap();
+        db_file.set_free_list_head(ids[0].page_id()).unwrap();
+    }
+
+    let report = match StorageEngine::open_with_options(&path, 10, &verify()) {
+        Err(DatabaseError::IntegrityCheckFailed(report)) => report,
+        other => panic!("expected a failed integrity check, got {:?}", other.map(|_| ())),
+    };
+    assert_eq!(
+        report.problems,
+        [
+            IntegrityProblem::MissingPages { first: last_page, count: 1 },
+            IntegrityProblem::BrokenFreeList {
+                page_id: ids[0].page_id(),
+                reason: "the page is in use".to_string(),
+            },
+        ]
+    );
+
+    let mut engine = StorageEngine::open_with_options(&path, 10, &repair()).expect("repair failed");
+    assert_eq!(engine.database_file.free_list_head(), 0);
+    assert_eq!(fs::metadata(&path).unwrap().len(), (last_page + 1) * PAGE_SIZE as u64);
+    let lost = ids.iter().filter(|id| id.page_id() == last_page).count();
+    assert_eq!(engine.count().expect("count failed"), ids.len() - lost);
+
+    // The restored page takes new documents
+    let mut doc = Document::new();
+    doc.set("n", Value::I32(100));
+    engine.insert_document(&doc).expect("insert failed");
+    assert!(engine.check_integrity().expect("check failed").is_clean());
+}
@@ -0,0 +1,78 @@
+use database::storage::storage_engine::{PackingStrategy, StorageEngine};
+use database::{Document, Value};
+use tempfile::tempdir;
+
+fn create_engine(strategy: PackingStrategy) -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10)
+        .expect("Failed to create storage engine")
+        .with_packing_strategy(strategy);
+    (engine, temp_dir)
+}
+
+fn padded_doc(bytes: usize) -> Document {
+    let mut doc = Document::new();
+    doc.set("padding", Value::String("x".repeat(bytes)));
+    doc
+}
+
+/// Leaves two pages behind: the first with about 3000 bytes free, the second with
+/// about 2000, returning their page ids.
+fn two_partly_full_pages(engine: &mut StorageEngine) -> (u64, u64) {
+    let roomier = engine.insert_document(&padded_doc(5000)).expect("insert failed");
+    let tighter = engine.insert_document(&padded_doc(6000)).expect("insert failed");
+    assert_ne!(roomier.page_id(), tighter.page_id());
+    (roomier.page_id(), tighter.page_id())
+}
+
+#[test]
+fn test_default_strategy_is_first_fit() {
+    let (engine, _dir) = create_engine(PackingStrategy::default());
+    assert_eq!(engine.packing_strategy(), PackingStrategy::FirstFit);
+}
+
+#[test]
+fn test_first_fit_uses_lowest_page_with_room() {
+    let (mut engine, _dir) = create_engine(PackingStrategy::FirstFit);
+    let (roomier, _) = two_partly_full_pages(&mut engine);
+
+    let id = engine.insert_document(&padded_doc(1500)).expect("insert failed");
+    assert_eq!(id.page_id(), roomier);
+}
+
+#[test]
+fn test_best_fit_uses_tightest_page_with_room() {
+    let (mut engine, _dir) = create_engine(PackingStrategy::BestFit);
+    let (roomier, tighter) = two_partly_full_pages(&mut engine);
+
+    let id = engine.insert_document(&padded_doc(1500)).expect("insert failed");
+    assert_eq!(id.page_id(), tighter);
+
+    // Too big for the tighter page, so the roomier one is the best fit
+    let id = engine.insert_document(&padded_doc(2500)).expect("insert failed");
+    assert_eq!(id.page_id(), roomier);
+}
+
+#[test]
+fn test_append_only_never_reuses_earlier_pages() {
+    let (mut engine, _dir) = create_engine(PackingStrategy::AppendOnly);
+    let (_, tighter) = two_partly_full_pages(&mut engine);
+
+    // Fits the last page
+    let id = engine.insert_document(&padded_doc(1500)).expect("insert failed");
+    assert_eq!(id.page_id(), tighter);
+
+    // Would fit the first page, but only the last page is a candidate
+    let id = engine.insert_document(&padded_doc(2500)).expect("insert failed");
+    assert_eq!(id.page_id(), tighter + 1);
+
+    for (_, doc) in engine.scan_all().expect("scan failed") {
+        assert!(doc.get("padding").is_some());
+    }
+}
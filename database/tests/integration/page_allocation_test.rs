@@ -0,0 +1,128 @@
+use database::{
+    page_layout::PageLayout,
+    storage::storage_engine::StorageEngine,
+    Document, Value,
+};
+use tempfile::tempdir;
+
+fn create_engine(buffer_pool_size: usize) -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine =
+        StorageEngine::new(&db_path, buffer_pool_size).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn padded(i: i32, padding: usize) -> Document {
+    let mut doc = Document::new();
+    doc.set("i", Value::I32(i));
+    doc.set("padding", Value::String("x".repeat(padding)));
+    doc
+}
+
+#[test]
+fn test_inserts_spill_onto_new_pages() {
+    // A tiny pool forces full pages out, so later inserts must allocate fresh ones
+    let (mut engine, _temp_dir) = create_engine(2);
+
+    let ids: Vec<_> = (0..200)
+        .map(|i| engine.insert_document(&padded(i, 200)).expect("insert failed"))
+        .collect();
+
+    let pages: std::collections::BTreeSet<_> = ids.iter().map(|id| id.page_id()).collect();
+    assert!(pages.len() > 3, "expected several pages, got {:?}", pages);
+
+    for (i, id) in ids.iter().enumerate() {
+        let doc = engine.get_document(id).expect("get failed");
+        assert_eq!(doc.get("i"), Some(&Value::I32(i as i32)));
+    }
+    assert_eq!(engine.count().expect("count failed"), 200);
+}
+
+#[test]
+fn test_document_too_big_for_current_page_gets_a_new_one() {
+    let (mut engine, _temp_dir) = create_engine(10);
+
+    let small = engine.insert_document(&padded(0, 4000)).expect("insert failed");
+    // Fits on an empty page, but not beside the first document
+    let large = engine.insert_document(&padded(1, 6000)).expect("insert failed");
+    assert_ne!(small.page_id(), large.page_id());
+
+    assert_eq!(
+        engine.get_document(&large).expect("get failed").get("i"),
+        Some(&Value::I32(1))
+    );
+}
+
+#[test]
+fn test_document_larger_than_a_page_is_rejected() {
+    let (mut engine, _temp_dir) = create_engine(10);
+    engine.insert_document(&padded(0, 10)).expect("insert failed");
+    let pages_before = engine.database_file.page_count();
+
+    let err = engine
+        .insert_document(&padded(1, PageLayout::max_document_size()))
+        .expect_err("a document larger than a page should not be stored");
+    assert!(err.to_string().contains("does not fit on a page"), "{}", err);
+
+    // No page was allocated for it
+    assert_eq!(engine.database_file.page_count(), pages_before);
+}
+
+#[test]
+fn test_oversized_update_leaves_original_in_place() {
+    let (mut engine, _temp_dir) = create_engine(10);
+    let id = engine.insert_document(&padded(0, 10)).expect("insert failed");
+
+    let err = engine
+        .update_document(&id, &padded(1, PageLayout::max_document_size()))
+        .expect_err("a document larger than a page should not be stored");
+    assert!(err.to_string().contains("does not fit on a page"), "{}", err);
+
+    let doc = engine.get_document(&id).expect("original should still be readable");
+    assert_eq!(doc.get("i"), Some(&Value::I32(0)));
+    assert_eq!(engine.count().expect("count failed"), 1);
+}
+
+#[test]
+fn test_freed_pages_are_skipped_until_reallocated() {
+    let (mut engine, _temp_dir) = create_engine(10);
+
+    // Each document takes most of a page
+    let first = engine.insert_document(&padded(0, 6000)).expect("insert failed");
+    assert_eq!(first.page_id(), 1);
+    for _ in 0..3 {
+        engine.database_file.allocate_page().expect("allocate failed");
+    }
+    engine.database_file.free_page(2).expect("free page failed");
+    engine.database_file.free_page(4).expect("free page failed");
+    engine
+        .rebuild_free_space_directory()
+        .expect("rebuild failed");
+
+    assert_eq!(engine.count().expect("count failed"), 1);
+    assert_eq!(engine.scan_all().expect("scan failed").len(), 1);
+    assert_eq!(engine.vacuum().expect("vacuum failed"), 0);
+
+    // The empty page still in use is filled first, then the freed pages are handed out
+    // by the allocator instead of being written over as if they were empty
+    let ids: Vec<_> = (1..5)
+        .map(|i| engine.insert_document(&padded(i, 6000)).expect("insert failed"))
+        .collect();
+    let pages: Vec<_> = ids.iter().map(|id| id.page_id()).collect();
+    assert_eq!(pages, vec![3, 4, 2, 5]);
+    assert_eq!(engine.database_file.free_list_head(), 0);
+
+    let doc = engine.get_document(&first).expect("first document lost");
+    assert_eq!(doc.get("i"), Some(&Value::I32(0)));
+    for (i, id) in (1..5).zip(&ids) {
+        let doc = engine.get_document(id).expect("get failed");
+        assert_eq!(doc.get("i"), Some(&Value::I32(i)));
+    }
+    assert_eq!(engine.count().expect("count failed"), 5);
+}
@@ -0,0 +1,71 @@
+use database::{
+    error::DatabaseError,
+    storage::storage_engine::StorageEngine,
+    Document, Value,
+};
+use std::collections::BTreeMap;
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn padded(i: i32) -> Document {
+    let mut doc = Document::new();
+    doc.set("i", Value::I32(i));
+    doc.set("padding", Value::String("x".repeat(1000)));
+    doc
+}
+
+#[test]
+fn test_page_ids_and_slots_match_inserts() {
+    let (mut engine, _temp_dir) = create_engine();
+    assert!(engine.page_ids().is_empty());
+
+    let mut expected: BTreeMap<u64, Vec<u16>> = BTreeMap::new();
+    for i in 0..20 {
+        let id = engine.insert_document(&padded(i)).expect("insert failed");
+        expected.entry(id.page_id()).or_default().push(id.slot_id());
+    }
+    assert!(expected.len() > 1, "documents should span several pages");
+
+    assert_eq!(engine.page_ids(), expected.keys().copied().collect::<Vec<_>>());
+    for (page_id, slots) in &expected {
+        assert_eq!(&engine.slots_on_page(*page_id).expect("slots failed"), slots);
+    }
+}
+
+#[test]
+fn test_slots_on_page_skips_deleted() {
+    let (mut engine, _temp_dir) = create_engine();
+    let ids: Vec<_> = (0..3)
+        .map(|i| engine.insert_document(&padded(i)).expect("insert failed"))
+        .collect();
+    engine.delete_document(&ids[1]).expect("delete failed");
+
+    assert_eq!(
+        engine.slots_on_page(ids[0].page_id()).expect("slots failed"),
+        vec![ids[0].slot_id(), ids[2].slot_id()]
+    );
+}
+
+#[test]
+fn test_slots_on_non_data_page_fails() {
+    let (mut engine, _temp_dir) = create_engine();
+    engine.insert_document(&padded(0)).expect("insert failed");
+
+    for page_id in [0, 99] {
+        match engine.slots_on_page(page_id) {
+            Err(DatabaseError::Storage(_)) => {}
+            other => panic!("expected a storage error for page {}, got {:?}", page_id, other),
+        }
+    }
+}
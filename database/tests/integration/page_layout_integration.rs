@@ -1,12 +1,10 @@
 use database::storage::{
-    page::{Page, PageType}, 
+    page::Page, 
     page_layout::PageLayout,
 };
 
 fn create_initialized_page(page_id: u64) -> Page {
-    let mut page = Page::new(page_id, PageType::Data);
-    PageLayout::initialize_page(&mut page).expect("Failed to initialize page");
-    page
+    PageLayout::new_page(page_id)
 }
 
 #[test]
@@ -189,5 +187,5 @@ fn test_memory_safety_and_bounds() {
     let _count = PageLayout::get_document_count(&page).expect("Failed to get document count");
     let utilization = PageLayout::get_utilization_percentage(&page).expect("Failed to get utilization");
     
-    assert!(utilization >= 0.0 && utilization <= 100.0);
+    assert!((0.0..=100.0).contains(&utilization));
 }
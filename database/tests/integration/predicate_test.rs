@@ -0,0 +1,105 @@
+use database::{
+    document::predicate::Predicate,
+    storage::storage_engine::{QueryOptions, StorageEngine},
+    Document, Value,
+};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn populate(engine: &mut StorageEngine) {
+    for name in ["Alice", "alfred", "Alvaro", "Bob", "Malika"] {
+        let mut doc = Document::new();
+        doc.set("name", Value::String(name.to_string()));
+        engine.insert_document(&doc).expect("insert failed");
+    }
+    let mut numeric = Document::new();
+    numeric.set("name", Value::I32(42));
+    engine.insert_document(&numeric).expect("insert failed");
+}
+
+fn names(engine: &mut StorageEngine, predicate: &Predicate) -> Vec<String> {
+    engine
+        .find_matching(predicate, &QueryOptions::default())
+        .expect("query failed")
+        .into_iter()
+        .map(|(_, doc)| match doc.get("name") {
+            Some(Value::String(name)) => name.clone(),
+            other => panic!("unexpected name {:?}", other),
+        })
+        .collect()
+}
+
+#[test]
+fn test_find_matching_string_operators() {
+    let (mut engine, _temp_dir) = create_engine();
+    populate(&mut engine);
+
+    assert_eq!(
+        names(&mut engine, &Predicate::eq_ignore_case("name", "ALICE")),
+        vec!["Alice"]
+    );
+    assert_eq!(
+        names(&mut engine, &Predicate::starts_with("name", "al")),
+        vec!["Alice", "alfred", "Alvaro"]
+    );
+    assert_eq!(
+        names(&mut engine, &Predicate::contains("name", "li")),
+        vec!["Alice", "Malika"]
+    );
+    assert_eq!(
+        names(&mut engine, &Predicate::regex("name", "^[A-Z][a-z]+$").unwrap()),
+        vec!["Alice", "Alvaro", "Bob", "Malika"]
+    );
+    assert!(names(&mut engine, &Predicate::contains("name", "42")).is_empty());
+}
+
+#[test]
+fn test_find_matching_respects_limit() {
+    let (mut engine, _temp_dir) = create_engine();
+    populate(&mut engine);
+
+    let options = QueryOptions {
+        limit: Some(1),
+        ..QueryOptions::default()
+    };
+    let results = engine
+        .find_matching(&Predicate::starts_with("name", "al"), &options)
+        .expect("query failed");
+    assert_eq!(results.len(), 1);
+}
+
+#[test]
+fn test_find_matching_null_versus_missing() {
+    let (mut engine, _temp_dir) = create_engine();
+    let mut with_null = Document::new();
+    with_null.set("name", Value::String("Alice".to_string()));
+    with_null.set("nickname", Value::Null);
+    let mut without = Document::new();
+    without.set("name", Value::String("Bob".to_string()));
+    let mut with_value = Document::new();
+    with_value.set("name", Value::String("Carol".to_string()));
+    with_value.set("nickname", Value::String("Caz".to_string()));
+    for doc in [&with_null, &without, &with_value] {
+        engine.insert_document(doc).expect("insert failed");
+    }
+
+    assert_eq!(
+        names(&mut engine, &Predicate::exists("nickname")),
+        vec!["Alice", "Carol"]
+    );
+    assert_eq!(
+        names(&mut engine, &Predicate::eq("nickname", Value::Null)),
+        vec!["Alice"]
+    );
+}
@@ -0,0 +1,136 @@
+use database::{
+    error::DatabaseError,
+    storage::storage_engine::{DocumentId, QueryOptions, StorageEngine},
+    Document, Value,
+};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn populate(engine: &mut StorageEngine, count: i32) {
+    // Inserted in descending order so index order differs from physical order
+    for n in (0..count).rev() {
+        let mut doc = Document::new();
+        doc.set("n", Value::I32(n));
+        engine.insert_document(&doc).expect("insert failed");
+    }
+}
+
+fn assert_too_many(result: database::result::Result<Vec<(DocumentId, Document)>>, cap: usize) {
+    let err = result.expect_err("query should exceed the cap");
+    match err {
+        DatabaseError::TooManyResults { max_buffered } => assert_eq!(max_buffered, cap),
+        other => panic!("Expected TooManyResults, got {:?}", other),
+    }
+}
+
+fn numbers(documents: &[(DocumentId, Document)]) -> Vec<i32> {
+    documents
+        .iter()
+        .map(|(_, doc)| match doc.get("n") {
+            Some(Value::I32(n)) => *n,
+            other => panic!("unexpected n: {:?}", other),
+        })
+        .collect()
+}
+
+const CAPPED: QueryOptions = QueryOptions {
+    limit: None,
+    max_buffered_docs: Some(5),
+};
+
+#[test]
+fn test_scan_exceeding_cap_fails() {
+    let (mut engine, _dir) = create_engine();
+    populate(&mut engine, 20);
+
+    assert_too_many(engine.scan_all_with_options(&CAPPED), 5);
+
+    // Results within the cap are returned as usual
+    let (mut small, _dir) = create_engine();
+    populate(&mut small, 5);
+    assert_eq!(small.scan_all_with_options(&CAPPED).unwrap().len(), 5);
+}
+
+#[test]
+fn test_limit_within_cap_pages_through() {
+    let (mut engine, _dir) = create_engine();
+    populate(&mut engine, 20);
+
+    let options = QueryOptions {
+        limit: Some(5),
+        ..CAPPED
+    };
+    let documents = engine.scan_all_with_options(&options).expect("scan failed");
+    assert_eq!(numbers(&documents), vec![19, 18, 17, 16, 15]);
+
+    // A limit above the cap does not lift it
+    let options = QueryOptions {
+        limit: Some(6),
+        ..CAPPED
+    };
+    assert_too_many(engine.scan_all_with_options(&options), 5);
+}
+
+#[test]
+fn test_find_range_cap_without_index() {
+    let (mut engine, _dir) = create_engine();
+    populate(&mut engine, 20);
+
+    let range = |engine: &mut StorageEngine, options: &QueryOptions| {
+        engine.find_range_with_options("n", Some(Value::I32(10)), None, (true, true), options)
+    };
+
+    assert_too_many(range(&mut engine, &CAPPED), 5);
+    let limited = QueryOptions {
+        limit: Some(3),
+        ..CAPPED
+    };
+    assert_eq!(numbers(&range(&mut engine, &limited).unwrap()), vec![19, 18, 17]);
+
+    let narrow = engine
+        .find_range_with_options("n", Some(Value::I32(16)), None, (true, true), &CAPPED)
+        .expect("find failed");
+    assert_eq!(narrow.len(), 4);
+}
+
+#[test]
+fn test_find_range_cap_with_index() {
+    let (mut engine, _dir) = create_engine();
+    populate(&mut engine, 20);
+    engine.create_index("n").expect("create_index failed");
+
+    let range = |engine: &mut StorageEngine, options: &QueryOptions| {
+        engine.find_range_with_options("n", Some(Value::I32(10)), None, (true, true), options)
+    };
+
+    assert_too_many(range(&mut engine, &CAPPED), 5);
+    let limited = QueryOptions {
+        limit: Some(3),
+        ..CAPPED
+    };
+    assert_eq!(numbers(&range(&mut engine, &limited).unwrap()), vec![10, 11, 12]);
+    assert_eq!(range(&mut engine, &QueryOptions::default()).unwrap().len(), 10);
+}
+
+#[test]
+fn test_zero_limit_returns_nothing() {
+    let (mut engine, _dir) = create_engine();
+    populate(&mut engine, 3);
+
+    let options = QueryOptions {
+        limit: Some(0),
+        max_buffered_docs: Some(0),
+    };
+    assert!(engine.scan_all_with_options(&options).unwrap().is_empty());
+}
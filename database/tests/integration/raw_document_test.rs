@@ -0,0 +1,121 @@
+use database::{
+    bson::{deserialize_document, serialize_document},
+    error::DatabaseError,
+    storage::storage_engine::StorageEngine,
+    Document, Value,
+};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn make_doc() -> Document {
+    let mut doc = Document::new();
+    doc.set("name", Value::String("Alice".to_string()));
+    doc.set("tags", Value::Array(vec![Value::I32(1), Value::I32(2)]));
+    doc
+}
+
+#[test]
+fn test_insert_raw_then_get_raw_returns_identical_bytes() {
+    let (mut engine, _dir) = create_engine();
+    let bytes = serialize_document(&make_doc()).unwrap();
+
+    let id = engine.insert_raw(&bytes).expect("insert_raw failed");
+    assert_eq!(engine.get_document_raw(&id).expect("get_document_raw failed"), bytes);
+}
+
+#[test]
+fn test_get_raw_deserializes_to_inserted_document() {
+    let (mut engine, _dir) = create_engine();
+    let doc = make_doc();
+    let id = engine.insert_document(&doc).expect("insert failed");
+
+    let bytes = engine.get_document_raw(&id).expect("get_document_raw failed");
+    assert_eq!(deserialize_document(&bytes).unwrap(), doc);
+}
+
+#[test]
+fn test_insert_raw_maintains_indexes() {
+    let (mut engine, _dir) = create_engine();
+    engine.create_index("name").expect("create_index failed");
+    let doc = make_doc();
+
+    let id = engine
+        .insert_raw(&serialize_document(&doc).unwrap())
+        .expect("insert_raw failed");
+    let found = engine
+        .find_range("name", Some(Value::String("Alice".to_string())), None, (true, true))
+        .expect("find failed");
+    assert_eq!(found, vec![(id, doc)]);
+}
+
+#[test]
+fn test_insert_raw_rejects_malformed_bytes() {
+    let (mut engine, _dir) = create_engine();
+    let bytes = serialize_document(&make_doc()).unwrap();
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    let truncated = &bytes[..bytes.len() - 1];
+
+    for bad in [&trailing[..], truncated, &[][..], &[5, 0, 0, 0][..]] {
+        let err = engine.insert_raw(bad).expect_err("malformed bytes were stored");
+        assert!(matches!(err, DatabaseError::Validation(_)));
+    }
+    assert_eq!(engine.count().expect("count failed"), 0);
+}
+
+#[test]
+fn test_get_raw_rejects_stale_id() {
+    let (mut engine, _dir) = create_engine();
+    let stale = engine.insert_document(&make_doc()).expect("insert failed");
+    engine.delete_document(&stale).expect("delete failed");
+    let current = engine.insert_document(&make_doc()).expect("insert failed");
+    assert_eq!(current, stale);
+
+    assert!(matches!(
+        engine.get_document_raw(&stale),
+        Err(DatabaseError::Corruption(_))
+    ));
+    assert!(engine.get_document_raw(&current).is_ok());
+}
+
+#[test]
+fn test_document_size_matches_serialized_length() {
+    let (mut engine, _dir) = create_engine();
+    let doc = make_doc();
+    let document_id = engine.insert_document(&doc).expect("insert failed");
+
+    let serialized = serialize_document(&doc).expect("serialize failed");
+    let size = engine.document_size(&document_id).expect("size failed");
+    assert_eq!(size, serialized.len());
+    assert_eq!(size, doc.serialized_size());
+    assert_eq!(
+        size,
+        engine.get_document_raw(&document_id).expect("get failed").len()
+    );
+}
+
+#[test]
+fn test_document_size_rejects_stale_id() {
+    let (mut engine, _dir) = create_engine();
+    let stale = engine.insert_document(&make_doc()).expect("insert failed");
+    engine.delete_document(&stale).expect("delete failed");
+    assert!(engine.document_size(&stale).is_err());
+
+    engine.insert_document(&make_doc()).expect("insert failed");
+    assert!(matches!(
+        engine.document_size(&stale),
+        Err(DatabaseError::Corruption(_))
+    ));
+}
@@ -0,0 +1,58 @@
+use database::storage::storage_engine::StorageEngine;
+use database::{Document, Value};
+use std::path::Path;
+use tempfile::tempdir;
+
+const POOL_PAGES: usize = 10;
+
+// A database of about 20 pages, checkpointed so reopening it reads no pages
+fn populated_database(path: &Path) {
+    database::storage::file::DatabaseFile::create(path).expect("Failed to create database file");
+    let mut engine = StorageEngine::new(path, POOL_PAGES).expect("Failed to create storage engine");
+    for i in 0..40 {
+        let mut doc = Document::new();
+        doc.set("n", Value::I32(i));
+        doc.set("padding", Value::String("x".repeat(3500)));
+        engine.insert_document(&doc).expect("insert failed");
+    }
+    engine.checkpoint().expect("checkpoint failed");
+}
+
+#[test]
+fn test_scan_without_read_ahead_misses_every_page() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.db");
+    populated_database(&path);
+
+    let mut engine = StorageEngine::new(&path, POOL_PAGES).expect("reopen failed");
+    assert_eq!(engine.read_ahead(), 0);
+    assert_eq!(engine.scan_all().expect("scan failed").len(), 40);
+
+    let pages = engine.page_ids().len() as u64;
+    let stats = engine.buffer_pool_stats();
+    assert_eq!((stats.hits, stats.misses, stats.prefetched), (0, pages, 0));
+    assert_eq!(engine.database_file.read_count(), pages);
+}
+
+#[test]
+fn test_scan_with_read_ahead_serves_upcoming_pages_as_hits() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("test.db");
+    populated_database(&path);
+
+    let mut engine = StorageEngine::new(&path, POOL_PAGES)
+        .expect("reopen failed")
+        .with_read_ahead(4);
+    assert_eq!(engine.read_ahead(), 4);
+    assert_eq!(engine.scan_all().expect("scan failed").len(), 40);
+
+    let pages = engine.page_ids().len() as u64;
+    assert!(pages > 10, "expected a multi-page database, got {} pages", pages);
+    let stats = engine.buffer_pool_stats();
+    // Every page was loaded by read-ahead, once, and then pinned from memory
+    assert_eq!(stats.prefetched, pages);
+    assert_eq!(stats.misses, 0);
+    assert_eq!(stats.hits, pages);
+    assert_eq!(stats.hit_ratio(), 1.0);
+    assert_eq!(engine.database_file.read_count(), pages);
+}
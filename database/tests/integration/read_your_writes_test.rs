@@ -0,0 +1,93 @@
+use database::{storage::storage_engine::StorageEngine, Document, Value};
+use tempfile::tempdir;
+
+// A tiny pool, so pages are evicted and re-read between operations
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 2).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn versioned(version: i32, padding: usize) -> Document {
+    with_version(Document::new(), version, padding)
+}
+
+fn with_version(mut doc: Document, version: i32, padding: usize) -> Document {
+    doc.set("version", Value::I32(version));
+    doc.set("padding", Value::String("x".repeat(padding)));
+    doc
+}
+
+#[test]
+fn test_insert_then_get() {
+    let (mut engine, _dir) = create_engine();
+    for i in 0..20 {
+        let doc = versioned(i, 1000);
+        let id = engine.insert_document(&doc).expect("insert failed");
+        assert_eq!(engine.get_document(&id).expect("get failed"), doc);
+    }
+}
+
+#[test]
+fn test_update_then_get() {
+    let (mut engine, _dir) = create_engine();
+    engine.create_index("version").expect("create_index failed");
+    let original = versioned(0, 100);
+    let mut id = engine.insert_document(&original).expect("insert failed");
+
+    // Alternate shrinking and growing so updates happen both in place and by relocation.
+    // Updates keep the stored `_id`, so build each version on the original one.
+    for i in 1..10 {
+        let padding = if i % 2 == 0 { 50 } else { 3000 };
+        let doc = with_version(Document::with_id(*original.get_id().unwrap()), i, padding);
+        id = engine.update_document(&id, &doc).expect("update failed");
+
+        assert_eq!(engine.get_document(&id).expect("get failed"), doc);
+        let found = engine
+            .find_range("version", Some(Value::I32(i)), Some(Value::I32(i)), (true, true))
+            .expect("find failed");
+        assert_eq!(found, vec![(id, doc)]);
+    }
+}
+
+#[test]
+fn test_delete_then_get() {
+    let (mut engine, _dir) = create_engine();
+    let ids: Vec<_> = (0..10)
+        .map(|i| engine.insert_document(&versioned(i, 1000)).expect("insert failed"))
+        .collect();
+
+    for (deleted, id) in ids.iter().enumerate() {
+        engine.delete_document(id).expect("delete failed");
+        assert!(engine.get_document(id).is_err());
+        assert_eq!(engine.count().expect("count failed"), ids.len() - deleted - 1);
+    }
+    assert!(engine.scan_all().expect("scan failed").is_empty());
+}
+
+#[test]
+fn test_failed_update_then_get() {
+    let (engine, _dir) = create_engine();
+    let mut engine = engine.with_document_cache(16);
+    engine.create_index("version").expect("create_index failed");
+    let original = versioned(0, 100);
+    let id = engine.insert_document(&original).expect("insert failed");
+
+    // Too large for any page, so the update fails after reading the original
+    let doc = with_version(Document::with_id(*original.get_id().unwrap()), 1, 9000);
+    assert!(engine.update_document(&id, &doc).is_err());
+
+    // Cache, index and pages all still hold the original
+    assert_eq!(engine.get_document(&id).expect("get failed"), original);
+    let found = engine
+        .find_range("version", Some(Value::I32(0)), Some(Value::I32(1)), (true, true))
+        .expect("find failed");
+    assert_eq!(found, vec![(id, original.clone())]);
+    assert_eq!(engine.scan_all().expect("scan failed"), vec![(id, original)]);
+}
@@ -0,0 +1,271 @@
+use database::{
+    storage::{
+        page::PAGE_SIZE,
+        storage_engine::{ScanOptions, StorageEngine},
+    },
+    Document, Value,
+};
+use std::fs::OpenOptions;
+use std::io::{Seek, SeekFrom, Write};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn make_doc(i: i32) -> Document {
+    let mut doc = Document::new();
+    doc.set("n", Value::I32(i));
+    doc
+}
+
+#[test]
+fn test_scan_all_returns_live_documents() {
+    let (mut engine, _dir) = create_engine();
+
+    let mut ids = vec![];
+    for i in 0..5 {
+        ids.push(engine.insert_document(&make_doc(i)).expect("insert failed"));
+    }
+    engine.delete_document(&ids[2]).expect("delete failed");
+
+    let scanned = engine.scan_all().expect("scan failed");
+    let values: Vec<_> = scanned.iter().map(|(_, doc)| doc.get("n").cloned()).collect();
+    assert_eq!(
+        values,
+        vec![
+            Some(Value::I32(0)),
+            Some(Value::I32(1)),
+            Some(Value::I32(3)),
+            Some(Value::I32(4))
+        ]
+    );
+}
+
+#[test]
+fn test_scan_by_id_orders_by_id() {
+    let (mut engine, _dir) = create_engine();
+
+    let docs: Vec<Document> = (0..10).map(make_doc).collect();
+    let mut ids = vec![];
+    for doc in &docs {
+        ids.push(engine.insert_document(doc).expect("insert failed"));
+    }
+
+    // Free the first half's slots, then refill them in reverse order.
+    for id in ids.iter().take(5) {
+        engine.delete_document(id).expect("delete failed");
+    }
+    for doc in docs.iter().take(5).rev() {
+        engine.insert_document(doc).expect("reinsert failed");
+    }
+
+    let physical: Vec<_> = engine
+        .scan_all()
+        .expect("scan failed")
+        .into_iter()
+        .map(|(_, doc)| doc.id().clone())
+        .collect();
+    let expected: Vec<_> = docs.iter().map(|doc| doc.id().clone()).collect();
+    assert_ne!(physical, expected, "reinserts should shuffle physical order");
+
+    let by_id: Vec<_> = engine
+        .scan_by_id()
+        .expect("scan failed")
+        .into_iter()
+        .map(|(_, doc)| doc.id().clone())
+        .collect();
+    assert_eq!(by_id, expected);
+}
+
+#[test]
+fn test_truncate_clears_all_documents() {
+    let (mut engine, _dir) = create_engine();
+
+    for i in 0..200 {
+        engine.insert_document(&make_doc(i)).expect("insert failed");
+    }
+    assert_eq!(engine.count().expect("count failed"), 200);
+
+    engine.truncate().expect("truncate failed");
+    assert_eq!(engine.count().expect("count failed"), 0);
+    assert!(engine.scan_all().expect("scan failed").is_empty());
+    assert_eq!(engine.database_file.page_count(), 1, "only the header page remains");
+
+    let id = engine.insert_document(&make_doc(7)).expect("insert after truncate failed");
+    let doc = engine.get_document(&id).expect("get after truncate failed");
+    assert_eq!(doc.get("n"), Some(&Value::I32(7)));
+    assert_eq!(engine.count().expect("count failed"), 1);
+}
+
+#[test]
+fn test_infer_schema_with_sample_limit() {
+    let (mut engine, _dir) = create_engine();
+
+    for i in 0..10 {
+        let mut doc = make_doc(i);
+        if i % 2 == 0 {
+            doc.set("even", Value::Bool(true));
+        }
+        engine.insert_document(&doc).expect("insert failed");
+    }
+
+    let schema = engine.infer_schema(None).expect("infer failed");
+    assert_eq!(schema.documents_sampled, 10);
+    assert_eq!(schema.presence("n"), Some(1.0));
+    assert_eq!(schema.types("n"), Some(vec!["i32"]));
+    assert_eq!(schema.presence("even"), Some(0.5));
+
+    let sampled = engine.infer_schema(Some(3)).expect("infer failed");
+    assert_eq!(sampled.documents_sampled, 3);
+}
+
+#[test]
+fn test_infer_schema_sample_limit_stops_reading_pages() {
+    let (mut engine, _dir) = create_engine();
+
+    // Large documents, so they spread over several pages
+    for i in 0..20 {
+        let mut doc = make_doc(i);
+        doc.set("pad", Value::String("x".repeat(2000)));
+        engine.insert_document(&doc).expect("insert failed");
+    }
+    let data_pages = engine.database_file.page_count() - 1;
+    assert!(data_pages > 2, "documents should span several pages");
+
+    let before = engine.buffer_pool_stats();
+    let schema = engine.infer_schema(Some(1)).expect("infer failed");
+    let after = engine.buffer_pool_stats();
+
+    assert_eq!(schema.documents_sampled, 1);
+    let page_reads = (after.hits + after.misses) - (before.hits + before.misses);
+    assert_eq!(page_reads, 1, "only the first data page should be read");
+}
+
+#[test]
+fn test_scan_skips_corrupt_page() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+    drop(database::storage::file::DatabaseFile::create(&db_path).expect("create failed"));
+
+    // Large documents, so they spread over several pages
+    let mut engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    let mut ids = vec![];
+    for i in 0..30 {
+        let mut doc = make_doc(i);
+        doc.set("padding", Value::String("x".repeat(1000)));
+        ids.push(engine.insert_document(&doc).expect("insert failed"));
+    }
+    engine.checkpoint().expect("checkpoint failed");
+    let page_count = engine.database_file.page_count();
+    assert!(page_count >= 3, "expected documents on several pages");
+    drop(engine);
+
+    // Flip a byte in the middle of one page behind the checksum's back. Pages sit at the
+    // end of the file, after the file header.
+    let bad_page = 1;
+    let file_len = std::fs::metadata(&db_path).unwrap().len();
+    let offset = file_len - (page_count - bad_page) * PAGE_SIZE as u64 + PAGE_SIZE as u64 / 2;
+    let mut file = OpenOptions::new().write(true).open(&db_path).unwrap();
+    file.seek(SeekFrom::Start(offset)).unwrap();
+    file.write_all(&[0xAB]).unwrap();
+    drop(file);
+
+    let mut engine = StorageEngine::new(&db_path, 10).expect("Failed to reopen storage engine");
+    assert!(engine.scan_all().is_err(), "strict scans fail on the bad page");
+    assert!(engine.scan(&ScanOptions::default()).is_err());
+
+    let result = engine
+        .scan(&ScanOptions { skip_corrupt: true })
+        .expect("lenient scan failed");
+    assert_eq!(result.corrupt_pages, vec![bad_page]);
+
+    let expected: Vec<_> = ids.iter().filter(|id| id.page_id() != bad_page).collect();
+    assert!(!expected.is_empty() && expected.len() < ids.len());
+    let returned: Vec<_> = result.documents.iter().map(|(id, _)| id).collect();
+    assert_eq!(returned, expected);
+    for (id, doc) in &result.documents {
+        let n = ids.iter().position(|other| other == id).unwrap() as i32;
+        assert_eq!(doc.get("n"), Some(&Value::I32(n)));
+    }
+}
+
+#[test]
+fn test_scan_without_corruption_reports_nothing() {
+    let (mut engine, _dir) = create_engine();
+    for i in 0..5 {
+        engine.insert_document(&make_doc(i)).expect("insert failed");
+    }
+
+    let result = engine
+        .scan(&ScanOptions { skip_corrupt: true })
+        .expect("scan failed");
+    assert_eq!(result.documents.len(), 5);
+    assert!(result.corrupt_pages.is_empty());
+}
+
+#[test]
+fn test_iter_documents_after_reopen_skips_deleted() {
+    let (mut engine, dir) = create_engine();
+    let mut ids = vec![];
+    for i in 0..5 {
+        ids.push(engine.insert_document(&make_doc(i)).expect("insert failed"));
+    }
+    engine.delete_document(&ids[1]).expect("delete failed");
+    engine.checkpoint().expect("checkpoint failed");
+    drop(engine);
+
+    let db_path = dir.path().join("test.db");
+    let mut engine = StorageEngine::new(&db_path, 10).expect("Failed to reopen storage engine");
+    let listed: Vec<_> = engine
+        .iter_documents()
+        .map(|result| result.expect("document failed to read"))
+        .collect();
+    assert_eq!(listed, engine.scan_all().expect("scan failed"));
+    let returned: Vec<_> = listed.iter().map(|(id, _)| *id).collect();
+    assert_eq!(returned, [ids[0], ids[2], ids[3], ids[4]]);
+}
+
+#[test]
+fn test_iter_documents_continues_past_unreadable_page() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+    drop(database::storage::file::DatabaseFile::create(&db_path).expect("create failed"));
+
+    // Two documents per page
+    let mut engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    let mut ids = vec![];
+    for i in 0..6 {
+        let mut doc = make_doc(i);
+        doc.set("padding", Value::String("x".repeat(3500)));
+        ids.push(engine.insert_document(&doc).expect("insert failed"));
+    }
+    engine.checkpoint().expect("checkpoint failed");
+    drop(engine);
+
+    let bad_page = ids[2].page_id();
+    assert_eq!(ids[3].page_id(), bad_page);
+    let mut file = OpenOptions::new().write(true).open(&db_path).unwrap();
+    file.seek(SeekFrom::Start(bad_page * PAGE_SIZE as u64 + PAGE_SIZE as u64 / 2)).unwrap();
+    file.write_all(&[0xAB]).unwrap();
+    drop(file);
+
+    let mut engine = StorageEngine::new(&db_path, 10).expect("Failed to reopen storage engine");
+    let results: Vec<_> = engine.iter_documents().collect();
+    assert_eq!(results.len(), 5, "one error in place of the bad page's two documents");
+    assert!(results[2].is_err());
+    let returned: Vec<_> = results
+        .iter()
+        .filter_map(|result| result.as_ref().ok())
+        .map(|(id, _)| *id)
+        .collect();
+    assert_eq!(returned, [ids[0], ids[1], ids[4], ids[5]]);
+}
@@ -0,0 +1,84 @@
+use database::page_layout::{PageLayout, SlotLayout};
+use database::storage::storage_engine::{DocumentId, StorageEngine};
+use database::{Document, Value};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn make_doc(i: i32) -> Document {
+    let mut doc = Document::new();
+    doc.set("n", Value::I32(i));
+    doc
+}
+
+fn layouts(engine: &mut StorageEngine) -> Vec<SlotLayout> {
+    engine
+        .page_ids()
+        .into_iter()
+        .map(|page_id| {
+            let page = engine.database_file.read_page(page_id).expect("read failed");
+            PageLayout::slot_layout(&page).expect("unreadable slot directory")
+        })
+        .collect()
+}
+
+#[test]
+fn test_stable_layout_stores_many_small_documents() {
+    let (engine, dir) = create_engine();
+    let mut engine = engine.with_slot_layout(SlotLayout::Stable);
+    assert_eq!(engine.slot_layout(), SlotLayout::Stable);
+
+    let ids: Vec<DocumentId> = (0..2000)
+        .map(|i| engine.insert_document(&make_doc(i)).expect("insert failed"))
+        .collect();
+    // Small documents pack hundreds of slots onto each page
+    assert!(ids.iter().any(|id| id.slot_id() > 100));
+
+    for id in ids.iter().step_by(3) {
+        engine.delete_document(id).expect("delete failed");
+    }
+    engine.vacuum().expect("vacuum failed");
+    engine.checkpoint().expect("checkpoint failed");
+    drop(engine);
+
+    let mut engine = StorageEngine::new(&dir.path().join("test.db"), 10).expect("reopen failed");
+    assert!(layouts(&mut engine).iter().all(|layout| *layout == SlotLayout::Stable));
+    for (i, id) in ids.iter().enumerate() {
+        let found = engine.get_document(id);
+        if i % 3 == 0 {
+            assert!(found.is_err(), "document {} should be deleted", i);
+        } else {
+            assert_eq!(found.expect("get failed").get("n"), Some(&Value::I32(i as i32)));
+        }
+    }
+}
+
+#[test]
+fn test_layout_applies_to_new_pages_only() {
+    let (mut engine, _dir) = create_engine();
+    assert_eq!(engine.slot_layout(), SlotLayout::Shifting);
+    let small = make_doc(0);
+    let first = engine.insert_document(&small).expect("insert failed");
+
+    // The existing page keeps its layout, and both kinds are read back
+    let mut engine = engine.with_slot_layout(SlotLayout::Stable);
+    // Too big to share the first page
+    let mut padding = make_doc(1);
+    padding.set("padding", Value::String("x".repeat(PageLayout::max_document_size() - 50)));
+    let second = engine.insert_document(&padding).expect("insert failed");
+    assert_ne!(first.page_id(), second.page_id());
+
+    assert_eq!(layouts(&mut engine), [SlotLayout::Shifting, SlotLayout::Stable]);
+    assert_eq!(engine.get_document(&first).expect("get failed"), small);
+    assert_eq!(engine.get_document(&second).expect("get failed"), padding);
+}
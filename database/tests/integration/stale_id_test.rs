@@ -0,0 +1,97 @@
+use database::{
+    error::DatabaseError,
+    storage::storage_engine::{DocumentId, StorageEngine},
+    Document, Value,
+};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn named(name: &str) -> Document {
+    let mut doc = Document::new();
+    doc.set("name", Value::String(name.to_string()));
+    doc
+}
+
+// Deletes the only document and inserts another into the slot it freed
+fn reuse_slot(engine: &mut StorageEngine) -> (DocumentId, DocumentId) {
+    let stale = engine.insert_document(&named("alice")).expect("insert failed");
+    engine.delete_document(&stale).expect("delete failed");
+    let current = engine.insert_document(&named("bob")).expect("insert failed");
+    assert_eq!(current, stale, "expected the freed slot to be reused");
+    (stale, current)
+}
+
+fn assert_corruption(result: database::result::Result<impl std::fmt::Debug>) {
+    let err = result.expect_err("stale id should be rejected");
+    match err {
+        DatabaseError::Corruption(msg) => assert!(msg.contains("stale document id")),
+        other => panic!("Expected Corruption, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_valid_id_is_retrieved() {
+    let (mut engine, _dir) = create_engine();
+    let doc = named("alice");
+    let id = engine.insert_document(&doc).expect("insert failed");
+
+    assert_eq!(id.object_id(), doc.get_id().copied());
+    assert_eq!(engine.get_document(&id).expect("get failed"), doc);
+}
+
+#[test]
+fn test_stale_id_on_reused_slot_is_rejected() {
+    let (mut engine, _dir) = create_engine();
+    let (stale, current) = reuse_slot(&mut engine);
+
+    assert_corruption(engine.get_document(&stale));
+    let doc = engine.get_document(&current).expect("get failed");
+    assert_eq!(doc.get("name"), Some(&Value::String("bob".to_string())));
+}
+
+#[test]
+fn test_stale_id_cannot_modify_reused_slot() {
+    let (mut engine, _dir) = create_engine();
+    let (stale, current) = reuse_slot(&mut engine);
+
+    assert_corruption(engine.update_document(&stale, &named("mallory")));
+    assert_corruption(engine.delete_document(&stale));
+
+    let doc = engine.get_document(&current).expect("get failed");
+    assert_eq!(doc.get("name"), Some(&Value::String("bob".to_string())));
+}
+
+#[test]
+fn test_id_stays_valid_across_in_place_update() {
+    let (mut engine, _dir) = create_engine();
+    let id = engine.insert_document(&named("alice")).expect("insert failed");
+
+    let new_id = engine.update_document(&id, &named("bob")).expect("update failed");
+    assert_eq!(new_id, id);
+
+    let doc = engine.get_document(&id).expect("get failed");
+    assert_eq!(doc.get("name"), Some(&Value::String("bob".to_string())));
+    assert_eq!(doc.get_id().copied(), id.object_id());
+}
+
+#[test]
+fn test_untagged_id_is_not_verified() {
+    let (mut engine, _dir) = create_engine();
+    let (stale, _) = reuse_slot(&mut engine);
+
+    let untagged = DocumentId::new(stale.page_id(), stale.slot_id());
+    assert_eq!(untagged.object_id(), None);
+    let doc = engine.get_document(&untagged).expect("get failed");
+    assert_eq!(doc.get("name"), Some(&Value::String("bob".to_string())));
+}
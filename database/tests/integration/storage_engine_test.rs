@@ -28,7 +28,7 @@ fn test_insert_document_basic() {
 
     // We expect this to fail with our current implementation since
     // we haven't implemented page allocation yet
-    assert!(!result.is_err());
+    assert!(result.is_ok());
 }
 
 #[test]
@@ -43,7 +43,7 @@ fn test_document_serialization() {
     assert!(serialized.is_ok());
 
     let bytes = serialized.unwrap();
-    assert!(bytes.len() > 0);
+    assert!(!bytes.is_empty());
 
     // The serialized bytes should be reasonable in size
     // BSON has some overhead but should be fairly compact
@@ -0,0 +1,67 @@
+use database::bson::BsonDecoder;
+use database::storage::storage_engine::StorageEngine;
+use database::{Document, Value};
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+// Records the size of every read passed through it
+struct ReadLog<R> {
+    inner: R,
+    reads: Arc<Mutex<Vec<usize>>>,
+}
+
+impl<R: Read> Read for ReadLog<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.reads.lock().unwrap().push(n);
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_streaming_read_decodes_large_document_incrementally() {
+    let (mut engine, _dir) = create_engine();
+    let mut doc = Document::new();
+    doc.set("title", Value::String("large".to_string()));
+    doc.set("body", Value::String("lorem ipsum ".repeat(600)));
+    let id = engine.insert_document(&doc).expect("insert failed");
+    let stored_size = engine.document_size(&id).expect("size failed");
+    assert!(stored_size > 7000, "document is only {} bytes", stored_size);
+
+    let reads = Arc::new(Mutex::new(Vec::new()));
+    let reader = ReadLog {
+        inner: engine.read_document_streaming(&id).expect("open reader failed"),
+        reads: Arc::clone(&reads),
+    };
+    let decoded = BsonDecoder::new(reader).decode_document().expect("decode failed");
+    assert_eq!(decoded, doc);
+
+    // The decoder pulled the bytes in several chunks, never the whole document at once
+    let reads = reads.lock().unwrap();
+    assert!(reads.len() > 2, "reads: {:?}", reads);
+    assert!(reads.iter().all(|&n| n < stored_size));
+    assert_eq!(reads.iter().sum::<usize>(), stored_size);
+}
+
+#[test]
+fn test_streaming_read_rejects_deleted_document() {
+    let (mut engine, _dir) = create_engine();
+    let mut doc = Document::new();
+    doc.set("n", Value::I32(1));
+    let id = engine.insert_document(&doc).expect("insert failed");
+    engine.delete_document(&id).expect("delete failed");
+    assert!(engine.read_document_streaming(&id).is_err());
+}
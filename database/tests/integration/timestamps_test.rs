@@ -0,0 +1,82 @@
+use database::document::validator::DocumentValidator;
+use database::{storage::storage_engine::StorageEngine, Document, Value};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10)
+        .expect("Failed to create storage engine")
+        .with_timestamps(true);
+    (engine, temp_dir)
+}
+
+fn named(name: &str) -> Document {
+    let mut doc = Document::new();
+    doc.set("name", Value::String(name.to_string()));
+    doc
+}
+
+#[test]
+fn test_timestamps_disabled_by_default() {
+    let (engine, dir) = create_engine();
+    drop(engine);
+
+    let mut engine = StorageEngine::new(&dir.path().join("test.db"), 10).unwrap();
+    assert!(!engine.timestamps_enabled());
+    let id = engine.insert_document(&named("plain")).unwrap();
+    let stored = engine.get_document(&id).unwrap();
+    assert_eq!(stored.created_at(), None);
+    assert_eq!(stored.updated_at(), None);
+}
+
+#[test]
+fn test_insert_stamps_created_and_updated() {
+    let (mut engine, _dir) = create_engine();
+
+    let id = engine.insert_document(&named("a")).expect("insert failed");
+    let stored = engine.get_document(&id).expect("get failed");
+
+    let created = stored.created_at().expect("_created should be set");
+    assert_eq!(stored.updated_at(), Some(created));
+}
+
+#[test]
+fn test_created_fixed_and_updated_advances() {
+    let (mut engine, _dir) = create_engine();
+
+    let mut id = engine.insert_document(&named("v0")).expect("insert failed");
+    let created = engine.get_document(&id).unwrap().created_at().unwrap();
+    let mut last_updated = created;
+
+    for i in 1..=5 {
+        let mut replacement = named(&format!("v{}", i));
+        // An update trying to rewrite _created is ignored
+        replacement.set("_created", Value::DateTime(chrono::Utc::now() + chrono::Duration::days(1)));
+        id = engine.update_document(&id, &replacement).expect("update failed");
+
+        let stored = engine.get_document(&id).expect("get failed");
+        assert_eq!(stored.created_at(), Some(created));
+        let updated = stored.updated_at().expect("_updated should be set");
+        assert!(updated > last_updated, "_updated must advance on every update");
+        last_updated = updated;
+    }
+}
+
+#[test]
+fn test_stamped_documents_pass_timestamp_aware_validator() {
+    let (mut engine, _dir) = create_engine();
+    let id = engine.insert_document(&named("a")).expect("insert failed");
+    let stored = engine.get_document(&id).expect("get failed");
+
+    assert!(DocumentValidator::new().validate_document(&stored).is_err());
+    assert!(DocumentValidator::new()
+        .with_timestamp_fields()
+        .validate_document(&stored)
+        .is_ok());
+}
@@ -0,0 +1,58 @@
+use database::storage::storage_engine::StorageEngine;
+use database::{Document, Value};
+use tempfile::tempdir;
+
+fn create_engine() -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10).expect("Failed to create storage engine");
+    (engine, temp_dir)
+}
+
+fn user_doc() -> Document {
+    let mut doc = Document::new();
+    doc.set("name", Value::String("Ada".to_string()));
+    doc.set("age", Value::I32(36));
+    doc.set("tags", Value::Array(vec![Value::String("admin".to_string())]));
+    doc
+}
+
+#[test]
+fn test_update_fields_keeps_other_fields_and_id() {
+    let (mut engine, _dir) = create_engine();
+    let original = user_doc();
+    let id = engine.insert_document(&original).expect("insert failed");
+
+    let mut changes = Document::new();
+    changes.set("age", Value::I32(37));
+    let new_id = engine.update_fields(&id, &changes).expect("update failed");
+
+    let stored = engine.get_document(&new_id).expect("get failed");
+    assert_eq!(stored.get_id(), original.get_id());
+    assert_eq!(stored.get("age"), Some(&Value::I32(37)));
+    assert_eq!(stored.get("name"), original.get("name"));
+    assert_eq!(stored.get("tags"), original.get("tags"));
+    assert_eq!(stored.len(), original.len());
+}
+
+#[test]
+fn test_update_fields_can_add_fields_and_grow() {
+    let (mut engine, _dir) = create_engine();
+    let original = user_doc();
+    let id = engine.insert_document(&original).expect("insert failed");
+
+    // Parsed from JSON, as an editor would, so `changes` carries an unrelated new id
+    let changes = Document::from_json(r#"{"bio": "wrote the first program"}"#).unwrap();
+    let new_id = engine.update_fields(&id, &changes).expect("update failed");
+
+    let stored = engine.get_document(&new_id).expect("get failed");
+    assert_eq!(stored.get_id(), original.get_id());
+    assert_eq!(stored.get("bio"), Some(&Value::String("wrote the first program".to_string())));
+    assert_eq!(stored.get("age"), Some(&Value::I32(36)));
+    assert_eq!(engine.count().unwrap(), 1);
+}
@@ -1,4 +1,8 @@
-use database::{storage::storage_engine::StorageEngine, Document, Value};
+use database::{
+    storage::{file::FIRST_DATA_PAGE_ID, storage_engine::StorageEngine},
+    Document, Value,
+};
+use std::path::Path;
 use tempfile::tempdir;
 
 fn create_engine() -> (StorageEngine, tempfile::TempDir) {
@@ -109,3 +113,150 @@ fn test_vacuum_idempotent() {
     assert!(first > 0);
     assert_eq!(second, 0, "second vacuum should find nothing to compact");
 }
+
+#[test]
+fn test_reclaimable_bytes_tracks_deletions() {
+    let (mut engine, _dir) = create_engine();
+    assert_eq!(engine.reclaimable_bytes().expect("stats failed"), 0);
+
+    let mut ids = vec![];
+    for i in 0..10 {
+        let id = engine
+            .insert_document(&make_doc(&format!("doc_{}", i)))
+            .expect("insert failed");
+        ids.push(id);
+    }
+    assert_eq!(engine.reclaimable_bytes().expect("stats failed"), 0);
+
+    // Delete the first five documents. The five after them stay live, so every deleted
+    // byte sits below the last live document and counts as reclaimable
+    let mut deleted_bytes = 0;
+    for id in ids.iter().take(5) {
+        deleted_bytes += database::bson::serialize_document(&engine.get_document(id).unwrap())
+            .unwrap()
+            .len();
+        engine.delete_document(id).expect("delete failed");
+    }
+    assert_eq!(engine.reclaimable_bytes().expect("stats failed"), deleted_bytes);
+
+    engine.vacuum().expect("vacuum failed");
+    assert_eq!(engine.reclaimable_bytes().expect("stats failed"), 0);
+}
+
+#[test]
+fn test_compact_step_resumes_until_all_pages_compacted() {
+    let (mut engine, _dir) = create_engine();
+
+    // Large documents spread over several pages; deleting every other one fragments each
+    let mut ids = vec![];
+    for i in 0..40 {
+        let mut doc = make_doc(&format!("doc_{}", i));
+        doc.set("padding", Value::String("x".repeat(1000)));
+        ids.push(engine.insert_document(&doc).expect("insert failed"));
+    }
+    let mut fragmented: Vec<u64> = ids.iter().map(|id| id.page_id()).collect();
+    fragmented.dedup();
+    assert!(fragmented.len() > 3, "expected documents on several pages");
+
+    for id in ids.iter().step_by(2) {
+        engine.delete_document(id).expect("delete failed");
+    }
+
+    let mut compacted = 0;
+    let mut steps = 0;
+    loop {
+        let progress = engine.compact_step(2).expect("compact_step failed");
+        assert!(progress.pages_visited <= 2);
+        compacted += progress.pages_compacted;
+        steps += 1;
+        if progress.pass_complete {
+            break;
+        }
+        assert!(engine.compaction_cursor() > FIRST_DATA_PAGE_ID);
+    }
+
+    assert!(steps > 1, "expected the work to be spread over several steps");
+    assert_eq!(compacted, fragmented.len());
+    assert_eq!(engine.compaction_cursor(), FIRST_DATA_PAGE_ID);
+    assert_eq!(engine.vacuum().expect("vacuum failed"), 0);
+
+    for (i, id) in ids.iter().enumerate() {
+        let result = engine.get_document(id);
+        if i % 2 == 0 {
+            assert!(result.is_err(), "deleted document {} came back", i);
+        } else {
+            let doc = result.expect("surviving document should be readable");
+            assert_eq!(doc.get("name"), Some(&Value::String(format!("doc_{}", i))));
+        }
+    }
+}
+
+#[test]
+fn test_compact_step_with_zero_budget_does_nothing() {
+    let (mut engine, _dir) = create_engine();
+    let id = engine.insert_document(&make_doc("a")).expect("insert failed");
+    engine.delete_document(&id).expect("delete failed");
+
+    let progress = engine.compact_step(0).expect("compact_step failed");
+    assert_eq!(progress.pages_visited, 0);
+    assert!(!progress.pass_complete);
+    assert_eq!(engine.vacuum().expect("vacuum failed"), 1);
+}
+
+fn padded_doc(i: usize, padding: usize) -> Document {
+    let mut doc = make_doc(&format!("doc_{}", i));
+    doc.set("padding", Value::String("x".repeat(padding)));
+    doc
+}
+
+// Fills the first data page, then deletes all but its last document, leaving the page mostly
+// tombstones with the survivor's bytes at the far end of the data area
+fn churn_first_page(engine: &mut StorageEngine) -> Vec<database::storage_engine::DocumentId> {
+    let ids: Vec<_> = (0..7)
+        .map(|i| engine.insert_document(&padded_doc(i, 1000)).expect("insert failed"))
+        .collect();
+    assert!(ids.iter().all(|id| id.page_id() == FIRST_DATA_PAGE_ID));
+    for id in &ids[..6] {
+        engine.delete_document(id).expect("delete failed");
+    }
+    ids
+}
+
+fn open_engine(path: &Path) -> StorageEngine {
+    database::storage::file::DatabaseFile::create(path).expect("create failed");
+    StorageEngine::new(path, 10).expect("Failed to create storage engine")
+}
+
+#[test]
+fn test_tombstone_threshold_compacts_page_on_insert() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut engine =
+        open_engine(&dir.path().join("test.db")).with_tombstone_compaction_threshold(0.5);
+    assert_eq!(engine.tombstone_compaction_threshold(), Some(0.5));
+    let ids = churn_first_page(&mut engine);
+
+    // Lands where the deleted documents were
+    let id = engine
+        .insert_document(&padded_doc(7, 4000))
+        .expect("insert failed");
+    assert_eq!(id.page_id(), FIRST_DATA_PAGE_ID, "the churned page should have been compacted and reused");
+
+    let survivor = engine.get_document(&ids[6]).expect("survivor should keep its id");
+    assert_eq!(survivor.get("name"), Some(&Value::String("doc_6".to_string())));
+    assert_eq!(engine.vacuum().expect("vacuum failed"), 0);
+}
+
+#[test]
+fn test_without_tombstone_threshold_page_is_not_compacted() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut engine = open_engine(&dir.path().join("test.db"));
+    assert_eq!(engine.tombstone_compaction_threshold(), None);
+    churn_first_page(&mut engine);
+
+    // The deleted documents left one hole, which takes the new document without compacting
+    let id = engine
+        .insert_document(&padded_doc(7, 4000))
+        .expect("insert failed");
+    assert_eq!(id.page_id(), FIRST_DATA_PAGE_ID);
+    assert_eq!(engine.vacuum().expect("vacuum failed"), 1, "the tombstones should remain");
+}
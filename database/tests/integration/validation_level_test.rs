@@ -0,0 +1,112 @@
+use database::error::DatabaseError;
+use database::storage::storage_engine::{StorageEngine, ValidationLevel};
+use database::{Document, Value};
+use tempfile::tempdir;
+
+fn create_engine(level: ValidationLevel) -> (StorageEngine, tempfile::TempDir) {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let db_path = temp_dir.path().join("test.db");
+
+    let _db_file = database::storage::file::DatabaseFile::create(&db_path)
+        .expect("Failed to create database file");
+    drop(_db_file);
+
+    let engine = StorageEngine::new(&db_path, 10)
+        .expect("Failed to create storage engine")
+        .with_validation_level(level);
+    (engine, temp_dir)
+}
+
+// Valid size, but the field name breaks the naming rules
+fn badly_named_doc() -> Document {
+    let mut doc = Document::new();
+    doc.set("bad-name", Value::I32(1));
+    doc
+}
+
+fn is_validation_error(err: &DatabaseError) -> bool {
+    matches!(err, DatabaseError::Validation(_))
+}
+
+#[test]
+fn test_full_validation_is_default_and_rejects_invalid_document() {
+    let (mut engine, _dir) = create_engine(ValidationLevel::default());
+    assert_eq!(engine.validation_level(), ValidationLevel::Full);
+
+    let err = engine
+        .insert_document(&badly_named_doc())
+        .expect_err("invalid document should be rejected");
+    assert!(is_validation_error(&err), "unexpected error: {}", err);
+    assert_eq!(engine.count().unwrap(), 0);
+}
+
+#[test]
+fn test_no_validation_stores_document_as_is() {
+    let (mut engine, _dir) = create_engine(ValidationLevel::None);
+
+    let id = engine
+        .insert_document(&badly_named_doc())
+        .expect("insert without validation failed");
+    let stored = engine.get_document(&id).expect("get failed");
+    assert_eq!(stored.get("bad-name"), Some(&Value::I32(1)));
+}
+
+#[test]
+fn test_cheap_validation_skips_field_rules_but_checks_size() {
+    let (mut engine, _dir) = create_engine(ValidationLevel::Cheap);
+    engine
+        .insert_document(&badly_named_doc())
+        .expect("cheap validation should not check field names");
+
+    let mut oversized = Document::new();
+    oversized.set("blob", Value::Binary(vec![0u8; 17 * 1024 * 1024]));
+    let err = engine
+        .insert_document(&oversized)
+        .expect_err("oversized document should be rejected");
+    assert!(is_validation_error(&err), "unexpected error: {}", err);
+}
+
+#[test]
+fn test_insert_many_uses_current_level() {
+    let (mut engine, _dir) = create_engine(ValidationLevel::Full);
+    let documents = vec![badly_named_doc(), badly_named_doc()];
+
+    assert!(engine.insert_many(&documents).is_err());
+
+    engine.set_validation_level(ValidationLevel::None);
+    let ids = engine.insert_many(&documents).expect("trusted bulk load failed");
+    assert_eq!(ids.len(), 2);
+    assert_eq!(engine.count().unwrap(), 2);
+}
+
+// A data field that would be confused with the document's own `_id`
+fn shadowing_id_doc() -> Document {
+    let mut doc = Document::new();
+    doc.set("_id", Value::String("not the id".to_string()));
+    doc
+}
+
+#[test]
+fn test_reserved_field_names_rejected_unless_validation_disabled() {
+    for level in [ValidationLevel::Full, ValidationLevel::Cheap] {
+        let (mut engine, _dir) = create_engine(level);
+        let err = engine
+            .insert_document(&shadowing_id_doc())
+            .expect_err("reserved field name should be rejected");
+        assert!(is_validation_error(&err), "unexpected error: {}", err);
+
+        let mut valid = Document::new();
+        valid.set("name", Value::I32(1));
+        let id = engine.insert_document(&valid).expect("insert failed");
+        let err = engine
+            .update_document(&id, &shadowing_id_doc())
+            .expect_err("reserved field name should be rejected");
+        assert!(is_validation_error(&err), "unexpected error: {}", err);
+    }
+
+    let (mut engine, _dir) = create_engine(ValidationLevel::None);
+    engine
+        .insert_document(&shadowing_id_doc())
+        .expect("insert without validation failed");
+    assert_eq!(engine.count().unwrap(), 1);
+}
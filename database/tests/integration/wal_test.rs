@@ -0,0 +1,66 @@
+use database::storage::wal::WriteAheadLog;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tempfile::tempdir;
+
+const THREADS: usize = 8;
+const COMMITS_PER_THREAD: usize = 40;
+
+#[test]
+fn test_group_commit_shares_fsyncs() {
+    let temp_dir = tempdir().expect("Failed to create temp directory");
+    let wal_path = temp_dir.path().join("test.wal");
+
+    let wal = Arc::new(
+        WriteAheadLog::open(&wal_path)
+            .expect("Failed to open WAL")
+            .with_batch_window(Duration::from_millis(5)),
+    );
+
+    let handles: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let wal = Arc::clone(&wal);
+            thread::spawn(move || {
+                (0..COMMITS_PER_THREAD)
+                    .map(|i| {
+                        let payload = format!("txn-{}-{}", t, i);
+                        let lsn = wal.commit(payload.as_bytes()).expect("commit failed");
+                        // Durable as soon as commit returns
+                        assert!(wal.durable_lsn() >= lsn);
+                        payload
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+
+    let committed: HashSet<String> = handles
+        .into_iter()
+        .flat_map(|handle| handle.join().expect("committer panicked"))
+        .collect();
+    let commits = THREADS * COMMITS_PER_THREAD;
+    assert_eq!(committed.len(), commits);
+
+    let syncs = wal.sync_count() as usize;
+    assert!(
+        syncs * 4 <= commits,
+        "expected batched fsyncs, got {} for {} commits",
+        syncs,
+        commits
+    );
+    drop(wal);
+
+    // Every acknowledged commit is in the log, in LSN order
+    let records = WriteAheadLog::read_records(&wal_path).expect("Failed to read WAL");
+    assert_eq!(records.len(), commits);
+    for (i, record) in records.iter().enumerate() {
+        assert_eq!(record.lsn, i as u64 + 1);
+    }
+    let recovered: HashSet<String> = records
+        .into_iter()
+        .map(|record| String::from_utf8(record.payload).expect("payload should be utf-8"))
+        .collect();
+    assert_eq!(recovered, committed);
+}
@@ -14,7 +14,7 @@ fn test_document_lifecycle() {
     doc.set("bool", Value::Bool(true));
     doc.set("int32", Value::I32(42));
     doc.set("int64", Value::I64(i64::MAX));
-    doc.set("double", Value::F64(3.14159));
+    doc.set("double", Value::F64(std::f64::consts::PI));
     doc.set("string", Value::String("Hello, BSON!".to_string()));
     
     // Add array with mixed types
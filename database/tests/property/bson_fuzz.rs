@@ -0,0 +1,60 @@
+use database::bson::{deserialize_document, serialize_document};
+use database::{Document, Value};
+use proptest::prelude::*;
+
+/// A valid document touching every fixed-width and length-prefixed type
+fn sample_document_bytes() -> Vec<u8> {
+    let mut doc = Document::new();
+    doc.set("i32", Value::I32(-7));
+    doc.set("i64", Value::I64(i64::MIN));
+    doc.set("f64", Value::F64(1.5));
+    doc.set("s", Value::String("hello".to_string()));
+    doc.set("bin", Value::Binary(vec![1, 2, 3]));
+    doc.set("arr", Value::Array(vec![Value::I32(1), Value::Bool(true)]));
+    doc.set("when", Value::DateTime(chrono::DateTime::from_timestamp_millis(0).unwrap()));
+    serialize_document(&doc).expect("sample document should serialize")
+}
+
+/// Prefix `body` with a length that matches, so decoding gets past the header check
+fn with_length_prefix(body: &[u8]) -> Vec<u8> {
+    let mut bytes = ((body.len() + 4) as u32).to_le_bytes().to_vec();
+    bytes.extend_from_slice(body);
+    bytes
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    // Any byte string decodes to Ok or a BsonError; it must never panic
+    #[test]
+    fn property_random_bytes_never_panic(bytes in prop::collection::vec(any::<u8>(), 0..256)) {
+        let _ = deserialize_document(&bytes);
+    }
+
+    #[test]
+    fn property_random_body_with_valid_length_never_panics(
+        body in prop::collection::vec(any::<u8>(), 0..256)
+    ) {
+        let _ = deserialize_document(&with_length_prefix(&body));
+    }
+
+    #[test]
+    fn property_corrupted_document_never_panics(
+        flips in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..8)
+    ) {
+        let mut bytes = sample_document_bytes();
+        for (index, byte) in flips {
+            let position = index.index(bytes.len());
+            bytes[position] = byte;
+        }
+        let _ = deserialize_document(&bytes);
+    }
+
+    #[test]
+    fn property_truncated_document_never_panics(cut in any::<prop::sample::Index>()) {
+        let bytes = sample_document_bytes();
+        let body = &bytes[4..4 + cut.index(bytes.len() - 4)];
+        // Keep the length consistent so truncation lands inside field values
+        prop_assert!(deserialize_document(&with_length_prefix(body)).is_err());
+    }
+}
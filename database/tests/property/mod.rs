@@ -1,6 +1,7 @@
 // Property-based and fuzz tests module
 // Tests that verify system properties and edge cases
 
+mod bson_fuzz;
 mod document_iteration_test;
 mod id_persistence_test;
 mod page_layout_advanced_qa;
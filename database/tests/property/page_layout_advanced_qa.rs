@@ -1,11 +1,9 @@
 /// Advanced Quality Assurance Tests for Page Layout Manager
 /// These tests focus on complex scenarios, edge cases, and potential race conditions
-use database::storage::{page::{Page, PageType}, page_layout::PageLayout};
+use database::storage::{page::Page, page_layout::PageLayout};
 
 fn create_test_page() -> Page {
-    let mut page = Page::new(1, PageType::Data);
-    PageLayout::initialize_page(&mut page).expect("Failed to initialize page");
-    page
+    PageLayout::new_page(1)
 }
 
 #[cfg(test)]
@@ -144,7 +142,7 @@ mod advanced_qa_tests {
         
         // Test utilization calculation at capacity boundary
         let utilization = PageLayout::get_utilization_percentage(&page).expect("Failed to get utilization");
-        assert!(utilization >= 0.0 && utilization <= 100.0);
+        assert!((0.0..=100.0).contains(&utilization));
     }
 
     /// Test slot directory behavior under extreme conditions
@@ -177,7 +175,7 @@ mod advanced_qa_tests {
             let doc = vec![b'Z'; 2]; // Slightly larger documents
             if let Ok(new_slot) = PageLayout::insert_document(&mut page, &doc) {
                 // Check if slot was reused
-                if slots.iter().any(|&s| s == new_slot) {
+                if slots.contains(&new_slot) {
                     reuse_count += 1;
                 }
                 
@@ -247,7 +245,7 @@ mod advanced_qa_tests {
         assert!(count > 0, "Should still have documents after updates");
         
         let utilization = PageLayout::get_utilization_percentage(&page).expect("Failed to get utilization");
-        assert!(utilization >= 0.0 && utilization <= 100.0, "Invalid utilization after updates");
+        assert!((0.0..=100.0).contains(&utilization), "Invalid utilization after updates");
     }
 
     /// Test page state transitions and recovery
@@ -291,7 +289,7 @@ mod advanced_qa_tests {
         assert_eq!(compacted_count, fragmented_count, "Count should remain same after compaction");
         
         let compacted_utilization = PageLayout::get_utilization_percentage(&page).expect("Failed to get utilization");
-        assert!(compacted_utilization >= 0.0 && compacted_utilization <= 100.0);
+        assert!((0.0..=100.0).contains(&compacted_utilization));
         
         // Phase 4: Verify remaining documents are intact
         for (i, (slot, expected_doc)) in phase_documents.iter().enumerate() {
@@ -368,7 +366,7 @@ mod advanced_qa_tests {
         assert_eq!(count as usize, valid_slots.len(), "Document count inconsistent after errors");
         
         let utilization = PageLayout::get_utilization_percentage(&page).expect("Failed to get utilization");
-        assert!(utilization >= 0.0 && utilization <= 100.0, "Invalid utilization after errors");
+        assert!((0.0..=100.0).contains(&utilization), "Invalid utilization after errors");
         
         // Test that page is still functional after error conditions
         let new_doc = vec![b'Z'; 30];
@@ -1,9 +1,7 @@
-use database::storage::{page::{Page, PageType}, page_layout::PageLayout};
+use database::storage::{page::Page, page_layout::PageLayout};
 
 fn create_test_page() -> Page {
-    let mut page = Page::new(1, PageType::Data);
-    PageLayout::initialize_page(&mut page).expect("Failed to initialize page");
-    page
+    PageLayout::new_page(1)
 }
 
 /// Generate test data of specific size
@@ -24,13 +22,8 @@ mod stress_tests {
         let tiny_doc = b"x";
         
         // Insert until we can't anymore
-        loop {
-            match PageLayout::insert_document(&mut page, tiny_doc) {
-                Ok(slot_id) => {
-                    inserted_slots.push(slot_id);
-                }
-                Err(_) => break,
-            }
+        while let Ok(slot_id) = PageLayout::insert_document(&mut page, tiny_doc) {
+            inserted_slots.push(slot_id);
             
             // Safety check to prevent infinite loop
             if inserted_slots.len() > 2000 {
@@ -83,12 +76,10 @@ mod stress_tests {
         assert!(utilization > 50.0, "Page should be well utilized");
         
         // Verify all documents are still readable
-        let mut slot_id = 0;
-        for &size in &document_sizes {
+        for (slot_id, &size) in document_sizes.iter().enumerate() {
             let expected_data = generate_test_data(size, (size % 256) as u8);
-            let actual_data = PageLayout::get_document(&page, slot_id).unwrap();
+            let actual_data = PageLayout::get_document(&page, slot_id as u16).unwrap();
             assert_eq!(actual_data, expected_data, "Document at slot {} corrupted", slot_id);
-            slot_id += 1;
         }
     }
 
@@ -262,7 +253,7 @@ mod data_integrity_tests {
         let mut page = create_test_page();
         
         // Insert documents with different patterns
-        let patterns = vec![
+        let patterns = [
             vec![0x00; 100],
             vec![0xFF; 100], 
             vec![0xAA; 100],
@@ -326,8 +317,8 @@ mod data_integrity_tests {
         let retrieved = PageLayout::get_document(&page, slot_id).unwrap();
         
         assert_eq!(retrieved.len(), large_size);
-        for i in 0..large_size {
-            assert_eq!(retrieved[i], (i % 256) as u8, "Byte {} corrupted", i);
+        for (i, byte) in retrieved.iter().enumerate() {
+            assert_eq!(*byte, (i % 256) as u8, "Byte {} corrupted", i);
         }
     }
 
@@ -495,7 +486,7 @@ mod consistency_tests {
         ];
         
         // Should reuse the deleted slots (in some order)
-        let expected_reused = vec![2, 5, 8];
+        let expected_reused = [2, 5, 8];
         for slot in reused_slots {
             assert!(expected_reused.contains(&slot), "Should reuse deleted slot");
         }
@@ -515,11 +506,8 @@ mod performance_tests {
         
         // Insert many documents
         let mut count = 0;
-        loop {
-            match PageLayout::insert_document(&mut page, doc) {
-                Ok(_) => count += 1,
-                Err(_) => break,
-            }
+        while PageLayout::insert_document(&mut page, doc).is_ok() {
+            count += 1;
             
             // Safety limit
             if count > 1000 {